@@ -0,0 +1,55 @@
+//! Time taken by assertions against a large file whose first byte differs, which should not
+//! depend on the size of the file.
+//!
+//! Run with `cargo bench --bench early_mismatch`, which fails if an early mismatch in a large file
+//! isn't much faster than a match.
+
+use std::panic::{self, AssertUnwindSafe};
+use std::time::{Duration, Instant};
+use std::{fs, process};
+
+const ITERATIONS: u32 = 5;
+
+fn time(f: impl Fn()) -> Duration {
+    let start = Instant::now();
+    for _ in 0..ITERATIONS {
+        f();
+    }
+    start.elapsed() / ITERATIONS
+}
+
+fn main() {
+    let path = std::env::temp_dir().join(format!("expect-test-bytes-bench-{}", process::id()));
+    let mut results = Vec::new();
+    for size_mib in [1, 16, 256] {
+        let data = vec![0x55; size_mib << 20];
+        fs::write(&path, &data).unwrap();
+        let mut mismatching = data.clone();
+        mismatching[0] = 0;
+
+        let expect = expect_test_bytes::ExpectFile::new(path.clone());
+        let matching = time(|| expect.assert_eq(&data));
+        let early_mismatch = time(|| {
+            let result = panic::catch_unwind(AssertUnwindSafe(|| expect.assert_eq(&mismatching)));
+            assert!(result.is_err());
+        });
+        results.push((size_mib, matching, early_mismatch));
+    }
+    fs::remove_file(&path).unwrap();
+
+    println!(
+        "\n{:>9}  {:>12}  {:>14}",
+        "file", "matching", "early mismatch"
+    );
+    for &(size_mib, matching, early_mismatch) in &results {
+        println!("{size_mib:>5} MiB  {matching:>12.2?}  {early_mismatch:>14.2?}");
+    }
+    for (size_mib, matching, early_mismatch) in results {
+        // Reading the whole file takes longer than failing at its first byte should
+        assert!(
+            size_mib < 16 || early_mismatch * 4 < matching,
+            "an early mismatch in a {size_mib} MiB file took {early_mismatch:.2?}, \
+             {matching:.2?} when matching, so it reads more of the file than it should"
+        );
+    }
+}
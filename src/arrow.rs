@@ -0,0 +1,566 @@
+//! Logical comparison of Arrow IPC files and streams by their schema and column values.
+
+use crate::columnar::{self, Column, Table, Value};
+use crate::structure::Difference;
+
+const FILE_MAGIC: &[u8] = b"ARROW1";
+const CONTINUATION: u32 = 0xffff_ffff;
+/// A table without fields, read in place of an absent type table so that its fields take their
+/// defaults: a vtable of 4 bytes, followed by the table pointing 4 bytes back to it.
+const EMPTY_TABLE: &[u8] = &[4, 0, 4, 0, 4, 0, 0, 0];
+
+/// A table of a flatbuffer.
+#[derive(Clone, Copy)]
+struct FlatTable<'a> {
+    buf: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> FlatTable<'a> {
+    fn root(buf: &'a [u8]) -> Option<Self> {
+        let pos = usize::try_from(read_u32(buf, 0)?).ok()?;
+        Self::at(buf, pos)
+    }
+
+    fn at(buf: &'a [u8], pos: usize) -> Option<Self> {
+        // Checked here so that fields can be read without checking the vtable again
+        read_u32(buf, pos)?;
+        Some(Self { buf, pos })
+    }
+
+    /// Returns the position of field `index`, or `None` if it's absent.
+    fn field(&self, index: usize) -> Option<usize> {
+        let offset = i64::from(i32::from_le_bytes(
+            self.buf.get(self.pos..self.pos + 4)?.try_into().ok()?,
+        ));
+        let vtable = usize::try_from(i64::try_from(self.pos).ok()? - offset).ok()?;
+        let vtable_len = usize::from(read_u16(self.buf, vtable)?);
+        let entry = 4 + 2 * index;
+        if entry + 2 > vtable_len {
+            return None;
+        }
+        match read_u16(self.buf, vtable + entry)? {
+            0 => None,
+            offset => Some(self.pos + usize::from(offset)),
+        }
+    }
+
+    fn u8(&self, index: usize, default: u8) -> Option<u8> {
+        self.field(index)
+            .map_or(Some(default), |pos| self.buf.get(pos).copied())
+    }
+
+    fn i16(&self, index: usize, default: i16) -> Option<i16> {
+        self.field(index).map_or(Some(default), |pos| {
+            Some(i16::from_le_bytes(
+                self.buf.get(pos..pos + 2)?.try_into().ok()?,
+            ))
+        })
+    }
+
+    fn i32(&self, index: usize, default: i32) -> Option<i32> {
+        self.field(index).map_or(Some(default), |pos| {
+            Some(i32::from_le_bytes(
+                self.buf.get(pos..pos + 4)?.try_into().ok()?,
+            ))
+        })
+    }
+
+    fn i64(&self, index: usize) -> Option<i64> {
+        self.field(index)
+            .map_or(Some(0), |pos| read_i64(self.buf, pos))
+    }
+
+    /// Follows the offset stored at `pos`.
+    fn deref(&self, pos: usize) -> Option<usize> {
+        pos.checked_add(usize::try_from(read_u32(self.buf, pos)?).ok()?)
+    }
+
+    /// Returns table field `index`, or `None` if it's absent.
+    fn table(&self, index: usize) -> Option<FlatTable<'a>> {
+        Self::at(self.buf, self.deref(self.field(index)?)?)
+    }
+
+    fn string(&self, index: usize) -> Option<String> {
+        let (pos, len) = self.vector(index)?;
+        String::from_utf8(self.buf.get(pos..pos.checked_add(len)?)?.to_vec()).ok()
+    }
+
+    /// Returns the position of the first element of vector field `index` and its length, which
+    /// is 0 if the field is absent.
+    fn vector(&self, index: usize) -> Option<(usize, usize)> {
+        let pos = match self.field(index) {
+            Some(pos) => self.deref(pos)?,
+            None => return Some((0, 0)),
+        };
+        let len = usize::try_from(read_u32(self.buf, pos)?).ok()?;
+        Some((pos + 4, len))
+    }
+
+    fn tables(&self, index: usize) -> Option<Vec<FlatTable<'a>>> {
+        let (pos, len) = self.vector(index)?;
+        (0..len)
+            .map(|i| Self::at(self.buf, self.deref(pos + 4 * i)?))
+            .collect()
+    }
+
+    /// Reads a vector of structs of two `i64` each, such as field nodes and buffers.
+    fn pairs(&self, index: usize) -> Option<Vec<(i64, i64)>> {
+        let (pos, len) = self.vector(index)?;
+        (0..len)
+            .map(|i| {
+                let pos = pos.checked_add(16 * i)?;
+                Some((read_i64(self.buf, pos)?, read_i64(self.buf, pos + 8)?))
+            })
+            .collect()
+    }
+}
+
+fn read_u16(buf: &[u8], pos: usize) -> Option<u16> {
+    Some(u16::from_le_bytes(
+        buf.get(pos..pos.checked_add(2)?)?.try_into().ok()?,
+    ))
+}
+
+fn read_u32(buf: &[u8], pos: usize) -> Option<u32> {
+    Some(u32::from_le_bytes(
+        buf.get(pos..pos.checked_add(4)?)?.try_into().ok()?,
+    ))
+}
+
+fn read_i64(buf: &[u8], pos: usize) -> Option<i64> {
+    Some(i64::from_le_bytes(
+        buf.get(pos..pos.checked_add(8)?)?.try_into().ok()?,
+    ))
+}
+
+/// Types of the `Type` union of the schema, which decide how the values are read.
+#[derive(Debug, Clone, Copy)]
+enum DataType {
+    Null,
+    Int {
+        bits: u32,
+        signed: bool,
+    },
+    Float {
+        bits: u32,
+    },
+    /// Strings and binaries with 32 or 64 bit offsets.
+    Binary {
+        text: bool,
+        large: bool,
+    },
+    Bool,
+    Decimal {
+        bytes: usize,
+        scale: i32,
+    },
+    /// Dates, times, timestamps and durations, stored as integers.
+    Temporal {
+        bits: u32,
+    },
+    FixedSizeBinary {
+        bytes: usize,
+    },
+    Interval {
+        bytes: usize,
+    },
+}
+
+const TIME_UNITS: [&str; 4] = ["SECOND", "MILLISECOND", "MICROSECOND", "NANOSECOND"];
+
+/// Reads the type of a field, returning it with its description, or `None` for nested and
+/// dictionary-encoded fields, which aren't supported.
+fn read_type(field: &FlatTable<'_>) -> Option<(DataType, String)> {
+    if field.field(4).is_some() || !field.tables(5)?.is_empty() {
+        return None;
+    }
+    let table = match field.field(3) {
+        Some(_) => field.table(3)?,
+        None => FlatTable::at(EMPTY_TABLE, 4)?,
+    };
+    Some(match field.u8(2, 0)? {
+        1 => (DataType::Null, "Null".to_owned()),
+        2 => {
+            let bits = u32::try_from(table.i32(0, 0)?).ok()?;
+            let signed = table.u8(1, 0)? != 0;
+            if ![8, 16, 32, 64].contains(&bits) {
+                return None;
+            }
+            let name = format!("{}Int{bits}", if signed { "" } else { "U" });
+            (DataType::Int { bits, signed }, name)
+        }
+        3 => {
+            let bits = match table.i16(0, 0)? {
+                0 => 16,
+                1 => 32,
+                2 => 64,
+                _ => return None,
+            };
+            (DataType::Float { bits }, format!("Float{bits}"))
+        }
+        type_id @ (4 | 5 | 19 | 20) => {
+            let text = type_id == 5 || type_id == 20;
+            let large = type_id >= 19;
+            let name = format!(
+                "{}{}",
+                if large { "Large" } else { "" },
+                if text { "Utf8" } else { "Binary" }
+            );
+            (DataType::Binary { text, large }, name)
+        }
+        6 => (DataType::Bool, "Bool".to_owned()),
+        7 => {
+            let precision = table.i32(0, 0)?;
+            let scale = table.i32(1, 0)?;
+            let bits = table.i32(2, 128)?;
+            let bytes = match bits {
+                32 | 64 | 128 | 256 => usize::try_from(bits / 8).ok()?,
+                _ => return None,
+            };
+            (
+                DataType::Decimal { bytes, scale },
+                format!("Decimal{bits}({precision}, {scale})"),
+            )
+        }
+        type_id @ (8..=11 | 18) => read_temporal_type(type_id, &table)?,
+        15 => {
+            let bytes = usize::try_from(table.i32(0, 0)?).ok()?;
+            (
+                DataType::FixedSizeBinary { bytes },
+                format!("FixedSizeBinary({bytes})"),
+            )
+        }
+        _ => return None,
+    })
+}
+
+/// Reads the type of a date, time, timestamp, interval or duration field.
+fn read_temporal_type(type_id: u8, table: &FlatTable<'_>) -> Option<(DataType, String)> {
+    let unit = |index, default| {
+        let unit = table.i16(index, default)?;
+        TIME_UNITS.get(usize::try_from(unit).ok()?).copied()
+    };
+    Some(match type_id {
+        8 => {
+            let (bits, unit) = match table.i16(0, 1)? {
+                0 => (32, "DAY"),
+                1 => (64, "MILLISECOND"),
+                _ => return None,
+            };
+            (DataType::Temporal { bits }, format!("Date{bits}({unit})"))
+        }
+        9 => {
+            let bits = u32::try_from(table.i32(1, 32)?).ok()?;
+            if bits != 32 && bits != 64 {
+                return None;
+            }
+            let name = format!("Time{bits}({})", unit(0, 1)?);
+            (DataType::Temporal { bits }, name)
+        }
+        10 => {
+            let timezone = table.string(1)?;
+            let name = if timezone.is_empty() {
+                format!("Timestamp({})", unit(0, 0)?)
+            } else {
+                format!("Timestamp({}, {timezone})", unit(0, 0)?)
+            };
+            (DataType::Temporal { bits: 64 }, name)
+        }
+        11 => {
+            let (bytes, unit) = match table.i16(0, 0)? {
+                0 => (4, "YEAR_MONTH"),
+                1 => (8, "DAY_TIME"),
+                2 => (16, "MONTH_DAY_NANO"),
+                _ => return None,
+            };
+            (DataType::Interval { bytes }, format!("Interval({unit})"))
+        }
+        18 => (
+            DataType::Temporal { bits: 64 },
+            format!("Duration({})", unit(0, 1)?),
+        ),
+        19 => (
+            DataType::Binary {
+                text: false,
+                large: true,
+            },
+            "LargeBinary".to_owned(),
+        ),
+        20 => (
+            DataType::Binary {
+                text: true,
+                large: true,
+            },
+            "LargeUtf8".to_owned(),
+        ),
+        _ => return None,
+    })
+}
+
+/// An encapsulated message of an IPC stream.
+struct Message<'a> {
+    header_type: u8,
+    header: FlatTable<'a>,
+    body: &'a [u8],
+}
+
+/// Reads the length prefix of the message at `pos`, returning the position of the metadata and
+/// its length, which is 0 at the end of the stream.
+fn read_prefix(data: &[u8], pos: usize) -> Option<(usize, usize)> {
+    // Streams may end without an end-of-stream marker
+    if pos == data.len() {
+        return Some((pos, 0));
+    }
+    let len = read_u32(data, pos)?;
+    // The continuation marker is missing before version 0.15
+    let (pos, len) = if len == CONTINUATION {
+        (pos + 8, read_u32(data, pos + 4)?)
+    } else {
+        (pos + 4, len)
+    };
+    Some((pos, usize::try_from(len).ok()?))
+}
+
+/// Reads the message with `len` bytes of metadata at `pos`, returning it with the position after
+/// its body.
+fn read_message(data: &[u8], pos: usize, len: usize) -> Option<(Message<'_>, usize)> {
+    let metadata = data.get(pos..pos.checked_add(len)?)?;
+    let message = FlatTable::root(metadata)?;
+    let pos = pos + len;
+    let body_len = usize::try_from(message.i64(3)?).ok()?;
+    let body = data.get(pos..pos.checked_add(body_len)?)?;
+    let message = Message {
+        header_type: message.u8(1, 0)?,
+        header: message.table(2)?,
+        body,
+    };
+    Some((message, pos + body_len))
+}
+
+/// Returns the buffer of a record batch at the index of `buffers`, decompressing it if needed.
+fn read_buffer(
+    body: &[u8],
+    buffers: &mut std::slice::Iter<'_, (i64, i64)>,
+    codec: Option<u8>,
+) -> Option<Vec<u8>> {
+    let &(offset, len) = buffers.next()?;
+    let offset = usize::try_from(offset).ok()?;
+    let buffer = body.get(offset..offset.checked_add(usize::try_from(len).ok()?)?)?;
+    let codec = match codec {
+        Some(codec) if !buffer.is_empty() => codec,
+        _ => return Some(buffer.to_vec()),
+    };
+    let uncompressed_len = read_i64(buffer, 0)?;
+    let compressed = &buffer[8..];
+    if uncompressed_len == -1 {
+        return Some(compressed.to_vec());
+    }
+    let data = match codec {
+        0 => crate::lz4::decompress_frames(compressed)?,
+        1 => crate::zstd::decompress(compressed).ok()?,
+        _ => return None,
+    };
+    (i64::try_from(data.len()).ok()? == uncompressed_len).then(|| data)
+}
+
+fn bit(bitmap: &[u8], i: usize) -> Option<bool> {
+    Some(bitmap.get(i / 8)? >> (i % 8) & 1 != 0)
+}
+
+/// Reads the `i`-th `bytes` bytes of `values` as a little-endian signed integer.
+fn read_int(values: &[u8], i: usize, bytes: usize) -> Option<i128> {
+    let value = values.get(i * bytes..(i + 1) * bytes)?;
+    let initial = if value.last().map_or(false, |&b| b & 0x80 != 0) {
+        -1
+    } else {
+        0
+    };
+    Some(
+        value
+            .iter()
+            .rev()
+            .fold(initial, |int, &b| int << 8 | i128::from(b)),
+    )
+}
+
+/// Converts the bits of a half-precision float.
+fn f16_to_f64(bits: u16) -> f64 {
+    let sign = if bits & 0x8000 == 0 { 1.0 } else { -1.0 };
+    let exponent = i32::from(bits >> 10 & 0x1f);
+    let fraction = f64::from(bits & 0x3ff);
+    sign * match exponent {
+        0 => fraction * 2_f64.powi(-24),
+        0x1f if fraction == 0.0 => f64::INFINITY,
+        0x1f => f64::NAN,
+        _ => (1.0 + fraction / 1024.0) * 2_f64.powi(exponent - 15),
+    }
+}
+
+/// Reads the values of a column of `len` rows from the buffers of a record batch.
+fn read_column(
+    data_type: DataType,
+    len: usize,
+    mut buffer: impl FnMut() -> Option<Vec<u8>>,
+) -> Option<Vec<Value>> {
+    if let DataType::Null = data_type {
+        return Some(vec![Value::Null; len]);
+    }
+    let validity = buffer()?;
+    let values = buffer()?;
+    let data = match data_type {
+        DataType::Binary { .. } => Some(buffer()?),
+        _ => None,
+    };
+    (0..len)
+        .map(|i| {
+            if !validity.is_empty() && !bit(&validity, i)? {
+                return Some(Value::Null);
+            }
+            Some(match data_type {
+                DataType::Null => Value::Null,
+                DataType::Int { bits, signed } => {
+                    let bytes = usize::try_from(bits / 8).ok()?;
+                    let value = read_int(&values, i, bytes)?;
+                    if signed || value >= 0 {
+                        Value::Int(value)
+                    } else {
+                        Value::Int(value + (1 << bits))
+                    }
+                }
+                DataType::Float { bits: 16 } => {
+                    let bits = u16::try_from(read_int(&values, i, 2)? & 0xffff).ok()?;
+                    Value::float(f16_to_f64(bits))
+                }
+                DataType::Float { bits: 32 } => {
+                    let bytes = values.get(i * 4..i * 4 + 4)?;
+                    Value::float(f64::from(f32::from_le_bytes(bytes.try_into().ok()?)))
+                }
+                DataType::Float { .. } => {
+                    let bytes = values.get(i * 8..i * 8 + 8)?;
+                    Value::float(f64::from_le_bytes(bytes.try_into().ok()?))
+                }
+                DataType::Binary { text, large } => {
+                    let (start, end) = if large {
+                        (read_int(&values, i, 8)?, read_int(&values, i + 1, 8)?)
+                    } else {
+                        (read_int(&values, i, 4)?, read_int(&values, i + 1, 4)?)
+                    };
+                    let (start, end) = (usize::try_from(start).ok()?, usize::try_from(end).ok()?);
+                    let bytes = data.as_ref()?.get(start..end)?.to_vec();
+                    if text {
+                        Value::Text(String::from_utf8(bytes).ok()?)
+                    } else {
+                        Value::Bytes(bytes)
+                    }
+                }
+                DataType::Bool => Value::Bool(bit(&values, i)?),
+                // 256-bit decimals don't fit in an `i128`
+                DataType::Decimal { bytes: 32, .. } => {
+                    Value::Bytes(values.get(i * 32..i * 32 + 32)?.to_vec())
+                }
+                DataType::Decimal { bytes, scale } => Value::Decimal {
+                    value: read_int(&values, i, bytes)?,
+                    scale,
+                },
+                DataType::Temporal { bits } => {
+                    Value::Int(read_int(&values, i, usize::try_from(bits / 8).ok()?)?)
+                }
+                DataType::FixedSizeBinary { bytes } | DataType::Interval { bytes } => {
+                    Value::Bytes(values.get(i * bytes..(i + 1) * bytes)?.to_vec())
+                }
+            })
+        })
+        .collect()
+}
+
+/// Parses an Arrow IPC file, or a stream starting with its schema message.
+fn parse(data: &[u8]) -> Option<Table> {
+    let (mut pos, end) = if data.starts_with(FILE_MAGIC) {
+        // The footer repeats the schema and locates the batches, which follow each other anyway
+        let footer_len = read_u32(data, data.len().checked_sub(10)?)?;
+        let footer_start = data
+            .len()
+            .checked_sub(10 + usize::try_from(footer_len).ok()?)?;
+        (8, footer_start)
+    } else {
+        (0, data.len())
+    };
+    let data = &data[..end];
+    let mut fields = None;
+    let mut groups = Vec::new();
+    loop {
+        let (metadata, len) = read_prefix(data, pos)?;
+        if len == 0 {
+            break;
+        }
+        let (message, next) = read_message(data, metadata, len)?;
+        pos = next;
+        match message.header_type {
+            1 if fields.is_none() => {
+                let schema = message.header;
+                // Big-endian data isn't supported
+                if schema.i16(0, 0)? != 0 {
+                    return None;
+                }
+                fields = Some(
+                    schema
+                        .tables(1)?
+                        .iter()
+                        .map(|field| {
+                            let (data_type, type_name) = read_type(field)?;
+                            let column = Column {
+                                name: field.string(0)?,
+                                data_type: type_name,
+                                nullable: field.u8(1, 0)? != 0,
+                            };
+                            Some((column, data_type))
+                        })
+                        .collect::<Option<Vec<_>>>()?,
+                );
+            }
+            3 => {
+                let fields = fields.as_ref()?;
+                let batch = message.header;
+                let len = usize::try_from(batch.i64(0)?).ok()?;
+                let nodes = batch.pairs(1)?;
+                let buffers = batch.pairs(2)?;
+                let codec = match batch.field(3) {
+                    Some(_) => Some(batch.table(3)?.u8(0, 0)?),
+                    None => None,
+                };
+                if nodes.len() != fields.len() {
+                    return None;
+                }
+                let mut buffers = buffers.iter();
+                let columns = fields
+                    .iter()
+                    .map(|(_, data_type)| {
+                        read_column(*data_type, len, || {
+                            read_buffer(message.body, &mut buffers, codec)
+                        })
+                    })
+                    .collect::<Option<_>>()?;
+                groups.push(columns);
+            }
+            // Dictionaries are only used by dictionary-encoded fields, which aren't supported
+            _ => return None,
+        }
+    }
+    Some(Table {
+        columns: fields?.into_iter().map(|(column, _)| column).collect(),
+        groups,
+    })
+}
+
+/// Compares two Arrow IPC files or streams by their schema and values, or returns `None` if
+/// either can't be parsed.
+pub(crate) fn equal(expected: &[u8], actual: &[u8]) -> Option<bool> {
+    Some(columnar::equal(&parse(expected)?, &parse(actual)?))
+}
+
+/// Finds the first difference in the schema or values of two Arrow IPC files or streams, or
+/// returns `None` if either can't be parsed.
+pub(crate) fn first_difference(expected: &[u8], actual: &[u8]) -> Option<Difference> {
+    columnar::first_difference(&parse(expected)?, &parse(actual)?, "record batch")
+}
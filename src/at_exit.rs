@@ -0,0 +1,16 @@
+//! Callbacks that run when the process exits, for summaries printed after all tests.
+
+use std::os::raw::c_int;
+
+extern "C" {
+    fn atexit(callback: extern "C" fn()) -> c_int;
+}
+
+/// Runs `callback` when the process exits.
+///
+/// # Safety
+///
+/// `callback` must not unwind and must only use state that lives until the process exits.
+pub(crate) unsafe fn register(callback: extern "C" fn()) {
+    atexit(callback);
+}
@@ -0,0 +1,60 @@
+//! Batch update mode, where updated files are summarized when the process exits instead of being
+//! listed one by one.
+//!
+//! The updates themselves are recorded by [`stats`](crate::stats), which calls into this module
+//! at exit.
+
+use std::collections::BTreeSet;
+use std::io;
+use std::path::Path;
+
+use crate::stats::Update;
+use crate::style::SUCCESS;
+
+/// When set in update mode, updated files are summarized and their directories synced when the
+/// process exits.
+pub(crate) const BATCH_VAR_NAME: &str = "EXPECT_BYTES_BATCH_UPDATE";
+
+pub(crate) fn is_enabled() -> bool {
+    std::env::var_os(BATCH_VAR_NAME).is_some()
+}
+
+/// Syncs each directory containing one of `paths` once, so that the new directory entries are
+/// durable without syncing every file as it's written.
+pub(crate) fn sync_dirs<'a>(paths: impl Iterator<Item = &'a Path>) {
+    let dirs: BTreeSet<&Path> = paths.filter_map(Path::parent).collect();
+    for dir in dirs {
+        // Directories can't be opened for syncing on all platforms
+        if let Ok(dir) = std::fs::File::open(dir) {
+            let _ = dir.sync_all();
+        }
+    }
+}
+
+/// Writes the end-of-run table of updated files.
+pub(crate) fn write_summary<W: io::Write>(updates: &[Update], writer: &mut W) -> io::Result<()> {
+    if updates.is_empty() {
+        return Ok(());
+    }
+    let total: usize = updates.iter().map(|update| update.len).sum();
+    writeln!(
+        writer,
+        "\n{SUCCESS}updated{SUCCESS:#}: {} {}, {total} bytes",
+        updates.len(),
+        if updates.len() == 1 { "file" } else { "files" }
+    )?;
+    let width = updates
+        .iter()
+        .map(|update| update.len.to_string().len())
+        .max()
+        .unwrap_or(0);
+    for update in updates {
+        writeln!(
+            writer,
+            "   {:>width$}  {}",
+            update.len,
+            update.path.display()
+        )?;
+    }
+    Ok(())
+}
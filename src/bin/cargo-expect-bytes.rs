@@ -0,0 +1,5 @@
+//! The `cargo expect-bytes` subcommand.
+
+fn main() {
+    std::process::exit(expect_test_bytes::cli::main(std::env::args_os().skip(1)));
+}
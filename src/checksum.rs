@@ -0,0 +1,198 @@
+//! Checksum fields embedded in the data, for [`ExpectFile::checksum_field`].
+//!
+//! [`ExpectFile::checksum_field`]: crate::ExpectFile::checksum_field
+
+use std::borrow::Cow;
+use std::fmt;
+use std::io;
+use std::ops::Range;
+
+use crate::style::NOTE;
+
+/// Algorithm used to compute a checksum field.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ChecksumAlgorithm {
+    /// CRC-32 as used by zlib, PNG and ZIP.
+    Crc32,
+    /// Adler-32 as used by zlib.
+    Adler32,
+    /// The sum of the bytes, wrapping on overflow of the field.
+    Sum,
+}
+
+impl fmt::Display for ChecksumAlgorithm {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Crc32 => "CRC-32",
+            Self::Adler32 => "Adler-32",
+            Self::Sum => "Sum",
+        })
+    }
+}
+
+const CRC32_TABLE: [u32; 256] = {
+    let mut table = [0; 256];
+    let mut i = 0_u32;
+    while i < 256 {
+        let mut crc = i;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = if crc & 1 == 0 {
+                crc >> 1
+            } else {
+                (crc >> 1) ^ 0xedb8_8320
+            };
+            bit += 1;
+        }
+        table[i as usize] = crc;
+        i += 1;
+    }
+    table
+};
+
+impl ChecksumAlgorithm {
+    fn compute(self, data: &[u8]) -> u64 {
+        match self {
+            Self::Crc32 => {
+                let crc = data.iter().fold(!0_u32, |crc, &b| {
+                    CRC32_TABLE[((crc ^ u32::from(b)) & 0xff) as usize] ^ (crc >> 8)
+                });
+                u64::from(!crc)
+            }
+            Self::Adler32 => {
+                const MOD: u32 = 65521;
+                let (a, b) = data.iter().fold((1_u32, 0_u32), |(a, b), &byte| {
+                    let a = (a + u32::from(byte)) % MOD;
+                    (a, (b + a) % MOD)
+                });
+                u64::from((b << 16) | a)
+            }
+            Self::Sum => data
+                .iter()
+                .fold(0_u64, |sum, &b| sum.wrapping_add(u64::from(b))),
+        }
+    }
+}
+
+/// A checksum stored in the data, covering another range of the data.
+///
+/// ```
+/// use expect_test_bytes::{ChecksumAlgorithm, ChecksumField};
+///
+/// // A big-endian CRC-32 of everything after the 4-byte checksum
+/// let field = ChecksumField::new(ChecksumAlgorithm::Crc32, 0..4, 4..usize::MAX).big_endian();
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChecksumField {
+    algorithm: ChecksumAlgorithm,
+    field: Range<usize>,
+    data: Range<usize>,
+    big_endian: bool,
+    excluded: bool,
+}
+
+impl ChecksumField {
+    /// Creates a little-endian checksum field stored at `field` and computed over `data`.
+    ///
+    /// The checksum is truncated to the length of `field`, which must be at most 8 bytes. `data`
+    /// is clamped to the length of the data, so `usize::MAX` can be used as the end.
+    ///
+    /// # Panics
+    ///
+    /// Will panic if `field` is longer than 8 bytes.
+    #[must_use]
+    pub fn new(algorithm: ChecksumAlgorithm, field: Range<usize>, data: Range<usize>) -> Self {
+        assert!(field.len() <= 8, "checksum fields are at most 8 bytes long");
+        Self {
+            algorithm,
+            field,
+            data,
+            big_endian: false,
+            excluded: false,
+        }
+    }
+
+    /// Makes the checksum stored in big-endian byte order.
+    #[must_use]
+    pub fn big_endian(mut self) -> Self {
+        self.big_endian = true;
+        self
+    }
+
+    /// Excludes the field from comparisons, for data whose checksum is only filled in later.
+    ///
+    /// Differences in the field are still noted in failure reports.
+    #[must_use]
+    pub fn excluded(mut self) -> Self {
+        self.excluded = true;
+        self
+    }
+
+    /// Returns the value stored in the field and the value computed from the data, or `None` if
+    /// the field is out of bounds.
+    fn values(&self, data: &[u8]) -> Option<(u64, u64)> {
+        let stored = data.get(self.field.clone())?;
+        let stored = if self.big_endian {
+            stored.iter().fold(0, |value, &b| value << 8 | u64::from(b))
+        } else {
+            stored
+                .iter()
+                .rev()
+                .fold(0, |value, &b| value << 8 | u64::from(b))
+        };
+        let start = self.data.start.min(data.len());
+        let end = self.data.end.min(data.len()).max(start);
+        let bits = 8 * self.field.len();
+        let mask = if bits == 64 {
+            u64::MAX
+        } else {
+            (1 << bits) - 1
+        };
+        Some((stored, self.algorithm.compute(&data[start..end]) & mask))
+    }
+}
+
+pub(crate) fn any_excluded(fields: &[ChecksumField]) -> bool {
+    fields.iter().any(|field| field.excluded)
+}
+
+/// Zeroes the excluded fields in `data`.
+pub(crate) fn mask_excluded<'a>(
+    fields: &[ChecksumField],
+    data: impl Into<Cow<'a, [u8]>>,
+) -> Cow<'a, [u8]> {
+    let mut data = data.into();
+    for field in fields.iter().filter(|field| field.excluded) {
+        let end = field.field.end.min(data.len());
+        let start = field.field.start.min(end);
+        if start < end {
+            data.to_mut()[start..end].fill(0);
+        }
+    }
+    data
+}
+
+/// Writes a note for each checksum field in `data` that doesn't match the data it covers.
+pub(crate) fn write_stale_fields<W: io::Write>(
+    fields: &[ChecksumField],
+    data: &[u8],
+    description: &str,
+    writer: &mut W,
+) {
+    for field in fields {
+        let (stored, computed) = match field.values(data) {
+            Some((stored, computed)) if stored != computed => (stored, computed),
+            _ => continue,
+        };
+        let width = 2 + 2 * field.field.len();
+        writeln!(
+            writer,
+            "{NOTE}note{NOTE:#}: {algorithm} at {offset:#x} is stale in {description}: \
+             field says {stored:#0width$x}, data implies {computed:#0width$x}",
+            algorithm = field.algorithm,
+            offset = field.field.start,
+        )
+        .unwrap();
+    }
+}
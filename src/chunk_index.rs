@@ -0,0 +1,161 @@
+//! Per-chunk hash index stored next to large snapshots.
+//!
+//! The index lets verification compare the actual data against chunk digests without reading the
+//! snapshot file, and read only the region around the first differing chunk on failure.
+//!
+//! The index is rewritten along with the file in update mode and is meant to be committed next to
+//! it, so it only depends on the contents. It records the length of the file and a digest of its
+//! chunk digests, and is stale once the length changes or the index itself is edited. A file that
+//! is edited by hand at the same length needs its index removed or rewritten by an update.
+
+use crate::sha256::{sha256, to_hex};
+use std::ffi::OsString;
+use std::io::{self, Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::{fmt, fs};
+
+const HEADER: &str = "expect-test-bytes chunk index";
+
+/// Parsed contents of an index sidecar file.
+pub(crate) struct ChunkIndex {
+    chunk_size: usize,
+    len: u64,
+    digests: Vec<String>,
+}
+
+/// Result of checking the actual data against the index.
+pub(crate) enum ChunkCheck {
+    /// Every chunk and the total length match.
+    Equal,
+    /// At least one chunk differs.
+    Different {
+        /// Indices of all differing chunks.
+        chunks: Vec<usize>,
+        /// Region of the snapshot file around the first differing chunk.
+        window: Vec<u8>,
+        /// Offset of `window` in the snapshot file.
+        window_offset: usize,
+    },
+}
+
+/// Returns the path of the index sidecar for the snapshot at `path`.
+pub(crate) fn index_path(path: &Path) -> PathBuf {
+    let mut os: OsString = path.as_os_str().to_owned();
+    os.push(".chunks");
+    os.into()
+}
+
+/// Returns the digest of the length and chunk digests of an index, which tells apart edited
+/// indices.
+fn digest_of(len: u64, digests: &[String]) -> String {
+    let mut data = format!("{len}\n");
+    for digest in digests {
+        data.push_str(digest);
+        data.push('\n');
+    }
+    to_hex(&sha256(data.as_bytes()))
+}
+
+impl ChunkIndex {
+    /// Builds the index of `data`.
+    pub(crate) fn build(data: &[u8], chunk_size: usize) -> Self {
+        Self {
+            chunk_size,
+            len: data.len() as u64,
+            digests: data
+                .chunks(chunk_size)
+                .map(|chunk| to_hex(&sha256(chunk)))
+                .collect(),
+        }
+    }
+
+    pub(crate) fn read(path: &Path) -> io::Result<Option<Self>> {
+        let contents = match fs::read_to_string(index_path(path)) {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e),
+        };
+        Ok(Self::parse(&contents))
+    }
+
+    fn parse(contents: &str) -> Option<Self> {
+        let mut lines = contents.lines();
+        if lines.next()? != HEADER {
+            return None;
+        }
+        let chunk_size = lines.next()?.strip_prefix("chunk_size ")?.parse().ok()?;
+        let len = lines.next()?.strip_prefix("len ")?.parse().ok()?;
+        let digest = lines.next()?.strip_prefix("digest ")?;
+        let digests: Vec<String> = lines.map(str::to_owned).collect();
+        if digest != digest_of(len, &digests) {
+            return None;
+        }
+        Some(Self {
+            chunk_size,
+            len,
+            digests,
+        })
+    }
+
+    /// Compares `actual` against the index, reading only the necessary parts of the snapshot.
+    ///
+    /// Returns `None` if the index is stale or doesn't use `chunk_size`.
+    pub(crate) fn check(
+        &self,
+        path: &Path,
+        actual: &[u8],
+        chunk_size: usize,
+        window_half_size: usize,
+    ) -> io::Result<Option<ChunkCheck>> {
+        let metadata = fs::metadata(path)?;
+        if self.chunk_size != chunk_size || metadata.len() != self.len {
+            return Ok(None);
+        }
+
+        let actual_chunks = actual.chunks(chunk_size).count();
+        let chunks: Vec<usize> = (0..self.digests.len().max(actual_chunks))
+            .filter(|&i| {
+                let actual_digest = actual
+                    .chunks(chunk_size)
+                    .nth(i)
+                    .map(|chunk| to_hex(&sha256(chunk)));
+                self.digests.get(i) != actual_digest.as_ref()
+            })
+            .collect();
+        let first = match chunks.first() {
+            Some(&first) => first,
+            None => return Ok(Some(ChunkCheck::Equal)),
+        };
+
+        let len = usize::try_from(self.len).unwrap_or(usize::MAX);
+        let window_offset = (first * chunk_size)
+            .saturating_sub(window_half_size)
+            .min(len);
+        let window_end = len.min((first + 1) * chunk_size + window_half_size);
+
+        let mut file = fs::File::open(path)?;
+        file.seek(SeekFrom::Start(window_offset as u64))?;
+        let mut window = Vec::with_capacity(window_end - window_offset);
+        file.take((window_end - window_offset) as u64)
+            .read_to_end(&mut window)?;
+
+        Ok(Some(ChunkCheck::Different {
+            chunks,
+            window,
+            window_offset,
+        }))
+    }
+}
+
+impl fmt::Display for ChunkIndex {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{HEADER}")?;
+        writeln!(f, "chunk_size {}", self.chunk_size)?;
+        writeln!(f, "len {}", self.len)?;
+        writeln!(f, "digest {}", digest_of(self.len, &self.digests))?;
+        for digest in &self.digests {
+            writeln!(f, "{digest}")?;
+        }
+        Ok(())
+    }
+}
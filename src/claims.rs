@@ -0,0 +1,103 @@
+//! Registry of the files claimed by each test, kept under the target directory so that it's
+//! shared by all test binaries of the workspace.
+
+use std::fmt::Write as _;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, PoisonError};
+
+use crate::global::Global;
+use crate::{run, sha256};
+
+/// When set, asserting different contents for a file in two tests fails.
+pub(crate) const CLAIMS_VAR_NAME: &str = "EXPECT_BYTES_CLAIMS";
+
+/// Serializes the claims of the threads of this process.
+static CLAIMING: Global<Mutex<()>> = Global::new();
+
+pub(crate) fn is_enabled() -> bool {
+    std::env::var_os(CLAIMS_VAR_NAME).is_some()
+}
+
+/// Returns the directory of the registry, or `None` if the target directory can't be found.
+fn registry_dir() -> Option<PathBuf> {
+    let target_dir = match std::env::var_os("CARGO_TARGET_DIR") {
+        Some(dir) => PathBuf::from(dir),
+        // Test binaries are somewhere under the target directory, which cargo tags as a cache
+        None => std::env::current_exe()
+            .ok()?
+            .ancestors()
+            .find(|dir| dir.join("CACHEDIR.TAG").is_file())?
+            .to_owned(),
+    };
+    Some(target_dir.join("expect-test-bytes-claims"))
+}
+
+/// Claims the file at `path`, canonicalized, for the current test with `contents`.
+///
+/// Returns the name of another test that claimed it with different contents.
+pub(crate) fn claim(path: &Path, contents: &[u8]) -> Option<String> {
+    let thread = std::thread::current();
+    let test = test_id(thread.name()?);
+    let dir = registry_dir()?;
+    fs::create_dir_all(&dir).ok()?;
+    claim_in(&dir, &test, path, &sha256::sha256(contents))
+}
+
+/// Returns the identity of the test named `name` in the registry.
+///
+/// The registry is shared by the workspace, so test names are qualified with the package and the
+/// test binary, whose file name cargo suffixes with a hash of the build.
+pub(crate) fn test_id(name: &str) -> String {
+    let binary = std::env::current_exe().ok().and_then(|exe| {
+        let stem = exe.file_stem()?.to_string_lossy().into_owned();
+        Some(match stem.rsplit_once('-') {
+            Some((binary, _hash)) => binary.to_owned(),
+            None => stem,
+        })
+    });
+    // Set by cargo when it runs the tests of a package
+    let package = std::env::var("CARGO_PKG_NAME").ok();
+    match (package, binary) {
+        (Some(package), Some(binary)) => format!("{package}/{binary}::{name}"),
+        (Some(qualifier), None) | (None, Some(qualifier)) => format!("{qualifier}::{name}"),
+        (None, None) => name.to_owned(),
+    }
+}
+
+/// Claims `path` for `test` with contents with `digest` in the registry in `dir`.
+///
+/// Each file has a file in the registry with a line of the digest and the name of each test
+/// claiming it.
+pub(crate) fn claim_in(dir: &Path, test: &str, path: &Path, digest: &[u8; 32]) -> Option<String> {
+    let digest = sha256::to_hex(digest);
+    let claims_path = dir.join(format!(
+        "{}.claims",
+        sha256::to_hex(&sha256::sha256(path.to_string_lossy().as_bytes()))
+    ));
+
+    let _claiming = CLAIMING
+        .get_or_init(Mutex::default)
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner);
+    let _lock = run::lock();
+    let claims = fs::read_to_string(&claims_path).unwrap_or_default();
+    let mut new_claims = format!("# {}\n", path.display());
+    for (claim_digest, claim_test) in claims
+        .lines()
+        .filter(|line| !line.starts_with('#'))
+        .filter_map(|line| line.split_once(' '))
+    {
+        if claim_test == test {
+            continue;
+        }
+        if claim_digest != digest {
+            return Some(claim_test.to_owned());
+        }
+        writeln!(new_claims, "{claim_digest} {claim_test}").unwrap();
+    }
+    writeln!(new_claims, "{digest} {test}").unwrap();
+    // A registry that can't be written must not fail the test
+    let _ = fs::write(&claims_path, new_claims);
+    None
+}
@@ -0,0 +1,232 @@
+//! The `cargo expect-bytes` subcommand, for listing, verifying and blessing the snapshots of a
+//! crate.
+
+use std::collections::BTreeSet;
+use std::ffi::OsString;
+use std::io::{self, Write as _};
+use std::panic::{self, AssertUnwindSafe};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use crate::style::{ERROR, SUCCESS};
+use crate::{batch, parts, ExpectFile, UPDATE_EXPECT_VAR_NAME};
+
+const USAGE: &str = "\
+Usage:
+    cargo expect-bytes list
+        List the snapshots referenced by the sources of the crate, with their sizes.
+    cargo expect-bytes verify <snapshot> -- <command> [<args>...]
+        Check the snapshot against the standard output of the command. Set UPDATE_EXPECT=1 to
+        update it instead.
+    cargo expect-bytes bless [<cargo test args>...]
+        Run the tests in update mode and summarize the updated files.
+";
+
+/// Directories of a crate with Rust sources that reference snapshots.
+const SOURCE_DIRS: [&str; 4] = ["src", "tests", "benches", "examples"];
+
+/// Runs the subcommand with `args`, without the name of the program, and returns the exit code.
+#[doc(hidden)]
+pub fn main(args: impl IntoIterator<Item = OsString>) -> i32 {
+    let mut args: Vec<_> = args.into_iter().collect();
+    // Cargo passes the name of the subcommand first
+    if args.first().map_or(false, |arg| arg == "expect-bytes") {
+        args.remove(0);
+    }
+    let command = args.first().and_then(|arg| arg.to_str()).unwrap_or("");
+    let result = match command {
+        "list" => crate_root().and_then(|root| list(&root, &mut io::stdout())),
+        "verify" => verify(&args[1..]),
+        "bless" => bless(&args[1..]),
+        "help" | "--help" | "-h" => {
+            print!("{USAGE}");
+            return 0;
+        }
+        _ => {
+            eprint!("{USAGE}");
+            return 2;
+        }
+    };
+    match result {
+        Ok(code) => code,
+        Err(e) => {
+            eprintln!("{ERROR}error{ERROR:#}: {e}");
+            2
+        }
+    }
+}
+
+/// Returns the closest directory containing a `Cargo.toml`, starting from the working directory.
+fn crate_root() -> io::Result<PathBuf> {
+    let dir = std::env::current_dir()?;
+    dir.ancestors()
+        .find(|dir| dir.join("Cargo.toml").is_file())
+        .map(Path::to_owned)
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no Cargo.toml found"))
+}
+
+/// Returns the snapshot paths referenced by `expect_file!`, `expect_group!` and the mirrors of
+/// `expect_out_file!` in `source`, as written.
+pub(crate) fn snapshot_paths(source: &str) -> Vec<&str> {
+    let mut paths = Vec::new();
+    for (i, _) in source.match_indices("expect_") {
+        let rest = &source[i..];
+        let args = ["expect_file![", "expect_group!["]
+            .iter()
+            .find_map(|name| rest.strip_prefix(name));
+        let literal = match args {
+            // The literal must be the only argument, not the start of a longer expression
+            Some(args) => args.trim_start().strip_prefix('"').filter(|literal| {
+                literal.find('"').map_or(false, |end| {
+                    literal[end + 1..].trim_start().starts_with(']')
+                })
+            }),
+            None => rest.strip_prefix("expect_out_file![").and_then(|args| {
+                let args = &args[..args.find(']').unwrap_or(args.len())];
+                let mirror = args.find("mirror")?;
+                args[mirror + "mirror".len()..]
+                    .trim_start()
+                    .strip_prefix('=')?
+                    .trim_start()
+                    .strip_prefix('"')
+            }),
+        };
+        if let Some(path) = literal.and_then(|literal| Some(&literal[..literal.find('"')?])) {
+            paths.push(path);
+        }
+    }
+    paths
+}
+
+/// Finds the snapshots referenced by the Rust sources under `root`, resolved like
+/// [`expect_file!`](crate::expect_file).
+pub(crate) fn find_snapshots(root: &Path) -> io::Result<BTreeSet<PathBuf>> {
+    let mut snapshots = BTreeSet::new();
+    let mut dirs: Vec<_> = SOURCE_DIRS.iter().map(|dir| root.join(dir)).collect();
+    while let Some(dir) = dirs.pop() {
+        let entries = match crate::not_found_to_none(dir.read_dir())? {
+            Some(entries) => entries,
+            None => continue,
+        };
+        for entry in entries {
+            let path = entry?.path();
+            if path.is_dir() {
+                dirs.push(path);
+            } else if path
+                .extension()
+                .map_or(false, |extension| extension == "rs")
+            {
+                // Sources that aren't UTF-8 can't reference snapshots
+                let source = match std::fs::read_to_string(&path) {
+                    Ok(source) => source,
+                    Err(e) if e.kind() == io::ErrorKind::InvalidData => continue,
+                    Err(e) => return Err(e),
+                };
+                let source_dir = path.parent().unwrap_or(root);
+                for snapshot in snapshot_paths(&source) {
+                    let snapshot = source_dir.join(snapshot);
+                    let snapshot = snapshot.strip_prefix(root).unwrap_or(&snapshot).to_owned();
+                    snapshots.insert(snapshot);
+                }
+            }
+        }
+    }
+    Ok(snapshots)
+}
+
+/// Writes the snapshots of the crate at `root` with their sizes, followed by a summary.
+pub(crate) fn list<W: io::Write>(root: &Path, writer: &mut W) -> io::Result<i32> {
+    let snapshots: Vec<_> = find_snapshots(root)?
+        .into_iter()
+        .map(|snapshot| {
+            let len = snapshot_len(&root.join(&snapshot))?;
+            Ok((snapshot, len))
+        })
+        .collect::<io::Result<_>>()?;
+    let sizes: Vec<_> = snapshots
+        .iter()
+        .map(|(_, len)| len.map_or_else(|| "missing".to_owned(), |len| len.to_string()))
+        .collect();
+    let width = sizes.iter().map(String::len).max().unwrap_or(0);
+    for ((snapshot, _), size) in snapshots.iter().zip(&sizes) {
+        writeln!(writer, "{size:>width$}  {}", snapshot.display())?;
+    }
+    let total: usize = snapshots.iter().filter_map(|(_, len)| *len).sum();
+    let missing = snapshots.iter().filter(|(_, len)| len.is_none()).count();
+    writeln!(
+        writer,
+        "\n{} {}, {total} bytes, {missing} missing",
+        snapshots.len(),
+        if snapshots.len() == 1 {
+            "snapshot"
+        } else {
+            "snapshots"
+        }
+    )?;
+    Ok(0)
+}
+
+/// Returns the length of the snapshot at `path`, or the total length of the files of a group.
+fn snapshot_len(path: &Path) -> io::Result<Option<usize>> {
+    if !path.is_dir() {
+        return Ok(parts::read(path)?.map(|data| data.len()));
+    }
+    let mut len = 0;
+    for entry in path.read_dir()? {
+        len += snapshot_len(&entry?.path())?.unwrap_or(0);
+    }
+    Ok(Some(len))
+}
+
+/// Checks a snapshot against the standard output of a command.
+fn verify(args: &[OsString]) -> io::Result<i32> {
+    let (snapshot, command) = match args {
+        [snapshot, separator, _, ..] if separator == "--" => (snapshot, &args[2..]),
+        _ => {
+            eprint!("{USAGE}");
+            return Ok(2);
+        }
+    };
+    let output = Command::new(&command[0])
+        .args(&command[1..])
+        .stderr(std::process::Stdio::inherit())
+        .output()?;
+    let command_line = command
+        .iter()
+        .map(|arg| arg.to_string_lossy())
+        .collect::<Vec<_>>()
+        .join(" ");
+    if !output.status.success() {
+        eprintln!(
+            "{ERROR}error{ERROR:#}: `{command_line}` failed with {}",
+            output.status
+        );
+        return Ok(2);
+    }
+    let expect = ExpectFile::new(PathBuf::from(snapshot));
+    // The report is printed by the assertion, so the panic message would only repeat it
+    panic::set_hook(Box::new(|_| {}));
+    let result = panic::catch_unwind(AssertUnwindSafe(|| expect.assert_eq(&output.stdout)));
+    drop(panic::take_hook());
+    if result.is_err() {
+        return Ok(1);
+    }
+    writeln!(
+        io::stdout(),
+        "{SUCCESS}ok{SUCCESS:#}: {} matches the output of `{command_line}`",
+        expect.path.display()
+    )?;
+    Ok(0)
+}
+
+/// Runs `cargo test` in update mode, with the updated files summarized by each test process.
+fn bless(args: &[OsString]) -> io::Result<i32> {
+    let cargo = std::env::var_os("CARGO").unwrap_or_else(|| "cargo".into());
+    let status = Command::new(cargo)
+        .arg("test")
+        .args(args)
+        .env(UPDATE_EXPECT_VAR_NAME, "1")
+        .env(batch::BATCH_VAR_NAME, "1")
+        .status()?;
+    Ok(status.code().unwrap_or(1))
+}
@@ -0,0 +1,45 @@
+//! Binary serialization formats for serde values.
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+/// Binary serialization format used by [`ExpectFile::assert_decodes_eq`] and
+/// [`ExpectFile::assert_serialized_eq`].
+///
+/// [`ExpectFile::assert_decodes_eq`]: crate::ExpectFile::assert_decodes_eq
+/// [`ExpectFile::assert_serialized_eq`]: crate::ExpectFile::assert_serialized_eq
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Codec {
+    /// [bincode](https://docs.rs/bincode/1) 1.x with its default options.
+    Bincode,
+    /// [postcard](https://docs.rs/postcard/1) 1.x.
+    Postcard,
+}
+
+impl Codec {
+    fn name(self) -> &'static str {
+        match self {
+            Self::Bincode => "bincode",
+            Self::Postcard => "postcard",
+        }
+    }
+
+    /// Encodes `value`, describing the problem on failure.
+    pub(crate) fn encode<T: Serialize + ?Sized>(self, value: &T) -> Result<Vec<u8>, String> {
+        match self {
+            Self::Bincode => bincode::serialize(value).map_err(|e| e.to_string()),
+            Self::Postcard => postcard::to_allocvec(value).map_err(|e| e.to_string()),
+        }
+        .map_err(|e| format!("failed to encode with {}: {e}", self.name()))
+    }
+
+    /// Decodes `data`, describing the problem on failure.
+    pub(crate) fn decode<T: DeserializeOwned>(self, data: &[u8]) -> Result<T, String> {
+        match self {
+            Self::Bincode => bincode::deserialize(data).map_err(|e| e.to_string()),
+            Self::Postcard => postcard::from_bytes(data).map_err(|e| e.to_string()),
+        }
+        .map_err(|e| format!("failed to decode with {}: {e}", self.name()))
+    }
+}
@@ -0,0 +1,185 @@
+//! Logical comparison of columnar files by their schema and column values, shared by Arrow IPC
+//! and Parquet.
+
+use std::fmt;
+
+use crate::structure::Difference;
+
+/// A decoded value of a column.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum Value {
+    Null,
+    Bool(bool),
+    Int(i128),
+    /// The bits of an `f64`, so that equal NaNs compare equal.
+    Float(u64),
+    /// An integer scaled down by 10 to the power of `scale`.
+    Decimal {
+        value: i128,
+        scale: i32,
+    },
+    Text(String),
+    Bytes(Vec<u8>),
+}
+
+impl Value {
+    pub(crate) fn float(value: f64) -> Self {
+        Self::Float(value.to_bits())
+    }
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Null => f.write_str("null"),
+            Self::Bool(b) => write!(f, "{b}"),
+            Self::Int(i) => write!(f, "{i}"),
+            Self::Float(bits) => write!(f, "{:?}", f64::from_bits(*bits)),
+            Self::Decimal { value, scale } => {
+                let digits = value.unsigned_abs().to_string();
+                let sign = if *value < 0 { "-" } else { "" };
+                match usize::try_from(*scale) {
+                    Ok(0) => write!(f, "{value}"),
+                    Err(_) => write!(f, "{value}e{}", -scale),
+                    Ok(scale) => {
+                        let digits = format!("{digits:0>width$}", width = scale + 1);
+                        let (int, frac) = digits.split_at(digits.len() - scale);
+                        write!(f, "{sign}{int}.{frac}")
+                    }
+                }
+            }
+            Self::Text(s) => write!(f, "{s:?}"),
+            Self::Bytes(b) => {
+                f.write_str("0x")?;
+                for byte in b {
+                    write!(f, "{byte:02x}")?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+/// A column of the schema.
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) struct Column {
+    pub(crate) name: String,
+    /// The type of the column as written in reports, such as `INT64 (TIMESTAMP(MICROS))`.
+    pub(crate) data_type: String,
+    pub(crate) nullable: bool,
+}
+
+impl fmt::Display for Column {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "`{}`: {}", self.name, self.data_type)?;
+        if !self.nullable {
+            f.write_str(" not null")?;
+        }
+        Ok(())
+    }
+}
+
+/// The schema and values of a columnar file.
+#[derive(Debug)]
+pub(crate) struct Table {
+    pub(crate) columns: Vec<Column>,
+    /// The values of each column in each row group or record batch.
+    pub(crate) groups: Vec<Vec<Vec<Value>>>,
+}
+
+impl Table {
+    /// Returns the values of `column` across all groups.
+    fn values(&self, column: usize) -> impl Iterator<Item = &Value> {
+        self.groups.iter().flat_map(move |group| &group[column])
+    }
+
+    fn row_count(&self) -> usize {
+        self.groups
+            .iter()
+            .map(|group| group.first().map_or(0, Vec::len))
+            .sum()
+    }
+
+    /// Returns the group containing `row` and the row's index in it.
+    fn locate(&self, mut row: usize) -> (usize, usize) {
+        for (i, group) in self.groups.iter().enumerate() {
+            let len = group.first().map_or(0, Vec::len);
+            if row < len {
+                return (i, row);
+            }
+            row -= len;
+        }
+        (self.groups.len(), row)
+    }
+}
+
+/// Compares two tables by their schema and values, regardless of how the rows are split into
+/// groups.
+pub(crate) fn equal(expected: &Table, actual: &Table) -> bool {
+    expected.columns == actual.columns
+        && expected.row_count() == actual.row_count()
+        && (0..expected.columns.len()).all(|i| expected.values(i).eq(actual.values(i)))
+}
+
+/// Finds the first difference in the schema or values of two tables, naming the groups they're
+/// split into `group_kind`, such as "row group".
+pub(crate) fn first_difference(
+    expected: &Table,
+    actual: &Table,
+    group_kind: &str,
+) -> Option<Difference> {
+    let description =
+        first_schema_difference(&expected.columns, &actual.columns).or_else(|| {
+            (0..expected.columns.len())
+                .find_map(|i| {
+                    let row = expected
+                        .values(i)
+                        .zip(actual.values(i))
+                        .position(|(e, a)| e != a)?;
+                    let (group, group_row) = expected.locate(row);
+                    Some(format!(
+                    "column {} differs at row {row} (row {group_row} of {group_kind} {group}): \
+                     expected {}, found {}",
+                    expected.columns[i],
+                    expected.values(i).nth(row)?,
+                    actual.values(i).nth(row)?
+                ))
+                })
+                .or_else(|| {
+                    let (expected_rows, actual_rows) = (expected.row_count(), actual.row_count());
+                    (expected_rows != actual_rows).then(|| {
+                        format!("the file has {actual_rows} rows, expected {expected_rows}")
+                    })
+                })
+        })?;
+    Some(Difference {
+        description,
+        regions: None,
+    })
+}
+
+fn first_schema_difference(expected: &[Column], actual: &[Column]) -> Option<String> {
+    if expected.len() != actual.len() {
+        let list = |columns: &[Column]| {
+            columns
+                .iter()
+                .map(|column| format!("`{}`", column.name))
+                .collect::<Vec<_>>()
+                .join(", ")
+        };
+        return Some(format!(
+            "the schema differs: expected columns {}, found {}",
+            list(expected),
+            list(actual)
+        ));
+    }
+    let (i, (e, a)) = expected
+        .iter()
+        .zip(actual)
+        .enumerate()
+        .find(|(_, (e, a))| e != a)?;
+    Some(format!(
+        "column #{} of the schema differs: expected {e}, found {a}",
+        i + 1
+    ))
+}
@@ -0,0 +1,47 @@
+//! Pluggable equality for [`ExpectFile`](crate::ExpectFile).
+
+/// Outcome of comparing the expected and actual data.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ComparisonResult {
+    /// The data is considered equal and the assertion passes.
+    Equal,
+    /// The data differs and the assertion fails or updates the file.
+    Different,
+}
+
+impl From<bool> for ComparisonResult {
+    fn from(equal: bool) -> Self {
+        if equal {
+            Self::Equal
+        } else {
+            Self::Different
+        }
+    }
+}
+
+/// Decides whether the expected and actual data are equal.
+///
+/// Implemented for all `Fn(&[u8], &[u8]) -> ComparisonResult + Send + Sync` closures and functions
+/// where the first argument is the expected data and the second is the actual data.
+///
+/// ```
+/// use expect_test_bytes::{expect_file, ComparisonResult};
+///
+/// fn ignore_case(expected: &[u8], actual: &[u8]) -> ComparisonResult {
+///     expected.eq_ignore_ascii_case(actual).into()
+/// }
+///
+/// expect_file!["test_data/example"]
+///     .with_comparator(ignore_case)
+///     .assert_eq(b"EXAMPLE\n");
+/// ```
+pub trait Comparator: Send + Sync {
+    /// Compares `expected` to `actual`.
+    fn compare(&self, expected: &[u8], actual: &[u8]) -> ComparisonResult;
+}
+
+impl<F: Fn(&[u8], &[u8]) -> ComparisonResult + Send + Sync> Comparator for F {
+    fn compare(&self, expected: &[u8], actual: &[u8]) -> ComparisonResult {
+        self(expected, actual)
+    }
+}
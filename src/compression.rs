@@ -0,0 +1,61 @@
+//! Detection of compressed snapshots by their magic bytes.
+
+use std::io::{self, Read as _};
+use std::path::Path;
+
+use crate::{gzip, zstd};
+
+/// A compression format of snapshots.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Format {
+    Gzip,
+    Zstd,
+}
+
+impl Format {
+    pub(crate) fn name(self) -> &'static str {
+        match self {
+            Self::Gzip => "gzip",
+            Self::Zstd => "zstd",
+        }
+    }
+}
+
+/// Returns the compression format of `data` from its magic bytes.
+pub(crate) fn detect(data: &[u8]) -> Option<Format> {
+    if data.starts_with(&gzip::MAGIC) {
+        Some(Format::Gzip)
+    } else if data.starts_with(&zstd::MAGIC) {
+        Some(Format::Zstd)
+    } else {
+        None
+    }
+}
+
+/// Returns the compression format of the file at `path`, if it exists and is compressed.
+pub(crate) fn detect_file(path: &Path) -> Option<Format> {
+    let mut magic = Vec::with_capacity(4);
+    std::fs::File::open(path)
+        .and_then(|file| file.take(4).read_to_end(&mut magic))
+        .ok()?;
+    detect(&magic)
+}
+
+/// Decompresses `data` in `format`.
+pub(crate) fn decompress(format: Format, data: &[u8]) -> io::Result<Vec<u8>> {
+    match format {
+        Format::Gzip => gzip::decompress(data),
+        Format::Zstd => zstd::decompress(data),
+    }
+    .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
+
+/// Compresses `data` in `format`, without actually making it smaller.
+///
+/// This keeps updated snapshots readable by the usual tools of the format.
+pub(crate) fn compress(format: Format, data: &[u8]) -> Vec<u8> {
+    match format {
+        Format::Gzip => gzip::compress_stored(data),
+        Format::Zstd => zstd::compress_raw(data),
+    }
+}
@@ -0,0 +1,227 @@
+//! Snapshot container format holding multiple named blobs.
+//!
+//! Layout, with all integers little-endian:
+//!
+//! ```text
+//! magic            b"ETBC"
+//! version          u16
+//! header length    u32, length of the rest of the header
+//! tool             u32 length + UTF-8, e.g. "expect-test-bytes 0.1.0"
+//! ...              header fields added by newer minor revisions, skipped by older readers
+//! entry count      u32
+//! entries          u32 name length + UTF-8 name, u64 data length + data
+//! ```
+
+use std::{error, fmt};
+
+const MAGIC: &[u8; 4] = b"ETBC";
+/// Version written by this crate. Readers reject containers with a different version.
+const VERSION: u16 = 1;
+const TOOL: &str = concat!("expect-test-bytes ", env!("CARGO_PKG_VERSION"));
+
+/// Multiple named blobs stored in one snapshot file.
+///
+/// ```
+/// use expect_test_bytes::Container;
+///
+/// let container = Container::new()
+///     .with_entry("header", b"\x7fELF".to_vec())
+///     .with_entry("body", b"...".to_vec());
+/// let bytes = container.to_bytes();
+/// assert_eq!(Container::parse(&bytes).unwrap(), container);
+/// ```
+///
+/// Containers are equal if their entries are equal, regardless of the tool that wrote them.
+#[derive(Debug, Clone, Default)]
+pub struct Container {
+    entries: Vec<(String, Vec<u8>)>,
+    tool: Option<String>,
+}
+
+impl PartialEq for Container {
+    fn eq(&self, other: &Self) -> bool {
+        self.entries == other.entries
+    }
+}
+
+impl Eq for Container {}
+
+/// Error returned by [`Container::parse`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ContainerError {
+    /// The data doesn't start with the container magic bytes.
+    BadMagic,
+    /// The container was written with an unsupported format version.
+    UnsupportedVersion(u16),
+    /// The data ends in the middle of the container.
+    Truncated,
+    /// An entry name or the tool name isn't valid UTF-8.
+    InvalidName,
+}
+
+impl fmt::Display for ContainerError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::BadMagic => write!(f, "not a snapshot container"),
+            Self::UnsupportedVersion(version) => write!(
+                f,
+                "unsupported snapshot container version {version}, expected {VERSION}; \
+                 the file may have been written by a newer version of expect-test-bytes"
+            ),
+            Self::Truncated => write!(f, "truncated snapshot container"),
+            Self::InvalidName => write!(f, "invalid UTF-8 in snapshot container"),
+        }
+    }
+}
+
+impl error::Error for ContainerError {}
+
+struct Reader<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, n: usize) -> Result<&'a [u8], ContainerError> {
+        if self.data.len() < n {
+            return Err(ContainerError::Truncated);
+        }
+        let (taken, rest) = self.data.split_at(n);
+        self.data = rest;
+        Ok(taken)
+    }
+
+    fn u16(&mut self) -> Result<u16, ContainerError> {
+        let bytes = self.take(2)?;
+        Ok(u16::from_le_bytes([bytes[0], bytes[1]]))
+    }
+
+    fn u32(&mut self) -> Result<u32, ContainerError> {
+        let bytes = self.take(4)?;
+        Ok(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    }
+
+    fn u64(&mut self) -> Result<u64, ContainerError> {
+        let mut bytes = [0; 8];
+        bytes.copy_from_slice(self.take(8)?);
+        Ok(u64::from_le_bytes(bytes))
+    }
+
+    fn len_u32(&mut self) -> Result<usize, ContainerError> {
+        usize::try_from(self.u32()?).map_err(|_| ContainerError::Truncated)
+    }
+
+    fn string(&mut self) -> Result<String, ContainerError> {
+        let len = self.len_u32()?;
+        let bytes = self.take(len)?;
+        String::from_utf8(bytes.to_vec()).map_err(|_| ContainerError::InvalidName)
+    }
+}
+
+fn push_len_u32(out: &mut Vec<u8>, len: usize) {
+    out.extend_from_slice(
+        &u32::try_from(len)
+            .expect("container field too long")
+            .to_le_bytes(),
+    );
+}
+
+impl Container {
+    /// Creates an empty container.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Appends an entry.
+    #[must_use]
+    pub fn with_entry(mut self, name: impl Into<String>, data: impl Into<Vec<u8>>) -> Self {
+        self.push(name, data);
+        self
+    }
+
+    /// Appends an entry.
+    pub fn push(&mut self, name: impl Into<String>, data: impl Into<Vec<u8>>) {
+        self.entries.push((name.into(), data.into()));
+    }
+
+    /// Returns the entries in order.
+    pub fn entries(&self) -> impl Iterator<Item = (&str, &[u8])> {
+        self.entries
+            .iter()
+            .map(|(name, data)| (name.as_str(), data.as_slice()))
+    }
+
+    /// Returns the data of the first entry named `name`.
+    #[must_use]
+    pub fn get(&self, name: &str) -> Option<&[u8]> {
+        self.entries()
+            .find(|(entry_name, _)| *entry_name == name)
+            .map(|(_, data)| data)
+    }
+
+    /// Returns the name and version of the tool that wrote a parsed container.
+    #[must_use]
+    pub fn tool(&self) -> Option<&str> {
+        self.tool.as_deref()
+    }
+
+    /// Serializes the container.
+    ///
+    /// # Panics
+    ///
+    /// Will panic if an entry name is longer than `u32::MAX` bytes.
+    #[must_use]
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut header = Vec::new();
+        push_len_u32(&mut header, TOOL.len());
+        header.extend_from_slice(TOOL.as_bytes());
+
+        let mut out = MAGIC.to_vec();
+        out.extend_from_slice(&VERSION.to_le_bytes());
+        push_len_u32(&mut out, header.len());
+        out.extend_from_slice(&header);
+        push_len_u32(&mut out, self.entries.len());
+        for (name, data) in &self.entries {
+            push_len_u32(&mut out, name.len());
+            out.extend_from_slice(name.as_bytes());
+            out.extend_from_slice(&(data.len() as u64).to_le_bytes());
+            out.extend_from_slice(data);
+        }
+        out
+    }
+
+    /// Parses a serialized container.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `data` isn't a container, uses an unsupported version or is truncated.
+    pub fn parse(data: &[u8]) -> Result<Self, ContainerError> {
+        let mut reader = Reader { data };
+        if reader.take(4).map_err(|_| ContainerError::BadMagic)? != MAGIC {
+            return Err(ContainerError::BadMagic);
+        }
+        let version = reader.u16()?;
+        if version != VERSION {
+            return Err(ContainerError::UnsupportedVersion(version));
+        }
+
+        let header_len = reader.len_u32()?;
+        let mut header = Reader {
+            data: reader.take(header_len)?,
+        };
+        let tool = header.string()?;
+
+        let count = reader.len_u32()?;
+        let mut entries = Vec::with_capacity(count.min(1024));
+        for _ in 0..count {
+            let name = reader.string()?;
+            let len = usize::try_from(reader.u64()?).map_err(|_| ContainerError::Truncated)?;
+            entries.push((name, reader.take(len)?.to_vec()));
+        }
+        Ok(Self {
+            entries,
+            tool: Some(tool),
+        })
+    }
+}
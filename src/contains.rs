@@ -0,0 +1,44 @@
+//! Substring search for containment assertions.
+
+/// Finds the first offset where `needle` occurs in `haystack`.
+pub(crate) fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    if needle.is_empty() {
+        return Some(0);
+    }
+    haystack
+        .windows(needle.len())
+        .position(|window| window == needle)
+}
+
+/// Finds the longest prefix of `needle` that occurs in `haystack`.
+///
+/// Returns the offset of the match in `haystack` and its length. The first of equally long
+/// matches is returned.
+pub(crate) fn longest_partial_match(haystack: &[u8], needle: &[u8]) -> (usize, usize) {
+    let mut best = (0, 0);
+    for offset in 0..haystack.len() {
+        let len = haystack[offset..]
+            .iter()
+            .zip(needle)
+            .take_while(|(a, b)| a == b)
+            .count();
+        if len > best.1 {
+            best = (offset, len);
+        }
+    }
+    best
+}
+
+/// Finds the occurrence of `needle` in `haystack` closest to `offset`.
+pub(crate) fn find_nearest(haystack: &[u8], needle: &[u8], offset: usize) -> Option<usize> {
+    if needle.is_empty() {
+        return None;
+    }
+    let last = haystack.len().checked_sub(needle.len())?;
+    let is_match = |i: usize| i <= last && haystack[i..i + needle.len()] == *needle;
+    // Search outwards to stop at the nearest occurrence, preferring the earlier one on ties
+    (0..=offset.max(last)).find_map(|distance| {
+        let before = offset.checked_sub(distance).filter(|&i| is_match(i));
+        before.or_else(|| Some(offset + distance).filter(|&i| is_match(i)))
+    })
+}
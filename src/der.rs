@@ -0,0 +1,205 @@
+//! Element-by-element comparison of ASN.1 DER data, such as certificates and keys.
+
+use std::fmt::Write as _;
+
+use crate::structure::Difference;
+
+const CONSTRUCTED: u8 = 0x20;
+const OBJECT_IDENTIFIER: u32 = 6;
+
+/// A tag-length-value element.
+struct Element<'a> {
+    /// The class bits and the constructed bit of the identifier.
+    class: u8,
+    number: u32,
+    contents: &'a [u8],
+    children: Vec<Element<'a>>,
+}
+
+impl Element<'_> {
+    fn is_constructed(&self) -> bool {
+        self.class & CONSTRUCTED != 0
+    }
+
+    fn is_universal(&self) -> bool {
+        self.class & 0xc0 == 0
+    }
+
+    fn name(&self) -> String {
+        let universal = match self.number {
+            1 => "BOOLEAN",
+            2 => "INTEGER",
+            3 => "BIT STRING",
+            4 => "OCTET STRING",
+            5 => "NULL",
+            OBJECT_IDENTIFIER => "OBJECT IDENTIFIER",
+            10 => "ENUMERATED",
+            12 => "UTF8String",
+            16 => "SEQUENCE",
+            17 => "SET",
+            19 => "PrintableString",
+            20 => "T61String",
+            22 => "IA5String",
+            23 => "UTCTime",
+            24 => "GeneralizedTime",
+            30 => "BMPString",
+            _ => "",
+        };
+        match self.class & 0xc0 {
+            0 if !universal.is_empty() => universal.to_owned(),
+            0 => format!("UNIVERSAL({})", self.number),
+            0x40 => format!("APPLICATION({})", self.number),
+            0x80 => format!("CONTEXT({})", self.number),
+            _ => format!("PRIVATE({})", self.number),
+        }
+    }
+}
+
+/// Parses the elements in `data`, or returns `None` if it isn't a sequence of DER elements.
+fn parse_elements(mut data: &[u8]) -> Option<Vec<Element<'_>>> {
+    let mut elements = Vec::new();
+    while !data.is_empty() {
+        let identifier = data[0];
+        let mut pos = 1;
+        let number = if identifier & 0x1f == 0x1f {
+            // High tag number in base 128
+            let mut number = 0_u32;
+            loop {
+                let b = *data.get(pos)?;
+                pos += 1;
+                number = number.checked_mul(128)? | u32::from(b & 0x7f);
+                if b & 0x80 == 0 {
+                    break number;
+                }
+            }
+        } else {
+            u32::from(identifier & 0x1f)
+        };
+        let first_len = *data.get(pos)?;
+        pos += 1;
+        let len = match first_len {
+            0..=0x7f => usize::from(first_len),
+            // Indefinite lengths aren't allowed in DER
+            0x80 => return None,
+            _ => {
+                let len_len = usize::from(first_len & 0x7f);
+                let len_bytes = data.get(pos..pos.checked_add(len_len)?)?;
+                pos += len_len;
+                len_bytes.iter().try_fold(0_usize, |len, &b| {
+                    len.checked_mul(256)?.checked_add(usize::from(b))
+                })?
+            }
+        };
+        let contents = data.get(pos..pos.checked_add(len)?)?;
+        let class = identifier & 0xe0;
+        let children = if class & CONSTRUCTED == 0 {
+            Vec::new()
+        } else {
+            parse_elements(contents)?
+        };
+        elements.push(Element {
+            class,
+            number,
+            contents,
+            children,
+        });
+        data = &data[pos + len..];
+    }
+    Some(elements)
+}
+
+/// Formats the contents of an OBJECT IDENTIFIER in dotted notation.
+fn format_oid(contents: &[u8]) -> Option<String> {
+    let mut arcs = Vec::new();
+    let mut arc = 0_u64;
+    for &b in contents {
+        arc = arc.checked_mul(128)? | u64::from(b & 0x7f);
+        if b & 0x80 == 0 {
+            arcs.push(arc);
+            arc = 0;
+        }
+    }
+    let (&first, rest) = arcs.split_first()?;
+    let mut out = match first {
+        0..=39 => format!("0.{first}"),
+        40..=79 => format!("1.{}", first - 40),
+        _ => format!("2.{}", first - 80),
+    };
+    for arc in rest {
+        write!(out, ".{arc}").unwrap();
+    }
+    Some(out)
+}
+
+fn first_difference_in(
+    parent: &str,
+    expected: &[Element<'_>],
+    actual: &[Element<'_>],
+) -> Option<Difference> {
+    let container = if parent.is_empty() {
+        "the data".to_owned()
+    } else {
+        format!("`{parent}`")
+    };
+    for (i, (e, a)) in expected.iter().zip(actual).enumerate() {
+        let (expected_name, actual_name) = (e.name(), a.name());
+        if (e.class, e.number) != (a.class, a.number) {
+            let element = if parent.is_empty() {
+                format!("top-level element {i}")
+            } else {
+                format!("`{parent}[{i}]`")
+            };
+            return Some(Difference {
+                description: format!(
+                    "{element} has a different tag: expected {expected_name}, found {actual_name}"
+                ),
+                regions: None,
+            });
+        }
+        let path = if parent.is_empty() {
+            expected_name
+        } else {
+            format!("{parent}[{i}].{expected_name}")
+        };
+        if e.contents == a.contents {
+            continue;
+        }
+        if e.is_constructed() {
+            if let Some(difference) = first_difference_in(&path, &e.children, &a.children) {
+                return Some(difference);
+            }
+        }
+        if e.is_universal() && e.number == OBJECT_IDENTIFIER {
+            if let (Some(expected_oid), Some(actual_oid)) =
+                (format_oid(e.contents), format_oid(a.contents))
+            {
+                return Some(Difference {
+                    description: format!(
+                        "`{path}` differs: expected {expected_oid}, found {actual_oid}"
+                    ),
+                    regions: None,
+                });
+            }
+        }
+        let diff_idx = crate::first_diff_index(e.contents, a.contents).unwrap_or(0);
+        return Some(Difference {
+            description: format!("`{path}` differs at byte {diff_idx:#x} of its contents"),
+            regions: Some((e.contents.to_vec(), a.contents.to_vec())),
+        });
+    }
+    (expected.len() != actual.len()).then(|| Difference {
+        description: format!(
+            "{container} has {} elements, expected {}",
+            actual.len(),
+            expected.len()
+        ),
+        regions: None,
+    })
+}
+
+/// Finds the path of the first differing element of two DER encodings, or returns `None` if
+/// either can't be parsed.
+pub(crate) fn first_difference(expected: &[u8], actual: &[u8]) -> Option<Difference> {
+    let (expected, actual) = (parse_elements(expected)?, parse_elements(actual)?);
+    first_difference_in("", &expected, &actual)
+}
@@ -0,0 +1,50 @@
+//! Snapshots embedded into the test binary at compile time.
+
+use std::io;
+
+use crate::{ExpectFile, Part};
+
+/// A snapshot file whose contents were embedded at compile time, created by
+/// [`expect_bytes_file!`](crate::expect_bytes_file).
+///
+/// The assertion doesn't read the file, so the test binary can run without the source tree, such
+/// as on a device. In update mode, the file is rewritten and the next build embeds the new
+/// contents.
+#[derive(Debug)]
+pub struct EmbeddedFile {
+    #[doc(hidden)]
+    pub data: &'static [u8],
+    #[doc(hidden)]
+    pub file: ExpectFile,
+}
+
+impl EmbeddedFile {
+    /// Checks whether the embedded contents are equal to `actual`.
+    ///
+    /// # Panics
+    ///
+    /// Will panic when the contents don't equal `actual` and `UPDATE_EXPECT` is not set or if
+    /// writing to stdout or updating the file fails.
+    #[track_caller]
+    pub fn assert_eq(&self, actual: &[u8]) {
+        self.file
+            .run(|writer| self.assert_eq_nopanic_imp(actual, writer));
+    }
+
+    pub(crate) fn assert_eq_nopanic_imp<W: io::Write>(
+        &self,
+        actual: &[u8],
+        writer: &mut W,
+    ) -> Result<(), ()> {
+        if self.data == actual {
+            return Ok(());
+        }
+        if self.file.updates() {
+            return self.file.update(Some(self.data), actual, writer);
+        }
+        self.file.write_failure_header(writer, true);
+        self.file
+            .write_diff(self.data, actual, None, Part::Whole, writer);
+        Err(())
+    }
+}
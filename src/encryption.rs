@@ -0,0 +1,61 @@
+//! Snapshots stored encrypted with AES-256-GCM, for fixtures that can't be committed in the clear.
+//!
+//! Files are the 12-byte nonce followed by the ciphertext and tag. The nonce is derived from the
+//! key and the contents, so updating a file with unchanged contents doesn't change it, at the
+//! cost of revealing whether two files have the same contents.
+
+use aes_gcm::aead::{Aead, KeyInit};
+use aes_gcm::{Aes256Gcm, Key, Nonce};
+
+use crate::sha256;
+
+/// Key as 64 hexadecimal digits.
+pub(crate) const KEY_VAR_NAME: &str = "EXPECT_BYTES_KEY";
+
+const NONCE_LEN: usize = 12;
+
+/// Returns the cipher with the key from the environment, describing the problem on failure.
+fn cipher() -> Result<(Aes256Gcm, [u8; 32]), String> {
+    let hex = std::env::var(KEY_VAR_NAME)
+        .map_err(|_| format!("{KEY_VAR_NAME} must be set to the key of encrypted files"))?;
+    let key = parse_key(hex.trim())
+        .ok_or_else(|| format!("{KEY_VAR_NAME} must be 64 hexadecimal digits, a 256-bit key"))?;
+    Ok((Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(&key)), key))
+}
+
+fn parse_key(hex: &str) -> Option<[u8; 32]> {
+    if hex.len() != 64 || !hex.is_ascii() {
+        return None;
+    }
+    let mut key = [0; 32];
+    for (byte, digits) in key.iter_mut().zip(hex.as_bytes().chunks(2)) {
+        *byte = u8::from_str_radix(std::str::from_utf8(digits).ok()?, 16).ok()?;
+    }
+    Some(key)
+}
+
+/// Encrypts `plaintext`, describing the problem on failure.
+pub(crate) fn encrypt(plaintext: &[u8]) -> Result<Vec<u8>, String> {
+    let (cipher, key) = cipher()?;
+    let mut keyed = key.to_vec();
+    keyed.extend_from_slice(plaintext);
+    let nonce = &sha256::sha256(&keyed)[..NONCE_LEN];
+    let ciphertext = cipher
+        .encrypt(Nonce::from_slice(nonce), plaintext)
+        .map_err(|_| "failed to encrypt".to_owned())?;
+    let mut data = nonce.to_vec();
+    data.extend_from_slice(&ciphertext);
+    Ok(data)
+}
+
+/// Decrypts `data`, describing the problem on failure.
+pub(crate) fn decrypt(data: &[u8]) -> Result<Vec<u8>, String> {
+    let (cipher, _) = cipher()?;
+    if data.len() < NONCE_LEN {
+        return Err("the file is too short to be encrypted".to_owned());
+    }
+    let (nonce, ciphertext) = data.split_at(NONCE_LEN);
+    cipher
+        .decrypt(Nonce::from_slice(nonce), ciphertext)
+        .map_err(|_| format!("failed to decrypt, check that {KEY_VAR_NAME} is the right key"))
+}
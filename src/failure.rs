@@ -0,0 +1,84 @@
+//! Details of the failure of the current assertion, for the summaries of failures.
+
+use std::cell::Cell;
+use std::io;
+
+/// When set, a single greppable line is printed after the report of each failure.
+pub(crate) const FAIL_LINE_VAR_NAME: &str = "EXPECT_BYTES_FAIL_LINE";
+
+/// Details recorded while the report of the current assertion is written.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Details {
+    pub(crate) first_diff: Option<usize>,
+    pub(crate) expected_len: Option<usize>,
+    pub(crate) actual_len: Option<usize>,
+}
+
+thread_local! {
+    static CURRENT: Cell<Details> = const {
+        Cell::new(Details {
+            first_diff: None,
+            expected_len: None,
+            actual_len: None,
+        })
+    };
+}
+
+/// Records `diff_idx` as the first differing offset, unless one was already recorded.
+pub(crate) fn record_diff(diff_idx: usize) {
+    CURRENT.with(|current| {
+        let mut details = current.get();
+        details.first_diff = details.first_diff.or(Some(diff_idx));
+        current.set(details);
+    });
+}
+
+/// Records the lengths of the file, if it exists, and of the actual data.
+pub(crate) fn record_lens(expected_len: Option<usize>, actual_len: usize) {
+    CURRENT.with(|current| {
+        let mut details = current.get();
+        details.expected_len = expected_len;
+        details.actual_len = Some(actual_len);
+        current.set(details);
+    });
+}
+
+/// Returns the first differing offset recorded by the current assertion.
+pub(crate) fn first_diff() -> Option<usize> {
+    CURRENT.with(|current| current.get().first_diff)
+}
+
+/// Takes the details recorded by the current assertion.
+pub(crate) fn take() -> Details {
+    CURRENT.with(Cell::take)
+}
+
+pub(crate) fn fail_line_from_env() -> bool {
+    std::env::var_os(FAIL_LINE_VAR_NAME).is_some()
+}
+
+/// Writes the single-line summary of the failure at `location`, e.g.
+/// `EXPECT-FAIL path=test_data/frame.bin offset=0x1c expected_len=64 actual_len=66`.
+///
+/// Unknown details are left out, and paths containing whitespace or quotes are quoted.
+pub(crate) fn write_line<W: io::Write>(
+    writer: &mut W,
+    location: &str,
+    details: Details,
+) -> io::Result<()> {
+    if location.contains(|c: char| c.is_whitespace() || c == '"') {
+        write!(writer, "EXPECT-FAIL path={location:?}")?;
+    } else {
+        write!(writer, "EXPECT-FAIL path={location}")?;
+    }
+    if let Some(first_diff) = details.first_diff {
+        write!(writer, " offset={first_diff:#x}")?;
+    }
+    if let Some(expected_len) = details.expected_len {
+        write!(writer, " expected_len={expected_len}")?;
+    }
+    if let Some(actual_len) = details.actual_len {
+        write!(writer, " actual_len={actual_len}")?;
+    }
+    writeln!(writer)
+}
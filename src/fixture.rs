@@ -0,0 +1,89 @@
+//! Regions of large shared fixtures, such as disk images.
+
+use std::io;
+use std::path::PathBuf;
+
+use crate::{parts, resolve_root, ExpectFile};
+
+/// A region of a large raw image checked into the repository, shared by many tests instead of
+/// carving out small copies.
+///
+/// A fixture can be read as the input of a test, or converted into an [`ExpectFile`] that checks
+/// only its window, like [`ExpectFile::slice`].
+///
+/// ```no_run
+/// use expect_test_bytes::{ExpectFile, Fixture};
+///
+/// # fn read_block(_: &[u8]) -> Vec<u8> { Vec::new() }
+/// let superblock = Fixture::new("src/test_data/disk.img").window(0x10000, 4096);
+/// let image = Fixture::new("src/test_data/disk.img").read().unwrap();
+/// ExpectFile::from(superblock).assert_eq(&read_block(&image));
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Fixture {
+    path: PathBuf,
+    window: Option<(usize, usize)>,
+}
+
+impl Fixture {
+    /// Creates a fixture for the whole file at `path`.
+    ///
+    /// Like with [`expect_file!`](crate::expect_file), relative paths are resolved against the
+    /// `EXPECT_BYTES_ROOT` environment variable if it's set, but otherwise against the working
+    /// directory, which is the package root for tests.
+    #[must_use]
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: resolve_root(path.into()),
+            window: None,
+        }
+    }
+
+    /// Limits the fixture to the `len` bytes at `offset` of the file.
+    #[must_use]
+    pub fn window(mut self, offset: usize, len: usize) -> Self {
+        self.window = Some((offset, len));
+        self
+    }
+
+    /// Reads the window of the file, or all of it.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if reading the file fails or the window extends past its end.
+    pub fn read(&self) -> io::Result<Vec<u8>> {
+        let not_found = || {
+            io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("fixture {} not found", self.path.display()),
+            )
+        };
+        let (offset, len) = match self.window {
+            Some(window) => window,
+            None => return parts::read(&self.path)?.ok_or_else(not_found),
+        };
+        let data = parts::read_window(&self.path, offset, len)?.ok_or_else(not_found)?;
+        if data.len() < len {
+            return Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                format!(
+                    "window {offset:#x}..{:#x} extends past the end of fixture {}",
+                    offset.saturating_add(len),
+                    self.path.display()
+                ),
+            ));
+        }
+        Ok(data)
+    }
+}
+
+impl From<Fixture> for ExpectFile {
+    /// Checks the window of the fixture, or the whole file.
+    fn from(fixture: Fixture) -> Self {
+        let file = Self::at_resolved_path(fixture.path);
+        match fixture.window {
+            Some((offset, len)) => file.slice(offset, len),
+            None => file,
+        }
+    }
+}
@@ -0,0 +1,202 @@
+//! Float fields compared with a tolerance, for [`ExpectFile::float_field`].
+//!
+//! [`ExpectFile::float_field`]: crate::ExpectFile::float_field
+
+use std::borrow::Cow;
+use std::fmt;
+use std::io;
+
+use crate::style::NOTE;
+
+/// Width of a float field.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Width {
+    F32,
+    F64,
+}
+
+impl Width {
+    fn len(self) -> usize {
+        match self {
+            Self::F32 => 4,
+            Self::F64 => 8,
+        }
+    }
+}
+
+impl fmt::Display for Width {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::F32 => "f32",
+            Self::F64 => "f64",
+        })
+    }
+}
+
+/// An IEEE 754 float stored in the data, or a run of them, compared with a tolerance while the
+/// rest of the data is compared byte for byte.
+///
+/// ```
+/// use expect_test_bytes::FloatField;
+///
+/// // The translations of 16 little-endian transforms of 64 bytes each, after an 8-byte header
+/// let field = FloatField::f32(8 + 48, 1e-5).repeated(64, 3 * 16);
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct FloatField {
+    width: Width,
+    offset: usize,
+    epsilon: f64,
+    stride: usize,
+    count: usize,
+    big_endian: bool,
+}
+
+impl FloatField {
+    /// Creates a little-endian `f32` field at `offset` whose values may differ by at most
+    /// `epsilon`.
+    #[must_use]
+    pub fn f32(offset: usize, epsilon: f64) -> Self {
+        Self::new(Width::F32, offset, epsilon)
+    }
+
+    /// Creates a little-endian `f64` field at `offset` whose values may differ by at most
+    /// `epsilon`.
+    #[must_use]
+    pub fn f64(offset: usize, epsilon: f64) -> Self {
+        Self::new(Width::F64, offset, epsilon)
+    }
+
+    fn new(width: Width, offset: usize, epsilon: f64) -> Self {
+        Self {
+            width,
+            offset,
+            epsilon,
+            stride: width.len(),
+            count: 1,
+            big_endian: false,
+        }
+    }
+
+    /// Repeats the field `count` times, `stride` bytes apart.
+    ///
+    /// Occurrences past the end of the data are ignored, so `usize::MAX` can be used as the count
+    /// for fields repeated until the end.
+    ///
+    /// # Panics
+    ///
+    /// Will panic if `stride` is shorter than the field, which would make occurrences overlap.
+    #[must_use]
+    pub fn repeated(mut self, stride: usize, count: usize) -> Self {
+        assert!(
+            stride >= self.width.len(),
+            "the stride of a float field must be at least its length"
+        );
+        self.stride = stride;
+        self.count = count;
+        self
+    }
+
+    /// Makes the field stored in big-endian byte order.
+    #[must_use]
+    pub fn big_endian(mut self) -> Self {
+        self.big_endian = true;
+        self
+    }
+
+    /// Returns the offsets of the occurrences within the first `len` bytes.
+    fn offsets(&self, len: usize) -> impl Iterator<Item = usize> + '_ {
+        (0..self.count).map_while(move |i| {
+            let offset = self.offset.checked_add(i.checked_mul(self.stride)?)?;
+            (offset.checked_add(self.width.len())? <= len).then(|| offset)
+        })
+    }
+
+    /// Returns the bytes of the occurrence at `offset` in little-endian byte order.
+    fn le_bytes(&self, data: &[u8], offset: usize) -> [u8; 8] {
+        let len = self.width.len();
+        let mut bytes = [0; 8];
+        bytes[..len].copy_from_slice(&data[offset..offset + len]);
+        if self.big_endian {
+            bytes[..len].reverse();
+        }
+        bytes
+    }
+
+    /// Decodes the occurrence at `offset`.
+    fn value(&self, data: &[u8], offset: usize) -> f64 {
+        let bytes = self.le_bytes(data, offset);
+        match self.width {
+            Width::F32 => f64::from(f32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]])),
+            Width::F64 => f64::from_le_bytes(bytes),
+        }
+    }
+
+    /// Formats the occurrence at `offset` with the precision of its width.
+    fn describe(&self, data: &[u8], offset: usize) -> String {
+        let bytes = self.le_bytes(data, offset);
+        match self.width {
+            Width::F32 => f32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]).to_string(),
+            Width::F64 => f64::from_le_bytes(bytes).to_string(),
+        }
+    }
+
+    /// Returns whether the occurrences at `offset` are within the tolerance of each other.
+    fn tolerates(&self, expected: &[u8], actual: &[u8], offset: usize) -> bool {
+        let range = offset..offset + self.width.len();
+        expected[range.clone()] == actual[range]
+            || (self.value(expected, offset) - self.value(actual, offset)).abs() <= self.epsilon
+    }
+}
+
+/// Replaces the occurrences of `fields` in `actual` that are within their tolerance with the
+/// bytes of `expected`, so that a byte for byte comparison only finds the other differences.
+pub(crate) fn tolerate<'a>(
+    fields: &[FloatField],
+    expected: &[u8],
+    actual: &'a [u8],
+) -> Cow<'a, [u8]> {
+    let mut actual = Cow::Borrowed(actual);
+    let len = expected.len().min(actual.len());
+    for field in fields {
+        for offset in field.offsets(len) {
+            if field.tolerates(expected, &actual, offset) {
+                let range = offset..offset + field.width.len();
+                if expected[range.clone()] != actual[range.clone()] {
+                    actual.to_mut()[range.clone()].copy_from_slice(&expected[range]);
+                }
+            }
+        }
+    }
+    actual
+}
+
+/// Writes a note with the values of the float field containing `diff_idx`, if there is one.
+pub(crate) fn write_note<W: io::Write>(
+    writer: &mut W,
+    fields: &[FloatField],
+    expected: &[u8],
+    actual: &[u8],
+    diff_idx: usize,
+) -> io::Result<()> {
+    let len = expected.len().min(actual.len());
+    for field in fields {
+        let offset = match field
+            .offsets(len)
+            .find(|&offset| (offset..offset + field.width.len()).contains(&diff_idx))
+        {
+            Some(offset) => offset,
+            None => continue,
+        };
+        return writeln!(
+            writer,
+            "{NOTE}note{NOTE:#}: the {} at {offset:#x} is {}, expected {}, \
+             outside the tolerance of {}",
+            field.width,
+            field.describe(actual, offset),
+            field.describe(expected, offset),
+            field.epsilon
+        );
+    }
+    Ok(())
+}
@@ -0,0 +1,46 @@
+//! Previous contents of updated snapshots, kept next to them as numbered generations named like
+//! `cp --backup=numbered` names them: `name.~1~` for the most recent one, then `name.~2~` and so
+//! on.
+
+use std::ffi::OsString;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::not_found_to_none;
+
+/// Number of previous generations kept when snapshots are updated.
+pub(crate) const KEEP_GENERATIONS_VAR_NAME: &str = "EXPECT_BYTES_KEEP_GENERATIONS";
+
+pub(crate) fn keep_from_env() -> usize {
+    std::env::var(KEEP_GENERATIONS_VAR_NAME)
+        .ok()
+        .and_then(|value| value.parse().ok())
+        .unwrap_or(0)
+}
+
+/// Returns the path of generation `n` of the snapshot at `path`.
+pub(crate) fn generation_path(path: &Path, n: usize) -> PathBuf {
+    let mut os: OsString = path.as_os_str().to_owned();
+    os.push(format!(".~{n}~"));
+    os.into()
+}
+
+/// Keeps `previous`, the contents being replaced, as generation 1 of the snapshot at `path`.
+///
+/// Older generations move up by one, and the ones past `keep` are removed.
+pub(crate) fn rotate(path: &Path, previous: &[u8], keep: usize) -> io::Result<()> {
+    // Generations past `keep` may remain from a larger setting
+    for n in keep.. {
+        if not_found_to_none(fs::remove_file(generation_path(path, n)))?.is_none() && n > keep {
+            break;
+        }
+    }
+    for n in (1..keep).rev() {
+        not_found_to_none(fs::rename(
+            generation_path(path, n),
+            generation_path(path, n + 1),
+        ))?;
+    }
+    fs::write(generation_path(path, 1), previous)
+}
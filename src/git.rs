@@ -0,0 +1,97 @@
+//! Checks of the git status of snapshot files, so that updated snapshots aren't left out of a
+//! commit.
+
+use std::io;
+use std::path::Path;
+use std::process::Command;
+
+use crate::style::NOTE;
+
+/// When set, assertions fail if their file isn't committed.
+pub(crate) const REQUIRE_CLEAN_VAR_NAME: &str = "EXPECT_BYTES_REQUIRE_CLEAN";
+
+pub(crate) fn require_clean_from_env() -> bool {
+    std::env::var_os(REQUIRE_CLEAN_VAR_NAME).is_some()
+}
+
+/// A file that differs from the last commit.
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) struct Uncommitted {
+    /// Path relative to the root of the repository.
+    pub(crate) path: String,
+    /// Whether git doesn't track the file at all.
+    pub(crate) untracked: bool,
+    /// Whether the changes are staged, so that they only need to be committed.
+    pub(crate) staged: bool,
+}
+
+/// Returns the files of `paths` that differ from the last commit, or `None` if they aren't in a
+/// git repository or git can't be run.
+pub(crate) fn uncommitted<'a>(
+    paths: impl IntoIterator<Item = &'a Path>,
+) -> Option<Vec<Uncommitted>> {
+    let output = Command::new("git")
+        .args([
+            "status",
+            "--porcelain=v1",
+            "-z",
+            "--untracked-files=all",
+            "--",
+        ])
+        .args(paths)
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let mut entries = stdout.split('\0');
+    let mut uncommitted = Vec::new();
+    while let Some(entry) = entries.next() {
+        if entry.len() < 4 {
+            continue;
+        }
+        let (status, path) = entry.split_at(3);
+        let status = status.as_bytes();
+        // Renames are followed by the original path
+        if matches!(status[0], b'R' | b'C') {
+            entries.next();
+        }
+        uncommitted.push(Uncommitted {
+            path: path.to_owned(),
+            untracked: status[0] == b'?',
+            staged: status[0] != b'?' && status[1] == b' ',
+        });
+    }
+    Some(uncommitted)
+}
+
+/// Writes a reminder to add the updated files that git doesn't have the latest contents of.
+pub(crate) fn write_reminder<W: io::Write>(
+    uncommitted: &[Uncommitted],
+    writer: &mut W,
+) -> io::Result<()> {
+    let unstaged: Vec<_> = uncommitted.iter().filter(|file| !file.staged).collect();
+    if unstaged.is_empty() {
+        return Ok(());
+    }
+    writeln!(
+        writer,
+        "\n{NOTE}reminder{NOTE:#}: {} updated {} not added to git:",
+        unstaged.len(),
+        if unstaged.len() == 1 {
+            "file is"
+        } else {
+            "files are"
+        }
+    )?;
+    for file in unstaged {
+        let status = if file.untracked {
+            "untracked"
+        } else {
+            "modified"
+        };
+        writeln!(writer, "   {status:>9}  {}", file.path)?;
+    }
+    Ok(())
+}
@@ -0,0 +1,46 @@
+//! Lazily initialized process-wide state.
+//!
+//! `Mutex::new` is only usable in statics since Rust 1.63, so this stands in for `OnceLock`.
+
+use std::ptr;
+use std::sync::atomic::{AtomicPtr, Ordering};
+
+/// A value that is initialized on first access and lives until the process exits.
+pub(crate) struct Global<T> {
+    ptr: AtomicPtr<T>,
+}
+
+impl<T> Global<T> {
+    // Bounds on `T` aren't allowed in `const fn` before Rust 1.61, so they're on `get_or_init`
+    pub(crate) const fn new() -> Self {
+        Self {
+            ptr: AtomicPtr::new(ptr::null_mut()),
+        }
+    }
+
+    /// Returns the value, initializing it with `init` if this is the first access.
+    pub(crate) fn get_or_init(&'static self, init: impl FnOnce() -> T) -> &'static T
+    where
+        T: Send + Sync,
+    {
+        let mut ptr = self.ptr.load(Ordering::Acquire);
+        if ptr.is_null() {
+            let new = Box::into_raw(Box::new(init()));
+            ptr = match self.ptr.compare_exchange(
+                ptr::null_mut(),
+                new,
+                Ordering::AcqRel,
+                Ordering::Acquire,
+            ) {
+                Ok(_) => new,
+                Err(existing) => {
+                    // SAFETY: `new` was created by `Box::into_raw` above and was never shared.
+                    drop(unsafe { Box::from_raw(new) });
+                    existing
+                }
+            };
+        }
+        // SAFETY: `ptr` is non-null, points to a leaked `Box` and is never freed.
+        unsafe { &*ptr }
+    }
+}
@@ -0,0 +1,317 @@
+//! Reading and writing gzip files ([RFC 1952](https://www.rfc-editor.org/rfc/rfc1952)), with a
+//! DEFLATE decoder ([RFC 1951](https://www.rfc-editor.org/rfc/rfc1951)).
+
+/// Magic bytes of a gzip member compressed with DEFLATE.
+pub(crate) const MAGIC: [u8; 3] = [0x1f, 0x8b, 0x08];
+
+const FHCRC: u8 = 1 << 1;
+const FEXTRA: u8 = 1 << 2;
+const FNAME: u8 = 1 << 3;
+const FCOMMENT: u8 = 1 << 4;
+
+/// Maximum length of a stored DEFLATE block.
+const MAX_STORED_LEN: usize = 0xffff;
+
+const CRC_TABLE: [u32; 256] = crc_table();
+
+const fn crc_table() -> [u32; 256] {
+    let mut table = [0; 256];
+    let mut i: u32 = 0;
+    while i < 256 {
+        let mut crc = i;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = if crc & 1 == 0 {
+                crc >> 1
+            } else {
+                (crc >> 1) ^ 0xedb8_8320
+            };
+            bit += 1;
+        }
+        table[i as usize] = crc;
+        i += 1;
+    }
+    table
+}
+
+/// Computes the CRC-32 of `data`.
+fn crc32(data: &[u8]) -> u32 {
+    !data.iter().fold(!0, |crc, &byte| {
+        CRC_TABLE[usize::from(crc.to_le_bytes()[0] ^ byte)] ^ (crc >> 8)
+    })
+}
+
+/// Decompresses the members of a gzip file.
+pub(crate) fn decompress(data: &[u8]) -> Result<Vec<u8>, String> {
+    let mut out = Vec::new();
+    let mut rest = data;
+    while !rest.is_empty() {
+        rest = decompress_member(rest, &mut out)?;
+    }
+    Ok(out)
+}
+
+/// Decompresses the gzip member at the start of `data` into `out`, returning the data after it.
+fn decompress_member<'a>(data: &'a [u8], out: &mut Vec<u8>) -> Result<&'a [u8], String> {
+    const TRUNCATED: &str = "the gzip file is truncated";
+    if !data.starts_with(&MAGIC) {
+        return Err("trailing data after the last gzip member".to_owned());
+    }
+    let flags = *data.get(3).ok_or(TRUNCATED)?;
+    let mut pos = 10;
+    if flags & FEXTRA != 0 {
+        let len = data.get(pos..pos + 2).ok_or(TRUNCATED)?;
+        pos += 2 + usize::from(u16::from_le_bytes([len[0], len[1]]));
+    }
+    for flag in [FNAME, FCOMMENT] {
+        if flags & flag != 0 {
+            let len = data
+                .get(pos..)
+                .and_then(|rest| rest.iter().position(|&byte| byte == 0))
+                .ok_or(TRUNCATED)?;
+            pos += len + 1;
+        }
+    }
+    if flags & FHCRC != 0 {
+        pos += 2;
+    }
+    let start = out.len();
+    let mut bits = BitReader {
+        data: data.get(pos..).ok_or(TRUNCATED)?,
+        pos: 0,
+    };
+    inflate(&mut bits, out)?;
+    let pos = pos + (bits.pos + 7) / 8;
+    let trailer = data.get(pos..pos + 8).ok_or(TRUNCATED)?;
+    let crc = u32::from_le_bytes([trailer[0], trailer[1], trailer[2], trailer[3]]);
+    let len = u32::from_le_bytes([trailer[4], trailer[5], trailer[6], trailer[7]]);
+    let member = &out[start..];
+    // The length is stored modulo 2^32
+    if crc != crc32(member) || len != low_u32(member.len()) {
+        return Err("the CRC-32 or length of a gzip member doesn't match its data".to_owned());
+    }
+    Ok(&data[pos + 8..])
+}
+
+/// Compresses `data` into a gzip file of stored blocks, which every gzip reader can read.
+pub(crate) fn compress_stored(data: &[u8]) -> Vec<u8> {
+    // No modification time, maximum compression flag or operating system
+    let mut out = vec![MAGIC[0], MAGIC[1], MAGIC[2], 0, 0, 0, 0, 0, 0, 0xff];
+    let mut blocks = data.chunks(MAX_STORED_LEN).peekable();
+    if blocks.peek().is_none() {
+        out.extend_from_slice(&[1, 0, 0, 0xff, 0xff]);
+    }
+    while let Some(block) = blocks.next() {
+        let len = u16::try_from(block.len()).unwrap();
+        out.push(u8::from(blocks.peek().is_none()));
+        out.extend_from_slice(&len.to_le_bytes());
+        out.extend_from_slice(&(!len).to_le_bytes());
+        out.extend_from_slice(block);
+    }
+    out.extend_from_slice(&crc32(data).to_le_bytes());
+    out.extend_from_slice(&low_u32(data.len()).to_le_bytes());
+    out
+}
+
+/// Returns `len` modulo 2^32, as stored in the trailer of a member.
+fn low_u32(len: usize) -> u32 {
+    let bytes = len.to_le_bytes();
+    u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]])
+}
+
+/// Reads bits from the least significant bit of each byte.
+struct BitReader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl BitReader<'_> {
+    fn bit(&mut self) -> Result<u32, String> {
+        let byte = self
+            .data
+            .get(self.pos / 8)
+            .ok_or("the DEFLATE stream is truncated")?;
+        let bit = (byte >> (self.pos % 8)) & 1;
+        self.pos += 1;
+        Ok(u32::from(bit))
+    }
+
+    fn bits(&mut self, count: u32) -> Result<u32, String> {
+        (0..count).try_fold(0, |value, i| Ok(value | self.bit()? << i))
+    }
+}
+
+/// A canonical Huffman code, decoded a bit at a time.
+struct Huffman {
+    /// Number of codes of each length.
+    counts: [u16; 16],
+    /// Symbols ordered by their codes.
+    symbols: Vec<u16>,
+}
+
+impl Huffman {
+    fn new(lengths: &[u8]) -> Result<Self, String> {
+        let mut counts = [0; 16];
+        for &len in lengths {
+            counts[usize::from(len)] += 1;
+        }
+        counts[0] = 0;
+        // Incomplete codes are allowed, but not ones with more codes than fit
+        let mut left = 1i32;
+        for &count in &counts[1..] {
+            left = left * 2 - i32::from(count);
+            if left < 0 {
+                return Err("a DEFLATE Huffman code is oversubscribed".to_owned());
+            }
+        }
+        let mut symbols: Vec<u16> = (0..).take(lengths.len()).collect();
+        symbols.retain(|&symbol| lengths[usize::from(symbol)] != 0);
+        symbols.sort_by_key(|&symbol| lengths[usize::from(symbol)]);
+        Ok(Self { counts, symbols })
+    }
+
+    fn decode(&self, bits: &mut BitReader<'_>) -> Result<u16, String> {
+        let (mut code, mut first, mut index) = (0, 0, 0);
+        for &count in &self.counts[1..] {
+            code |= bits.bit()?;
+            let count = u32::from(count);
+            if code < first + count {
+                return Ok(self.symbols[(index + code - first) as usize]);
+            }
+            index += count;
+            first = (first + count) << 1;
+            code <<= 1;
+        }
+        Err("invalid DEFLATE Huffman code".to_owned())
+    }
+}
+
+const LENGTH_BASES: [u16; 29] = [
+    3, 4, 5, 6, 7, 8, 9, 10, 11, 13, 15, 17, 19, 23, 27, 31, 35, 43, 51, 59, 67, 83, 99, 115, 131,
+    163, 195, 227, 258,
+];
+const LENGTH_EXTRA_BITS: [u8; 29] = [
+    0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 3, 3, 3, 3, 4, 4, 4, 4, 5, 5, 5, 5, 0,
+];
+const DISTANCE_BASES: [u16; 30] = [
+    1, 2, 3, 4, 5, 7, 9, 13, 17, 25, 33, 49, 65, 97, 129, 193, 257, 385, 513, 769, 1025, 1537,
+    2049, 3073, 4097, 6145, 8193, 12289, 16385, 24577,
+];
+const DISTANCE_EXTRA_BITS: [u8; 30] = [
+    0, 0, 0, 0, 1, 1, 2, 2, 3, 3, 4, 4, 5, 5, 6, 6, 7, 7, 8, 8, 9, 9, 10, 10, 11, 11, 12, 12, 13,
+    13,
+];
+/// Order of the code length code lengths of a dynamic block.
+const CODE_LENGTH_ORDER: [usize; 19] = [
+    16, 17, 18, 0, 8, 7, 9, 6, 10, 5, 11, 4, 12, 3, 13, 2, 14, 1, 15,
+];
+
+/// Decodes a DEFLATE stream into `out`, leaving `bits` after its final block.
+fn inflate(bits: &mut BitReader<'_>, out: &mut Vec<u8>) -> Result<(), String> {
+    loop {
+        let last = bits.bit()? == 1;
+        match bits.bits(2)? {
+            0 => {
+                bits.pos = (bits.pos + 7) / 8 * 8;
+                let start = bits.pos / 8;
+                let header = bits
+                    .data
+                    .get(start..start + 4)
+                    .ok_or("the DEFLATE stream is truncated")?;
+                let len = u16::from_le_bytes([header[0], header[1]]);
+                if len != !u16::from_le_bytes([header[2], header[3]]) {
+                    return Err("invalid stored DEFLATE block length".to_owned());
+                }
+                let block = bits
+                    .data
+                    .get(start + 4..start + 4 + usize::from(len))
+                    .ok_or("the DEFLATE stream is truncated")?;
+                out.extend_from_slice(block);
+                bits.pos += (4 + usize::from(len)) * 8;
+            }
+            1 => {
+                let mut lengths = [0; 288];
+                lengths[..144].fill(8);
+                lengths[144..256].fill(9);
+                lengths[256..280].fill(7);
+                lengths[280..].fill(8);
+                let literals = Huffman::new(&lengths)?;
+                let distances = Huffman::new(&[5; 30])?;
+                inflate_block(bits, out, &literals, &distances)?;
+            }
+            2 => {
+                let (literals, distances) = read_dynamic_codes(bits)?;
+                inflate_block(bits, out, &literals, &distances)?;
+            }
+            _ => return Err("invalid DEFLATE block type".to_owned()),
+        }
+        if last {
+            return Ok(());
+        }
+    }
+}
+
+fn read_dynamic_codes(bits: &mut BitReader<'_>) -> Result<(Huffman, Huffman), String> {
+    let literal_count = bits.bits(5)? as usize + 257;
+    let distance_count = bits.bits(5)? as usize + 1;
+    let code_length_count = bits.bits(4)? as usize + 4;
+    let mut code_lengths = [0; 19];
+    for &i in &CODE_LENGTH_ORDER[..code_length_count] {
+        code_lengths[i] = u8::try_from(bits.bits(3)?).unwrap();
+    }
+    let code_lengths = Huffman::new(&code_lengths)?;
+    let mut lengths = Vec::with_capacity(literal_count + distance_count);
+    while lengths.len() < literal_count + distance_count {
+        let (len, repeat) = match code_lengths.decode(bits)? {
+            symbol @ 0..=15 => (u8::try_from(symbol).unwrap(), 1),
+            16 => {
+                let previous = *lengths
+                    .last()
+                    .ok_or("a DEFLATE code length repeats nothing")?;
+                (previous, 3 + bits.bits(2)?)
+            }
+            17 => (0, 3 + bits.bits(3)?),
+            _ => (0, 11 + bits.bits(7)?),
+        };
+        lengths.extend(std::iter::repeat(len).take(repeat as usize));
+    }
+    if lengths.len() != literal_count + distance_count {
+        return Err("DEFLATE code lengths overflow the code".to_owned());
+    }
+    Ok((
+        Huffman::new(&lengths[..literal_count])?,
+        Huffman::new(&lengths[literal_count..])?,
+    ))
+}
+
+fn inflate_block(
+    bits: &mut BitReader<'_>,
+    out: &mut Vec<u8>,
+    literals: &Huffman,
+    distances: &Huffman,
+) -> Result<(), String> {
+    loop {
+        let symbol = literals.decode(bits)?;
+        match symbol {
+            0..=255 => out.push(u8::try_from(symbol).unwrap()),
+            256 => return Ok(()),
+            _ => {
+                let code = usize::from(symbol - 257);
+                let len = usize::from(*LENGTH_BASES.get(code).ok_or("invalid DEFLATE length")?)
+                    + bits.bits(u32::from(LENGTH_EXTRA_BITS[code]))? as usize;
+                let code = usize::from(distances.decode(bits)?);
+                let distance =
+                    usize::from(*DISTANCE_BASES.get(code).ok_or("invalid DEFLATE distance")?)
+                        + bits.bits(u32::from(DISTANCE_EXTRA_BITS[code]))? as usize;
+                if distance > out.len() {
+                    return Err("a DEFLATE distance points before the start".to_owned());
+                }
+                // The match may overlap the bytes it produces
+                let start = out.len() - distance;
+                for i in 0..len {
+                    out.push(out[start + i]);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,58 @@
+//! The header line of failure reports, which can be customized for problem matchers.
+
+use std::io;
+
+use crate::style::{ARROW, ERROR, MESSAGE};
+use crate::Position;
+
+/// Template of the header of failure reports, e.g. `{file}({line},{column}): error: {message}`.
+pub(crate) const HEADER_FORMAT_VAR_NAME: &str = "EXPECT_BYTES_HEADER_FORMAT";
+
+pub(crate) fn format_from_env() -> Option<String> {
+    std::env::var(HEADER_FORMAT_VAR_NAME).ok()
+}
+
+/// Writes the header of a report about `location`, asserted at `position` if known.
+///
+/// Without a `format`, the header is the usual colored error line followed by an arrow pointing
+/// at `location`.
+pub(crate) fn write<W: io::Write>(
+    writer: &mut W,
+    format: Option<&str>,
+    message: &str,
+    location: &str,
+    position: Option<&Position>,
+) {
+    match format {
+        Some(format) => {
+            let (file, line, column) = position.map_or((location, 0, 0), |position| {
+                (position.file, position.line, position.column)
+            });
+            let mut header = String::new();
+            let mut rest = format;
+            while let Some(start) = rest.find('{') {
+                header.push_str(&rest[..start]);
+                rest = &rest[start..];
+                let end = rest.find('}').map_or(rest.len(), |end| end + 1);
+                match &rest[..end] {
+                    "{message}" => header.push_str(message),
+                    "{path}" => header.push_str(location),
+                    "{file}" => header.push_str(file),
+                    "{line}" => header.push_str(&line.to_string()),
+                    "{column}" => header.push_str(&column.to_string()),
+                    unknown => header.push_str(unknown),
+                }
+                rest = &rest[end..];
+            }
+            header.push_str(rest);
+            writeln!(writer, "\n{header}").unwrap();
+        }
+        None => writeln!(
+            writer,
+            "
+{ERROR}error{ERROR:#}{MESSAGE}: {message}{MESSAGE:#}
+   {ARROW}-->{ARROW:#} {location}"
+        )
+        .unwrap(),
+    }
+}
@@ -0,0 +1,54 @@
+//! Numbers of differing bytes per chunk of large files, showing where differences concentrate.
+
+use std::io;
+
+use crate::style::BOLD;
+
+/// Number of chunks listed, from the one with the most differing bytes.
+const TOP_CHUNKS: usize = 5;
+
+/// Writes the table of the chunks of `chunk_size` bytes with the most differing bytes.
+///
+/// Bytes past the end of the shorter data count as differing.
+pub(crate) fn write<W: io::Write>(
+    writer: &mut W,
+    expected: &[u8],
+    actual: &[u8],
+    chunk_size: usize,
+) -> io::Result<()> {
+    let len = expected.len().max(actual.len());
+    let mut counts: Vec<(usize, usize)> = (0..len)
+        .step_by(chunk_size)
+        .enumerate()
+        .map(|(chunk, start)| {
+            let end = (start + chunk_size).min(len);
+            let differing = (start..end)
+                .filter(|&i| expected.get(i) != actual.get(i))
+                .count();
+            (chunk, differing)
+        })
+        .filter(|&(_, differing)| differing > 0)
+        .collect();
+    let chunks = (len + chunk_size - 1) / chunk_size;
+    writeln!(
+        writer,
+        "{BOLD}Differences by chunk{BOLD:#} ({chunk_size:#x} bytes each): {} of {chunks} {} differ",
+        counts.len(),
+        if chunks == 1 { "chunk" } else { "chunks" }
+    )?;
+    counts.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+    let hidden = counts.len().saturating_sub(TOP_CHUNKS);
+    counts.truncate(TOP_CHUNKS);
+    writeln!(writer, "   chunk      offset  differing bytes")?;
+    for (chunk, differing) in counts {
+        writeln!(
+            writer,
+            "{chunk:>8}  {:>#10x}  {differing:>15}",
+            chunk * chunk_size
+        )?;
+    }
+    if hidden > 0 {
+        writeln!(writer, "   ... and {hidden} more")?;
+    }
+    writeln!(writer)
+}
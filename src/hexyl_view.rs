@@ -0,0 +1,42 @@
+//! Hex dumps of both sides rendered by [hexyl](https://github.com/sharkdp/hexyl).
+
+use std::io;
+
+use hexyl::{BorderStyle, PrinterBuilder};
+
+use crate::style::BOLD;
+
+const ROW_LEN: usize = 16;
+/// Number of rows dumped for each side, starting two rows before the first difference.
+const ROWS: usize = 6;
+
+/// Writes hexyl dumps of the rows around `diff_idx` of `expected` and `actual`, which both start
+/// at `offset`.
+pub(crate) fn write_dumps<W: io::Write>(
+    writer: &mut W,
+    expected: &[u8],
+    actual: &[u8],
+    offset: usize,
+    diff_idx: usize,
+) {
+    // Align the rows to the offsets in the file
+    let first_row = (diff_idx.saturating_sub(2 * ROW_LEN) / ROW_LEN * ROW_LEN).max(offset);
+    let start = first_row - offset;
+    let end = start + ROWS * ROW_LEN;
+    writeln!(
+        writer,
+        "{BOLD}Diff{BOLD:#}:\nBinary files differ at byte {diff_idx:#x}\n"
+    )
+    .unwrap();
+    for (name, data) in [("Expect", expected), ("Actual", actual)] {
+        writeln!(writer, "{BOLD}{name}{BOLD:#}:").unwrap();
+        let region = &data[start.min(data.len())..end.min(data.len())];
+        let mut printer = PrinterBuilder::new(writer)
+            .show_color(true)
+            .with_border_style(BorderStyle::Unicode)
+            .enable_squeezing(false)
+            .build();
+        printer.display_offset(first_row as u64);
+        printer.print_all(region).unwrap();
+    }
+}
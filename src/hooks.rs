@@ -0,0 +1,58 @@
+//! Callbacks run by [`ExpectFile`](crate::ExpectFile) assertions.
+
+use crate::global::Global;
+use std::path::Path;
+use std::sync::{Arc, Mutex, PoisonError};
+
+/// Callback run after a file is written in update mode.
+///
+/// Receives the path of the file, its previous contents (`None` if it was created) and its new
+/// contents.
+pub type UpdateHook = dyn Fn(&Path, Option<&[u8]>, &[u8]) + Send + Sync;
+
+/// Callback that checks the actual data before it's compared or written.
+///
+/// Returns a description of the problem if the data is malformed.
+pub type Validator = dyn Fn(&[u8]) -> Result<(), String> + Send + Sync;
+
+/// Callback that describes what a byte offset into the data is, such as "record 17, field
+/// `payload`".
+pub type OffsetDescriber = dyn Fn(usize) -> String + Send + Sync;
+
+/// Callback that decodes the structure at a byte offset into the data, such as a flags byte or a
+/// packed field.
+///
+/// Receives the data and the offset into it, and returns `None` if there's nothing to decode.
+pub type DiffDecoder = dyn Fn(&[u8], usize) -> Option<String> + Send + Sync;
+
+static UPDATE_HOOKS: Global<Mutex<Vec<Arc<UpdateHook>>>> = Global::new();
+
+/// Registers a callback that is run after any file is written in update mode.
+///
+/// This is useful for regenerating derived artifacts, such as checksum files, in the same run.
+///
+/// ```
+/// expect_test_bytes::on_update(|path, _old, new| {
+///     println!("{} is now {} bytes", path.display(), new.len());
+/// });
+/// ```
+pub fn on_update(hook: impl Fn(&Path, Option<&[u8]>, &[u8]) + Send + Sync + 'static) {
+    UPDATE_HOOKS
+        .get_or_init(Mutex::default)
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .push(Arc::new(hook));
+}
+
+/// Runs the globally registered update hooks.
+pub(crate) fn run_update_hooks(path: &Path, old: Option<&[u8]>, new: &[u8]) {
+    // Clone the hooks so that hooks can register more hooks or update other files
+    let hooks = UPDATE_HOOKS
+        .get_or_init(Mutex::default)
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .clone();
+    for hook in hooks {
+        hook(path, old, new);
+    }
+}
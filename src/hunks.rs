@@ -0,0 +1,78 @@
+//! Byte-level hunks computed with the [`similar`] crate.
+
+use similar::{Algorithm, DiffOp};
+use std::io;
+use std::ops::Range;
+use std::time::{Duration, Instant};
+
+use crate::style::BOLD;
+
+/// Time after which `similar` falls back to a coarser diff.
+const DEADLINE: Duration = Duration::from_secs(1);
+/// Maximum number of hunks listed in the report.
+const MAX_HUNKS: usize = 16;
+
+/// A differing region.
+pub(crate) struct Hunk {
+    pub(crate) expected: Range<usize>,
+    pub(crate) actual: Range<usize>,
+}
+
+/// Computes the differing regions of `expected` and `actual` with Myers' algorithm.
+pub(crate) fn byte_hunks(expected: &[u8], actual: &[u8]) -> Vec<Hunk> {
+    let ops = similar::capture_diff_deadline(
+        Algorithm::Myers,
+        expected,
+        0..expected.len(),
+        actual,
+        0..actual.len(),
+        Some(Instant::now() + DEADLINE),
+    );
+
+    let mut hunks: Vec<Hunk> = Vec::new();
+    for op in ops {
+        if let DiffOp::Equal { .. } = op {
+            continue;
+        }
+        let (_, expected, actual) = op.as_tag_tuple();
+        match hunks.last_mut() {
+            // Merge adjacent deletes and inserts
+            Some(last)
+                if last.expected.end == expected.start && last.actual.end == actual.start =>
+            {
+                last.expected.end = expected.end;
+                last.actual.end = actual.end;
+            }
+            _ => hunks.push(Hunk { expected, actual }),
+        }
+    }
+    hunks
+}
+
+/// Writes the list of differing regions of `expected` and `actual`, which both start at
+/// `offset`.
+pub(crate) fn write_hunks<W: io::Write>(
+    writer: &mut W,
+    expected: &[u8],
+    actual: &[u8],
+    offset: usize,
+) {
+    let hunks = byte_hunks(expected, actual);
+    writeln!(writer, "\n{BOLD}Hunks{BOLD:#}: {}", hunks.len()).unwrap();
+    for hunk in hunks.iter().take(MAX_HUNKS) {
+        writeln!(
+            writer,
+            "  expect {:#x}..{:#x} ({} bytes) -> actual {:#x}..{:#x} ({} bytes)",
+            offset + hunk.expected.start,
+            offset + hunk.expected.end,
+            hunk.expected.len(),
+            offset + hunk.actual.start,
+            offset + hunk.actual.end,
+            hunk.actual.len(),
+        )
+        .unwrap();
+    }
+    if hunks.len() > MAX_HUNKS {
+        writeln!(writer, "  … {} more hunks", hunks.len() - MAX_HUNKS).unwrap();
+    }
+}
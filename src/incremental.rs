@@ -0,0 +1,338 @@
+//! Comparison against the file as it's read, stopping at the first difference.
+//!
+//! Only the region around the first difference is kept for the report, so a mismatch early in a
+//! large file doesn't read the rest of it.
+
+use std::fs;
+use std::io::{self, Read, Seek, SeekFrom};
+use std::path::Path;
+
+use crate::not_found_to_none;
+use crate::render::{BYTE_WINDOW_HALF_SIZE, SHIFT_SEARCH_LEN};
+
+/// Size of the reads from the file.
+const READ_LEN: usize = 64 * 1024;
+
+/// Result of comparing the file against the actual data.
+pub(crate) enum Incremental {
+    Equal,
+    Different {
+        diff_idx: usize,
+        /// Region of the file around `diff_idx`.
+        window: Vec<u8>,
+        /// Offset of `window` in the file.
+        window_offset: usize,
+        expected_len: usize,
+        /// Length of the common suffix after `diff_idx`, if `window` reaches the end of the file.
+        common_suffix: Option<usize>,
+    },
+}
+
+/// Compares the file at `path` against `actual`, or returns `Ok(None)` if it doesn't exist.
+pub(crate) fn compare(path: &Path, actual: &[u8]) -> io::Result<Option<Incremental>> {
+    let mut file = match not_found_to_none(fs::File::open(path))? {
+        Some(file) => file,
+        None => return Ok(None),
+    };
+    let mut buf = vec![0; READ_LEN];
+    let mut pos = 0;
+    let diff_idx = loop {
+        let n = match file.read(&mut buf) {
+            Ok(0) if pos == actual.len() => return Ok(Some(Incremental::Equal)),
+            // The file is shorter
+            Ok(0) => break pos,
+            Ok(n) => n,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        };
+        let rest = &actual[pos..];
+        let len = n.min(rest.len());
+        if let Some(i) = crate::first_diff_index(&buf[..len], &rest[..len]) {
+            break pos + i;
+        }
+        if n > rest.len() {
+            // The file is longer
+            break actual.len();
+        }
+        pos += n;
+    };
+
+    let expected_len = usize::try_from(file.metadata()?.len()).unwrap_or(usize::MAX);
+    let window_offset = diff_idx.saturating_sub(BYTE_WINDOW_HALF_SIZE);
+    let window_end = diff_idx + SHIFT_SEARCH_LEN.max(BYTE_WINDOW_HALF_SIZE + 1);
+    file.seek(SeekFrom::Start(window_offset as u64))?;
+    let mut window = Vec::with_capacity(window_end - window_offset);
+    file.take((window_end - window_offset) as u64)
+        .read_to_end(&mut window)?;
+    // Finding the suffix would read the rest of the file, which is only done if it's in memory
+    let common_suffix = (window_offset + window.len() == expected_len).then(|| {
+        crate::render::common_suffix_len(
+            &window,
+            &actual[window_offset..],
+            diff_idx - window_offset,
+        )
+    });
+    Ok(Some(Incremental::Different {
+        diff_idx,
+        window,
+        window_offset,
+        expected_len,
+        common_suffix,
+    }))
+}
+
+/// Regions of two sources of data around their first difference, returned by
+/// [`compare_readers`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct StreamDifference {
+    /// Offset of the first differing byte, or the length of the shorter source if one is a
+    /// prefix of the other.
+    pub diff_idx: usize,
+    /// Offset of both windows in their sources.
+    pub window_offset: usize,
+    /// Expected bytes around `diff_idx`.
+    pub expected_window: Vec<u8>,
+    /// Actual bytes around `diff_idx`.
+    pub actual_window: Vec<u8>,
+    /// Total length of the expected data.
+    pub expected_len: usize,
+    /// Total length of the actual data.
+    pub actual_len: usize,
+}
+
+/// Compares the file at `path` against the file at `actual_path` as both are read, returning the
+/// first difference as an error, or returns `Ok(None)` if the former doesn't exist.
+pub(crate) fn compare_files(
+    path: &Path,
+    actual_path: &Path,
+) -> io::Result<Option<Result<(), StreamDifference>>> {
+    let file = match not_found_to_none(fs::File::open(path))? {
+        Some(file) => file,
+        None => return Ok(None),
+    };
+    compare_with_file(file, &mut fs::File::open(actual_path)?).map(Some)
+}
+
+/// Compares the file at `path` against the data from the current position of `actual` to its
+/// end as both are read, returning the first difference as an error, or returns `Ok(None)` if the
+/// former doesn't exist.
+///
+/// `actual` is seeked back to read the window before the difference and to its end to find its
+/// length, so only the windows are kept in memory.
+pub(crate) fn compare_seekable(
+    path: &Path,
+    actual: &mut (impl Read + Seek),
+) -> io::Result<Option<Result<(), StreamDifference>>> {
+    let file = match not_found_to_none(fs::File::open(path))? {
+        Some(file) => file,
+        None => return Ok(None),
+    };
+    compare_with_file(file, actual).map(Some)
+}
+
+fn compare_with_file(
+    mut file: fs::File,
+    actual: &mut (impl Read + Seek),
+) -> io::Result<Result<(), StreamDifference>> {
+    let actual_start = actual.stream_position()?;
+    let mut buf = vec![0; READ_LEN];
+    let mut actual_buf = vec![0; READ_LEN];
+    let mut pos = 0;
+    let diff_idx = loop {
+        let n = read_full(&mut file, &mut buf)?;
+        let actual_n = read_full(actual, &mut actual_buf)?;
+        let len = n.min(actual_n);
+        if let Some(i) = crate::first_diff_index(&buf[..len], &actual_buf[..len]) {
+            break pos + i;
+        }
+        if n != actual_n {
+            // One of them is shorter
+            break pos + len;
+        }
+        if n == 0 {
+            return Ok(Ok(()));
+        }
+        pos += n;
+    };
+
+    let window_offset = diff_idx.saturating_sub(BYTE_WINDOW_HALF_SIZE);
+    let window_len = diff_idx + SHIFT_SEARCH_LEN.max(BYTE_WINDOW_HALF_SIZE + 1) - window_offset;
+    let expected_window = read_window(&mut file, window_offset as u64, window_len)?;
+    let actual_window = read_window(actual, actual_start + window_offset as u64, window_len)?;
+    let len = |position: u64| usize::try_from(position).unwrap_or(usize::MAX);
+    Ok(Err(StreamDifference {
+        diff_idx,
+        window_offset,
+        expected_window,
+        actual_window,
+        expected_len: len(file.metadata()?.len()),
+        actual_len: len(actual.seek(SeekFrom::End(0))? - actual_start),
+    }))
+}
+
+/// Reads at most `len` bytes at `offset` of `reader`.
+fn read_window(reader: &mut (impl Read + Seek), offset: u64, len: usize) -> io::Result<Vec<u8>> {
+    reader.seek(SeekFrom::Start(offset))?;
+    let mut window = Vec::with_capacity(len);
+    reader.take(len as u64).read_to_end(&mut window)?;
+    Ok(window)
+}
+
+/// Compares the file at `path` against the data read from `actual` as both are read, returning
+/// the first difference as an error, or returns `Ok(None)` if the former doesn't exist.
+pub(crate) fn compare_reader(
+    path: &Path,
+    actual: &mut impl Read,
+) -> io::Result<Option<Result<(), StreamDifference>>> {
+    let mut file = match not_found_to_none(fs::File::open(path))? {
+        Some(file) => file,
+        None => return Ok(None),
+    };
+    compare_streams(&mut file, actual).map(Some)
+}
+
+/// Compares the data read from `expected` against the data read from `actual` chunk by chunk,
+/// returning the region around their first difference, or `None` if they're equal.
+///
+/// Neither source needs to be seekable and at most a few chunks of each are kept in memory, so
+/// this also works for data that can't be loaded at once, such as the output of a decompressor
+/// or a download. Both sources are read to their end on a difference to find their lengths.
+///
+/// ```
+/// let expected: &[u8] = b"example\n";
+/// let actual: &[u8] = b"exemple\n";
+/// let difference = expect_test_bytes::compare_readers(expected, actual)
+///     .unwrap()
+///     .unwrap();
+/// assert_eq!(difference.diff_idx, 2);
+/// assert_eq!(&difference.actual_window[..], actual);
+/// ```
+///
+/// # Errors
+///
+/// Returns an error if reading either source fails.
+pub fn compare_readers(
+    mut expected: impl Read,
+    mut actual: impl Read,
+) -> io::Result<Option<StreamDifference>> {
+    Ok(compare_streams(&mut expected, &mut actual)?.err())
+}
+
+/// The previous and current reads of a source being compared.
+struct Reads {
+    prev: Vec<u8>,
+    buf: Vec<u8>,
+    len: usize,
+}
+
+impl Reads {
+    fn new() -> Self {
+        Self {
+            prev: Vec::new(),
+            buf: vec![0; READ_LEN],
+            len: 0,
+        }
+    }
+
+    fn read(&mut self, reader: &mut impl Read) -> io::Result<&[u8]> {
+        self.len = read_full(reader, &mut self.buf)?;
+        Ok(&self.buf[..self.len])
+    }
+
+    /// Keeps the current read as the previous one.
+    fn advance(&mut self) {
+        std::mem::swap(&mut self.prev, &mut self.buf);
+        self.buf.resize(READ_LEN, 0);
+    }
+
+    /// Returns the window of `window_len` bytes at `window_offset`, which is at most
+    /// `BYTE_WINDOW_HALF_SIZE` bytes before the current read at `pos`, and the total length of the
+    /// data read from `reader`.
+    fn finish(
+        mut self,
+        reader: &mut impl Read,
+        pos: usize,
+        window_offset: usize,
+        window_len: usize,
+    ) -> io::Result<(Vec<u8>, usize)> {
+        let seen_offset = pos - self.prev.len();
+        self.prev.extend_from_slice(&self.buf[..self.len]);
+        let mut window = self.prev.split_off(window_offset - seen_offset);
+        let extra = if window.len() < window_len {
+            let seen = window.len();
+            (&mut *reader)
+                .take((window_len - seen) as u64)
+                .read_to_end(&mut window)?;
+            window.len() - seen
+        } else {
+            window.truncate(window_len);
+            0
+        };
+        let rest = io::copy(reader, &mut io::sink())?;
+        let len =
+            (pos + self.len + extra).saturating_add(usize::try_from(rest).unwrap_or(usize::MAX));
+        Ok((window, len))
+    }
+}
+
+/// Compares `expected` against `actual` as both are read, returning the first difference as an
+/// error.
+///
+/// Neither source can be seeked, so their previous reads are kept for the windows before the
+/// difference and the rest of them is read to find their lengths.
+fn compare_streams(
+    expected: &mut impl Read,
+    actual: &mut impl Read,
+) -> io::Result<Result<(), StreamDifference>> {
+    let mut expected_reads = Reads::new();
+    let mut actual_reads = Reads::new();
+    let mut pos = 0;
+    let diff_idx = loop {
+        let expected_buf = expected_reads.read(expected)?;
+        let actual_buf = actual_reads.read(actual)?;
+        let len = expected_buf.len().min(actual_buf.len());
+        if let Some(i) = crate::first_diff_index(&expected_buf[..len], &actual_buf[..len]) {
+            break pos + i;
+        }
+        if expected_buf.len() != actual_buf.len() {
+            // One of them is shorter
+            break pos + len;
+        }
+        if len == 0 {
+            return Ok(Ok(()));
+        }
+        pos += len;
+        expected_reads.advance();
+        actual_reads.advance();
+    };
+
+    let window_offset = diff_idx.saturating_sub(BYTE_WINDOW_HALF_SIZE);
+    let window_len = diff_idx + SHIFT_SEARCH_LEN.max(BYTE_WINDOW_HALF_SIZE + 1) - window_offset;
+    let (expected_window, expected_len) =
+        expected_reads.finish(expected, pos, window_offset, window_len)?;
+    let (actual_window, actual_len) =
+        actual_reads.finish(actual, pos, window_offset, window_len)?;
+    Ok(Err(StreamDifference {
+        diff_idx,
+        window_offset,
+        expected_window,
+        actual_window,
+        expected_len,
+        actual_len,
+    }))
+}
+
+/// Reads from `reader` until `buf` is full or the end of the file is reached.
+fn read_full(reader: &mut impl Read, buf: &mut [u8]) -> io::Result<usize> {
+    let mut filled = 0;
+    while filled < buf.len() {
+        match reader.read(&mut buf[filled..]) {
+            Ok(0) => break,
+            Ok(n) => filled += n,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(filled)
+}
@@ -0,0 +1,178 @@
+//! Updating expected values written inline in the test's source code.
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, PoisonError};
+
+use crate::global::Global;
+use crate::{Position, ROOT_VAR_NAME};
+
+/// Serializes the read-modify-write of source files by concurrently running tests.
+static SOURCE_LOCK: Global<Mutex<()>> = Global::new();
+
+/// Returns the path of the source file containing `location`.
+pub(crate) fn source_path(location: &Position) -> PathBuf {
+    let path = Path::new(location.file);
+    match std::env::var_os(ROOT_VAR_NAME) {
+        Some(root) if path.is_relative() => Path::new(&root).join(path),
+        _ => path.to_owned(),
+    }
+}
+
+/// Replaces the last argument of the call at `location` with `literal` in the source file.
+pub(crate) fn update_last_argument(location: &Position, literal: &str) -> io::Result<()> {
+    let _guard = SOURCE_LOCK
+        .get_or_init(Mutex::default)
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner);
+    let path = source_path(location);
+    let source = fs::read_to_string(&path)?;
+    let updated = replace_last_argument(&source, location.line, location.column, literal)
+        .ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("couldn't find the call at {location}, or its brackets don't match"),
+            )
+        })?;
+    fs::write(path, updated)
+}
+
+/// Replaces the last argument of the first call at or after 1-based `line` and `column` with
+/// `literal`.
+///
+/// Brackets in string, raw string and character literals and in comments are skipped. Returns
+/// `None` if the call isn't found or its brackets don't match, rather than editing the wrong span.
+pub(crate) fn replace_last_argument(
+    source: &str,
+    line: u32,
+    column: u32,
+    literal: &str,
+) -> Option<String> {
+    let line_start = if line <= 1 {
+        0
+    } else {
+        source
+            .match_indices('\n')
+            .nth(usize::try_from(line - 2).ok()?)?
+            .0
+            + 1
+    };
+    let start = line_start
+        + source[line_start..]
+            .char_indices()
+            .nth(usize::try_from(column.checked_sub(1)?).ok()?)?
+            .0;
+    let open = start + source[start..].find('(')?;
+
+    let bytes = source.as_bytes();
+    let mut closers = Vec::new();
+    let mut argument_start = open + 1;
+    let mut previous_argument_start = argument_start;
+    let mut i = open;
+    let close = loop {
+        match *bytes.get(i)? {
+            b'(' => closers.push(b')'),
+            b'[' => closers.push(b']'),
+            b'{' => closers.push(b'}'),
+            closer @ (b')' | b']' | b'}') => {
+                // A mismatched bracket means something was misread
+                if closers.pop()? != closer {
+                    return None;
+                }
+                if closers.is_empty() {
+                    break i;
+                }
+            }
+            b',' if closers.len() == 1 => {
+                previous_argument_start = argument_start;
+                argument_start = i + 1;
+            }
+            _ => {
+                let end = skip_token(source, i)?;
+                // Comments before an argument aren't part of it
+                if bytes[i] == b'/' && end > i && source[argument_start..i].trim().is_empty() {
+                    argument_start = end + 1;
+                }
+                i = end;
+            }
+        }
+        i += 1;
+    };
+
+    // A trailing comma leaves an empty last argument
+    if source[argument_start..close].trim().is_empty() {
+        argument_start = previous_argument_start;
+    }
+    let argument = &source[argument_start..close];
+    let argument = argument.trim_end().trim_end_matches(',');
+    let leading = argument.len() - argument.trim_start().len();
+    let range = argument_start + leading..argument_start + argument.len();
+    if range.is_empty() {
+        return None;
+    }
+    let mut updated = source.to_owned();
+    updated.replace_range(range, literal);
+    Some(updated)
+}
+
+/// Returns the index of the last byte of the literal or comment starting at `i` in `source`, or
+/// `i` if none starts there, or `None` if it isn't terminated.
+fn skip_token(source: &str, i: usize) -> Option<usize> {
+    let bytes = source.as_bytes();
+    let is_ident = |j: usize| bytes[j].is_ascii_alphanumeric() || bytes[j] == b'_';
+    match bytes[i] {
+        b'"' => {
+            let mut j = i + 1;
+            while *bytes.get(j)? != b'"' {
+                j += if bytes[j] == b'\\' { 2 } else { 1 };
+            }
+            Some(j)
+        }
+        b'\'' => {
+            // `'a` is a lifetime or a label unless it's closed right after the character
+            let len = source[i + 1..].chars().next()?.len_utf8();
+            if bytes[i + 1] == b'\\' {
+                Some(i + 3 + source.get(i + 3..)?.find('\'')?)
+            } else if bytes.get(i + 1 + len) == Some(&b'\'') {
+                Some(i + 1 + len)
+            } else {
+                Some(i)
+            }
+        }
+        b'/' if bytes.get(i + 1) == Some(&b'/') => Some(i + source[i..].find('\n')?),
+        b'/' if bytes.get(i + 1) == Some(&b'*') => {
+            // Block comments nest
+            let mut depth = 0;
+            let mut j = i;
+            loop {
+                match bytes.get(j..j + 2)? {
+                    b"/*" => depth += 1,
+                    b"*/" => depth -= 1,
+                    _ => {
+                        j += 1;
+                        continue;
+                    }
+                }
+                j += 2;
+                if depth == 0 {
+                    return Some(j - 1);
+                }
+            }
+        }
+        // Raw strings, also `br"..."`, have no escapes and end with as many `#` as they start with
+        b'r' if i == 0
+            || !is_ident(i - 1)
+            || (bytes[i - 1] == b'b' && (i == 1 || !is_ident(i - 2))) =>
+        {
+            let hashes = bytes[i + 1..].iter().take_while(|&&b| b == b'#').count();
+            if bytes.get(i + 1 + hashes) != Some(&b'"') {
+                return Some(i);
+            }
+            let end = format!("\"{}", "#".repeat(hashes));
+            let body = i + 2 + hashes;
+            Some(body + source[body..].find(&end)? + end.len() - 1)
+        }
+        _ => Some(i),
+    }
+}
@@ -0,0 +1,12 @@
+//! Checksums stored next to snapshots when they're updated, to detect snapshots that were edited by
+//! hand or corrupted since.
+
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+
+/// Returns the path of the integrity sidecar for the snapshot at `path`.
+pub(crate) fn integrity_path(path: &Path) -> PathBuf {
+    let mut os: OsString = path.as_os_str().to_owned();
+    os.push(".integrity");
+    os.into()
+}
@@ -0,0 +1,35 @@
+//! Data made of interleaved streams, such as the channels of audio samples, shown one stream at a
+//! time in reports.
+
+/// Layout of [`ExpectFile::interleaved`](crate::ExpectFile::interleaved) data: frames of one
+/// element of each stream in turn.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Interleave {
+    pub(crate) streams: usize,
+    pub(crate) element_size: usize,
+}
+
+impl Interleave {
+    fn frame_size(self) -> usize {
+        self.streams * self.element_size
+    }
+
+    /// Returns the bytes of the elements of `stream` that are entirely in `data`, which starts at
+    /// `offset` in the interleaved data, and the offset of the first of them in the stream.
+    pub(crate) fn extract(self, data: &[u8], offset: usize, stream: usize) -> (Vec<u8>, usize) {
+        let frame_size = self.frame_size();
+        let first_frame =
+            offset / frame_size + usize::from(offset % frame_size > stream * self.element_size);
+        let mut bytes = Vec::new();
+        let mut frame = first_frame;
+        loop {
+            let start = frame * frame_size + stream * self.element_size - offset;
+            match data.get(start..start + self.element_size) {
+                Some(element) => bytes.extend_from_slice(element),
+                None => break,
+            }
+            frame += 1;
+        }
+        (bytes, first_frame * self.element_size)
+    }
+}
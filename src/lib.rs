@@ -8,11 +8,51 @@
 //!
 //! expect_test_bytes::expect_file!["test_data/example"].assert_eq(actual);
 //! ```
+//!
+//! # Features
+//!
+//! - `anstream`: Strip colors when the output isn't a terminal, and support legacy Windows
+//!   consoles, using the [`anstream`](https://docs.rs/anstream) crate. Reports are styled with
+//!   [`anstyle`](https://docs.rs/anstyle). This requires Rust 1.70.
+//! - `arrow`: Compare Arrow IPC files and streams by their schema and column values with
+//!   [`ExpectFile::arrow`].
+//! - `bytes`: Check the remaining bytes of a [`bytes::Buf`](https://docs.rs/bytes), such as a
+//!   frame in a Tokio read buffer, with [`ExpectFile::assert_eq_buf`].
+//! - `cli`: Build the `cargo expect-bytes` subcommand, which lists the snapshots referenced by a
+//!   crate with their sizes, checks a snapshot against the output of a command with
+//!   `cargo expect-bytes verify <snapshot> -- <command>`, and runs `cargo test` in update mode
+//!   with `cargo expect-bytes bless`. Install it with
+//!   `cargo install expect-test-bytes --features cli`.
+//! - `der`: Compare ASN.1 DER data, such as certificates and keys, element by element with
+//!   [`ExpectFile::der`].
+//! - `encryption`: Store files encrypted with AES-256-GCM with [`ExpectFile::encrypted`], using
+//!   the [`aes-gcm`](https://docs.rs/aes-gcm) crate.
+//! - `git`: Remind to `git add` updated files when the process exits, and fail assertions on
+//!   files that aren't committed when the `EXPECT_BYTES_REQUIRE_CLEAN` environment variable is
+//!   set. This runs the `git` command.
+//! - `hexyl`: Show hex dumps rendered by [hexyl](https://docs.rs/hexyl) with
+//!   [`DiffStyle::Hexyl`]. This requires Rust 1.88.
+//! - `parquet`: Compare Parquet files by their schema and column values with
+//!   [`ExpectFile::parquet`].
+//! - `pcap`: Compare pcap and pcapng captures packet by packet with [`ExpectFile::pcap`].
+//! - `serde`: Compare decoded serde values with [`ExpectFile::assert_decodes_eq`] and serialized
+//!   ones with [`ExpectFile::assert_serialized_eq`] and [`ExpectFile::assert_canonical_json_eq`].
+//!   The current versions of its dependencies require a newer Rust than the crate.
+//! - `similar`: List every differing region in [`DiffStyle::SideBySide`] reports and use the
+//!   patience algorithm for [`DiffStyle::Unified`], using the [`similar`](https://docs.rs/similar)
+//!   crate.
+//! - `sqlite`: Compare SQLite databases by their schema and rows with [`ExpectFile::sqlite`].
 
-use std::fmt::Write as _;
-use std::path::PathBuf;
-use std::sync::atomic::{AtomicBool, Ordering};
-use std::{fmt, fs, io};
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::ffi::OsString;
+use std::io::{Read, Seek, Write as _};
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU8, Ordering};
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::{Duration, Instant};
+use std::{fmt, fs, io, process, thread};
 
 const UPDATE_EXPECT_VAR_NAME: &str = if cfg!(test) {
     "UPDATE_EXPECT_BYTES"
@@ -28,8 +68,207 @@ You can update all `expect!` tests by running:
 To update a single test, place the cursor on `expect` token and use `run` feature of rust-analyzer.
 ";
 
+/// Command line of an external diff tool to run on failure, e.g. `vbindiff {expected} {actual}`.
+const DIFF_TOOL_VAR_NAME: &str = "EXPECT_BYTES_DIFF_TOOL";
+
+#[cfg(feature = "arrow")]
+mod arrow;
+mod at_exit;
+mod batch;
+#[cfg(feature = "serde")]
+mod canonical_json;
+mod checksum;
+mod chunk_index;
+mod claims;
+#[cfg(feature = "cli")]
+#[doc(hidden)]
+pub mod cli;
+#[cfg(feature = "serde")]
+mod codec;
+#[cfg(any(feature = "arrow", feature = "parquet"))]
+mod columnar;
+mod compare;
+mod compression;
+mod container;
+mod contains;
+#[cfg(feature = "der")]
+mod der;
+mod embedded;
+#[cfg(feature = "encryption")]
+mod encryption;
+mod failure;
+mod fixture;
+mod floats;
+mod generations;
+#[cfg(feature = "git")]
+mod git;
+mod global;
+mod gzip;
+mod header;
+mod heatmap;
+#[cfg(feature = "hexyl")]
+mod hexyl_view;
+mod hooks;
+#[cfg(feature = "similar")]
+mod hunks;
+mod incremental;
+mod inline;
+mod integrity;
+mod interleave;
+mod line_endings;
+mod log;
+#[cfg(any(feature = "arrow", feature = "parquet"))]
+mod lz4;
+mod metrics;
+pub mod normalize;
+mod output;
+mod overlay;
+mod pager;
+mod panic_hook;
+#[cfg(feature = "parquet")]
+mod parquet;
+mod parts;
+mod patch;
+mod pattern;
+#[cfg(feature = "pcap")]
+mod pcap;
+mod pending;
+mod quarantine;
+mod ranges;
+mod raster;
+mod records;
+mod regressions;
+mod render;
+mod report;
+mod riff;
+mod run;
+mod seed;
+mod segments;
+mod sha256;
+#[cfg(feature = "parquet")]
+mod snappy;
+#[cfg(feature = "sqlite")]
+mod sqlite;
+mod stable;
+mod stats;
+mod structure;
+mod style;
+mod suppressions;
+mod symbols;
+mod unified;
+mod varint;
+mod xxd;
+mod zstd;
+
+pub use checksum::{ChecksumAlgorithm, ChecksumField};
+use chunk_index::{ChunkCheck, ChunkIndex};
+#[cfg(feature = "serde")]
+pub use codec::Codec;
+pub use compare::{Comparator, ComparisonResult};
+pub use container::{Container, ContainerError};
+pub use embedded::EmbeddedFile;
+pub use fixture::Fixture;
+pub use floats::FloatField;
+use global::Global;
+pub use hooks::{on_update, DiffDecoder, OffsetDescriber, UpdateHook, Validator};
+use incremental::Incremental;
+pub use incremental::{compare_readers, StreamDifference};
+use interleave::Interleave;
+pub use log::ExpectLog;
+pub use metrics::{aggregate_metrics, assertion_metrics, AggregateMetrics, AssertionMetrics};
+pub use normalize::Normalizer;
+pub use panic_hook::install_panic_hook;
+pub use pattern::{Pattern, PatternError};
+use pending::Pending;
+use raster::Raster;
+pub use records::LengthPrefix;
+pub use regressions::RegressionCorpus;
+pub use render::DiffStyle;
+use render::{
+    write_byte_diff, write_shift_hint, write_side_by_side, write_unified, BYTE_WINDOW_HALF_SIZE,
+};
+pub use report::Outcome;
+use structure::Structure;
+use style::{BOLD, NOTE, SUCCESS};
+use suppressions::{Date, Suppression};
+pub use symbols::{SymbolMap, SymbolMapError};
+
+/// Default [`DiffStyle`], e.g. `side-by-side`.
+const DIFF_STYLE_VAR_NAME: &str = "EXPECT_BYTES_DIFF_STYLE";
+
+/// Alternate root directory that relative snapshot paths are resolved against.
+const ROOT_VAR_NAME: &str = "EXPECT_BYTES_ROOT";
+
+/// Time between calls to the producer in [`ExpectFile::assert_eventually_eq`].
+const POLL_INTERVAL: Duration = Duration::from_millis(10);
+
 static HELP_PRINTED: AtomicBool = AtomicBool::new(false);
 
+/// The [`UpdateMode`] set with [`set_update_mode`] as its discriminant plus one, or 0 if it's
+/// taken from the environment.
+static UPDATE_MODE: AtomicU8 = AtomicU8::new(0);
+
+/// Paths set with [`set_read_only_paths`].
+static READ_ONLY_PATHS: Global<Mutex<Vec<PathBuf>>> = Global::new();
+
+/// Path and [`ExpectFile::slice`] window of an updated file.
+type UpdatedFile = (PathBuf, Option<(usize, usize)>);
+
+/// SHA-256 digests of the contents written to each file or slice in update mode during this run.
+static UPDATED_FILES: Global<Mutex<HashMap<UpdatedFile, [u8; 32]>>> = Global::new();
+
+/// Records that `contents` is being written to `path`, or to a `slice` of it.
+///
+/// Returns `false` if different contents were already written to it during this run.
+fn record_update(path: &Path, slice: Option<(usize, usize)>, contents: &[u8]) -> bool {
+    let path = canonicalize_parent(path);
+    let digest = sha256::sha256(contents);
+    let mut updated = UPDATED_FILES
+        .get_or_init(Mutex::default)
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner);
+    let key = format!("{}:{slice:?}", path.display());
+    *updated.entry((path, slice)).or_insert(digest) == digest && run::record_update(&key, &digest)
+}
+
+/// Returns the length of the file at `path`, or 0 if it can't be read.
+fn file_len(path: &Path) -> usize {
+    fs::metadata(path).map_or(0, |metadata| {
+        usize::try_from(metadata.len()).unwrap_or(usize::MAX)
+    })
+}
+
+/// Canonicalizes the parent of `path`, since the file itself may not exist yet.
+fn canonicalize_parent(path: &Path) -> PathBuf {
+    match (path.parent(), path.file_name()) {
+        (Some(parent), Some(file_name)) => fs::canonicalize(parent)
+            .map_or_else(|_| path.to_owned(), |parent| parent.join(file_name)),
+        _ => path.to_owned(),
+    }
+}
+
+/// Returns the help text the first time a failure is reported.
+fn help() -> &'static str {
+    // Tests are run in the same process in arbitrary order, and stable output must not depend on
+    // the order either
+    let print_help = cfg!(test)
+        || stable::is_enabled()
+        || run::first_in_run("help").unwrap_or_else(|| !HELP_PRINTED.swap(true, Ordering::SeqCst));
+    if print_help {
+        HELP
+    } else {
+        ""
+    }
+}
+
+/// Resolves a relative snapshot path against the alternate root directory, if it's set.
+fn resolve_root(path: PathBuf) -> PathBuf {
+    match std::env::var_os(ROOT_VAR_NAME) {
+        Some(root) if path.is_relative() => Path::new(&root).join(path),
+        _ => path,
+    }
+}
+
 /// Converts `ErrorKind::NotFound` to `Ok(None)`
 fn not_found_to_none<T>(res: io::Result<T>) -> io::Result<Option<T>> {
     match res {
@@ -39,168 +278,3204 @@ fn not_found_to_none<T>(res: io::Result<T>) -> io::Result<Option<T>> {
     }
 }
 
+/// Splits the diff tool command line on whitespace and substitutes the `{expected}` and `{actual}`
+/// placeholders with the given paths.
+fn diff_tool_command(command: &str, expected: &Path, actual: &Path) -> Vec<OsString> {
+    command
+        .split_whitespace()
+        .map(|arg| match arg {
+            "{expected}" => expected.as_os_str().to_owned(),
+            "{actual}" => actual.as_os_str().to_owned(),
+            _ => arg
+                .replace("{expected}", &expected.to_string_lossy())
+                .replace("{actual}", &actual.to_string_lossy())
+                .into(),
+        })
+        .collect()
+}
+
+/// Writes `contents` to `path`, or to the update directory if `EXPECT_BYTES_UPDATE_DIR` is set.
+///
+/// If that fails, the update is added to the patch script in `EXPECT_BYTES_PATCH` if it's set, or
+/// written to the overlay directory otherwise. If that fails too, the commands that apply it are
+/// written to the report.
+///
+/// Returns whether `contents` was written to `path` itself.
+///
+/// # Panics
+///
+/// Will panic if writing to the update directory fails.
+fn write_or_overlay<W: io::Write>(path: &Path, contents: &[u8], writer: &mut W) -> bool {
+    if let Some(dir) = overlay::update_dir() {
+        let (written, script) = overlay::write(&dir, path, contents).unwrap_or_else(|e| {
+            panic!(
+                "failed to write {} to the update directory: {e}",
+                path.display()
+            )
+        });
+        writeln!(
+            writer,
+            "{NOTE}note{NOTE:#}: wrote {} instead since {} is set, run `sh {}` to apply",
+            written.display(),
+            overlay::UPDATE_DIR_VAR_NAME,
+            script.display()
+        )
+        .unwrap();
+        return false;
+    }
+    let error = match fs::write(path, contents) {
+        Ok(()) => return true,
+        Err(e) => e,
+    };
+    let fallback_error = match patch::patch_path() {
+        Some(script) => match patch::append(&script, path, contents) {
+            Ok(()) => {
+                writeln!(
+                    writer,
+                    "{NOTE}warning{NOTE:#}: failed to write {}: {error}
+         added it to {} instead, run `sh {}` to apply",
+                    path.display(),
+                    script.display(),
+                    script.display()
+                )
+                .unwrap();
+                return false;
+            }
+            Err(e) => format!("writing to the patch {} failed: {e}", script.display()),
+        },
+        None => match overlay::write(&overlay::overlay_dir(), path, contents) {
+            Ok((written, script)) => {
+                writeln!(
+                    writer,
+                    "{NOTE}warning{NOTE:#}: failed to write {}: {error}
+         wrote {} instead, run `sh {}` to apply",
+                    path.display(),
+                    written.display(),
+                    script.display()
+                )
+                .unwrap();
+                return false;
+            }
+            Err(e) => format!("writing to the overlay directory failed: {e}"),
+        },
+    };
+    // Nothing could be written, so the update is only kept in the report
+    write!(
+        writer,
+        "{NOTE}warning{NOTE:#}: failed to write {}: {error}, and {fallback_error}
+         run these commands to apply the update:\n\n{}{}",
+        path.display(),
+        patch::cd_command(),
+        patch::commands(path, contents)
+    )
+    .unwrap();
+    false
+}
+
+/// Removes the file at `path`, returning `None` if it doesn't exist, or records the removal in
+/// the apply script of the update directory if `EXPECT_BYTES_UPDATE_DIR` is set.
+fn remove_or_overlay(path: &Path) -> io::Result<Option<()>> {
+    match overlay::update_dir() {
+        Some(dir) if path.exists() => overlay::remove(&dir, path).map(Some),
+        Some(_) => Ok(None),
+        None => not_found_to_none(fs::remove_file(path)),
+    }
+}
+
+/// Makes the first character of `s` uppercase.
+fn capitalize(s: &str) -> Cow<'_, str> {
+    match s.chars().next() {
+        Some(first) if first.is_lowercase() => {
+            Cow::Owned(first.to_uppercase().chain(s.chars().skip(1)).collect())
+        }
+        _ => Cow::Borrowed(s),
+    }
+}
+
+/// Length of the chunks compared as a whole before looking for the differing byte.
+const FAST_COMPARE_CHUNK_LEN: usize = 4096;
+
 /// Finds the first index where the elements of `a` and `b` differ.
 ///
 /// If the elements don't differ but the number of elements differ, the first index where only one
 /// slice has an element is returned.
+///
+/// Whole chunks are compared with `memcmp` first and only the differing one is scanned byte by
+/// byte, which keeps equal data, such as each read of a passing comparison against a large file,
+/// at the speed of `memcmp`.
 fn first_diff_index(a: &[u8], b: &[u8]) -> Option<usize> {
-    a.iter()
-        .zip(b.iter())
+    let len = a.len().min(b.len());
+    let (a_prefix, b_prefix) = (&a[..len], &b[..len]);
+    let chunk_start = a_prefix
+        .chunks(FAST_COMPARE_CHUNK_LEN)
+        .zip(b_prefix.chunks(FAST_COMPARE_CHUNK_LEN))
+        .position(|(a_chunk, b_chunk)| a_chunk != b_chunk)
+        .map_or(len, |chunk| chunk * FAST_COMPARE_CHUNK_LEN);
+    a_prefix[chunk_start..]
+        .iter()
+        .zip(&b_prefix[chunk_start..])
         .position(|(x, y)| x != y)
-        .or_else(|| (a.len() != b.len()).then(|| a.len().min(b.len())))
+        .map(|i| chunk_start + i)
+        .or_else(|| (a.len() != b.len()).then(|| len))
+}
+
+/// What [`ExpectFile`] assertions do when the file doesn't exist outside of update mode.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MissingPolicy {
+    /// Fail the assertion.
+    Fail,
+    /// Create the file from the actual data with a warning, for bootstrapping new snapshots.
+    Create,
+    /// Create the file from the actual data but fail the assertion, so that a wrong snapshot isn't
+    /// accepted on the run that created it. The next run checks against the new file.
+    CreateAndFail,
+    /// Pass the assertion with a message that it was skipped.
+    Skip,
+}
+
+impl Default for MissingPolicy {
+    fn default() -> Self {
+        Self::Fail
+    }
+}
+
+/// Which snapshots [`ExpectFile`] assertions update, set with [`set_update_mode`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UpdateMode {
+    /// Update mismatching snapshots and create missing ones, like `UPDATE_EXPECT=1`.
+    All,
+    /// Only create missing snapshots, and fail on mismatches.
+    New,
+    /// Don't update or create snapshots.
+    Off,
+}
+
+/// Which part of the file an assertion checks.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Part {
+    Whole,
+    Prefix,
+    Suffix,
+    /// The window of [`ExpectFile::slice`], whose contents are the expected data.
+    Slice {
+        offset: usize,
+    },
+}
+
+impl Part {
+    /// Returns the checked part of `expected` and its offset, or `None` if the file is too short.
+    fn expected_part(self, expected: &[u8], actual_len: usize) -> Option<(&[u8], usize)> {
+        let offset = match self {
+            Part::Whole => return Some((expected, 0)),
+            Part::Slice { offset } => return Some((expected, offset)),
+            Part::Prefix => (expected.len() >= actual_len).then(|| 0)?,
+            Part::Suffix => expected.len().checked_sub(actual_len)?,
+        };
+        Some((&expected[offset..offset + actual_len], offset))
+    }
+
+    /// Returns the updated file contents, which keep the rest of a file longer than `actual`.
+    fn merge<'a>(self, expected: Option<&[u8]>, actual: &'a [u8]) -> Cow<'a, [u8]> {
+        match (self, expected) {
+            (Part::Prefix, Some(expected)) if expected.len() > actual.len() => {
+                Cow::Owned([actual, &expected[actual.len()..]].concat())
+            }
+            (Part::Suffix, Some(expected)) if expected.len() > actual.len() => {
+                Cow::Owned([&expected[..expected.len() - actual.len()], actual].concat())
+            }
+            _ => Cow::Borrowed(actual),
+        }
+    }
+}
+
+/// Contents of a hash-only snapshot file.
+struct Digest {
+    hex: String,
+    len: Option<u64>,
+}
+
+impl Digest {
+    fn of(data: &[u8]) -> Self {
+        Self {
+            hex: sha256::to_hex(&sha256::sha256(data)),
+            len: Some(data.len() as u64),
+        }
+    }
+
+    /// Parses `sha256:<hex digest> [length]`.
+    fn parse(contents: &[u8]) -> Option<Self> {
+        let contents = std::str::from_utf8(contents).ok()?.trim();
+        let mut parts = contents.strip_prefix("sha256:")?.split_whitespace();
+        let hex = parts.next()?;
+        if hex.len() != 64 || !hex.bytes().all(|b| b.is_ascii_hexdigit()) {
+            return None;
+        }
+        let len = match parts.next() {
+            Some(len) => Some(len.parse().ok()?),
+            None => None,
+        };
+        Some(Self {
+            hex: hex.to_ascii_lowercase(),
+            len,
+        })
+    }
+
+    /// Formats the digest and length for the failure report.
+    fn describe(&self) -> String {
+        match self.len {
+            Some(len) => format!("{} ({len} bytes)", self.hex),
+            None => format!("{} (unknown length)", self.hex),
+        }
+    }
 }
 
-const BYTE_WINDOW_HALF_SIZE: usize = 4;
+impl fmt::Display for Digest {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "sha256:{}", self.hex)?;
+        if let Some(len) = self.len {
+            write!(f, " {len}")?;
+        }
+        Ok(())
+    }
+}
 
-struct ByteWindowDisplay<'a> {
-    data: &'a [u8],
-    diff_idx: usize,
-    is_expected: bool,
+/// Self-updating file.
+///
+/// [`ExpectFile::assert_eq`] updates the file when the `UPDATE_EXPECT` environment variable is
+/// set, or as configured with [`set_update_mode`].
+///
+/// Relative paths are resolved against the directory of the source file, which is itself
+/// relative to the directory `cargo` is run in. Setting the `EXPECT_BYTES_ROOT` environment
+/// variable resolves them against that directory instead, for sandboxed builds where the source
+/// tree isn't in the working directory.
+///
+/// Files starting with the magic bytes of gzip or zstd are decompressed before being compared,
+/// whatever their extension, unless the actual data is compressed too. Updating them writes the
+/// new data uncompressed inside the same format.
+///
+/// When any file was updated, the number of files created and overwritten, the bytes written and
+/// the largest updated file are printed when the process exits, so that blessing an unexpectedly
+/// large snapshot doesn't go unnoticed.
+///
+/// Setting the `EXPECT_BYTES_BATCH_UPDATE` environment variable in update mode prints one table of
+/// the updated files when the process exits instead of a line for each file, for blessing
+/// thousands of snapshots at once. The directories containing the updated files are then synced
+/// once each.
+///
+/// Setting the `EXPECT_BYTES_STABLE_OUTPUT` environment variable prints reports without colors,
+/// with the help text on every failure and never paged or cut short, so that their format only
+/// depends on the version of this crate.
+///
+/// Setting the `EXPECT_BYTES_FAIL_LINE` environment variable prints a single line like
+/// `EXPECT-FAIL path=src/test_data/frame.bin offset=0x1c expected_len=64 actual_len=66` after the
+/// report of each failure, for log aggregation systems to alert on.
+///
+/// Setting the `EXPECT_BYTES_ABORT_ON_FAILURE` environment variable exits the process after
+/// reporting the first failed assertion, instead of running the rest of a suite whose failures
+/// would likely cascade.
+///
+/// Setting the `EXPECT_BYTES_QUARANTINE` environment variable makes failing assertions pass and
+/// lists the mismatching files when the process exits, to survey snapshot drift before blessing
+/// it.
+///
+/// Setting the `EXPECT_BYTES_PENDING` environment variable writes the actual data of a failing
+/// assertion next to the file for review. Files named like `frame.snap.bin` get the layout of
+/// insta's binary snapshots, so `cargo insta review` can accept or reject them with the rest of
+/// the snapshots; other files get `.new` appended to the name.
+///
+/// Setting the `EXPECT_BYTES_CLAIMS` environment variable records the contents each test asserts
+/// for each file in a registry under the target directory, shared by the whole workspace, and
+/// fails when two tests assert different contents for the same file, such as after copying a test
+/// without renaming its file. Tests are told apart by their package, test binary and name.
+///
+/// Setting the `EXPECT_BYTES_REPORT` environment variable to a path appends a line of JSON like
+/// `{"test":"tests::frame","path":"src/test_data/frame.bin","status":"failed","first_diff":28}` to
+/// that file for each assertion, with the status `passed`, `updated`, `failed` or `quarantined`,
+/// for aggregating the results of all test processes without parsing their output.
+///
+/// Setting the `EXPECT_BYTES_SUPPRESSIONS` environment variable to the path of a checked-in file,
+/// such as in the `[env]` table of `.cargo/config.toml`, accepts known differences listed in it
+/// with a warning. Each line names a snapshot relative to the file, comma-separated byte ranges
+/// and an expiration date, a reason such as an issue link, or both, like
+/// `test_data/frame.bin 0x10..0x18,0x40..0x44 2025-06-30 https://github.com/org/repo/issues/12`.
+/// Differences outside the ranges, or after the date, fail the assertion again.
+///
+/// Under cargo-nextest, which runs each test in its own process, the help text is printed once per
+/// run and updates are serialized across the processes of the run, which also detect conflicting
+/// updates to the same file between them.
+#[derive(Clone)]
+#[allow(clippy::struct_excessive_bools)] // The flags are independent builder options
+pub struct ExpectFile {
+    #[doc(hidden)]
+    pub path: PathBuf,
+    comparator: Option<Arc<dyn Comparator>>,
+    hash_only: bool,
+    encrypted: bool,
+    chunk_size: Option<usize>,
+    part_size: Option<usize>,
+    slice: Option<(usize, usize)>,
+    update_hooks: Vec<Arc<UpdateHook>>,
+    validators: Vec<Arc<Validator>>,
+    normalizers: Vec<Arc<Normalizer>>,
+    checksum_fields: Vec<ChecksumField>,
+    float_fields: Vec<FloatField>,
+    only_ranges: Vec<Range<usize>>,
+    structure: Option<Structure>,
+    offset_describer: Option<Arc<OffsetDescriber>>,
+    diff_decoders: Vec<Arc<DiffDecoder>>,
+    diff_style: Option<DiffStyle>,
+    max_report_lines: Option<usize>,
+    max_output_bytes: Option<usize>,
+    color: Option<bool>,
+    backtrace: bool,
+    header_format: Option<String>,
+    position: Option<Position>,
+    update_mirror: Option<PathBuf>,
+    seed: Option<u64>,
+    keep_generations: Option<usize>,
+    interleave: Option<Interleave>,
+    raster: Option<Raster>,
+    symbol_map: Option<SymbolMap>,
+    heatmap_chunk_size: Option<usize>,
+    decode_varints: bool,
+    hexdump: bool,
+    integrity: bool,
+    missing_policy: MissingPolicy,
+    xfail: Option<String>,
+    update_override: Option<bool>,
+    read_only: bool,
+    fallback: Option<&'static [u8]>,
 }
-impl fmt::Display for ByteWindowDisplay<'_> {
+
+// Assertions can be built once and shared between threads, such as in a `static`
+const _: fn() = || {
+    fn assert_send_sync<T: Send + Sync>() {}
+    assert_send_sync::<ExpectFile>();
+};
+
+impl fmt::Debug for ExpectFile {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        let start = self.diff_idx.saturating_sub(BYTE_WINDOW_HALF_SIZE);
-        let end = self.data.len().min(self.diff_idx + BYTE_WINDOW_HALF_SIZE);
+        f.debug_struct("ExpectFile")
+            .field("path", &self.path)
+            .finish_non_exhaustive()
+    }
+}
+
+impl ExpectFile {
+    #[doc(hidden)]
+    #[must_use]
+    pub fn new(path: PathBuf) -> Self {
+        Self::at_resolved_path(resolve_root(path))
+    }
+
+    fn at_resolved_path(path: PathBuf) -> Self {
+        Self {
+            path,
+            comparator: None,
+            hash_only: false,
+            encrypted: false,
+            chunk_size: None,
+            part_size: None,
+            slice: None,
+            update_hooks: Vec::new(),
+            validators: Vec::new(),
+            normalizers: Vec::new(),
+            checksum_fields: Vec::new(),
+            float_fields: Vec::new(),
+            only_ranges: Vec::new(),
+            structure: None,
+            offset_describer: None,
+            diff_decoders: Vec::new(),
+            diff_style: None,
+            max_report_lines: None,
+            max_output_bytes: None,
+            color: None,
+            backtrace: false,
+            header_format: None,
+            position: None,
+            update_mirror: None,
+            seed: None,
+            keep_generations: None,
+            interleave: None,
+            raster: None,
+            symbol_map: None,
+            heatmap_chunk_size: None,
+            decode_varints: false,
+            hexdump: false,
+            integrity: false,
+            missing_policy: MissingPolicy::Fail,
+            xfail: None,
+            update_override: None,
+            read_only: false,
+            fallback: None,
+        }
+    }
+
+    /// Marks the assertion as a known failure.
+    ///
+    /// The failure is reported but doesn't fail the test, while an assertion that unexpectedly
+    /// passes does. The file is never updated, so it keeps the intended contents.
+    ///
+    /// ```
+    /// # use expect_test_bytes::expect_file;
+    /// expect_file!["test_data/example"]
+    ///     .xfail("tracking issue #123")
+    ///     .assert_eq(b"work in progress\n");
+    /// ```
+    #[must_use]
+    pub fn xfail(mut self, reason: impl Into<String>) -> Self {
+        self.xfail = Some(reason.into());
+        self
+    }
+
+    /// Updates the file whether or not update mode is active, such as for intermediate fixtures
+    /// that are always regenerated.
+    ///
+    /// Files marked with [`ExpectFile::xfail`] or [`ExpectFile::read_only`] are still never
+    /// updated.
+    #[must_use]
+    pub fn force_update(mut self) -> Self {
+        self.update_override = Some(true);
+        self
+    }
 
-        // same as `self.diff_idx.min(BYTE_WINDOW_HALF_SIZE)`
-        let translated_diff_idx = self.diff_idx - start;
+    /// Never updates the file, even in update mode, to protect critical snapshots from bulk
+    /// updates. Mismatches fail the assertion as they do outside of update mode.
+    #[must_use]
+    pub fn never_update(mut self) -> Self {
+        self.update_override = Some(false);
+        self
+    }
+
+    /// Never writes the file, for reference data such as RFC test vectors that must not be
+    /// regenerated. Unlike [`ExpectFile::never_update`], this also overrides
+    /// [`ExpectFile::force_update`], [`UpdateMode::New`] and missing policies that create the file,
+    /// so that a missing file fails the assertion unless it's skipped.
+    ///
+    /// Files can also be made read-only for the whole process with [`set_read_only_paths`].
+    #[must_use]
+    pub fn read_only(mut self) -> Self {
+        self.read_only = true;
+        self
+    }
+
+    /// Uses `data`, usually embedded with `include_bytes!`, as the file's contents when the file
+    /// can't be read, such as in a sandbox or when the test binary is run without the source
+    /// tree. Update mode still writes to the file.
+    ///
+    /// ```ignore
+    /// expect_test_bytes::expect_file!["test_data/frame.bin"]
+    ///     .fallback(include_bytes!("test_data/frame.bin"))
+    ///     .assert_eq(b"\x01\x02");
+    /// ```
+    #[must_use]
+    pub fn fallback(mut self, data: &'static [u8]) -> Self {
+        self.fallback = Some(data);
+        self
+    }
+
+    /// Returns whether the assertions of this file update it.
+    fn updates(&self) -> bool {
+        // Known failures must not be blessed
+        self.xfail.is_none()
+            && !self.is_read_only()
+            && self.update_override.unwrap_or_else(is_update_mode)
+    }
+
+    /// Returns whether the file is marked with [`ExpectFile::read_only`] or is under one of the
+    /// paths set with [`set_read_only_paths`].
+    fn is_read_only(&self) -> bool {
+        self.read_only
+            || READ_ONLY_PATHS
+                .get_or_init(Mutex::default)
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .iter()
+                .any(|path| self.path.starts_with(path))
+    }
+
+    /// Sets what happens when the file doesn't exist outside of update mode.
+    #[must_use]
+    pub fn on_missing(mut self, missing_policy: MissingPolicy) -> Self {
+        self.missing_policy = missing_policy;
+        self
+    }
+
+    /// Sets the maximum number of lines of the failure report that are printed.
+    ///
+    /// Longer reports are written to a file in the temporary directory and only their start and
+    /// the path of the file are printed. If the `EXPECT_BYTES_PAGE` environment variable is set,
+    /// they are shown with `$PAGER` instead.
+    ///
+    /// Defaults to the `EXPECT_BYTES_MAX_LINES` environment variable, or 200.
+    #[must_use]
+    pub fn max_report_lines(mut self, max_report_lines: usize) -> Self {
+        self.max_report_lines = Some(max_report_lines);
+        self
+    }
+
+    /// Sets the maximum number of bytes of the failure report that are printed, for reports of
+    /// many differences with wide windows.
+    ///
+    /// Longer reports are cut off at the last blank line that fits, followed by
+    /// "… output truncated (N more hunks)" counting the blank-line separated parts cut off. This
+    /// applies before [`ExpectFile::max_report_lines`], and a spilled report file still contains
+    /// the full report. Stable output is never cut off.
+    ///
+    /// Defaults to the `EXPECT_BYTES_MAX_OUTPUT_BYTES` environment variable, or no limit.
+    #[must_use]
+    pub fn max_output_bytes(mut self, max_output_bytes: usize) -> Self {
+        self.max_output_bytes = Some(max_output_bytes);
+        self
+    }
+
+    /// Prints the failure report with colors if `color` is true, or without them otherwise,
+    /// regardless of whether stdout is a terminal or `NO_COLOR` is set.
+    ///
+    /// This keeps the output of an assertion byte-stable, such as when it's captured into a log
+    /// that's compared itself. Stable output mode still strips colors. The totals of updated files
+    /// printed at exit are plain if any assertion that updated a file is.
+    #[must_use]
+    pub fn colored(mut self, color: bool) -> Self {
+        self.color = Some(color);
+        self
+    }
+
+    /// Prints the failure report without colors, like `colored(false)`.
+    #[must_use]
+    pub fn plain(self) -> Self {
+        self.colored(false)
+    }
+
+    /// Sets the template of the header line of failure reports, such as
+    /// `{file}({line},{column}): error EXP001: {message}` for MSVC-style problem matchers.
+    ///
+    /// `{message}` is replaced with the error, `{path}` with the path of the file and `{file}`,
+    /// `{line}` and `{column}` with the position of the [`expect_file!`] invocation. The header
+    /// isn't colored. Defaults to the `EXPECT_BYTES_HEADER_FORMAT` environment variable, or the
+    /// usual `error: {message}` line followed by an arrow pointing at the path.
+    #[must_use]
+    pub fn with_header_format(mut self, format: impl Into<String>) -> Self {
+        self.header_format = Some(format.into());
+        self
+    }
+
+    /// Writes updates to `path` instead of the file, for files that are generated, such as by a
+    /// build script into `OUT_DIR`, from a copy in the source tree.
+    ///
+    /// Updating the generated file itself would be undone by the next build. Relative paths are
+    /// resolved like [`ExpectFile::new`]. [`expect_out_file!`] sets this.
+    #[must_use]
+    pub fn update_mirror(mut self, path: PathBuf) -> Self {
+        self.update_mirror = Some(resolve_root(path));
+        self
+    }
+
+    /// Records `seed`, the seed of the random number generator that produced the data, in a
+    /// `.seed` file next to the file when it's updated, and prints it along with the recorded seed
+    /// when the contents differ.
+    ///
+    /// This lets reviewers regenerate the exact bytes of randomized but seeded pipelines.
+    #[must_use]
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.seed = Some(seed);
+        self
+    }
+
+    /// Keeps the previous contents of the file when it's updated, as `name.~1~`, and shifts the
+    /// older ones to `name.~2~` and so on up to `name.~N~` for `keep` generations.
+    ///
+    /// This lets the data be compared against the golden file it replaced while investigating a
+    /// change, without digging binary blobs out of version control. Encrypted and hash-only files
+    /// don't keep generations.
+    ///
+    /// Defaults to the `EXPECT_BYTES_KEEP_GENERATIONS` environment variable, or 0.
+    #[must_use]
+    pub fn keep_generations(mut self, keep: usize) -> Self {
+        self.keep_generations = Some(keep);
+        self
+    }
+
+    #[doc(hidden)]
+    #[must_use]
+    pub fn at(mut self, position: Position) -> Self {
+        self.position = Some(position);
+        self
+    }
+
+    /// Fails with a regular panic instead of a silent unwind, so that the location of the caller
+    /// is printed, as well as a backtrace if `RUST_BACKTRACE` is set.
+    ///
+    /// This finds the call path that produced the bytes when the assertion is in a helper used by
+    /// many tests. Defaults to whether the `EXPECT_BYTES_BACKTRACE` environment variable is set.
+    #[must_use]
+    pub fn with_backtrace(mut self) -> Self {
+        self.backtrace = true;
+        self
+    }
+
+    /// Declares a checksum stored in the data.
+    ///
+    /// When the assertion fails, the failure report notes checksum fields that don't match the
+    /// data they cover, which tells a forgotten checksum update apart from corrupted data.
+    ///
+    /// ```
+    /// # use expect_test_bytes::{expect_file, ChecksumAlgorithm, ChecksumField};
+    /// expect_file!["test_data/example"]
+    ///     .checksum_field(ChecksumField::new(ChecksumAlgorithm::Sum, 7..8, 0..7))
+    ///     .assert_eq(b"example\n");
+    /// ```
+    #[must_use]
+    pub fn checksum_field(mut self, field: ChecksumField) -> Self {
+        self.checksum_fields.push(field);
+        self
+    }
+
+    /// Declares floats stored in the data that may differ by a tolerance, such as computed
+    /// transforms next to exact identifiers.
+    ///
+    /// The rest of the data is still compared byte for byte. When a float outside its tolerance
+    /// differs, the failure report notes both values.
+    ///
+    /// ```
+    /// # use expect_test_bytes::{expect_file, FloatField};
+    /// expect_file!["test_data/example"]
+    ///     .float_field(FloatField::f32(0, 1e-3).repeated(4, 2))
+    ///     .assert_eq(b"example\n");
+    /// ```
+    #[must_use]
+    pub fn float_field(mut self, field: FloatField) -> Self {
+        self.float_fields.push(field);
+        self
+    }
+
+    /// Compares only the bytes in `ranges` and ignores the rest, such as the framing of a format
+    /// whose payload is still under construction.
+    ///
+    /// Data that ends before the end of a range still differs from data that doesn't, and updates
+    /// write all of the data.
+    ///
+    /// ```
+    /// # use expect_test_bytes::expect_file;
+    /// expect_file!["test_data/example"]
+    ///     .only_ranges(&[0..2, 7..8])
+    ///     .assert_eq(b"ex-----\n and more");
+    /// ```
+    #[must_use]
+    pub fn only_ranges(mut self, ranges: &[Range<usize>]) -> Self {
+        self.only_ranges.extend_from_slice(ranges);
+        self
+    }
+
+    /// Stores the file encrypted with AES-256-GCM, with the key as 64 hexadecimal digits in the
+    /// `EXPECT_BYTES_KEY` environment variable, for fixtures such as licensed or sensitive sample
+    /// data that can't be committed in the clear.
+    ///
+    /// The file is decrypted when it's read and encrypted when it's updated, so reports show the
+    /// decrypted contents. Updating a file with unchanged contents leaves it unchanged. Sliced
+    /// files aren't supported.
+    ///
+    /// # Panics
+    ///
+    /// Assertions will panic if the key isn't set or the file can't be decrypted with it.
+    #[cfg(feature = "encryption")]
+    #[must_use]
+    pub fn encrypted(mut self) -> Self {
+        self.encrypted = true;
+        self
+    }
+
+    /// Compares the data as an Arrow IPC file or stream by its schema and column values.
+    ///
+    /// Record batch boundaries, buffer layout and compression aren't compared, so data written
+    /// in different batches or with LZ4 or Zstandard compressed buffers is equal. The failure
+    /// report names the first differing field of the schema, or the column and row of the first
+    /// differing value with the record batch it's in. Nested and dictionary-encoded fields
+    /// aren't supported, and such data is compared byte-wise.
+    #[cfg(feature = "arrow")]
+    #[must_use]
+    pub fn arrow(mut self) -> Self {
+        self.structure = Some(Structure::Arrow);
+        self
+    }
+
+    /// Compares the data as ASN.1 DER, such as an X.509 certificate or a PKCS #8 key.
+    ///
+    /// The failure report names the first differing element by its path, such as
+    /// `SEQUENCE[0].SEQUENCE[1].INTEGER`, and shows a byte diff of its contents instead of the
+    /// whole encoding.
+    #[cfg(feature = "der")]
+    #[must_use]
+    pub fn der(mut self) -> Self {
+        self.structure = Some(Structure::Der);
+        self
+    }
+
+    /// Compares the data as Parquet files by their schema and column values.
+    ///
+    /// Row group boundaries, page layout, encodings and compression aren't compared, so files
+    /// written with different writer settings are equal. The failure report names the first
+    /// differing column of the schema, or the column and row of the first differing value with
+    /// the row group it's in. Nested schemas and the LZO and Brotli codecs aren't supported, and
+    /// such files are compared byte-wise.
+    #[cfg(feature = "parquet")]
+    #[must_use]
+    pub fn parquet(mut self) -> Self {
+        self.structure = Some(Structure::Parquet);
+        self
+    }
+
+    /// Compares the data as a pcap or pcapng capture.
+    ///
+    /// The failure report names the first differing packet, such as "packet #12 payload differs
+    /// at byte 5", instead of an offset into the whole capture. With `ignore_timestamps`, packet
+    /// timestamps aren't compared.
+    #[cfg(feature = "pcap")]
+    #[must_use]
+    pub fn pcap(mut self, ignore_timestamps: bool) -> Self {
+        self.structure = Some(Structure::Pcap { ignore_timestamps });
+        self
+    }
+
+    /// Compares the data as records, each with a length prefix counting the bytes after it.
+    ///
+    /// The failure report lists the differing records by their index, including records that
+    /// changed length without shifting the ones after them, and shows a byte diff of the first
+    /// one instead of the whole data.
+    ///
+    /// ```
+    /// # use expect_test_bytes::{expect_file, LengthPrefix};
+    /// expect_file!["test_data/records.bin"].records(LengthPrefix::U32Le);
+    /// ```
+    #[must_use]
+    pub fn records(mut self, prefix: LengthPrefix) -> Self {
+        self.structure = Some(Structure::Records(prefix));
+        self
+    }
+
+    /// Compares the data as a RIFF or IFF file, such as WAV, AVI, WebP or AIFF.
+    ///
+    /// The failure report names the first differing chunk by its path, such as `RIFF/LIST/fmt `,
+    /// and shows a byte diff of its data instead of the whole file.
+    #[must_use]
+    pub fn riff(mut self) -> Self {
+        self.structure = Some(Structure::Riff);
+        self
+    }
+
+    /// Compares the data as SQLite databases by their schema and the rows of each table.
+    ///
+    /// Page allocation, free pages and header counters aren't compared, so databases with the
+    /// same contents are equal regardless of how they were written or vacuumed. Rows are
+    /// compared in rowid order including their rowids, and the failure report names the first
+    /// differing schema entry or row.
+    #[cfg(feature = "sqlite")]
+    #[must_use]
+    pub fn sqlite(mut self) -> Self {
+        self.structure = Some(Structure::Sqlite);
+        self
+    }
+
+    /// Adds a callback that describes the first differing offset in the failure report.
+    ///
+    /// The description is shown next to the raw offset, to map it back to the format being
+    /// tested.
+    ///
+    /// ```
+    /// # use expect_test_bytes::expect_file;
+    /// // Fixed-size records with a 4-byte header
+    /// expect_file!["test_data/example"].describe_offsets(|offset| {
+    ///     let field = if offset % 16 < 4 { "header" } else { "payload" };
+    ///     format!("record {}, field `{field}`", offset / 16)
+    /// });
+    /// ```
+    #[must_use]
+    pub fn describe_offsets(
+        mut self,
+        describer: impl Fn(usize) -> String + Send + Sync + 'static,
+    ) -> Self {
+        self.offset_describer = Some(Arc::new(describer));
+        self
+    }
+
+    /// Adds a callback that decodes the structure at the first differing byte, such as flags, an
+    /// enum or a packed field, and shows it under the diff window for both sides.
+    ///
+    /// The callback receives the data shown in the window and the offset of the differing byte
+    /// in it, since only a window of the data may be available, and returns `None` if there's
+    /// nothing to decode. Decoders run in the order they were added.
+    ///
+    /// ```
+    /// # use expect_test_bytes::expect_file;
+    /// expect_file!["test_data/example"].decode_diff(|data, offset| {
+    ///     let byte = data.get(offset)?;
+    ///     byte.is_ascii().then(|| format!("{:?}", char::from(*byte)))
+    /// });
+    /// ```
+    #[must_use]
+    pub fn decode_diff(
+        mut self,
+        decoder: impl Fn(&[u8], usize) -> Option<String> + Send + Sync + 'static,
+    ) -> Self {
+        self.diff_decoders.push(Arc::new(decoder));
+        self
+    }
+
+    /// Declares the data as `streams` interleaved streams of `element_size`-byte elements, such
+    /// as `interleaved(2, 2)` for 16-bit stereo samples or `interleaved(4, 1)` for RGBA pixels.
+    ///
+    /// The failure report names the differing streams and shows the bytes of each one separately,
+    /// with offsets counted in the stream, instead of rows mixing all of them.
+    ///
+    /// # Panics
+    ///
+    /// Will panic if `streams` or `element_size` is zero.
+    #[must_use]
+    pub fn interleaved(mut self, streams: usize, element_size: usize) -> Self {
+        assert!(
+            streams > 0 && element_size > 0,
+            "interleaved data needs at least one stream of non-empty elements"
+        );
+        self.interleave = Some(Interleave {
+            streams,
+            element_size,
+        });
+        self
+    }
+
+    /// Declares the data as a raster image `width` pixels wide with `bytes_per_pixel` bytes per
+    /// pixel, stored row by row without padding, such as a framebuffer.
+    ///
+    /// The failure report names the pixel and channel of the first differing byte, such as
+    /// `pixel (x=37, y=12), channel G`, next to its offset. Channels of 3- and 4-byte pixels are
+    /// named as RGB and RGBA.
+    ///
+    /// # Panics
+    ///
+    /// Will panic if `width` or `bytes_per_pixel` is zero.
+    #[must_use]
+    pub fn raster(mut self, width: usize, bytes_per_pixel: usize) -> Self {
+        assert!(
+            width > 0 && bytes_per_pixel > 0,
+            "raster images need a width and pixels of at least one byte"
+        );
+        self.raster = Some(Raster {
+            width,
+            bytes_per_pixel,
+        });
+        self
+    }
+
+    /// Names the symbol and section of the first differing byte in the failure report, such as
+    /// ``in `main`+0x34 of section `.text` ``, for snapshots of compiled artifacts.
+    ///
+    /// ```
+    /// # use expect_test_bytes::{expect_file, SymbolMap};
+    /// let expect = expect_file!["test_data/symbols.o"];
+    /// let map = SymbolMap::from_elf(&std::fs::read(&expect.path).unwrap()).unwrap();
+    /// expect.symbol_map(map);
+    /// ```
+    #[must_use]
+    pub fn symbol_map(mut self, map: SymbolMap) -> Self {
+        self.symbol_map = Some(map);
+        self
+    }
+
+    /// Adds a table of the chunks of `chunk_size` bytes with the most differing bytes to the
+    /// failure report, showing where differences concentrate in large files.
+    ///
+    /// The whole file is read to count the differences.
+    ///
+    /// # Panics
+    ///
+    /// Will panic if `chunk_size` is zero.
+    #[must_use]
+    pub fn with_heatmap(mut self, chunk_size: usize) -> Self {
+        assert!(chunk_size > 0, "chunk size must be positive");
+        self.heatmap_chunk_size = Some(chunk_size);
+        self
+    }
+
+    /// Decodes the LEB128 varints containing the first differing byte on both sides and shows
+    /// their values in the failure report.
+    ///
+    /// A single changed varint otherwise looks like a run of differing continuation bytes.
+    #[must_use]
+    pub fn decode_varints(mut self) -> Self {
+        self.decode_varints = true;
+        self
+    }
+
+    /// Writes an `xxd` dump of the contents to a `.hexdump` file next to the file whenever it's
+    /// updated, so that reviews of snapshot updates show a meaningful text diff.
+    ///
+    /// Hash-only and encrypted files don't get a dump.
+    #[must_use]
+    pub fn with_hexdump(mut self) -> Self {
+        self.hexdump = true;
+        self
+    }
+
+    /// Writes the SHA-256 digest of the contents to an `.integrity` file next to the file whenever
+    /// it's updated, and checks the file against it before comparing.
+    ///
+    /// A file that was edited by hand or corrupted since its last update fails with "snapshot
+    /// integrity check failed" instead of a diff against the wrong expectations. Files without
+    /// the sidecar aren't checked until they're updated.
+    #[must_use]
+    pub fn with_integrity(mut self) -> Self {
+        self.integrity = true;
+        self
+    }
+
+    /// Sets how differing bytes are shown in the failure report.
+    ///
+    /// Defaults to the style in the `EXPECT_BYTES_DIFF_STYLE` environment variable (`window`,
+    /// `side-by-side`, `unified` or, with the `hexyl` feature, `hexyl`), or [`DiffStyle::Window`].
+    #[must_use]
+    pub fn with_diff_style(mut self, diff_style: DiffStyle) -> Self {
+        self.diff_style = Some(diff_style);
+        self
+    }
+
+    fn diff_style(&self) -> DiffStyle {
+        self.diff_style.unwrap_or_else(|| {
+            std::env::var(DIFF_STYLE_VAR_NAME)
+                .ok()
+                .and_then(|value| DiffStyle::from_env_value(&value))
+                .unwrap_or_default()
+        })
+    }
 
-        for (i, byte) in self.data[start..=end].iter().enumerate() {
-            if i != 0 {
-                write!(f, " ").unwrap();
+    /// Writes the diff section for `expected` and `actual`, which both start at `offset`, in the
+    /// configured style.
+    fn write_bytes_diff<W: io::Write>(
+        &self,
+        writer: &mut W,
+        expected: &[u8],
+        actual: &[u8],
+        offset: usize,
+        diff_idx: usize,
+    ) {
+        failure::record_diff(diff_idx);
+        match self.interleave {
+            Some(interleave) => {
+                self.write_stream_diffs(writer, interleave, expected, actual, offset);
+            }
+            None => self.write_styled_diff(writer, expected, actual, offset, diff_idx),
+        }
+        if self.decode_varints {
+            varint::write_note(writer, expected, actual, offset, diff_idx).unwrap();
+        }
+        self.write_decoded(writer, expected, actual, offset, diff_idx);
+    }
+
+    /// Writes what the diff decoders decode at `diff_idx` in `expected` and `actual`, which both
+    /// start at `offset`.
+    fn write_decoded<W: io::Write>(
+        &self,
+        writer: &mut W,
+        expected: &[u8],
+        actual: &[u8],
+        offset: usize,
+        diff_idx: usize,
+    ) {
+        let index = diff_idx.saturating_sub(offset);
+        for decoder in &self.diff_decoders {
+            let (expected, actual) = match (decoder(expected, index), decoder(actual, index)) {
+                (None, None) => continue,
+                (expected, actual) => (expected, actual),
+            };
+            let describe =
+                |decoded: Option<String>| decoded.unwrap_or_else(|| "nothing".to_owned());
+            writeln!(
+                writer,
+                "{NOTE}note{NOTE:#}: byte {diff_idx:#x} decodes as {}, expected {}",
+                describe(actual),
+                describe(expected)
+            )
+            .unwrap();
+        }
+    }
+
+    /// Writes the diff section of each differing stream of interleaved data.
+    fn write_stream_diffs<W: io::Write>(
+        &self,
+        writer: &mut W,
+        interleave: Interleave,
+        expected: &[u8],
+        actual: &[u8],
+        offset: usize,
+    ) {
+        let streams: Vec<_> = (0..interleave.streams)
+            .map(|stream| {
+                (
+                    stream,
+                    interleave.extract(expected, offset, stream),
+                    interleave.extract(actual, offset, stream),
+                )
+            })
+            // Windows of the data may end at different offsets
+            .filter(|(_, (expected, _), (actual, _))| {
+                expected.iter().zip(actual).any(|(e, a)| e != a)
+            })
+            .collect();
+        if streams.is_empty() {
+            // Only a partial frame at the end differs
+            let diff_idx = offset + first_diff_index(expected, actual).unwrap_or(0);
+            self.write_styled_diff(writer, expected, actual, offset, diff_idx);
+            return;
+        }
+        writeln!(
+            writer,
+            "Streams differ: {} of {}\n",
+            streams
+                .iter()
+                .map(|(stream, _, _)| stream.to_string())
+                .collect::<Vec<_>>()
+                .join(", "),
+            interleave.streams
+        )
+        .unwrap();
+        for (stream, (expected, stream_offset), (actual, _)) in streams {
+            let diff_idx = stream_offset + first_diff_index(&expected, &actual).unwrap_or(0);
+            writeln!(
+                writer,
+                "{BOLD}Stream {stream}{BOLD:#}: element {} differs",
+                diff_idx / interleave.element_size
+            )
+            .unwrap();
+            self.write_styled_diff(writer, &expected, &actual, stream_offset, diff_idx);
+        }
+    }
+
+    /// Writes the diff section in the configured style.
+    fn write_styled_diff<W: io::Write>(
+        &self,
+        writer: &mut W,
+        expected: &[u8],
+        actual: &[u8],
+        offset: usize,
+        diff_idx: usize,
+    ) {
+        match self.diff_style() {
+            DiffStyle::Window => {
+                write_byte_diff(writer, (expected, offset), (actual, offset), diff_idx);
+            }
+            DiffStyle::SideBySide => {
+                write_side_by_side(writer, expected, actual, offset, diff_idx);
+                #[cfg(feature = "similar")]
+                hunks::write_hunks(writer, expected, actual, offset);
+            }
+            DiffStyle::Unified => write_unified(writer, expected, actual, offset, diff_idx),
+            #[cfg(feature = "hexyl")]
+            DiffStyle::Hexyl => {
+                hexyl_view::write_dumps(writer, expected, actual, offset, diff_idx);
+            }
+        }
+    }
+
+    /// Registers a check that `actual` must pass before it's compared or written.
+    ///
+    /// A failing check fails the assertion with its message, even in update mode, so malformed
+    /// data is neither reported as a confusing byte diff nor written to the file.
+    ///
+    /// ```
+    /// # use expect_test_bytes::expect_file;
+    /// expect_file!["test_data/example"]
+    ///     .validate(|actual| match actual.first() {
+    ///         Some(b'e') => Ok(()),
+    ///         _ => Err("invalid header magic".to_owned()),
+    ///     })
+    ///     .assert_eq(b"example\n");
+    /// ```
+    #[must_use]
+    pub fn validate(
+        mut self,
+        validator: impl Fn(&[u8]) -> Result<(), String> + Send + Sync + 'static,
+    ) -> Self {
+        self.validators.push(Arc::new(validator));
+        self
+    }
+
+    /// Adds a callback that rewrites the actual data before it's validated, compared or written.
+    ///
+    /// Normalizers run in the order they were added. See [`normalize`] for ready-made ones.
+    #[must_use]
+    pub fn normalize(mut self, normalizer: impl Fn(&mut Vec<u8>) + Send + Sync + 'static) -> Self {
+        self.normalizers.push(Arc::new(normalizer));
+        self
+    }
+
+    /// Registers a callback that is run after this file is written in update mode, in addition
+    /// to the ones registered with [`on_update`].
+    #[must_use]
+    pub fn on_update(
+        mut self,
+        hook: impl Fn(&Path, Option<&[u8]>, &[u8]) + Send + Sync + 'static,
+    ) -> Self {
+        self.update_hooks.push(Arc::new(hook));
+        self
+    }
+
+    /// Maintains a per-chunk SHA-256 index next to the file, at `<path>.chunks`.
+    ///
+    /// When the index is up to date, a passing assertion doesn't read the file at all and a
+    /// failing one only reads the region around the first differing chunk. The index is written
+    /// in update mode and is meant to be committed along with the file. The file is compared in
+    /// full once its length no longer matches the index, so an index next to a file edited by hand
+    /// at the same length should be removed.
+    ///
+    /// # Panics
+    ///
+    /// Will panic if `chunk_size` is zero.
+    #[must_use]
+    pub fn with_chunk_index(mut self, chunk_size: usize) -> Self {
+        assert!(chunk_size != 0, "chunk size must be non-zero");
+        self.chunk_size = Some(chunk_size);
+        self
+    }
+
+    /// Splits files larger than `part_size` bytes into parts in update mode.
+    ///
+    /// The parts are written to `<path>.part000`, `<path>.part001`, and so on, to stay under file
+    /// size limits such as GitHub's 100 MB. Parts are read back transparently whenever the file
+    /// itself doesn't exist, with or without this option.
+    ///
+    /// # Panics
+    ///
+    /// Will panic if `part_size` is zero.
+    #[must_use]
+    pub fn split_into_parts(mut self, part_size: usize) -> Self {
+        assert!(part_size != 0, "part size must be non-zero");
+        self.part_size = Some(part_size);
+        self
+    }
+
+    /// Checks only the `len` bytes at `offset` of the file.
+    ///
+    /// Many tests can share one large fixture, such as a disk image, by each checking a different
+    /// window of it. Only the window is read, and in update mode it's replaced with the actual
+    /// data while the rest of the file is kept.
+    ///
+    /// ```no_run
+    /// # use expect_test_bytes::expect_file;
+    /// # let partition_table = [0; 512];
+    /// expect_file!["test_data/disk.img"]
+    ///     .slice(0x1be, 64)
+    ///     .assert_eq(&partition_table[0x1be..0x1fe]);
+    /// ```
+    #[must_use]
+    pub fn slice(mut self, offset: usize, len: usize) -> Self {
+        self.slice = Some((offset, len));
+        self
+    }
+
+    /// Stores only a SHA-256 digest of the data in the file instead of the data itself.
+    ///
+    /// The file contains a single line such as `sha256:<hex digest> <length>`, which is rewritten
+    /// in update mode. On failure, only the digests and lengths are reported.
+    #[must_use]
+    pub fn hash_only(mut self) -> Self {
+        self.hash_only = true;
+        self
+    }
+
+    /// Replaces strict byte equality with a custom [`Comparator`].
+    ///
+    /// The report and update behavior stay the same, so a differing file is still updated with
+    /// the exact bytes from `actual`.
+    #[must_use]
+    pub fn with_comparator(mut self, comparator: impl Comparator + 'static) -> Self {
+        self.comparator = Some(Arc::new(comparator));
+        self
+    }
+
+    fn compare(&self, expected: &[u8], actual: &[u8]) -> ComparisonResult {
+        match &self.comparator {
+            Some(comparator) => comparator.compare(expected, actual),
+            None => (expected == actual).into(),
+        }
+    }
+
+    /// Checks whether file's contents are equal to `actual`.
+    ///
+    /// When the `UPDATE_EXPECT` environment variable is set, the file is updated or created with
+    /// the data from `actual`. If the file can't be written, for example in a read-only sandbox,
+    /// the data is written under the directory in `EXPECT_BYTES_OVERLAY_DIR` (by default a
+    /// directory for the package in the temporary directory) along with an `apply.sh` script
+    /// that copies the updates of the latest test run into place. If `EXPECT_BYTES_PATCH` is set,
+    /// the data is added to the shell script at that path instead, which contains it in base64 and
+    /// can be applied to the package directory on another machine. When `EXPECT_BYTES_UPDATE_DIR`
+    /// is set, every update is written under that directory instead, for read-only checkouts whose
+    /// updates are applied elsewhere.
+    ///
+    /// When the `EXPECT_BYTES_DIFF_TOOL` environment variable is set to a command line such as
+    /// `vbindiff {expected} {actual}`, the tool is run on failure with `{actual}` replaced by a
+    /// temporary file containing `actual`.
+    ///
+    /// # Panics
+    ///
+    /// Will panic when the file's contents don't equal `actual` and `UPDATE_EXPECT` is not set or
+    /// if writing to stdout or updating the file fails.
+    #[track_caller]
+    pub fn assert_eq(&self, actual: &[u8]) {
+        self.run(|writer| self.assert_eq_nopanic_imp(actual, writer));
+    }
+
+    /// Checks whether the file's contents are equal to the contents of the file at `actual`.
+    ///
+    /// Both files are compared as they're read, so a large output file doesn't need to be loaded
+    /// into memory. Options that need all of the data, such as [normalizers] or a
+    /// [comparator], and update mode read the file at `actual` like [`ExpectFile::assert_eq`]
+    /// does.
+    ///
+    /// [normalizers]: ExpectFile::normalize
+    /// [comparator]: ExpectFile::with_comparator
+    ///
+    /// # Panics
+    ///
+    /// Will panic if reading either file fails, when the contents differ and `UPDATE_EXPECT` is
+    /// not set or if writing to stdout or updating the file fails.
+    #[track_caller]
+    pub fn assert_eq_path(&self, actual: impl AsRef<Path>) {
+        self.run(|writer| self.assert_eq_path_nopanic_imp(actual.as_ref(), writer));
+    }
+
+    /// Checks whether the file's contents are equal to the data from the current position of
+    /// `actual` to its end, such as a block device or a large temporary file.
+    ///
+    /// The data is compared as it's read like [`ExpectFile::assert_eq_path`] does, seeking back
+    /// in `actual` for the region around the first difference instead of keeping what was read.
+    ///
+    /// ```
+    /// use std::io::Cursor;
+    ///
+    /// expect_test_bytes::expect_file!["test_data/example"]
+    ///     .assert_eq_seekable(Cursor::new(b"example\n"));
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// Will panic if reading the file or `actual` fails, when the contents differ and
+    /// `UPDATE_EXPECT` is not set or if writing to stdout or updating the file fails.
+    #[track_caller]
+    pub fn assert_eq_seekable(&self, mut actual: impl Read + Seek) {
+        self.run(|writer| self.assert_eq_seekable_nopanic_imp(&mut actual, writer));
+    }
+
+    /// Checks whether the file's contents are equal to the concatenation of the `actual`
+    /// segments, such as the buffers of a vectored write, without concatenating them.
+    ///
+    /// The segments are compared as they're read like [`ExpectFile::assert_eq_path`] does, and
+    /// the report locates the first difference in its segment.
+    ///
+    /// ```
+    /// use std::io::IoSlice;
+    ///
+    /// expect_test_bytes::expect_file!["test_data/example"]
+    ///     .assert_eq_vectored(&[IoSlice::new(b"exam"), IoSlice::new(b"ple\n")]);
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// Will panic if reading the file fails, when the contents differ and `UPDATE_EXPECT` is not
+    /// set or if writing to stdout or updating the file fails.
+    #[track_caller]
+    pub fn assert_eq_vectored<T: std::ops::Deref<Target = [u8]>>(&self, actual: &[T]) {
+        self.run(|writer| {
+            self.assert_eq_segments_nopanic_imp(
+                actual.iter().map(|segment| &**segment),
+                "segment",
+                writer,
+            )
+        });
+    }
+
+    /// Checks whether the file's contents are equal to the concatenation of the chunks yielded by
+    /// `actual`, such as the output of a streaming encoder.
+    ///
+    /// The chunks are compared as they're yielded like [`ExpectFile::assert_eq_path`] does, and
+    /// the report notes which chunk contained the first difference.
+    ///
+    /// ```
+    /// expect_test_bytes::expect_file!["test_data/example"]
+    ///     .assert_eq_chunks(["exam", "ple\n"].iter().map(|chunk| chunk.as_bytes()));
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// Will panic if reading the file fails, when the contents differ and `UPDATE_EXPECT` is not
+    /// set or if writing to stdout or updating the file fails.
+    #[track_caller]
+    pub fn assert_eq_chunks(&self, actual: impl Iterator<Item = impl AsRef<[u8]>>) {
+        self.run(|writer| self.assert_eq_segments_nopanic_imp(actual, "chunk", writer));
+    }
+
+    /// Checks whether the file's contents are equal to the remaining bytes of `actual`, such as a
+    /// frame in a `BytesMut` read buffer, consuming them.
+    ///
+    /// The chunks of the buffer are compared as they're taken like
+    /// [`ExpectFile::assert_eq_chunks`] does. Taking a chunk of `Bytes` or `BytesMut` doesn't copy
+    /// it.
+    ///
+    /// ```
+    /// use bytes::Buf;
+    ///
+    /// let frame = bytes::Bytes::from_static(b"exam").chain(&b"ple\n"[..]);
+    /// expect_test_bytes::expect_file!["test_data/example"].assert_eq_buf(frame);
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// Will panic if reading the file fails, when the contents differ and `UPDATE_EXPECT` is not
+    /// set or if writing to stdout or updating the file fails.
+    #[cfg(feature = "bytes")]
+    #[track_caller]
+    pub fn assert_eq_buf(&self, mut actual: impl bytes::Buf) {
+        self.run(|writer| self.assert_eq_buf_nopanic_imp(&mut actual, writer));
+    }
+
+    /// Checks whether the file's contents are equal to all of the data read from stdin, such as
+    /// the output of a process piped into the test.
+    ///
+    /// The data is compared as it's read like [`ExpectFile::assert_eq_path`] does.
+    ///
+    /// # Panics
+    ///
+    /// Will panic if reading the file or stdin fails, when the contents differ and `UPDATE_EXPECT`
+    /// is not set or if writing to stdout or updating the file fails.
+    #[track_caller]
+    pub fn assert_eq_stdin(&self) {
+        self.run(|writer| self.assert_eq_reader_nopanic_imp(&mut io::stdin().lock(), writer));
+    }
+
+    /// Checks whether the file's contents are equal to all of the data read from the file
+    /// descriptor `fd`, such as a pipe inherited from the harness that started the test.
+    ///
+    /// The data is compared as it's read like [`ExpectFile::assert_eq_path`] does. `fd` is left
+    /// open.
+    ///
+    /// # Safety
+    ///
+    /// `fd` must be an open file descriptor that isn't used by anything else while it's read.
+    ///
+    /// # Panics
+    ///
+    /// Will panic if reading the file or `fd` fails, when the contents differ and `UPDATE_EXPECT`
+    /// is not set or if writing to stdout or updating the file fails.
+    #[cfg(unix)]
+    #[track_caller]
+    pub unsafe fn assert_eq_fd(&self, fd: std::os::unix::io::RawFd) {
+        use std::os::unix::io::FromRawFd;
+
+        // SAFETY: Upheld by the caller, and the descriptor isn't closed since it's not owned
+        let mut file = std::mem::ManuallyDrop::new(unsafe { fs::File::from_raw_fd(fd) });
+        self.run(|writer| self.assert_eq_reader_nopanic_imp(&mut *file, writer));
+    }
+
+    /// Checks whether the file's contents are equal to the bytes returned by `produce`, calling it
+    /// repeatedly until they are or `timeout` has elapsed.
+    ///
+    /// This is useful for bytes that are produced asynchronously, such as by a device or a
+    /// daemon. The last bytes produced are checked like [`ExpectFile::assert_eq`] does, which
+    /// means update mode only updates the file after `timeout` has elapsed.
+    ///
+    /// # Panics
+    ///
+    /// Will panic when the file's contents still don't equal the produced bytes after `timeout`
+    /// and `UPDATE_EXPECT` is not set or if writing to stdout or updating the file fails.
+    #[track_caller]
+    pub fn assert_eventually_eq<T: AsRef<[u8]>>(
+        &self,
+        timeout: Duration,
+        produce: impl FnMut() -> T,
+    ) {
+        self.run(|writer| self.assert_eventually_eq_nopanic_imp(timeout, produce, writer));
+    }
+
+    /// Checks whether the file and `actual` decode to equal values of type `T` with `codec`.
+    ///
+    /// On failure, the report shows a line diff of the pretty-printed `Debug` output of the
+    /// values. If either side fails to decode, the bytes are compared like
+    /// [`ExpectFile::assert_eq`] does. Since the decoded values are compared, different encodings
+    /// of the same value pass.
+    ///
+    /// # Panics
+    ///
+    /// Will panic when the values or the bytes differ and `UPDATE_EXPECT` is not set or if writing
+    /// to stdout or updating the file fails.
+    #[cfg(feature = "serde")]
+    #[track_caller]
+    pub fn assert_decodes_eq<T>(&self, actual: &[u8], codec: Codec)
+    where
+        T: serde::de::DeserializeOwned + fmt::Debug + PartialEq,
+    {
+        self.run(|writer| self.assert_decodes_eq_nopanic_imp::<T, _>(actual, codec, writer));
+    }
+
+    /// Checks whether the file's contents are equal to `value` serialized with `codec`.
+    ///
+    /// In update mode, the file is written with the freshly serialized bytes. This is useful for
+    /// testing the stability of wire formats.
+    ///
+    /// # Panics
+    ///
+    /// Will panic if serializing `value` fails, when the file's contents don't equal the
+    /// serialized bytes and `UPDATE_EXPECT` is not set or if writing to stdout or updating the
+    /// file fails.
+    #[cfg(feature = "serde")]
+    #[track_caller]
+    pub fn assert_serialized_eq<T: serde::Serialize + ?Sized>(&self, value: &T, codec: Codec) {
+        let actual = codec.encode(value).unwrap_or_else(|e| panic!("{e}"));
+        self.assert_eq(&actual);
+    }
+
+    /// Checks whether the file's contents are equal to `value` serialized as canonical JSON,
+    /// such as a `serde_json::Value`.
+    ///
+    /// The JSON has sorted object keys, no whitespace and a single representation of each number
+    /// and string, like [RFC 8785](https://www.rfc-editor.org/rfc/rfc8785) specifies, so the
+    /// order of the entries of hash maps doesn't cause spurious differences.
+    ///
+    /// ```no_run
+    /// # use std::collections::HashMap;
+    /// let headers = HashMap::from([("type", "frame"), ("codec", "opus")]);
+    /// expect_test_bytes::expect_file!["test_data/headers.json"]
+    ///     .assert_canonical_json_eq(&headers);
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// Will panic if serializing `value` fails, such as for floats that aren't finite or map
+    /// keys that aren't strings or numbers, when the file's contents don't equal the JSON and
+    /// `UPDATE_EXPECT` is not set or if writing to stdout or updating the file fails.
+    #[cfg(feature = "serde")]
+    #[track_caller]
+    pub fn assert_canonical_json_eq<T: serde::Serialize + ?Sized>(&self, value: &T) {
+        let actual = canonical_json::to_vec(value).unwrap_or_else(|e| panic!("{e}"));
+        self.assert_eq(&actual);
+    }
+
+    /// Checks whether the file starts with `actual`.
+    ///
+    /// In update mode, only the start of the file is replaced with `actual`, so the rest of the
+    /// file is kept.
+    ///
+    /// # Panics
+    ///
+    /// Will panic when the file doesn't start with `actual` and `UPDATE_EXPECT` is not set, if
+    /// writing to stdout or updating the file fails or if the file is [hash-only].
+    ///
+    /// [hash-only]: ExpectFile::hash_only
+    #[track_caller]
+    pub fn assert_prefix_eq(&self, actual: &[u8]) {
+        self.run(|writer| self.assert_part_nopanic_imp(actual, Part::Prefix, writer));
+    }
+
+    /// Checks whether the file ends with `actual`.
+    ///
+    /// In update mode, only the end of the file is replaced with `actual`, so the rest of the
+    /// file is kept.
+    ///
+    /// # Panics
+    ///
+    /// Will panic when the file doesn't end with `actual` and `UPDATE_EXPECT` is not set, if
+    /// writing to stdout or updating the file fails or if the file is [hash-only].
+    ///
+    /// [hash-only]: ExpectFile::hash_only
+    #[track_caller]
+    pub fn assert_suffix_eq(&self, actual: &[u8]) {
+        self.run(|writer| self.assert_part_nopanic_imp(actual, Part::Suffix, writer));
+    }
+
+    /// Checks whether the file's contents appear somewhere in `actual`.
+    ///
+    /// On failure, the longest partial match is reported. The file is never updated since the
+    /// intended contents are ambiguous.
+    ///
+    /// # Panics
+    ///
+    /// Will panic when `actual` doesn't contain the file's contents, if writing to stdout fails
+    /// or if the file is [hash-only].
+    ///
+    /// [hash-only]: ExpectFile::hash_only
+    #[track_caller]
+    pub fn assert_contains(&self, actual: &[u8]) {
+        self.run(|writer| self.assert_contains_nopanic_imp(actual, true, writer));
+    }
+
+    /// Checks whether `actual` appears somewhere in the file.
+    ///
+    /// On failure, the longest partial match is reported. The file is never updated since the
+    /// intended contents are ambiguous.
+    ///
+    /// # Panics
+    ///
+    /// Will panic when the file doesn't contain `actual`, if writing to stdout fails or if the
+    /// file is [hash-only].
+    ///
+    /// [hash-only]: ExpectFile::hash_only
+    #[track_caller]
+    pub fn assert_contained_in(&self, actual: &[u8]) {
+        self.run(|writer| self.assert_contains_nopanic_imp(actual, false, writer));
+    }
+
+    /// Checks whether the file, which is an `xxd` dump, or a `hexdump -C` dump, of the expected
+    /// bytes, decodes to `actual`.
+    ///
+    /// This keeps byte-exact snapshots in repositories that only allow text files. Failures show
+    /// a unified diff of the dumps, and in update mode the file is written as an `xxd` dump.
+    ///
+    /// ```no_run
+    /// expect_test_bytes::expect_file!["test_data/frame.bin.xxd"].assert_eq_hexdump(b"\x01\x02");
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// Will panic when the file's decoded contents don't equal `actual` or the file isn't a valid
+    /// dump and `UPDATE_EXPECT` is not set, if writing to stdout or updating the file fails or if
+    /// the file is [hash-only] or sliced.
+    ///
+    /// [hash-only]: ExpectFile::hash_only
+    #[track_caller]
+    pub fn assert_eq_hexdump(&self, actual: &[u8]) {
+        self.run(|writer| self.assert_eq_hexdump_nopanic_imp(actual, writer));
+    }
+
+    /// Checks each entry of a corpus against the file of the same name in the directory at the
+    /// file's path.
+    ///
+    /// Every entry is checked instead of stopping at the first mismatch, and the report ends with
+    /// a list of all mismatching entries. The options of the file apply to each entry, and in
+    /// update mode each differing entry is updated.
+    ///
+    /// ```no_run
+    /// # use expect_test_bytes::expect_file;
+    /// # fn decode(_: &[u8]) -> Vec<u8> { Vec::new() }
+    /// let inputs = [("empty.bin", &b""[..]), ("short.bin", b"\x01\x02")];
+    /// expect_file!["test_data/decoded"]
+    ///     .assert_all(inputs.iter().map(|(name, input)| (name, decode(input))));
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// Will panic when any entry differs from its file and `UPDATE_EXPECT` is not set, or if
+    /// writing to stdout or updating a file fails.
+    #[track_caller]
+    pub fn assert_all<N: AsRef<Path>, D: AsRef<[u8]>>(
+        &self,
+        entries: impl IntoIterator<Item = (N, D)>,
+    ) {
+        self.run(|writer| self.assert_all_nopanic_imp(entries, writer));
+    }
+
+    /// Runs `f` on every file of a corpus directory, such as a `cargo fuzz` corpus, and checks each
+    /// output against the file of the same name in the directory at the file's path.
+    ///
+    /// This is [`ExpectFile::assert_all`] over the outputs, so every input is checked, the report
+    /// lists all mismatching outputs and update mode writes them. The snapshots are kept out of
+    /// the corpus so that the fuzzer doesn't pick them up as inputs. Relative corpus paths are
+    /// resolved like [`Fixture::new`] does.
+    ///
+    /// ```no_run
+    /// # use expect_test_bytes::expect_file;
+    /// # fn decode(_: &[u8]) -> Vec<u8> { Vec::new() }
+    /// expect_file!["test_data/decode_outputs"].assert_corpus_stable("fuzz/corpus/decode", decode);
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// Will panic if reading the corpus fails, when any output differs from its file and
+    /// `UPDATE_EXPECT` is not set, or if writing to stdout or updating a file fails.
+    #[track_caller]
+    pub fn assert_corpus_stable<D: AsRef<[u8]>>(
+        &self,
+        corpus: impl AsRef<Path>,
+        mut f: impl FnMut(&[u8]) -> D,
+    ) {
+        let corpus = resolve_root(corpus.as_ref().to_owned());
+        let inputs = regressions::read_inputs(&corpus)
+            .unwrap_or_else(|e| panic!("failed to read {}: {e}", corpus.display()));
+        self.assert_all(inputs.iter().map(|(path, input)| {
+            // Inputs are files directly in the corpus directory, so they have names
+            (path.file_name().unwrap(), f(input))
+        }));
+    }
+
+    /// Checks whether the file is a [`Container`] holding the labeled `entries`, in order.
+    ///
+    /// All differing entries are reported together. See also [`expect_group!`].
+    ///
+    /// ```no_run
+    /// # let (header, body) = ([0; 4], [0; 16]);
+    /// expect_test_bytes::expect_file!["test_data/frame.etbc"]
+    ///     .assert_group(&[("header", &header[..]), ("body", &body[..])]);
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// Will panic if a label is repeated or the file is sliced, when any entry differs and
+    /// `UPDATE_EXPECT` is not set, or if writing to stdout or updating the file fails.
+    #[track_caller]
+    pub fn assert_group(&self, entries: &[(&str, &[u8])]) {
+        self.run(|writer| self.assert_group_nopanic_imp(entries, writer));
+    }
+
+    /// Runs an assertion with its report buffered, prints the report and panics if the assertion
+    /// failed.
+    #[track_caller]
+    fn run(&self, assertion: impl FnOnce(&mut Vec<u8>) -> Result<(), ()>) {
+        let mut report = Vec::new();
+        let start = Instant::now();
+        let result = assertion(&mut report);
+        let mut result = self.apply_xfail(result, &mut report);
+        let duration = start.elapsed();
+        #[cfg(feature = "git")]
+        if result.is_ok() {
+            result = self.check_committed(&mut report);
+        }
+        let mut status = report::Outcome::of(result);
+        if result.is_err() && quarantine::is_enabled() {
+            status = report::Outcome::Quarantined;
+            quarantine::record(&self.path);
+            writeln!(
+                report,
+                "{NOTE}quarantined{NOTE:#}: the failure above is ignored since {} is set",
+                quarantine::QUARANTINE_VAR_NAME
+            )
+            .unwrap();
+            result = Ok(());
+        }
+        let max_lines = self
+            .max_report_lines
+            .unwrap_or_else(pager::max_lines_from_env);
+        let max_bytes = self
+            .max_output_bytes
+            .or_else(pager::max_output_bytes_from_env);
+        let mut stdout = output::stdout_with_color(self.color);
+        pager::print_report(&report, max_lines, max_bytes, &mut stdout);
+        let details = failure::take();
+        let location = self.path.display().to_string();
+        report::record(&location, status, details);
+        metrics::record(&location, status, duration);
+        if let Err(()) = result {
+            let backtrace = self.backtrace || panic_hook::backtrace_from_env();
+            panic_hook::fail(location, details, backtrace);
+        }
+    }
+
+    /// Fails if the file isn't committed and `EXPECT_BYTES_REQUIRE_CLEAN` is set, outside of
+    /// update mode.
+    #[cfg(feature = "git")]
+    fn check_committed<W: io::Write>(&self, writer: &mut W) -> Result<(), ()> {
+        if !git::require_clean_from_env() || self.updates() {
+            return Ok(());
+        }
+        let status = match git::uncommitted([self.path.as_path()]) {
+            Some(uncommitted) => match uncommitted.first() {
+                Some(file) if file.untracked => "untracked",
+                Some(_) => "modified since the last commit",
+                None => return Ok(()),
+            },
+            // Not in a repository
+            None => return Ok(()),
+        };
+        self.write_error_header(writer, "snapshot isn't committed");
+        writeln!(
+            writer,
+            "
+The file is {status}. Commit it, or unset {} to allow uncommitted snapshots.
+",
+            git::REQUIRE_CLEAN_VAR_NAME
+        )
+        .unwrap();
+        Err(())
+    }
+
+    /// Inverts the result of an assertion marked with [`ExpectFile::xfail`].
+    fn apply_xfail<W: io::Write>(&self, result: Result<(), ()>, writer: &mut W) -> Result<(), ()> {
+        let reason = match &self.xfail {
+            Some(reason) => reason,
+            None => return result,
+        };
+        if result.is_ok() {
+            self.write_error_header(writer, "expect test unexpectedly passed");
+            writeln!(
+                writer,
+                "
+The assertion is marked as a known failure: {reason}
+Remove the `.xfail(..)` call if it has been fixed.
+"
+            )
+            .unwrap();
+            Err(())
+        } else {
+            writeln!(
+                writer,
+                "{NOTE}note{NOTE:#}: the failure above is expected: {reason}"
+            )
+            .unwrap();
+            Ok(())
+        }
+    }
+
+    fn assert_eq_nopanic_imp<W: io::Write>(&self, actual: &[u8], writer: &mut W) -> Result<(), ()> {
+        self.assert_part_nopanic_imp(actual, self.eq_part(), writer)
+    }
+
+    fn assert_all_nopanic_imp<N: AsRef<Path>, D: AsRef<[u8]>, W: io::Write>(
+        &self,
+        entries: impl IntoIterator<Item = (N, D)>,
+        writer: &mut W,
+    ) -> Result<(), ()> {
+        let update = self.updates();
+        let mut count = 0;
+        let mut mismatches = Vec::new();
+        for (name, data) in entries {
+            count += 1;
+            // Each entry is checked with the options of the file
+            let entry = Self {
+                path: self.path.join(name.as_ref()),
+                ..self.clone()
+            };
+            if let (true, Some(parent)) = (update, entry.path.parent()) {
+                // Otherwise new entries would be written to the overlay directory
+                let _ = fs::create_dir_all(parent);
+            }
+            if entry.assert_eq_nopanic_imp(data.as_ref(), writer).is_err() {
+                mismatches.push(name.as_ref().to_owned());
+            }
+        }
+        if mismatches.is_empty() {
+            return Ok(());
+        }
+        self.write_error_header(
+            writer,
+            &format!("{} of {count} corpus entries differ", mismatches.len()),
+        );
+        writeln!(writer).unwrap();
+        for name in mismatches {
+            writeln!(writer, "    {}", name.display()).unwrap();
+        }
+        Err(())
+    }
+
+    fn assert_group_nopanic_imp<W: io::Write>(
+        &self,
+        entries: &[(&str, &[u8])],
+        writer: &mut W,
+    ) -> Result<(), ()> {
+        assert!(
+            self.slice.is_none(),
+            "group assertions aren't supported for slices"
+        );
+        let mut actual = Container::new();
+        for (i, (label, data)) in entries.iter().enumerate() {
+            assert!(
+                entries[..i].iter().all(|(other, _)| other != label),
+                "label `{label}` is repeated in the group"
+            );
+            actual.push(*label, *data);
+            metrics::record_compared(data.len());
+        }
+        let update = self.updates();
+
+        let expected = self.read_expected();
+        let parsed = expected.as_deref().map(Container::parse);
+        if let Some(Ok(parsed)) = &parsed {
+            if *parsed == actual {
+                return Ok(());
+            }
+        }
+        if !update {
+            match &parsed {
+                None => {
+                    if let Some(result) = self.handle_missing(writer) {
+                        return result;
+                    }
+                }
+                Some(Ok(parsed)) => {
+                    self.write_group_diff(parsed, &actual, writer);
+                    return Err(());
+                }
+                Some(Err(e)) => {
+                    self.write_failure_header(writer, true);
+                    writeln!(writer, "The file isn't a snapshot container: {e}\n").unwrap();
+                    return Err(());
+                }
+            }
+        }
+        self.update(expected.as_deref(), &actual.to_bytes(), writer)
+    }
+
+    /// Writes the combined report of the entries that differ between the groups.
+    fn write_group_diff<W: io::Write>(
+        &self,
+        expected: &Container,
+        actual: &Container,
+        writer: &mut W,
+    ) {
+        let labels: Vec<&str> = actual
+            .entries()
+            .map(|(label, _)| label)
+            .chain(
+                expected
+                    .entries()
+                    .map(|(label, _)| label)
+                    .filter(|label| actual.get(label).is_none()),
+            )
+            .collect();
+        let differing: Vec<&str> = labels
+            .iter()
+            .copied()
+            .filter(|label| expected.get(label) != actual.get(label))
+            .collect();
+        self.write_error_header(
+            writer,
+            &if differing.is_empty() {
+                "group entries are out of order".to_owned()
+            } else {
+                format!(
+                    "{} of {} group entries differ",
+                    differing.len(),
+                    labels.len()
+                )
+            },
+        );
+        writeln!(writer, "{}", help()).unwrap();
+        if differing.is_empty() {
+            let order = |group: &Container| {
+                group
+                    .entries()
+                    .map(|(label, _)| format!("`{label}`"))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            };
+            writeln!(
+                writer,
+                "Expected {}, found {}\n",
+                order(expected),
+                order(actual)
+            )
+            .unwrap();
+        }
+        for label in differing {
+            write!(writer, "{BOLD}Entry `{label}`{BOLD:#}: ").unwrap();
+            match (expected.get(label), actual.get(label)) {
+                (Some(expected), Some(actual)) => {
+                    writeln!(
+                        writer,
+                        "{} bytes, expected {}\n",
+                        actual.len(),
+                        expected.len()
+                    )
+                    .unwrap();
+                    let diff_idx = first_diff_index(expected, actual).unwrap_or(0);
+                    self.write_bytes_diff(writer, expected, actual, 0, diff_idx);
+                    writeln!(writer).unwrap();
+                }
+                (None, _) => writeln!(writer, "not in the file\n").unwrap(),
+                (_, None) => writeln!(writer, "not in the actual group\n").unwrap(),
+            }
+        }
+    }
+
+    fn assert_eq_path_nopanic_imp<W: io::Write>(
+        &self,
+        actual_path: &Path,
+        writer: &mut W,
+    ) -> Result<(), ()> {
+        let update = self.updates();
+        let comparison = if self.streams_actual(update) {
+            incremental::compare_files(&self.path, actual_path)
+                .unwrap_or_else(|e| panic!("failed to compare {}: {e}", actual_path.display()))
+        } else {
+            None
+        };
+        let difference = match comparison {
+            Some(Ok(())) => {
+                metrics::record_compared(file_len(&self.path));
+                return Ok(());
+            }
+            Some(Err(difference)) => difference,
+            // Reports about missing files and update mode need the data
+            None => {
+                let actual = fs::read(actual_path)
+                    .unwrap_or_else(|e| panic!("failed to read {}: {e}", actual_path.display()));
+                return self.assert_eq_nopanic_imp(&actual, writer);
+            }
+        };
+        self.write_file_difference(difference, writer);
+        self.run_diff_tool_if_set_for_path(actual_path, writer);
+        Err(())
+    }
+
+    /// Whether the actual data read from a file or stream can be compared as it's read.
+    fn streams_actual(&self, update: bool) -> bool {
+        !update
+            && self.slice.is_none()
+            && self.normalizers.is_empty()
+            && self.validators.is_empty()
+            && self.compares_incrementally()
+    }
+
+    /// Writes the report of the first difference between the file and streamed actual data.
+    fn write_file_difference<W: io::Write>(&self, difference: StreamDifference, writer: &mut W) {
+        let StreamDifference {
+            diff_idx,
+            window_offset,
+            expected_window,
+            actual_window,
+            expected_len,
+            actual_len,
+        } = difference;
+        metrics::record_compared(actual_len);
+        failure::record_lens(Some(expected_len), actual_len);
+        self.write_failure_header(writer, true);
+        self.write_bytes_diff(
+            writer,
+            &expected_window,
+            &actual_window,
+            window_offset,
+            diff_idx,
+        );
+        self.write_offset_description(writer, diff_idx);
+    }
+
+    fn assert_eq_seekable_nopanic_imp<W: io::Write>(
+        &self,
+        actual: &mut (impl Read + Seek),
+        writer: &mut W,
+    ) -> Result<(), ()> {
+        let update = self.updates();
+        let comparison = if self.streams_actual(update) {
+            incremental::compare_seekable(&self.path, actual)
+                .unwrap_or_else(|e| panic!("failed to compare {}: {e}", self.path.display()))
+        } else {
+            None
+        };
+        match comparison {
+            Some(Ok(())) => {
+                metrics::record_compared(file_len(&self.path));
+                Ok(())
+            }
+            Some(Err(difference)) => {
+                self.write_file_difference(difference, writer);
+                Err(())
+            }
+            // Reports about missing files and update mode need the data
+            None => self.assert_eq_reader_nopanic_imp(actual, writer),
+        }
+    }
+
+    /// Compares the concatenation of `segments`, and locates the first difference in the
+    /// `kind` of segment containing it.
+    fn assert_eq_segments_nopanic_imp<W: io::Write>(
+        &self,
+        segments: impl Iterator<Item = impl AsRef<[u8]>>,
+        kind: &str,
+        writer: &mut W,
+    ) -> Result<(), ()> {
+        let mut reader = segments::SegmentReader::new(segments);
+        let result = self.assert_eq_reader_nopanic_imp(&mut reader, writer);
+        if let (Err(()), Some(diff_idx)) = (result, failure::first_diff()) {
+            segments::write_note(writer, reader.lens(), diff_idx, kind).unwrap();
+        }
+        result
+    }
+
+    #[cfg(feature = "bytes")]
+    fn assert_eq_buf_nopanic_imp<W: io::Write>(
+        &self,
+        actual: &mut impl bytes::Buf,
+        writer: &mut W,
+    ) -> Result<(), ()> {
+        let chunks = std::iter::from_fn(|| {
+            let len = actual.chunk().len();
+            (len != 0).then(|| actual.copy_to_bytes(len))
+        });
+        self.assert_eq_segments_nopanic_imp(chunks, "chunk", writer)
+    }
+
+    fn assert_eq_reader_nopanic_imp<W: io::Write>(
+        &self,
+        actual: &mut impl Read,
+        writer: &mut W,
+    ) -> Result<(), ()> {
+        let update = self.updates();
+        let comparison = if self.streams_actual(update) {
+            incremental::compare_reader(&self.path, actual)
+                .unwrap_or_else(|e| panic!("failed to compare {}: {e}", self.path.display()))
+        } else {
+            None
+        };
+        match comparison {
+            Some(Ok(())) => {
+                metrics::record_compared(file_len(&self.path));
+                Ok(())
+            }
+            Some(Err(difference)) => {
+                self.write_file_difference(difference, writer);
+                Err(())
+            }
+            // Reports about missing files and update mode need the data
+            None => {
+                let mut data = Vec::new();
+                actual
+                    .read_to_end(&mut data)
+                    .unwrap_or_else(|e| panic!("failed to read the actual data: {e}"));
+                self.assert_eq_nopanic_imp(&data, writer)
+            }
+        }
+    }
+
+    /// Returns whether the file can be compared as it's read, since neither the comparison nor
+    /// the report need all of it.
+    fn compares_incrementally(&self) -> bool {
+        self.comparator.is_none()
+            && !self.hash_only
+            && self.checksum_fields.is_empty()
+            && self.float_fields.is_empty()
+            && self.only_ranges.is_empty()
+            && self.structure.is_none()
+            && !self.encrypted
+            && self.heatmap_chunk_size.is_none()
+            && self.fallback.is_none()
+            && self.diff_style() == DiffStyle::Window
+            && compression::detect_file(&self.path).is_none()
+    }
+
+    /// Compares the file as it's read, or returns `None` if it doesn't exist.
+    fn assert_eq_incrementally<W: io::Write>(
+        &self,
+        actual: &[u8],
+        writer: &mut W,
+    ) -> Option<Result<(), ()>> {
+        let (diff_idx, window, window_offset, expected_len, common_suffix) =
+            match incremental::compare(&self.path, actual).unwrap()? {
+                Incremental::Equal => return Some(Ok(())),
+                Incremental::Different {
+                    diff_idx,
+                    window,
+                    window_offset,
+                    expected_len,
+                    common_suffix,
+                } => (diff_idx, window, window_offset, expected_len, common_suffix),
+            };
+        failure::record_lens(Some(expected_len), actual.len());
+        self.write_failure_header(writer, true);
+        self.write_bytes_diff(
+            writer,
+            &window,
+            &actual[window_offset..],
+            window_offset,
+            diff_idx,
+        );
+        render::write_common_lengths(
+            writer,
+            (diff_idx, common_suffix),
+            expected_len,
+            actual.len(),
+        )
+        .unwrap();
+        write_shift_hint(writer, (&window, window_offset), actual, diff_idx);
+        self.write_offset_description(writer, diff_idx);
+        let expected_byte = window.get(diff_idx - window_offset).copied();
+        if line_endings::could_be_converted(expected_len, expected_byte, actual, diff_idx) {
+            if let Some(expected) = self.read_expected() {
+                line_endings::write_hint(writer, &self.path, &expected, actual).unwrap();
+            }
+        }
+        self.run_diff_tool_if_set(actual, writer);
+        Some(Err(()))
+    }
+
+    /// Returns the part of the file checked by [`ExpectFile::assert_eq`].
+    fn eq_part(&self) -> Part {
+        self.slice
+            .map_or(Part::Whole, |(offset, _)| Part::Slice { offset })
+    }
+
+    /// Reads the data to compare `actual` against, which is the file as stored if `actual` is
+    /// compressed itself.
+    fn read_expected_for(&self, actual: &[u8]) -> Option<Vec<u8>> {
+        if self.slice.is_none() && compression::detect(actual).is_some() {
+            self.read_stored_raw()
+        } else {
+            self.read_expected()
+        }
+    }
+
+    /// Reads the file, or only the window of a slice.
+    fn read_expected(&self) -> Option<Vec<u8>> {
+        match self.slice {
+            // Slices of compressed files are windows of the decompressed data
+            Some((offset, len)) if compression::detect_file(&self.path).is_some() => {
+                self.read_stored().map(|data| {
+                    let start = offset.min(data.len());
+                    data[start..start + len.min(data.len() - start)].to_vec()
+                })
+            }
+            Some((offset, len)) => {
+                match (parts::read_window(&self.path, offset, len), self.fallback) {
+                    (Ok(Some(window)), _) => Some(window),
+                    (Ok(None) | Err(_), Some(fallback)) => {
+                        let start = offset.min(fallback.len());
+                        Some(fallback[start..start + len.min(fallback.len() - start)].to_vec())
+                    }
+                    (result, None) => result.unwrap(),
+                }
+            }
+            None => self.read_stored(),
+        }
+    }
+
+    /// Reads the whole file, decrypting it if it's encrypted and decompressing it if it starts
+    /// with the magic bytes of gzip or zstd.
+    fn read_stored(&self) -> Option<Vec<u8>> {
+        self.read_stored_raw().map(|data| self.decompress(data))
+    }
+
+    /// Decompresses `data` read from the file if it starts with the magic bytes of gzip or zstd.
+    fn decompress(&self, data: Vec<u8>) -> Vec<u8> {
+        match compression::detect(&data) {
+            Some(format) => compression::decompress(format, &data).unwrap_or_else(|e| {
+                panic!(
+                    "failed to decompress {} as {}: {e}",
+                    self.path.display(),
+                    format.name()
+                )
+            }),
+            None => data,
+        }
+    }
+
+    /// Reads the whole file, decrypting it if it's encrypted.
+    fn read_stored_raw(&self) -> Option<Vec<u8>> {
+        let data = match (parts::read(&self.path), self.fallback) {
+            (Ok(Some(data)), _) => Some(data),
+            (Ok(None) | Err(_), Some(fallback)) => Some(fallback.to_vec()),
+            (result, None) => result.unwrap(),
+        };
+        #[cfg(feature = "encryption")]
+        if self.encrypted {
+            return data.map(|data| {
+                encryption::decrypt(&data)
+                    .unwrap_or_else(|e| panic!("{}: {e}", self.path.display()))
+            });
+        }
+        data
+    }
+
+    /// Panics if the options of the file don't support checking `part` of it.
+    fn assert_supported(&self, part: Part) {
+        assert!(
+            part == Part::Whole || !self.hash_only,
+            "prefix, suffix and slice assertions aren't supported for hash-only files"
+        );
+        assert!(
+            self.slice.is_none() || matches!(part, Part::Slice { .. }),
+            "prefix and suffix assertions aren't supported for slices"
+        );
+        assert!(
+            self.slice.is_none() || !self.encrypted,
+            "slices aren't supported for encrypted files"
+        );
+    }
+
+    fn assert_part_nopanic_imp<W: io::Write>(
+        &self,
+        actual: &[u8],
+        part: Part,
+        writer: &mut W,
+    ) -> Result<(), ()> {
+        self.assert_supported(part);
+        let actual = &*self.normalized(actual);
+        let mut update = self.updates();
+
+        self.validate_actual(actual, writer)?;
+        if part == Part::Whole && self.slice.is_none() && claims::is_enabled() {
+            self.claim(actual, writer)?;
+        }
+        if self.integrity && !update {
+            self.check_integrity(writer)?;
+        }
+        let suppressions = if part == Part::Whole && !self.hash_only {
+            suppressions::for_path(&self.path)
+        } else {
+            Vec::new()
+        };
+
+        if let Some(chunk_size) = self.chunk_size {
+            if part == Part::Whole && suppressions.is_empty() {
+                if let Some(result) = self.assert_eq_by_chunks(actual, chunk_size, update, writer) {
+                    return result;
+                }
+            }
+        }
+
+        // Pending snapshots need the whole actual data
+        if part == Part::Whole
+            && !update
+            && !pending::is_enabled()
+            && suppressions.is_empty()
+            && self.compares_incrementally()
+        {
+            if let Some(result) = self.assert_eq_incrementally(actual, writer) {
+                return result;
+            }
+        }
+
+        let expected = self.read_expected_for(actual);
+        let actual_digest = self.hash_only.then(|| Digest::of(actual));
+        if let Some(expected) = &expected {
+            if self.is_equal(expected, actual, actual_digest.as_ref(), part) {
+                return Ok(());
+            }
+            if !update && self.is_suppressed(&suppressions, expected, actual, writer) {
+                return Ok(());
+            }
+        }
+        let contents = || match &actual_digest {
+            Some(actual_digest) => Cow::Owned(format!("{actual_digest}\n").into_bytes()),
+            None => part.merge(expected.as_deref(), actual),
+        };
+        if expected.is_none() && !update {
+            match self.handle_missing(writer) {
+                Some(Err(())) => {
+                    self.write_pending(&contents(), writer);
+                    return Err(());
+                }
+                Some(Ok(())) => return Ok(()),
+                None => update = true,
+            }
+        }
+        if update {
+            return self.update(expected.as_deref(), &contents(), writer);
+        }
+
+        failure::record_lens(expected.as_ref().map(Vec::len), actual.len());
+        self.write_failure_header(writer, expected.is_some());
+        if let Some(expected) = &expected {
+            self.write_diff(expected, actual, actual_digest.as_ref(), part, writer);
+            suppressions::write_expired(writer, &suppressions, (expected, actual), Date::today())
+                .unwrap();
+        }
+        self.write_pending(&contents(), writer);
+
+        Err(())
+    }
+
+    /// Fails if the file doesn't match the digest recorded when it was last updated.
+    fn check_integrity<W: io::Write>(&self, writer: &mut W) -> Result<(), ()> {
+        let sidecar = integrity::integrity_path(&self.path);
+        let recorded = match not_found_to_none(fs::read(&sidecar)).unwrap() {
+            Some(recorded) => Digest::parse(&recorded)
+                .unwrap_or_else(|| panic!("{} isn't a valid digest", sidecar.display())),
+            None => return Ok(()),
+        };
+        let stored = match self.read_stored() {
+            Some(stored) => Digest::of(&stored),
+            // Left to the missing file policy
+            None => return Ok(()),
+        };
+        if stored.hex == recorded.hex && recorded.len.map_or(true, |len| stored.len == Some(len)) {
+            return Ok(());
+        }
+        self.write_error_header(writer, "snapshot integrity check failed");
+        writeln!(
+            writer,
+            "
+The file was changed since it was last updated:
+    recorded: {}
+    file:     {}
+
+Restore it from version control, or bless the actual data with `env UPDATE_EXPECT=1 cargo test`.
+",
+            recorded.describe(),
+            stored.describe()
+        )
+        .unwrap();
+        Err(())
+    }
+
+    /// Compares the file by the digests of its chunks, or returns `None` if the comparison
+    /// needs the whole file.
+    fn assert_eq_by_chunks<W: io::Write>(
+        &self,
+        actual: &[u8],
+        chunk_size: usize,
+        update: bool,
+        writer: &mut W,
+    ) -> Option<Result<(), ()>> {
+        if self.comparator.is_some()
+            || self.hash_only
+            || checksum::any_excluded(&self.checksum_fields)
+            || !self.float_fields.is_empty()
+            || !self.only_ranges.is_empty()
+            || self.structure.is_some()
+            || self.encrypted
+            || self.heatmap_chunk_size.is_some()
+            || compression::detect_file(&self.path).is_some()
+        {
+            return None;
+        }
+        match self.check_chunk_index(actual, chunk_size) {
+            Some(ChunkCheck::Equal) => Some(Ok(())),
+            Some(ChunkCheck::Different {
+                chunks,
+                window,
+                window_offset,
+            }) if !update && !pending::is_enabled() => {
+                failure::record_lens(None, actual.len());
+                self.write_failure_header(writer, true);
+                writeln!(
+                    writer,
+                    "Chunks differ: {} (chunk size {chunk_size:#x})\n",
+                    chunks
+                        .iter()
+                        .map(ToString::to_string)
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                )
+                .unwrap();
+                let diff_idx = window_offset
+                    + first_diff_index(&window, &actual[window_offset..]).unwrap_or(0);
+                let actual_window =
+                    &actual[window_offset..actual.len().min(window_offset + window.len() + 1)];
+                self.write_bytes_diff(writer, &window, actual_window, window_offset, diff_idx);
+                self.write_offset_description(writer, diff_idx);
+                self.run_diff_tool_if_set(actual, writer);
+                Some(Err(()))
+            }
+            _ => None,
+        }
+    }
+
+    /// Returns whether all differences are in the ranges of `suppressions` that haven't expired,
+    /// warning about each suppressed difference.
+    fn is_suppressed<W: io::Write>(
+        &self,
+        suppressions: &[Suppression],
+        expected: &[u8],
+        actual: &[u8],
+        writer: &mut W,
+    ) -> bool {
+        let today = Date::today();
+        let active: Vec<_> = suppressions
+            .iter()
+            .filter(|suppression| !suppression.is_expired(today))
+            .collect();
+        if active.is_empty()
+            || !self.is_equal(
+                &suppressions::mask(&active, expected),
+                &suppressions::mask(&active, actual),
+                None,
+                Part::Whole,
+            )
+        {
+            return false;
+        }
+        for suppression in active
+            .into_iter()
+            .filter(|suppression| suppression.differs(expected, actual))
+        {
+            let until = suppression
+                .until
+                .map_or_else(String::new, |until| format!(" until {until}"));
+            writeln!(
+                writer,
+                "{NOTE}warning{NOTE:#}: known difference in {} of {} accepted{until}",
+                suppression.describe(),
+                self.path.display()
+            )
+            .unwrap();
+        }
+        true
+    }
+
+    /// Claims the file for the current test with `actual`, failing if another test claimed it with
+    /// different contents.
+    fn claim<W: io::Write>(&self, actual: &[u8], writer: &mut W) -> Result<(), ()> {
+        let other = match claims::claim(&canonicalize_parent(&self.path), actual) {
+            Some(other) => other,
+            None => return Ok(()),
+        };
+        self.write_error_header(writer, "file claimed by another test");
+        writeln!(
+            writer,
+            "
+The test `{other}` asserts different contents for this file. Give one of the tests a file of its
+own, or delete `target/expect-test-bytes-claims` if that test no longer exists.
+"
+        )
+        .unwrap();
+        Err(())
+    }
+
+    /// Writes the pending snapshot with `contents` for reviewing the failure, if it's enabled.
+    ///
+    /// # Panics
+    ///
+    /// Will panic if writing the pending snapshot fails.
+    fn write_pending<W: io::Write>(&self, contents: &[u8], writer: &mut W) {
+        // The window of a slice can't be reviewed on its own, and encrypted files must not be
+        // written in the clear
+        if !pending::is_enabled() || self.slice.is_some() || self.encrypted {
+            return;
+        }
+        let pending = Pending::of(&self.path);
+        pending
+            .write(self.position.as_ref(), contents)
+            .unwrap_or_else(|e| {
+                panic!(
+                    "failed to write pending snapshot {}: {e}",
+                    pending.contents.display()
+                )
+            });
+        let review = if pending.metadata.is_some() {
+            "review it with `cargo insta review`"
+        } else {
+            "rename it over the file to accept it"
+        };
+        writeln!(
+            writer,
+            "{NOTE}note{NOTE:#}: pending snapshot written to {}; {review}",
+            pending.contents.display()
+        )
+        .unwrap();
+    }
+
+    /// Checks whether the file's contents `expected` match `actual`, whose digest is given for
+    /// hash-only files.
+    fn is_equal(
+        &self,
+        expected: &[u8],
+        actual: &[u8],
+        actual_digest: Option<&Digest>,
+        part: Part,
+    ) -> bool {
+        match actual_digest {
+            Some(actual_digest) => {
+                Digest::parse(expected).map_or(false, |d| d.hex == actual_digest.hex)
+            }
+            None if part == Part::Whole => {
+                if let Some(equal) = self
+                    .structure
+                    .and_then(|structure| structure.equal(expected, actual))
+                {
+                    return equal;
+                }
+                let actual = floats::tolerate(&self.float_fields, expected, actual);
+                self.compare(&self.masked(expected), &self.masked(actual))
+                    == ComparisonResult::Equal
+            }
+            None => part
+                .expected_part(expected, actual.len())
+                .map_or(false, |(expected, _)| {
+                    self.compare(expected, actual) == ComparisonResult::Equal
+                }),
+        }
+    }
+
+    /// Masks the bytes of `data` that aren't compared: excluded checksum fields and the bytes
+    /// outside of [`ExpectFile::only_ranges`].
+    fn masked<'a>(&self, data: impl Into<Cow<'a, [u8]>>) -> Cow<'a, [u8]> {
+        ranges::mask_outside(
+            &self.only_ranges,
+            checksum::mask_excluded(&self.checksum_fields, data),
+        )
+    }
+
+    fn assert_eventually_eq_nopanic_imp<T: AsRef<[u8]>, W: io::Write>(
+        &self,
+        timeout: Duration,
+        mut produce: impl FnMut() -> T,
+        writer: &mut W,
+    ) -> Result<(), ()> {
+        let deadline = Instant::now() + timeout;
+        loop {
+            let produced = produce();
+            let actual = self.normalized(produced.as_ref());
+            let equal = self.read_expected().map_or(false, |expected| {
+                let actual_digest = self.hash_only.then(|| Digest::of(&actual));
+                self.is_equal(&expected, &actual, actual_digest.as_ref(), self.eq_part())
+            });
+            if equal || Instant::now() >= deadline {
+                return self.assert_eq_nopanic_imp(produced.as_ref(), writer);
+            }
+            thread::sleep(POLL_INTERVAL);
+        }
+    }
+
+    #[cfg(feature = "serde")]
+    fn assert_decodes_eq_nopanic_imp<T, W: io::Write>(
+        &self,
+        actual: &[u8],
+        codec: Codec,
+        writer: &mut W,
+    ) -> Result<(), ()>
+    where
+        T: serde::de::DeserializeOwned + fmt::Debug + PartialEq,
+    {
+        let expected = match self.read_expected() {
+            Some(expected) => expected,
+            None => return self.assert_eq_nopanic_imp(actual, writer),
+        };
+        let update = self.updates();
+        match (codec.decode::<T>(&expected), codec.decode::<T>(actual)) {
+            (Ok(expected), Ok(actual)) if expected == actual => Ok(()),
+            (Ok(expected), Ok(actual_value)) if !update => {
+                self.validate_actual(actual, writer)?;
+                self.write_failure_header(writer, true);
+                let lines = |value: &T| -> Vec<String> {
+                    format!("{value:#?}").lines().map(str::to_owned).collect()
+                };
+                let diff = unified::unified_diff(&lines(&expected), &lines(&actual_value));
+                write!(
+                    writer,
+                    "{BOLD}Diff{BOLD:#}:
+Decoded values differ
+
+--- expected
++++ actual
+{diff}"
+                )
+                .unwrap();
+                Err(())
+            }
+            (Ok(_), Ok(_)) => self.assert_eq_nopanic_imp(actual, writer),
+            (expected_value, actual_value) => {
+                for (side, result) in [
+                    ("expected file", expected_value),
+                    ("actual data", actual_value),
+                ] {
+                    if let Err(e) = result {
+                        writeln!(
+                            writer,
+                            "{NOTE}warning{NOTE:#}: {e} in the {side}, comparing bytes"
+                        )
+                        .unwrap();
+                    }
+                }
+                self.assert_eq_nopanic_imp(actual, writer)
+            }
+        }
+    }
+
+    fn assert_eq_hexdump_nopanic_imp<W: io::Write>(
+        &self,
+        actual: &[u8],
+        writer: &mut W,
+    ) -> Result<(), ()> {
+        assert!(
+            !self.hash_only && self.slice.is_none(),
+            "hex dump assertions aren't supported for hash-only or sliced files"
+        );
+        let actual = &*self.normalized(actual);
+        self.validate_actual(actual, writer)?;
+        let mut dump = xxd::dump_lines(actual, 0).join("\n");
+        dump.push('\n');
+
+        let stored = self.read_stored();
+        let update = self.updates();
+        match stored
+            .as_deref()
+            .map(|stored| xxd::parse(&String::from_utf8_lossy(stored)))
+        {
+            Some(Ok(expected)) if expected == actual => Ok(()),
+            _ if update => self.update(stored.as_deref(), dump.as_bytes(), writer),
+            None => match self.handle_missing(writer) {
+                Some(result) => result,
+                None => self.update(None, dump.as_bytes(), writer),
+            },
+            Some(Ok(expected)) => {
+                failure::record_lens(Some(expected.len()), actual.len());
+                self.write_failure_header(writer, true);
+                let diff_idx = first_diff_index(&expected, actual).unwrap_or(0);
+                failure::record_diff(diff_idx);
+                write_unified(writer, &expected, actual, 0, diff_idx);
+                Err(())
+            }
+            Some(Err(line)) => {
+                self.write_failure_header(writer, true);
+                writeln!(writer, "Line {line} of the file isn't a valid hex dump\n").unwrap();
+                Err(())
+            }
+        }
+    }
+
+    /// Writes the diff section of the failure report.
+    fn write_diff<W: io::Write>(
+        &self,
+        expected: &[u8],
+        actual: &[u8],
+        actual_digest: Option<&Digest>,
+        part: Part,
+        writer: &mut W,
+    ) {
+        if let Some(actual_digest) = actual_digest {
+            let expect = Digest::parse(expected)
+                .map_or_else(|| format!("{BOLD}Invalid digest{BOLD:#}"), |d| d.describe());
+            writeln!(
+                writer,
+                "{BOLD}Diff{BOLD:#}:
+SHA-256 digests differ
+
+Expect: {expect}
+Actual: {actual}",
+                actual = actual_digest.describe(),
+            )
+            .unwrap();
+        } else if let Some(difference) = self
+            .structure
+            .filter(|_| part == Part::Whole)
+            .and_then(|structure| structure.first_difference(expected, actual))
+        {
+            writeln!(writer, "{}\n", capitalize(&difference.description)).unwrap();
+            if let Some((expected, actual)) = difference.regions {
+                let diff_idx = first_diff_index(&expected, &actual).unwrap_or(0);
+                self.write_bytes_diff(writer, &expected, &actual, 0, diff_idx);
             }
-            if i == translated_diff_idx {
-                let highlight_ansi_code = if self.is_expected { "32" } else { "31" };
-                write!(f, "\x1b[{highlight_ansi_code}m").unwrap();
+            self.run_diff_tool_if_set(actual, writer);
+        } else {
+            match part.expected_part(expected, actual.len()) {
+                Some((expected_part, offset)) => {
+                    let diff_idx = if part == Part::Whole {
+                        // Point at a difference that made the assertion fail
+                        first_diff_index(
+                            &self.masked(expected),
+                            &self.masked(floats::tolerate(&self.float_fields, expected, actual)),
+                        )
+                    } else {
+                        first_diff_index(expected_part, actual).map(|i| offset + i)
+                    }
+                    .unwrap_or(offset);
+                    self.write_bytes_diff(writer, expected_part, actual, offset, diff_idx);
+                    if part == Part::Whole {
+                        let prefix = first_diff_index(expected, actual)
+                            .unwrap_or_else(|| expected.len().min(actual.len()));
+                        let suffix = render::common_suffix_len(expected, actual, prefix);
+                        render::write_common_lengths(
+                            writer,
+                            (prefix, Some(suffix)),
+                            expected.len(),
+                            actual.len(),
+                        )
+                        .unwrap();
+                    }
+                    self.write_offset_description(writer, diff_idx);
+                    if part == Part::Whole {
+                        floats::write_note(writer, &self.float_fields, expected, actual, diff_idx)
+                            .unwrap();
+                        write_shift_hint(writer, (expected, 0), actual, diff_idx);
+                        line_endings::write_hint(writer, &self.path, expected, actual).unwrap();
+                        if let Some(chunk_size) = self.heatmap_chunk_size {
+                            heatmap::write(writer, expected, actual, chunk_size).unwrap();
+                        }
+                        checksum::write_stale_fields(
+                            &self.checksum_fields,
+                            expected,
+                            "the expected file",
+                            writer,
+                        );
+                        checksum::write_stale_fields(
+                            &self.checksum_fields,
+                            actual,
+                            "the actual data",
+                            writer,
+                        );
+                    }
+                }
+                None if part == Part::Suffix => {
+                    writeln!(
+                        writer,
+                        "The file is shorter than the suffix ({} < {} bytes)\n",
+                        expected.len(),
+                        actual.len()
+                    )
+                    .unwrap();
+                    // Align the ends
+                    let actual = &actual[actual.len() - expected.len()..];
+                    let diff_idx = first_diff_index(expected, actual).unwrap_or(0);
+                    self.write_bytes_diff(writer, expected, actual, 0, diff_idx);
+                    self.write_offset_description(writer, diff_idx);
+                }
+                None => {
+                    let diff_idx = first_diff_index(expected, actual).unwrap_or(0);
+                    self.write_bytes_diff(writer, expected, actual, 0, diff_idx);
+                    self.write_offset_description(writer, diff_idx);
+                }
             }
+            self.run_diff_tool_if_set(actual, writer);
+        }
+    }
 
-            write!(f, "{byte:02x}").unwrap();
-
-            if i == translated_diff_idx {
-                write!(f, "\x1b[0m").unwrap();
-            }
+    /// Writes the description of the first differing offset, if a describer is set.
+    fn write_offset_description<W: io::Write>(&self, writer: &mut W, diff_idx: usize) {
+        if let Some(describer) = &self.offset_describer {
+            writeln!(
+                writer,
+                "{NOTE}note{NOTE:#}: byte {diff_idx:#x} is {}",
+                describer(diff_idx)
+            )
+            .unwrap();
+        }
+        if let Some(raster) = self.raster {
+            writeln!(
+                writer,
+                "{NOTE}note{NOTE:#}: byte {diff_idx:#x} is {}",
+                raster.describe(diff_idx)
+            )
+            .unwrap();
+        }
+        if let Some(description) = self
+            .symbol_map
+            .as_ref()
+            .and_then(|map| map.describe(diff_idx))
+        {
+            writeln!(
+                writer,
+                "{NOTE}note{NOTE:#}: byte {diff_idx:#x} is {description}"
+            )
+            .unwrap();
         }
+    }
 
-        write!(f, " {}", CharacterPanel(&self.data[start..=end]))?;
-        Ok(())
+    /// Returns the path that updates are written to.
+    fn update_path(&self) -> &Path {
+        self.update_mirror.as_deref().unwrap_or(&self.path)
     }
-}
 
-/// <https://github.com/sharkdp/hexyl/blob/9ef7c346dda6320bb5d746810b9e93e1a66e7fc0/src/lib.rs#L30-L32>
-struct CharacterPanel<'a>(&'a [u8]);
-impl fmt::Display for CharacterPanel<'_> {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        for byte in self.0 {
-            let ch = match *byte {
-                0 => '⋄',
-                _ if byte.is_ascii_graphic() => *byte as char,
-                b' ' => ' ',
-                _ if byte.is_ascii_whitespace() => '_',
-                _ if byte.is_ascii() => '•',
-                _ => '×',
-            };
-            f.write_char(ch)?;
+    /// Writes `contents` to the file in update mode.
+    fn update<W: io::Write>(
+        &self,
+        expected: Option<&[u8]>,
+        contents: &[u8],
+        writer: &mut W,
+    ) -> Result<(), ()> {
+        let path = self.update_path();
+        // Other test processes of the run may be updating the same files
+        let _lock = run::lock();
+        if !record_update(path, self.slice, contents) {
+            self.write_error_header(writer, "conflicting snapshot");
+            writeln!(
+                writer,
+                "
+Another assertion already updated this file with different contents during this run.
+"
+            )
+            .unwrap();
+            return Err(());
+        }
+        // A slice's window is replaced in the rest of the file
+        let file;
+        let spliced;
+        let (expected, contents) = match self.slice {
+            Some(slice) => {
+                (file, spliced) = self.splice_slice(path, slice, contents, writer)?;
+                (file.as_deref(), &spliced[..])
+            }
+            None => (expected, contents),
+        };
+        stats::record(path, contents.len(), expected.is_none(), self.color);
+        report::record_update();
+        if !batch::is_enabled() {
+            writeln!(writer, "{SUCCESS}updating{SUCCESS:#}: {}", path.display()).unwrap();
+        }
+        let keep = self
+            .keep_generations
+            .unwrap_or_else(generations::keep_from_env);
+        // A generation of an encrypted file would be kept in the clear
+        if let (Some(previous), true, false, false) =
+            (expected, keep > 0, self.hash_only, self.encrypted)
+        {
+            if previous != contents {
+                if let Err(e) = generations::rotate(path, previous, keep) {
+                    writeln!(
+                        writer,
+                        "{NOTE}warning{NOTE:#}: failed to keep the previous contents of {}: {e}",
+                        path.display()
+                    )
+                    .unwrap();
+                }
+            }
+        }
+        let written = self.write_contents(contents, writer);
+        // The digests of an encrypted file's chunks would reveal its contents, and an index next
+        // to a file that wasn't written would describe other contents
+        if let (Some(chunk_size), false, false, true) =
+            (self.chunk_size, self.hash_only, self.encrypted, written)
+        {
+            let index = ChunkIndex::build(contents, chunk_size);
+            write_or_overlay(
+                &chunk_index::index_path(path),
+                index.to_string().as_bytes(),
+                writer,
+            );
+        }
+        if let Some(seed) = self.seed {
+            write_or_overlay(
+                &seed::seed_path(path),
+                format!("{seed}\n").as_bytes(),
+                writer,
+            );
+        }
+        // A dump of an encrypted file would reveal its contents
+        if self.hexdump && !self.hash_only && !self.encrypted {
+            let mut dump = xxd::dump_lines(contents, 0).join("\n");
+            dump.push('\n');
+            write_or_overlay(&xxd::dump_path(path), dump.as_bytes(), writer);
+        }
+        if self.integrity {
+            write_or_overlay(
+                &integrity::integrity_path(path),
+                format!("{}\n", Digest::of(contents)).as_bytes(),
+                writer,
+            );
+        }
+        for hook in &self.update_hooks {
+            hook(path, expected, contents);
+        }
+        hooks::run_update_hooks(path, expected, contents);
+        if expected.is_none()
+            && !self.updates()
+            && self.missing_policy == MissingPolicy::CreateAndFail
+        {
+            self.write_error_header(writer, "snapshot created, re-run to verify");
+            writeln!(writer).unwrap();
+            return Err(());
         }
         Ok(())
     }
-}
 
-/// Self-updating file.
-///
-/// [`ExpectFile::assert_eq`] updates the file when the `UPDATE_EXPECT` environment variable is
-/// set.
-#[derive(Debug)]
-pub struct ExpectFile {
-    #[doc(hidden)]
-    pub path: PathBuf,
-}
+    /// Reads the whole file at `path` and replaces the window of `slice` with `contents`.
+    ///
+    /// Returns the previous and the new contents of the file.
+    fn splice_slice<W: io::Write>(
+        &self,
+        path: &Path,
+        (offset, len): (usize, usize),
+        contents: &[u8],
+        writer: &mut W,
+    ) -> Result<(Option<Vec<u8>>, Vec<u8>), ()> {
+        // Slices of compressed files are windows of the decompressed data
+        let file = match parts::read(path) {
+            Ok(file) => file.map(|data| self.decompress(data)),
+            Err(e) => {
+                self.write_error_header(writer, "failed to update the slice");
+                writeln!(writer, "\nReading {} failed: {e}\n", path.display()).unwrap();
+                return Err(());
+            }
+        };
+        let mut new = file.clone().unwrap_or_default();
+        if new.len() < offset {
+            new.resize(offset, 0);
+        }
+        let end = offset.saturating_add(len).min(new.len());
+        new.splice(offset..end, contents.iter().copied());
+        Ok((file, new))
+    }
 
-impl ExpectFile {
-    /// Checks whether file's contents are equal to `actual`.
+    /// Writes `contents` to the file, or to its parts if it's larger than the part size.
     ///
-    /// When the `UPDATE_EXPECT` environment variable is set, the file is updated or created with
-    /// the data from `actual`.
+    /// Returns whether the file itself was written, rather than its parts or a fallback.
     ///
     /// # Panics
     ///
-    /// Will panic when the file's contents don't equal `actual` and `UPDATE_EXPECT` is not set or
-    /// if writing to stdout or updating the file fails.
-    pub fn assert_eq(&self, actual: &[u8]) {
-        if let Err(()) = self.assert_eq_nopanic_imp(actual, &mut io::stdout()) {
-            // Use resume_unwind instead of panic!() to prevent a backtrace, which is unnecessary noise.
-            std::panic::resume_unwind(Box::new(()));
+    /// Will panic if removing the previous file or parts fails.
+    fn write_contents<W: io::Write>(&self, contents: &[u8], writer: &mut W) -> bool {
+        let path = self.update_path();
+        // Updated compressed snapshots stay compressed in the same format
+        let compressed;
+        let contents = match self
+            .read_stored_raw()
+            .and_then(|stored| compression::detect(&stored))
+        {
+            Some(format) if compression::detect(contents).is_none() => {
+                compressed = compression::compress(format, contents);
+                &compressed
+            }
+            _ => contents,
+        };
+        #[cfg(feature = "encryption")]
+        let encrypted;
+        #[cfg(feature = "encryption")]
+        let contents = if self.encrypted {
+            encrypted =
+                encryption::encrypt(contents).unwrap_or_else(|e| panic!("{}: {e}", path.display()));
+            &encrypted
+        } else {
+            contents
+        };
+        let (written, result) = match self.part_size {
+            Some(part_size) if contents.len() > part_size => {
+                let mut parts = 0;
+                for part in contents.chunks(part_size) {
+                    write_or_overlay(&parts::part_path(path, parts), part, writer);
+                    parts += 1;
+                }
+                let result = remove_or_overlay(path)
+                    .and_then(|_| parts::remove_from(path, parts, remove_or_overlay));
+                (false, result)
+            }
+            _ => {
+                let written = write_or_overlay(path, contents, writer);
+                (written, parts::remove_from(path, 0, remove_or_overlay))
+            }
+        };
+        if let Err(e) = result {
+            panic!("failed to remove the previous snapshot: {e}");
         }
+        written
     }
-    fn assert_eq_nopanic_imp<W: io::Write>(&self, actual: &[u8], writer: &mut W) -> Result<(), ()> {
-        let expected = not_found_to_none(fs::read(&self.path)).unwrap();
-        if expected.as_deref() == Some(actual) {
-            return Ok(());
+
+    /// Applies the [`MissingPolicy`] when the file doesn't exist outside of update mode.
+    ///
+    /// Returns `None` if the file should be created.
+    fn handle_missing<W: io::Write>(&self, writer: &mut W) -> Option<Result<(), ()>> {
+        let creates_new = self.xfail.is_none()
+            && self.update_override.is_none()
+            && update_mode() == UpdateMode::New;
+        let missing_policy = match self.missing_policy {
+            MissingPolicy::Create | MissingPolicy::CreateAndFail if self.is_read_only() => {
+                MissingPolicy::Fail
+            }
+            _ if self.is_read_only() => self.missing_policy,
+            MissingPolicy::CreateAndFail => MissingPolicy::CreateAndFail,
+            _ if creates_new => MissingPolicy::Create,
+            missing_policy => missing_policy,
+        };
+        match missing_policy {
+            MissingPolicy::Fail => {
+                self.write_failure_header(writer, false);
+                Some(Err(()))
+            }
+            MissingPolicy::Create | MissingPolicy::CreateAndFail => {
+                writeln!(
+                    writer,
+                    "{NOTE}warning{NOTE:#}: creating missing {}",
+                    self.path.display()
+                )
+                .unwrap();
+                None
+            }
+            MissingPolicy::Skip => {
+                writeln!(
+                    writer,
+                    "{NOTE}skipped{NOTE:#}: {} doesn't exist",
+                    self.path.display()
+                )
+                .unwrap();
+                Some(Ok(()))
+            }
         }
-        if std::env::var_os(UPDATE_EXPECT_VAR_NAME).is_some() {
-            writeln!(
-                writer,
-                "\x1b[1m\x1b[92mupdating\x1b[0m: {}",
-                self.path.display()
-            )
-            .unwrap();
-            fs::write(&self.path, actual).unwrap();
+    }
+
+    /// Runs the normalizers on `actual`.
+    fn normalized<'a>(&self, actual: &'a [u8]) -> Cow<'a, [u8]> {
+        // Every assertion on data in memory starts by normalizing it
+        metrics::record_compared(actual.len());
+        if self.normalizers.is_empty() {
+            return Cow::Borrowed(actual);
+        }
+        let mut actual = actual.to_vec();
+        for normalizer in &self.normalizers {
+            normalizer(&mut actual);
+        }
+        Cow::Owned(actual)
+    }
+
+    /// Runs the validators on `actual`, reporting the first failure.
+    fn validate_actual<W: io::Write>(&self, actual: &[u8], writer: &mut W) -> Result<(), ()> {
+        for validator in &self.validators {
+            if let Err(message) = validator(actual) {
+                self.write_error_header(writer, "invalid actual data");
+                writeln!(writer, "\n{message}\n").unwrap();
+                return Err(());
+            }
+        }
+        Ok(())
+    }
+
+    fn assert_contains_nopanic_imp<W: io::Write>(
+        &self,
+        actual: &[u8],
+        file_in_actual: bool,
+        writer: &mut W,
+    ) -> Result<(), ()> {
+        assert!(
+            !self.hash_only,
+            "containment assertions aren't supported for hash-only files"
+        );
+        let actual = &*self.normalized(actual);
+        self.validate_actual(actual, writer)?;
+
+        let expected = self.read_expected();
+        let (haystack, needle) = match &expected {
+            Some(expected) if file_in_actual => (actual, &expected[..]),
+            Some(expected) => (&expected[..], actual),
+            None => {
+                return match self.handle_missing(writer) {
+                    Some(result) => result,
+                    None => self.update(None, actual, writer),
+                };
+            }
+        };
+        if contains::find(haystack, needle).is_some() {
             return Ok(());
         }
-        let print_help = if cfg!(test) {
-            true // Tests are run in the same process in arbitrary order
+
+        self.write_failure_header(writer, true);
+        let (offset, len) = contains::longest_partial_match(haystack, needle);
+        writeln!(
+            writer,
+            "{} not found in {}
+Longest partial match: {len} of {} bytes at offset {offset:#x}
+",
+            if file_in_actual { "File" } else { "Actual" },
+            if file_in_actual { "actual" } else { "file" },
+            needle.len(),
+        )
+        .unwrap();
+        let diff_idx = offset + len;
+        if file_in_actual {
+            write_byte_diff(writer, (needle, offset), (haystack, 0), diff_idx);
         } else {
-            !HELP_PRINTED.swap(true, Ordering::SeqCst)
-        };
-        let help = if print_help { HELP } else { "" };
+            write_byte_diff(writer, (haystack, 0), (needle, offset), diff_idx);
+        }
+        Err(())
+    }
+
+    /// Reads the chunk index and checks `actual` against it, if the index exists and is
+    /// up to date.
+    fn check_chunk_index(&self, actual: &[u8], chunk_size: usize) -> Option<ChunkCheck> {
+        // The index is written next to updated files, and is stale for any other file
+        let index = ChunkIndex::read(self.update_path()).ok()??;
+        index
+            .check(&self.path, actual, chunk_size, BYTE_WINDOW_HALF_SIZE)
+            .ok()?
+    }
 
+    fn write_failure_header<W: io::Write>(&self, writer: &mut W, expected_found: bool) {
+        self.write_error_header(writer, "expect test failed");
         writeln!(
             writer,
-            "
-\x1b[1m\x1b[91merror\x1b[97m: expect test failed\x1b[0m
-   \x1b[1m\x1b[34m-->\x1b[0m {location}
-{help}
-\x1b[1mExpect\x1b[0m:
+            "{help}
+{BOLD}Expect{BOLD:#}:
 {expect}
 
-\x1b[1mActual\x1b[0m:
+{BOLD}Actual{BOLD:#}:
 <binary>
 ",
-            location = self.path.display(),
-            expect = if expected.is_some() {
-                "<binary>"
+            help = help(),
+            expect = if expected_found {
+                "<binary>".to_owned()
             } else {
-                "\x1b[1mNot found\x1b[0m"
+                format!("{BOLD}Not found{BOLD:#}")
             },
         )
         .unwrap();
+        if self.fallback.is_some() && parts::read(&self.path).map_or(true, |data| data.is_none()) {
+            writeln!(
+                writer,
+                "{NOTE}note{NOTE:#}: the file couldn't be read, so the contents given with \
+                 `fallback()` were checked"
+            )
+            .unwrap();
+        }
+        if self.is_read_only() && self.update_override.unwrap_or_else(is_update_mode) {
+            writeln!(
+                writer,
+                "{NOTE}note{NOTE:#}: the file isn't written since it's read-only"
+            )
+            .unwrap();
+        } else if self.update_override == Some(false) && is_update_mode() {
+            writeln!(
+                writer,
+                "{NOTE}note{NOTE:#}: the file isn't updated since it's marked with `never_update()`"
+            )
+            .unwrap();
+        }
+        if let Some(seed) = self.seed {
+            let recorded = match seed::read(&self.path) {
+                Some(recorded) if recorded == seed => " like the file".to_owned(),
+                Some(recorded) => format!(", but the file with seed {recorded}"),
+                None => String::new(),
+            };
+            writeln!(
+                writer,
+                "{NOTE}note{NOTE:#}: the actual data was generated with seed {seed}{recorded}"
+            )
+            .unwrap();
+        }
+    }
+
+    /// Writes the header of a report about the file, pointing at the checked bytes.
+    fn write_error_header<W: io::Write>(&self, writer: &mut W, message: &str) {
+        let location = match self.slice {
+            Some((offset, len)) => format!(
+                "{} (bytes {offset:#x}..{:#x})",
+                self.path.display(),
+                offset.saturating_add(len)
+            ),
+            None => self.path.display().to_string(),
+        };
+        let format = self.header_format.clone().or_else(header::format_from_env);
+        header::write(
+            writer,
+            format.as_deref(),
+            message,
+            &location,
+            self.position.as_ref(),
+        );
+    }
+
+    fn run_diff_tool_if_set<W: io::Write>(&self, actual: &[u8], writer: &mut W) {
+        if let Some(command) = std::env::var_os(DIFF_TOOL_VAR_NAME) {
+            self.run_diff_tool(&command.to_string_lossy(), actual, writer);
+        }
+    }
 
-        if let Some(expected) = expected {
-            let diff_idx = first_diff_index(&expected, actual).unwrap_or(0);
+    fn run_diff_tool_if_set_for_path<W: io::Write>(&self, actual_path: &Path, writer: &mut W) {
+        if let Some(command) = std::env::var_os(DIFF_TOOL_VAR_NAME) {
+            self.spawn_diff_tool(&command.to_string_lossy(), actual_path, writer);
+        }
+    }
 
+    /// Writes `actual` to a temporary file and runs the external diff tool on it and the expected
+    /// file.
+    fn run_diff_tool<W: io::Write>(&self, command: &str, actual: &[u8], writer: &mut W) {
+        let file_name = self.path.file_name().unwrap_or_default().to_string_lossy();
+        let actual_path =
+            std::env::temp_dir().join(format!("{}-{file_name}.actual", process::id()));
+        if let Err(e) = fs::write(&actual_path, actual) {
             writeln!(
                 writer,
-                "\x1b[1mDiff\x1b[0m:
-Binary files differ at byte {diff_idx:#x}
-
-Expect: {expect}
-Actual: {actual}
-        {offset}\x1b[1m^^\x1b[0m",
-                expect = ByteWindowDisplay {
-                    data: &expected,
-                    diff_idx,
-                    is_expected: true
-                },
-                actual = ByteWindowDisplay {
-                    data: actual,
-                    diff_idx,
-                    is_expected: false
-                },
-                offset = "   ".repeat(diff_idx.min(BYTE_WINDOW_HALF_SIZE)),
+                "{BOLD}Diff tool{BOLD:#}: failed to write actual: {e}"
             )
             .unwrap();
+            return;
         }
+        self.spawn_diff_tool(command, &actual_path, writer);
+    }
 
-        Err(())
+    /// Runs the external diff tool on the expected file and the file at `actual_path`.
+    fn spawn_diff_tool<W: io::Write>(&self, command: &str, actual_path: &Path, writer: &mut W) {
+        let args = diff_tool_command(command, &self.path, actual_path);
+        let (program, args) = match args.split_first() {
+            Some(split) => split,
+            None => return,
+        };
+        writeln!(writer, "{BOLD}Diff tool{BOLD:#}: {command}").unwrap();
+        match process::Command::new(program).args(args).status() {
+            Ok(status) if status.success() => {}
+            // Diff tools conventionally exit with 1 when the files differ
+            Ok(status) if status.code() == Some(1) => {}
+            Ok(status) => writeln!(writer, "diff tool exited with {status}").unwrap(),
+            Err(e) => writeln!(writer, "failed to run diff tool: {e}").unwrap(),
+        }
     }
 }
 
@@ -213,19 +3488,346 @@ Actual: {actual}
 #[macro_export]
 macro_rules! expect_file {
     [$path:expr] => {
-        $crate::ExpectFile {
-            path: {
-                let path = ::std::path::Path::new($path);
+        $crate::ExpectFile::new({
+            let path = ::std::path::Path::new($path);
+            if path.is_absolute() {
+                path.to_owned()
+            } else {
+                ::std::path::Path::new(file!()).parent().unwrap().join(path)
+            }
+        })
+        .at($crate::Position {
+            file: file!(),
+            line: line!(),
+            column: column!(),
+        })
+    };
+}
+
+/// Creates an instance of [`ExpectFile`] for a file generated by the build script into `OUT_DIR`,
+/// with updates written to its copy in the source tree, at a path relative to the current file:
+///
+/// ```ignore
+/// # use expect_test_bytes::expect_out_file;
+/// expect_out_file!["tables.bin", mirror = "test_data/tables.bin"]
+///     .assert_eq(&reference_tables());
+/// ```
+///
+/// The build script is expected to generate the file from the copy, so that the next build picks
+/// up updates. `OUT_DIR` is only set for crates with a build script.
+#[macro_export]
+macro_rules! expect_out_file {
+    [$path:expr, mirror = $mirror:expr] => {
+        $crate::ExpectFile::new(::std::path::Path::new(env!("OUT_DIR")).join($path))
+            .update_mirror({
+                let path = ::std::path::Path::new($mirror);
                 if path.is_absolute() {
                     path.to_owned()
                 } else {
                     ::std::path::Path::new(file!()).parent().unwrap().join(path)
                 }
-            },
+            })
+            .at($crate::Position {
+                file: file!(),
+                line: line!(),
+                column: column!(),
+            })
+    };
+}
+
+/// Creates an [`EmbeddedFile`] from a path relative to the current file, embedding the file's
+/// contents at compile time.
+///
+/// The file must exist when the test is built, so create an empty one for a new snapshot and run
+/// the test in update mode.
+///
+/// ```
+/// expect_test_bytes::expect_bytes_file!["test_data/example"].assert_eq(b"example\n");
+/// ```
+#[macro_export]
+macro_rules! expect_bytes_file {
+    [$path:literal] => {
+        $crate::EmbeddedFile {
+            data: ::std::include_bytes!($path),
+            file: $crate::expect_file![$path],
         }
     };
 }
 
+/// Creates an [`ExpectLog`] checked against the file at the path, like [`expect_file!`].
+#[macro_export]
+macro_rules! expect_log {
+    [$path:expr] => {
+        $crate::ExpectLog::new($crate::expect_file![$path])
+    };
+}
+
+/// Checks `(label, data)` pairs against the [`Container`] file at the path, like
+/// [`ExpectFile::assert_group`].
+///
+/// ```no_run
+/// # let (header, body, trailer) = (vec![0; 4], vec![0; 16], vec![0; 2]);
+/// expect_test_bytes::expect_group![
+///     "test_data/frame.etbc",
+///     ("header", header),
+///     ("body", body),
+///     ("trailer", trailer),
+/// ];
+/// ```
+#[macro_export]
+macro_rules! expect_group {
+    [$path:expr, $(($label:expr, $data:expr)),+ $(,)?] => {
+        $crate::expect_file![$path]
+            .assert_group(&[$(($label, ::std::convert::AsRef::<[u8]>::as_ref(&$data))),+])
+    };
+}
+
+/// Returns whether update mode is active, which is when the [`UpdateMode`] is
+/// [`UpdateMode::All`].
+///
+/// Setup code can use it to regenerate intermediate fixtures only when blessing snapshots. Files
+/// marked with [`ExpectFile::force_update`] or [`ExpectFile::never_update`] ignore it.
+///
+/// ```
+/// # fn regenerate_fixtures() {}
+/// if expect_test_bytes::is_update_mode() {
+///     regenerate_fixtures();
+/// }
+/// ```
+#[must_use]
+pub fn is_update_mode() -> bool {
+    update_mode() == UpdateMode::All
+}
+
+/// Returns the [`UpdateMode`] set with [`set_update_mode`], or [`UpdateMode::All`] if the
+/// `UPDATE_EXPECT` environment variable is set and [`UpdateMode::Off`] otherwise.
+#[must_use]
+pub fn update_mode() -> UpdateMode {
+    match UPDATE_MODE.load(Ordering::SeqCst) {
+        1 => UpdateMode::All,
+        2 => UpdateMode::New,
+        3 => UpdateMode::Off,
+        _ if std::env::var_os(UPDATE_EXPECT_VAR_NAME).is_some() => UpdateMode::All,
+        _ => UpdateMode::Off,
+    }
+}
+
+/// Sets the [`UpdateMode`] of the process, overriding the `UPDATE_EXPECT` environment variable.
+///
+/// This is meant for custom test harnesses and wrappers where environment variables are awkward
+/// to pass through.
+///
+/// ```
+/// use expect_test_bytes::UpdateMode;
+///
+/// // Create snapshots of new tests, but never rewrite existing ones
+/// expect_test_bytes::set_update_mode(UpdateMode::New);
+/// # expect_test_bytes::set_update_mode(UpdateMode::Off);
+/// ```
+pub fn set_update_mode(mode: UpdateMode) {
+    UPDATE_MODE.store(mode as u8 + 1, Ordering::SeqCst);
+}
+
+/// Makes the files under `paths` read-only for the process, like [`ExpectFile::read_only`],
+/// replacing the paths of previous calls.
+///
+/// The paths are compared with the paths of the files as given to [`ExpectFile::new`], so they
+/// are usually relative to the directory `cargo` is run in.
+///
+/// ```
+/// // RFC test vectors are never regenerated, even with `UPDATE_EXPECT=1`
+/// expect_test_bytes::set_read_only_paths(["src/test_data/rfc8439"]);
+/// # expect_test_bytes::set_read_only_paths(Vec::<std::path::PathBuf>::new());
+/// ```
+pub fn set_read_only_paths(paths: impl IntoIterator<Item = impl Into<PathBuf>>) {
+    *READ_ONLY_PATHS
+        .get_or_init(Mutex::default)
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner) = paths.into_iter().map(Into::into).collect();
+}
+
+/// Checks whether `actual` matches `pattern`. See [`Pattern`] for the syntax.
+///
+/// On failure, the element of the pattern that failed furthest into `actual` is reported.
+///
+/// ```
+/// expect_test_bytes::assert_matches(b"\x01\x02nonce", r#"01 02 (len=5)"#);
+/// ```
+///
+/// # Panics
+///
+/// Will panic if `pattern` is invalid, when `actual` doesn't match it or if writing to stdout
+/// fails.
+#[track_caller]
+pub fn assert_matches(actual: &[u8], pattern: &str) {
+    let location = Position::caller();
+    let pattern = Pattern::parse(pattern).unwrap_or_else(|e| panic!("invalid pattern: {e}"));
+    run_inline(&location, |writer| {
+        assert_matches_nopanic_imp(actual, &pattern, &location, writer)
+    });
+}
+
+fn assert_matches_nopanic_imp<W: io::Write>(
+    actual: &[u8],
+    pattern: &Pattern,
+    location: &Position,
+    writer: &mut W,
+) -> Result<(), ()> {
+    metrics::record_compared(actual.len());
+    if pattern.is_match(actual) {
+        return Ok(());
+    }
+    write_inline_error_header(writer, "bytes don't match the pattern", location);
+    writeln!(writer).unwrap();
+    pattern.check(actual, writer)
+}
+
+/// Checks whether `actual` is `expected_len` bytes long.
+///
+/// When the `UPDATE_EXPECT` environment variable is set, `expected_len` is updated in the source
+/// code.
+///
+/// ```
+/// expect_test_bytes::assert_len(b"example\n", 8);
+/// ```
+///
+/// # Panics
+///
+/// Will panic when the length isn't `expected_len` and `UPDATE_EXPECT` is not set or if writing
+/// to stdout or updating the source code fails.
+#[track_caller]
+pub fn assert_len(actual: &[u8], expected_len: usize) {
+    let location = Position::caller();
+    run_inline(&location, |writer| {
+        metrics::record_compared(actual.len());
+        assert_inline_nopanic_imp(
+            &location,
+            actual.len() == expected_len,
+            (
+                &format!("{expected_len} bytes"),
+                &format!("{} bytes", actual.len()),
+            ),
+            &actual.len().to_string(),
+            writer,
+        )
+    });
+}
+
+/// Checks whether the SHA-256 digest of `actual` is `expected`, written in hex.
+///
+/// When the `UPDATE_EXPECT` environment variable is set, `expected` is updated in the source
+/// code.
+///
+/// ```
+/// expect_test_bytes::assert_sha256(
+///     b"example\n",
+///     "13550350a8681c84c861aac2e5b440161c2b33a3e4f302ac680ca5b686de48de",
+/// );
+/// ```
+///
+/// # Panics
+///
+/// Will panic when the digest isn't `expected` and `UPDATE_EXPECT` is not set or if writing
+/// to stdout or updating the source code fails.
+#[track_caller]
+pub fn assert_sha256(actual: &[u8], expected: &str) {
+    let location = Position::caller();
+    run_inline(&location, |writer| {
+        assert_sha256_nopanic_imp(&location, actual, expected, writer)
+    });
+}
+
+fn assert_sha256_nopanic_imp<W: io::Write>(
+    location: &Position,
+    actual: &[u8],
+    expected: &str,
+    writer: &mut W,
+) -> Result<(), ()> {
+    metrics::record_compared(actual.len());
+    let actual = Digest::of(actual);
+    let expect = match Digest::parse(format!("sha256:{expected}").as_bytes()) {
+        Some(expected) if expected.hex == actual.hex => return Ok(()),
+        Some(expected) => expected.hex,
+        None => format!("{BOLD}Invalid digest{BOLD:#}"),
+    };
+    assert_inline_nopanic_imp(
+        location,
+        false,
+        (&expect, &actual.describe()),
+        &format!("{:?}", actual.hex),
+        writer,
+    )
+}
+
+/// Prints the report of an assertion written inline in the source code and panics if it failed.
+#[track_caller]
+fn run_inline(location: &dyn fmt::Display, assertion: impl FnOnce(&mut Vec<u8>) -> Result<(), ()>) {
+    let mut report = Vec::new();
+    let start = Instant::now();
+    let result = assertion(&mut report);
+    let duration = start.elapsed();
+    pager::print_report(
+        &report,
+        pager::max_lines_from_env(),
+        pager::max_output_bytes_from_env(),
+        &mut output::stdout(),
+    );
+    let details = failure::take();
+    let status = report::Outcome::of(result);
+    let location = location.to_string();
+    report::record(&location, status, details);
+    metrics::record(&location, status, duration);
+    if let Err(()) = result {
+        panic_hook::fail(location, details, panic_hook::backtrace_from_env());
+    }
+}
+
+/// Reports an inline assertion, or updates its expected value to `actual_literal` in update
+/// mode.
+fn assert_inline_nopanic_imp<W: io::Write>(
+    location: &Position,
+    equal: bool,
+    (expect, actual): (&str, &str),
+    actual_literal: &str,
+    writer: &mut W,
+) -> Result<(), ()> {
+    if equal {
+        return Ok(());
+    }
+    if is_update_mode() {
+        writeln!(writer, "{SUCCESS}updating{SUCCESS:#}: {location}").unwrap();
+        report::record_update();
+        inline::update_last_argument(location, actual_literal).unwrap_or_else(|e| {
+            panic!(
+                "failed to update {}: {e}",
+                inline::source_path(location).display()
+            )
+        });
+        return Ok(());
+    }
+    write_inline_error_header(writer, "expect test failed", location);
+    writeln!(
+        writer,
+        "{help}
+{BOLD}Expect{BOLD:#}: {expect}
+{BOLD}Actual{BOLD:#}: {actual}",
+        help = help(),
+    )
+    .unwrap();
+    Err(())
+}
+
+/// Writes the header of a report about an assertion written inline at `position`.
+fn write_inline_error_header<W: io::Write>(writer: &mut W, message: &str, position: &Position) {
+    header::write(
+        writer,
+        header::format_from_env().as_deref(),
+        message,
+        &position.to_string(),
+        Some(position),
+    );
+}
+
 /// Bytes.
 ///
 /// Self-updating hasn't been implemented yet.
@@ -238,7 +3840,7 @@ pub struct Expect<'a> {
 }
 
 /// Position of original `expect!` in the source file.
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy)]
 pub struct Position {
     #[doc(hidden)]
     pub file: &'static str,
@@ -248,6 +3850,18 @@ pub struct Position {
     pub column: u32,
 }
 
+impl Position {
+    #[track_caller]
+    fn caller() -> Self {
+        let location = std::panic::Location::caller();
+        Self {
+            file: location.file(),
+            line: location.line(),
+            column: location.column(),
+        }
+    }
+}
+
 impl fmt::Display for Position {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(f, "{}:{}:{}", self.file, self.line, self.column)
@@ -0,0 +1,102 @@
+//! Detection of files whose line endings were converted, such as by a git checkout on Windows.
+
+use std::io;
+use std::path::Path;
+
+use crate::style::NOTE;
+
+/// Line endings of the file and of the actual data the file could have been converted from.
+#[derive(Clone, Copy)]
+enum Conversion {
+    /// Each LF that isn't part of a CRLF was converted to CRLF.
+    ToCrlf,
+    /// Each CRLF was converted to LF.
+    ToLf,
+}
+
+impl Conversion {
+    /// Returns the conversion that could turn data of `actual_len` bytes into `expected_len` bytes
+    /// by adding or removing a CR for some of them.
+    fn from_lens(expected_len: usize, actual_len: usize) -> Option<Self> {
+        if expected_len > actual_len && expected_len - actual_len <= actual_len {
+            Some(Self::ToCrlf)
+        } else if expected_len < actual_len && (actual_len - expected_len) * 2 <= actual_len {
+            Some(Self::ToLf)
+        } else {
+            None
+        }
+    }
+
+    /// Returns whether the first difference of the file and `actual`, at `diff_idx` where the file
+    /// has `expected_byte`, could come from this conversion.
+    fn could_start_at(self, expected_byte: Option<u8>, actual: &[u8], diff_idx: usize) -> bool {
+        match self {
+            Self::ToCrlf => expected_byte == Some(b'\r') && actual.get(diff_idx) == Some(&b'\n'),
+            Self::ToLf => {
+                expected_byte == Some(b'\n') && actual.get(diff_idx..diff_idx + 2) == Some(b"\r\n")
+            }
+        }
+    }
+
+    /// Returns whether `expected` is `actual` converted, stopping at the first byte that rules it
+    /// out.
+    fn converts(self, expected: &[u8], actual: &[u8]) -> bool {
+        let mut expected = expected.iter();
+        for (i, &byte) in actual.iter().enumerate() {
+            let matches = match self {
+                Self::ToCrlf if byte == b'\n' && (i == 0 || actual[i - 1] != b'\r') => {
+                    expected.next() == Some(&b'\r') && expected.next() == Some(&byte)
+                }
+                Self::ToLf if byte == b'\r' && actual.get(i + 1) == Some(&b'\n') => true,
+                _ => expected.next() == Some(&byte),
+            };
+            if !matches {
+                return false;
+            }
+        }
+        expected.next().is_none()
+    }
+
+    /// Returns the line endings of the file and of the actual data.
+    fn endings(self) -> (&'static str, &'static str) {
+        match self {
+            Self::ToCrlf => ("CRLF", "LF"),
+            Self::ToLf => ("LF", "CRLF"),
+        }
+    }
+}
+
+/// Returns whether the file could be `actual` with converted line endings judging by its length
+/// and by `expected_byte`, its byte at the first difference, so that it's only read when it could
+/// be.
+pub(crate) fn could_be_converted(
+    expected_len: usize,
+    expected_byte: Option<u8>,
+    actual: &[u8],
+    diff_idx: usize,
+) -> bool {
+    Conversion::from_lens(expected_len, actual.len()).map_or(false, |conversion| {
+        conversion.could_start_at(expected_byte, actual, diff_idx)
+    })
+}
+
+/// Writes a note if `expected` is `actual` with its line endings converted, and how to prevent
+/// it.
+pub(crate) fn write_hint<W: io::Write>(
+    writer: &mut W,
+    path: &Path,
+    expected: &[u8],
+    actual: &[u8],
+) -> io::Result<()> {
+    let (file_endings, actual_endings) = match Conversion::from_lens(expected.len(), actual.len()) {
+        Some(conversion) if conversion.converts(expected, actual) => conversion.endings(),
+        _ => return Ok(()),
+    };
+    let file_name = path.file_name().unwrap_or_default().to_string_lossy();
+    writeln!(
+        writer,
+        "{NOTE}note{NOTE:#}: the file has {file_endings} line endings where the actual data \
+         has {actual_endings}, as if they were converted on checkout
+Add `{file_name} -text` to `.gitattributes` to keep it byte for byte."
+    )
+}
@@ -0,0 +1,127 @@
+//! Append-mode snapshots of logs written record by record.
+
+use std::io;
+use std::thread;
+
+use crate::style::NOTE;
+use crate::{parts, ExpectFile};
+
+/// A snapshot file checked one appended segment at a time, such as a write-ahead log or an event
+/// log.
+///
+/// Each [`append_assert`](ExpectLog::append_assert) checks the next segment of the file. When the
+/// log is dropped, the file must not have any segments left over. In update mode, the segments
+/// aren't checked and the file is rewritten with all of them when the log is dropped.
+///
+/// ```no_run
+/// # let records: [&[u8]; 0] = [];
+/// // Checks each record, and then that there are no more records
+/// let mut log = expect_test_bytes::expect_log!["test_data/events.log"];
+/// for record in records {
+///     log.append_assert(record);
+/// }
+/// ```
+///
+/// The file's options, such as [`ExpectFile::on_missing`], apply to each segment.
+#[derive(Debug)]
+pub struct ExpectLog {
+    file: ExpectFile,
+    /// Offset of the next segment.
+    offset: usize,
+    segments: usize,
+    /// The segments so far in update mode.
+    written: Option<Vec<u8>>,
+    finished: bool,
+}
+
+impl ExpectLog {
+    /// Creates a log checked against `file`.
+    #[must_use]
+    pub fn new(file: ExpectFile) -> Self {
+        let update = file.updates();
+        Self {
+            file,
+            offset: 0,
+            segments: 0,
+            written: update.then(Vec::new),
+            finished: false,
+        }
+    }
+
+    /// Checks whether the next segment of the file is `actual`.
+    ///
+    /// # Panics
+    ///
+    /// Will panic when the next segment isn't `actual` and `UPDATE_EXPECT` is not set, or if
+    /// writing to stdout fails.
+    #[track_caller]
+    pub fn append_assert(&mut self, actual: &[u8]) {
+        self.run(|log, writer| log.append_assert_nopanic_imp(actual, writer));
+    }
+
+    /// Runs `assertion` like [`ExpectFile::run`], with mutable access to the log.
+    #[track_caller]
+    fn run(&mut self, assertion: impl FnOnce(&mut Self, &mut Vec<u8>) -> Result<(), ()>) {
+        let mut report = Vec::new();
+        let result = assertion(self, &mut report);
+        self.file.run(|writer| {
+            writer.extend_from_slice(&report);
+            result
+        });
+    }
+
+    pub(crate) fn append_assert_nopanic_imp<W: io::Write>(
+        &mut self,
+        actual: &[u8],
+        writer: &mut W,
+    ) -> Result<(), ()> {
+        self.segments += 1;
+        if let Some(written) = &mut self.written {
+            written.extend_from_slice(actual);
+            return Ok(());
+        }
+        self.file.slice = Some((self.offset, actual.len()));
+        self.offset += actual.len();
+        self.file
+            .assert_eq_nopanic_imp(actual, writer)
+            .map_err(|()| {
+                writeln!(writer, "{NOTE}note{NOTE:#}: in segment #{}", self.segments).unwrap();
+            })
+    }
+
+    /// Checks that no segments are left over, or rewrites the file in update mode.
+    pub(crate) fn finish_nopanic_imp<W: io::Write>(&mut self, writer: &mut W) -> Result<(), ()> {
+        self.finished = true;
+        self.file.slice = None;
+        let expected = parts::read(&self.file.path).unwrap();
+        if let Some(written) = self.written.take() {
+            if expected.as_deref() == Some(&written[..]) {
+                return Ok(());
+            }
+            return self.file.update(expected.as_deref(), &written, writer);
+        }
+        let len = expected.map_or(0, |expected| expected.len());
+        if len <= self.offset {
+            return Ok(());
+        }
+        self.file.write_failure_header(writer, true);
+        writeln!(
+            writer,
+            "The log has {} more bytes after segment #{}, which ends at {:#x}\n",
+            len - self.offset,
+            self.segments,
+            self.offset
+        )
+        .unwrap();
+        Err(())
+    }
+}
+
+impl Drop for ExpectLog {
+    fn drop(&mut self) {
+        // Don't panic again if a segment failed
+        if !self.finished && !thread::panicking() {
+            self.run(Self::finish_nopanic_imp);
+        }
+    }
+}
@@ -0,0 +1,111 @@
+//! Reading LZ4 blocks and frames, which compress Arrow IPC buffers and Parquet pages.
+
+/// Magic number of an LZ4 frame.
+#[cfg(feature = "arrow")]
+const MAGIC: u32 = 0x184d_2204;
+
+/// Decompresses the LZ4 frames of `data`, skipping skippable frames.
+#[cfg(feature = "arrow")]
+pub(crate) fn decompress_frames(data: &[u8]) -> Option<Vec<u8>> {
+    let mut out = Vec::new();
+    let mut pos = 0;
+    while pos < data.len() {
+        let magic = read_u32(data, pos)?;
+        pos += 4;
+        if magic & 0xffff_fff0 == 0x184d_2a50 {
+            pos = pos.checked_add(usize::try_from(read_u32(data, pos)?).ok()? + 4)?;
+            continue;
+        }
+        if magic != MAGIC {
+            return None;
+        }
+        let flags = *data.get(pos)?;
+        if flags >> 6 != 1 || flags & 1 != 0 {
+            // Unknown versions and dictionaries
+            return None;
+        }
+        let block_checksums = flags & 0x10 != 0;
+        let content_size = flags & 0x08 != 0;
+        let content_checksum = flags & 0x04 != 0;
+        // Flags, block descriptor, content size and header checksum
+        pos += 2 + if content_size { 8 } else { 0 } + 1;
+        // Blocks of a frame may refer to the previous ones, so they're all decoded into one buffer
+        let frame_start = out.len();
+        loop {
+            let header = read_u32(data, pos)?;
+            pos += 4;
+            if header == 0 {
+                break;
+            }
+            let len = usize::try_from(header & 0x7fff_ffff).ok()?;
+            let block = data.get(pos..pos.checked_add(len)?)?;
+            if header & 0x8000_0000 == 0 {
+                decompress_block_into(block, frame_start, &mut out)?;
+            } else {
+                out.extend_from_slice(block);
+            }
+            pos += len + if block_checksums { 4 } else { 0 };
+        }
+        if content_checksum {
+            pos += 4;
+        }
+    }
+    Some(out)
+}
+
+/// Decompresses a raw LZ4 block.
+#[cfg(feature = "parquet")]
+pub(crate) fn decompress_block(block: &[u8]) -> Option<Vec<u8>> {
+    let mut out = Vec::new();
+    decompress_block_into(block, 0, &mut out)?;
+    Some(out)
+}
+
+/// Decompresses `block` into `out`, where matches may refer back to `window_start`.
+fn decompress_block_into(block: &[u8], window_start: usize, out: &mut Vec<u8>) -> Option<()> {
+    let mut pos = 0;
+    loop {
+        let token = *block.get(pos)?;
+        pos += 1;
+        let literal_len = read_len(block, &mut pos, usize::from(token >> 4))?;
+        out.extend_from_slice(block.get(pos..pos.checked_add(literal_len)?)?);
+        pos += literal_len;
+        // The last sequence only has literals
+        if pos == block.len() {
+            return Some(());
+        }
+        let offset = usize::from(u16::from_le_bytes([*block.get(pos)?, *block.get(pos + 1)?]));
+        pos += 2;
+        let match_len = read_len(block, &mut pos, usize::from(token & 0xf))? + 4;
+        if offset == 0 || offset > out.len() - window_start {
+            return None;
+        }
+        // The match may overlap the bytes it produces
+        let start = out.len() - offset;
+        for i in 0..match_len {
+            out.push(out[start + i]);
+        }
+    }
+}
+
+/// Reads a length that continues in the following bytes when its 4 bits in the token are all set.
+fn read_len(block: &[u8], pos: &mut usize, initial: usize) -> Option<usize> {
+    let mut len = initial;
+    if initial == 15 {
+        loop {
+            let byte = *block.get(*pos)?;
+            *pos += 1;
+            len = len.checked_add(usize::from(byte))?;
+            if byte != 255 {
+                break;
+            }
+        }
+    }
+    Some(len)
+}
+
+#[cfg(feature = "arrow")]
+fn read_u32(data: &[u8], pos: usize) -> Option<u32> {
+    let bytes = data.get(pos..pos.checked_add(4)?)?;
+    Some(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+}
@@ -0,0 +1,107 @@
+//! Metrics of the assertions of the process, for test observability tooling to find the
+//! comparisons that dominate the runtime of a suite.
+
+use std::cell::Cell;
+use std::sync::{Mutex, PoisonError};
+use std::time::Duration;
+
+use crate::global::Global;
+use crate::report::Outcome;
+
+/// Metrics of a single assertion, returned by [`assertion_metrics`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct AssertionMetrics {
+    /// Name of the thread the assertion ran on, which is the name of the test with the default
+    /// test harness.
+    pub test: Option<String>,
+    /// Path of the file, or the source location of an inline assertion.
+    pub location: String,
+    /// Number of actual bytes compared, including ones compared again by retries.
+    pub bytes_compared: usize,
+    /// Time taken by the comparison and its report, but not by printing the report.
+    pub duration: Duration,
+    /// Outcome of the assertion.
+    pub outcome: Outcome,
+}
+
+/// Totals of the assertions of the process, returned by [`aggregate_metrics`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct AggregateMetrics {
+    /// Number of assertions.
+    pub assertions: usize,
+    /// Number of assertions that failed, including quarantined ones.
+    pub failed: usize,
+    /// Number of actual bytes compared.
+    pub bytes_compared: u64,
+    /// Total time taken by the assertions.
+    pub duration: Duration,
+}
+
+thread_local! {
+    /// Number of actual bytes compared by the current assertion.
+    static COMPARED: Cell<usize> = const { Cell::new(0) };
+}
+
+static METRICS: Global<Mutex<Vec<AssertionMetrics>>> = Global::new();
+
+/// Records that the current assertion compared `len` actual bytes.
+pub(crate) fn record_compared(len: usize) {
+    COMPARED.with(|compared| compared.set(compared.get().saturating_add(len)));
+}
+
+/// Records the metrics of the assertion at `location` that just finished.
+pub(crate) fn record(location: &str, outcome: Outcome, duration: Duration) {
+    let metrics = AssertionMetrics {
+        test: std::thread::current().name().map(str::to_owned),
+        location: location.to_owned(),
+        // Always taken so that it doesn't leak into the next assertion
+        bytes_compared: COMPARED.with(Cell::take),
+        duration,
+        outcome,
+    };
+    METRICS
+        .get_or_init(Mutex::default)
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .push(metrics);
+}
+
+/// Returns the metrics of the assertions that finished in this process, in the order they
+/// finished.
+///
+/// ```no_run
+/// let mut metrics = expect_test_bytes::assertion_metrics();
+/// metrics.sort_by_key(|metrics| std::cmp::Reverse(metrics.duration));
+/// for metrics in metrics.iter().take(5) {
+///     println!("{:?} {} ({} bytes)", metrics.duration, metrics.location, metrics.bytes_compared);
+/// }
+/// ```
+#[must_use]
+pub fn assertion_metrics() -> Vec<AssertionMetrics> {
+    METRICS
+        .get_or_init(Mutex::default)
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .clone()
+}
+
+/// Returns the totals of the assertions that finished in this process.
+#[must_use]
+pub fn aggregate_metrics() -> AggregateMetrics {
+    let metrics = METRICS
+        .get_or_init(Mutex::default)
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner);
+    let mut aggregate = AggregateMetrics::default();
+    for metrics in metrics.iter() {
+        aggregate.assertions += 1;
+        if matches!(metrics.outcome, Outcome::Failed | Outcome::Quarantined) {
+            aggregate.failed += 1;
+        }
+        aggregate.bytes_compared += metrics.bytes_compared as u64;
+        aggregate.duration += metrics.duration;
+    }
+    aggregate
+}
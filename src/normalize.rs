@@ -0,0 +1,291 @@
+//! Ready-made normalizers for [`ExpectFile::normalize`](crate::ExpectFile::normalize).
+//!
+//! Normalizers rewrite volatile parts of the actual data, such as timestamps, to fixed values
+//! before it's compared or written, so snapshots don't churn on every rebuild. Data that isn't
+//! recognized is left unchanged.
+//!
+//! The archive normalizers understand the structure of the format, while the scrubbers
+//! ([`uuids`], [`unix_timestamps`] and [`filetimes`]) replace anything that looks like the
+//! pattern.
+//!
+//! ```
+//! # use expect_test_bytes::{expect_file, normalize};
+//! # let archive = b"example\n";
+//! expect_file!["test_data/example"]
+//!     .normalize(normalize::gzip)
+//!     .assert_eq(archive);
+//! ```
+
+// The normalizers here match `Normalizer` even when they don't change the length
+#![allow(clippy::ptr_arg)]
+
+/// Callback that rewrites the actual data before it's compared or written.
+///
+/// The data is given as a `Vec` since normalizers may change its length.
+pub type Normalizer = dyn Fn(&mut Vec<u8>) + Send + Sync;
+
+const GZIP_MAGIC: [u8; 3] = [0x1f, 0x8b, 8];
+
+const ZIP_LOCAL_HEADER_SIGNATURE: u32 = 0x0403_4b50;
+const ZIP_CENTRAL_HEADER_SIGNATURE: u32 = 0x0201_4b50;
+const ZIP_END_SIGNATURE: u32 = 0x0605_4b50;
+const ZIP_END_LEN: usize = 22;
+const ZIP_LOCAL_HEADER_LEN: usize = 30;
+const ZIP_CENTRAL_HEADER_LEN: usize = 46;
+/// Extra field with Unix modification, access and creation times.
+const ZIP_EXTENDED_TIMESTAMP_ID: u16 = 0x5455;
+
+/// MS-DOS time of midnight.
+const DOS_TIME: u16 = 0;
+/// MS-DOS date of 1980-01-01, the earliest one representable.
+const DOS_DATE: u16 = (1 << 5) | 1;
+
+/// Zeroes the MTIME field of a gzip header.
+///
+/// Only the first member of a multi-member gzip file is normalized.
+pub fn gzip(data: &mut Vec<u8>) {
+    if data.len() >= 10 && data[..3] == GZIP_MAGIC {
+        data[4..8].fill(0);
+    }
+}
+
+/// Sorts the entries of a ZIP archive by name and sets their modification times to
+/// 1980-01-01 00:00.
+///
+/// Both the MS-DOS timestamps and the extended timestamp extra fields are normalized, in the
+/// local file headers and the central directory. ZIP64 and multi-disk archives are left
+/// unchanged.
+pub fn zip(data: &mut Vec<u8>) {
+    if let Some(canonical) = canonical_zip(data) {
+        *data = canonical;
+    }
+}
+
+fn read_u16(data: &[u8], offset: usize) -> Option<u16> {
+    let bytes = data.get(offset..offset.checked_add(2)?)?;
+    Some(u16::from_le_bytes([bytes[0], bytes[1]]))
+}
+
+fn read_u32(data: &[u8], offset: usize) -> Option<u32> {
+    let bytes = data.get(offset..offset.checked_add(4)?)?;
+    Some(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+}
+
+fn write_u16(data: &mut [u8], offset: usize, value: u16) {
+    data[offset..offset + 2].copy_from_slice(&value.to_le_bytes());
+}
+
+fn write_u32(data: &mut [u8], offset: usize, value: u32) {
+    data[offset..offset + 4].copy_from_slice(&value.to_le_bytes());
+}
+
+/// Finds the offset of the end of central directory record.
+fn find_zip_end(data: &[u8]) -> Option<usize> {
+    let last = data.len().checked_sub(ZIP_END_LEN)?;
+    // The record is followed by a comment of at most 0xffff bytes
+    (last.saturating_sub(0xffff)..=last).rev().find(|&offset| {
+        read_u32(data, offset) == Some(ZIP_END_SIGNATURE)
+            && read_u16(data, offset + 20).map(usize::from) == Some(last - offset)
+    })
+}
+
+struct ZipEntry {
+    central_header: Vec<u8>,
+    local_offset: usize,
+    name_len: usize,
+}
+
+impl ZipEntry {
+    fn name(&self) -> &[u8] {
+        &self.central_header[ZIP_CENTRAL_HEADER_LEN..][..self.name_len]
+    }
+}
+
+fn canonical_zip(data: &[u8]) -> Option<Vec<u8>> {
+    let end = find_zip_end(data)?;
+    let disk = read_u16(data, end + 4)?;
+    let entry_count = read_u16(data, end + 10)?;
+    if disk != 0 || read_u16(data, end + 8)? != entry_count {
+        return None;
+    }
+    let directory_offset = usize::try_from(read_u32(data, end + 16)?).ok()?;
+
+    let mut entries = Vec::with_capacity(entry_count.into());
+    let mut offset = directory_offset;
+    for _ in 0..entry_count {
+        if read_u32(data, offset)? != ZIP_CENTRAL_HEADER_SIGNATURE {
+            return None;
+        }
+        let name_len = usize::from(read_u16(data, offset + 28)?);
+        let len = ZIP_CENTRAL_HEADER_LEN
+            + name_len
+            + usize::from(read_u16(data, offset + 30)?)
+            + usize::from(read_u16(data, offset + 32)?);
+        let local_offset = read_u32(data, offset + 42)?;
+        if local_offset == u32::MAX {
+            // ZIP64
+            return None;
+        }
+        entries.push(ZipEntry {
+            central_header: data.get(offset..offset + len)?.to_vec(),
+            local_offset: usize::try_from(local_offset).ok()?,
+            name_len,
+        });
+        offset += len;
+    }
+
+    // Each local record extends up to the next one or the central directory
+    let mut local_offsets: Vec<_> = entries.iter().map(|entry| entry.local_offset).collect();
+    local_offsets.sort_unstable();
+    let record_end = |start: usize| {
+        local_offsets
+            .iter()
+            .copied()
+            .find(|&offset| offset > start)
+            .unwrap_or(directory_offset)
+    };
+    entries.sort_by(|a, b| a.name().cmp(b.name()));
+
+    // Anything before the first record, such as a self-extractor stub, is kept as is
+    let mut out = data
+        .get(..local_offsets.first().copied().unwrap_or(directory_offset))?
+        .to_vec();
+    for entry in &mut entries {
+        let mut record = data
+            .get(entry.local_offset..record_end(entry.local_offset))?
+            .to_vec();
+        normalize_zip_header(
+            &mut record,
+            ZIP_LOCAL_HEADER_SIGNATURE,
+            ZIP_LOCAL_HEADER_LEN,
+        )?;
+        normalize_zip_header(
+            &mut entry.central_header,
+            ZIP_CENTRAL_HEADER_SIGNATURE,
+            ZIP_CENTRAL_HEADER_LEN,
+        )?;
+        write_u32(
+            &mut entry.central_header,
+            42,
+            u32::try_from(out.len()).ok()?,
+        );
+        out.extend_from_slice(&record);
+    }
+    let new_directory_offset = u32::try_from(out.len()).ok()?;
+    for entry in &entries {
+        out.extend_from_slice(&entry.central_header);
+    }
+    let new_end = out.len();
+    out.extend_from_slice(&data[end..]);
+    write_u32(&mut out, new_end + 16, new_directory_offset);
+    Some(out)
+}
+
+/// Normalizes the timestamps of a local or central directory file header.
+fn normalize_zip_header(header: &mut [u8], signature: u32, header_len: usize) -> Option<()> {
+    if read_u32(header, 0)? != signature {
+        return None;
+    }
+    // The fields up to the name length are 2 bytes longer in central directory headers
+    let shift = if signature == ZIP_LOCAL_HEADER_SIGNATURE {
+        0
+    } else {
+        2
+    };
+    let name_len = usize::from(read_u16(header, 26 + shift)?);
+    let extra_len = usize::from(read_u16(header, 28 + shift)?);
+    normalize_zip_extra(
+        header
+            .get_mut(header_len + name_len..)?
+            .get_mut(..extra_len)?,
+    );
+    write_u16(header, 10 + shift, DOS_TIME);
+    write_u16(header, 12 + shift, DOS_DATE);
+    Some(())
+}
+
+/// Zeroes the times in extended timestamp extra fields.
+fn normalize_zip_extra(mut extra: &mut [u8]) {
+    while let (Some(id), Some(len)) = (read_u16(extra, 0), read_u16(extra, 2)) {
+        let len = usize::from(len);
+        let (field, rest) = extra.split_at_mut((4 + len).min(extra.len()));
+        if id == ZIP_EXTENDED_TIMESTAMP_ID {
+            // The first byte is a flags byte, followed by the 4-byte times
+            if let Some(times) = field.get_mut(5..) {
+                times.fill(0);
+            }
+        }
+        extra = rest;
+    }
+}
+
+/// Start of the range of plausible timestamps, 2000-01-01 00:00 UTC in Unix time.
+const PLAUSIBLE_TIMES_START: u64 = 946_684_800;
+/// End of the range of plausible timestamps, 2100-01-01 00:00 UTC in Unix time.
+const PLAUSIBLE_TIMES_END: u64 = 4_102_444_800;
+/// Seconds between 1601-01-01, the Windows epoch, and 1970-01-01.
+const FILETIME_EPOCH_OFFSET: u64 = 11_644_473_600;
+const FILETIME_TICKS_PER_SECOND: u64 = 10_000_000;
+
+const UUID_LEN: usize = 36;
+const NIL_UUID: &[u8; UUID_LEN] = b"00000000-0000-0000-0000-000000000000";
+
+/// Replaces textual RFC 4122 UUIDs, such as `67e55044-10b1-426f-9247-bb680e5fe0c8`, with the nil
+/// UUID.
+///
+/// Only hyphenated UUIDs with a known version and the RFC 4122 variant are recognized.
+pub fn uuids(data: &mut Vec<u8>) {
+    let mut i = 0;
+    while i + UUID_LEN <= data.len() {
+        if is_uuid(&data[i..i + UUID_LEN]) {
+            data[i..i + UUID_LEN].copy_from_slice(NIL_UUID);
+            i += UUID_LEN;
+        } else {
+            i += 1;
+        }
+    }
+}
+
+fn is_uuid(s: &[u8]) -> bool {
+    let well_formed = s.iter().enumerate().all(|(i, &b)| {
+        (b == b'-') == matches!(i, 8 | 13 | 18 | 23) && (b == b'-' || b.is_ascii_hexdigit())
+    });
+    well_formed
+        && matches!(s[14], b'1'..=b'8')
+        && matches!(s[19], b'8' | b'9' | b'a' | b'b' | b'A' | b'B')
+}
+
+/// Zeroes little-endian 8-byte Unix timestamps in seconds between 2000 and 2100.
+///
+/// Any 8 bytes in that range are replaced, so this can also hit unrelated data that happens to
+/// look like a timestamp.
+pub fn unix_timestamps(data: &mut Vec<u8>) {
+    zero_u64s_in_range(data, PLAUSIBLE_TIMES_START, PLAUSIBLE_TIMES_END);
+}
+
+/// Zeroes little-endian Windows `FILETIME`s between 2000 and 2100.
+///
+/// Any 8 bytes in that range are replaced, so this can also hit unrelated data that happens to
+/// look like a timestamp.
+pub fn filetimes(data: &mut Vec<u8>) {
+    zero_u64s_in_range(
+        data,
+        (PLAUSIBLE_TIMES_START + FILETIME_EPOCH_OFFSET) * FILETIME_TICKS_PER_SECOND,
+        (PLAUSIBLE_TIMES_END + FILETIME_EPOCH_OFFSET) * FILETIME_TICKS_PER_SECOND,
+    );
+}
+
+/// Zeroes non-overlapping little-endian `u64`s in `start..end`.
+fn zero_u64s_in_range(data: &mut [u8], start: u64, end: u64) {
+    let mut i = 0;
+    while i + 8 <= data.len() {
+        let mut bytes = [0; 8];
+        bytes.copy_from_slice(&data[i..i + 8]);
+        if (start..end).contains(&u64::from_le_bytes(bytes)) {
+            data[i..i + 8].fill(0);
+            i += 8;
+        } else {
+            i += 1;
+        }
+    }
+}
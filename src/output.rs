@@ -0,0 +1,69 @@
+//! Streams that reports and summaries are printed to.
+//!
+//! Colors are stripped when `NO_COLOR` is set. With the `anstream` feature, they're also stripped
+//! when the stream isn't a terminal, and are written with console API calls on legacy Windows
+//! consoles.
+
+use std::io;
+
+use crate::stable::{self, StripAnsi};
+
+/// Returns the stream for failure reports.
+pub(crate) fn stdout() -> Box<dyn io::Write> {
+    stdout_with_color(None)
+}
+
+/// Returns the stream for the failure reports of an assertion, which keeps or strips colors
+/// regardless of the terminal if `color` is set.
+pub(crate) fn stdout_with_color(color: Option<bool>) -> Box<dyn io::Write> {
+    if let Some(color) = color {
+        return with_color(io::stdout(), color);
+    }
+    auto(io::stdout())
+}
+
+/// Wraps `stream` to strip colors when it isn't a terminal or `NO_COLOR` is set.
+#[cfg(feature = "anstream")]
+pub(crate) fn auto<'a, S>(stream: S) -> Box<dyn io::Write + 'a>
+where
+    S: anstream::stream::RawStream + anstream::stream::AsLockedWrite + 'a,
+{
+    Box::new(anstream::AutoStream::auto(stream))
+}
+
+/// Wraps `stream` to strip colors when `NO_COLOR` is set, since telling terminals apart needs
+/// the `anstream` feature.
+#[cfg(not(feature = "anstream"))]
+pub(crate) fn auto<'a>(stream: impl io::Write + 'a) -> Box<dyn io::Write + 'a> {
+    // An empty value doesn't count, like with `anstream`
+    let no_color = std::env::var_os("NO_COLOR").map_or(false, |value| !value.is_empty());
+    with_color(stream, !no_color)
+}
+
+/// Wraps `stream` to strip colors unless `color` is set.
+pub(crate) fn with_color<'a>(stream: impl io::Write + 'a, color: bool) -> Box<dyn io::Write + 'a> {
+    if color {
+        Box::new(stream)
+    } else {
+        Box::new(StripAnsi::new(stream))
+    }
+}
+
+/// Returns the stream for end-of-run summaries, which strips colors in stable mode.
+pub(crate) fn stderr() -> Box<dyn io::Write> {
+    stderr_with_color(None)
+}
+
+/// Returns the stream for end-of-run summaries, which keeps or strips colors regardless of the
+/// terminal if `color` is set, and strips them in stable mode.
+pub(crate) fn stderr_with_color(color: Option<bool>) -> Box<dyn io::Write> {
+    let stderr = match color {
+        Some(color) => with_color(io::stderr(), color),
+        None => auto(io::stderr()),
+    };
+    if stable::is_enabled() {
+        Box::new(StripAnsi::new(stderr))
+    } else {
+        stderr
+    }
+}
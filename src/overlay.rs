@@ -0,0 +1,134 @@
+//! Updates written outside of the source tree, either when the snapshot can't be written in place
+//! or for every update when `EXPECT_BYTES_UPDATE_DIR` is set.
+//!
+//! The intended contents are written under an overlay directory mirroring the original paths,
+//! together with a shell script that copies them back. The script only applies the updates of the
+//! latest test run, so that applying it doesn't undo later changes with the updates of earlier
+//! runs.
+
+use std::ffi::OsString;
+use std::fs;
+use std::io::{self, Read as _, Write as _};
+use std::path::{Component, Path, PathBuf};
+
+use crate::{run, sha256};
+
+/// Directory that snapshots are written to when they can't be written in place.
+pub(crate) const OVERLAY_DIR_VAR_NAME: &str = "EXPECT_BYTES_OVERLAY_DIR";
+/// Directory that all updates are written to instead of in place, for read-only checkouts.
+pub(crate) const UPDATE_DIR_VAR_NAME: &str = "EXPECT_BYTES_UPDATE_DIR";
+
+/// Name of the script in the overlay directory that applies the updates.
+pub(crate) const APPLY_SCRIPT_NAME: &str = "apply.sh";
+
+/// Returns the overlay directory, by default one for each package in the temporary directory.
+pub(crate) fn overlay_dir() -> PathBuf {
+    std::env::var_os(OVERLAY_DIR_VAR_NAME).map_or_else(
+        || {
+            let package = package_dir();
+            let hash = sha256::to_hex(&sha256::sha256(package.to_string_lossy().as_bytes()));
+            std::env::temp_dir().join(format!("expect-test-bytes-overlay-{}", &hash[..16]))
+        },
+        PathBuf::from,
+    )
+}
+
+/// Returns the directory of the package under test.
+pub(crate) fn package_dir() -> PathBuf {
+    // Cargo runs tests with the package's directory as the working directory
+    std::env::var_os("CARGO_MANIFEST_DIR").map_or_else(|| absolute(Path::new(".")), PathBuf::from)
+}
+
+pub(crate) fn update_dir() -> Option<PathBuf> {
+    std::env::var_os(UPDATE_DIR_VAR_NAME).map(PathBuf::from)
+}
+
+/// Returns the absolute form of `path`.
+pub(crate) fn absolute(path: &Path) -> PathBuf {
+    if path.is_absolute() {
+        path.to_owned()
+    } else {
+        std::env::current_dir().map_or_else(|_| path.to_owned(), |dir| dir.join(path))
+    }
+}
+
+/// Returns where `path` goes under the overlay directory `dir`.
+pub(crate) fn mirror_path(dir: &Path, path: &Path) -> PathBuf {
+    let mut mirrored = dir.to_owned();
+    for component in absolute(path).components() {
+        if let Component::Normal(part) = component {
+            mirrored.push(part);
+        }
+    }
+    mirrored
+}
+
+/// Quotes `path` for a POSIX shell.
+pub(crate) fn shell_quote(path: &Path) -> String {
+    format!("'{}'", path.to_string_lossy().replace('\'', r"'\''"))
+}
+
+/// Writes `contents` for `path` into the overlay directory `dir` and appends a copy command to
+/// the apply script.
+///
+/// Returns the path of the written file and the path of the apply script.
+pub(crate) fn write(dir: &Path, path: &Path, contents: &[u8]) -> io::Result<(PathBuf, PathBuf)> {
+    let mirrored = mirror_path(dir, path);
+    if let Some(parent) = mirrored.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(&mirrored, contents)?;
+
+    let (script_path, mut script) = open_script(dir)?;
+    let destination = absolute(path);
+    let mut destination_dir: OsString = destination
+        .parent()
+        .map_or_else(OsString::new, |parent| parent.as_os_str().to_owned());
+    if destination_dir.is_empty() {
+        destination_dir.push(".");
+    }
+    writeln!(
+        script,
+        "mkdir -p {} && cp {} {}",
+        shell_quote(Path::new(&destination_dir)),
+        shell_quote(&mirrored),
+        shell_quote(&destination)
+    )?;
+
+    Ok((mirrored, script_path))
+}
+
+/// Appends a command removing `path` to the apply script of the overlay directory `dir`.
+pub(crate) fn remove(dir: &Path, path: &Path) -> io::Result<()> {
+    let (_, mut script) = open_script(dir)?;
+    writeln!(script, "rm -f {}", shell_quote(&absolute(path)))
+}
+
+/// Opens the apply script of the overlay directory `dir` for appending, starting it over if it's
+/// missing or was written by another test run.
+fn open_script(dir: &Path) -> io::Result<(PathBuf, fs::File)> {
+    fs::create_dir_all(dir)?;
+    let script_path = dir.join(APPLY_SCRIPT_NAME);
+    let script = open_run_script(&script_path, "")?;
+    Ok((script_path, script))
+}
+
+/// Opens the shell script at `path` for appending commands, starting it over with a header ending
+/// in `preamble` if it was written by an earlier run.
+pub(crate) fn open_run_script(path: &Path, preamble: &str) -> io::Result<fs::File> {
+    let header = format!("#!/bin/sh\nset -e\n# run {}\n{preamble}", run::id());
+    let mut start = Vec::new();
+    let is_current = fs::File::open(path)
+        .and_then(|script| script.take(header.len() as u64).read_to_end(&mut start))
+        .map_or(false, |_| start == header.as_bytes());
+    let mut script = fs::OpenOptions::new()
+        .create(true)
+        .append(is_current)
+        .write(true)
+        .truncate(!is_current)
+        .open(path)?;
+    if !is_current {
+        script.write_all(header.as_bytes())?;
+    }
+    Ok(script)
+}
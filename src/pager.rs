@@ -0,0 +1,142 @@
+//! Output of long failure reports.
+
+use std::fs;
+use std::io::{self, Write as _};
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use crate::stable::{self, StripAnsi};
+
+/// Maximum number of report lines printed before the report is spilled to a file.
+pub(crate) const MAX_LINES_VAR_NAME: &str = "EXPECT_BYTES_MAX_LINES";
+/// When set, long reports are shown with `$PAGER` instead of being spilled to a file.
+pub(crate) const PAGE_VAR_NAME: &str = "EXPECT_BYTES_PAGE";
+/// Maximum number of bytes of a report that are printed, after which it's cut off.
+pub(crate) const MAX_OUTPUT_BYTES_VAR_NAME: &str = "EXPECT_BYTES_MAX_OUTPUT_BYTES";
+
+pub(crate) const DEFAULT_MAX_LINES: usize = 200;
+
+static SPILLED_REPORTS: AtomicUsize = AtomicUsize::new(0);
+
+pub(crate) fn max_lines_from_env() -> usize {
+    std::env::var(MAX_LINES_VAR_NAME)
+        .ok()
+        .and_then(|value| value.parse().ok())
+        .unwrap_or(DEFAULT_MAX_LINES)
+}
+
+pub(crate) fn max_output_bytes_from_env() -> Option<usize> {
+    std::env::var(MAX_OUTPUT_BYTES_VAR_NAME)
+        .ok()
+        .and_then(|value| value.parse().ok())
+}
+
+/// Writes `report` to `writer`, or its first `max_lines` lines and the path of a file containing
+/// the full report if it's longer.
+///
+/// Reports longer than `max_bytes` are cut off first, keeping the full report for the file. In
+/// stable output mode, the report is printed in full without colors.
+pub(crate) fn print_report<W: io::Write>(
+    report: &[u8],
+    max_lines: usize,
+    max_bytes: Option<usize>,
+    writer: &mut W,
+) {
+    if stable::is_enabled() {
+        StripAnsi::new(writer).write_all(report).unwrap();
+        return;
+    }
+    let capped = max_bytes.and_then(|max_bytes| cap(report, max_bytes));
+    let shown = capped.as_deref().unwrap_or(report);
+    let line_count = shown.split(|&b| b == b'\n').count() - 1;
+    if line_count <= max_lines {
+        writer.write_all(shown).unwrap();
+        return;
+    }
+
+    if std::env::var_os(PAGE_VAR_NAME).is_some() && page(report).is_ok() {
+        return;
+    }
+
+    let path = spill_path();
+    let truncated_len = match max_lines.checked_sub(1) {
+        Some(last_line) => shown
+            .iter()
+            .enumerate()
+            .filter(|(_, &b)| b == b'\n')
+            .nth(last_line)
+            .map_or(shown.len(), |(i, _)| i + 1),
+        None => 0,
+    };
+    writer.write_all(&shown[..truncated_len]).unwrap();
+    match fs::write(&path, report) {
+        Ok(()) => writeln!(
+            writer,
+            "… {} more lines, full report written to {}",
+            line_count - max_lines,
+            path.display()
+        ),
+        Err(e) => writeln!(
+            writer,
+            "… {} more lines, failed to write full report to {}: {e}",
+            line_count - max_lines,
+            path.display()
+        ),
+    }
+    .unwrap();
+}
+
+/// Cuts `report` off at the last blank line, or else the last line, that fits in `max_bytes`,
+/// followed by the number of the blank-line separated hunks cut off. Returns `None` if the report
+/// fits.
+fn cap(report: &[u8], max_bytes: usize) -> Option<Vec<u8>> {
+    if report.len() <= max_bytes {
+        return None;
+    }
+    let head = &report[..max_bytes];
+    let len = head
+        .windows(2)
+        .rposition(|w| w == b"\n\n")
+        .map(|i| i + 2)
+        .or_else(|| head.iter().rposition(|&b| b == b'\n').map(|i| i + 1))
+        .unwrap_or(0);
+    let hunks = report[len..]
+        .split(|&b| b == b'\n')
+        .fold((0, true), |(hunks, blank), line| {
+            let starts_hunk = blank && !line.is_empty();
+            (hunks + usize::from(starts_hunk), line.is_empty())
+        })
+        .0;
+    let mut capped = report[..len].to_vec();
+    let noun = if hunks == 1 { "hunk" } else { "hunks" };
+    writeln!(capped, "… output truncated ({hunks} more {noun})").unwrap();
+    Some(capped)
+}
+
+fn spill_path() -> PathBuf {
+    let n = SPILLED_REPORTS.fetch_add(1, Ordering::Relaxed);
+    std::env::temp_dir().join(format!(
+        "expect-test-bytes-report-{}-{n}.txt",
+        std::process::id()
+    ))
+}
+
+/// Pipes `report` through `$PAGER`, defaulting to `less -R`.
+fn page(report: &[u8]) -> io::Result<()> {
+    let pager = std::env::var("PAGER").unwrap_or_else(|_| "less -R".to_owned());
+    let mut args = pager.split_whitespace();
+    let program = args
+        .next()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::Other, "empty PAGER"))?;
+    let mut child = Command::new(program)
+        .args(args)
+        .stdin(Stdio::piped())
+        .spawn()?;
+    if let Some(mut stdin) = child.stdin.take() {
+        // The pager may exit before reading everything
+        let _ = stdin.write_all(report);
+    }
+    child.wait()?;
+    Ok(())
+}
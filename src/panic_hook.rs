@@ -0,0 +1,105 @@
+//! Unwinding from failed assertions, with an opt-in panic hook that summarizes them in one line.
+
+use std::io::{self, Write as _};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+
+use crate::failure::{self, Details};
+use crate::output;
+use crate::style::ERROR;
+
+/// When set, failed assertions panic with a message like [`ExpectFile::with_backtrace`] does.
+///
+/// [`ExpectFile::with_backtrace`]: crate::ExpectFile::with_backtrace
+const BACKTRACE_VAR_NAME: &str = "EXPECT_BYTES_BACKTRACE";
+
+/// When set, the process exits after the first failed assertion.
+pub(crate) const ABORT_VAR_NAME: &str = "EXPECT_BYTES_ABORT_ON_FAILURE";
+
+static INSTALLED: AtomicBool = AtomicBool::new(false);
+
+/// Payload of the panic of a failed assertion when the hook is installed.
+pub(crate) struct Failure {
+    pub(crate) location: String,
+    pub(crate) first_diff: Option<usize>,
+}
+
+/// Installs a panic hook that prints a compact summary of failed assertions.
+///
+/// Failed assertions unwind without a message, so the default output for them is the test name
+/// or an opaque `Box<dyn Any>`. With the hook, each failure is summarized by the snapshot, the
+/// first differing offset and how to update it:
+///
+/// ```text
+/// thread 'tests::encode' failed an expect test: src/frame.bin, first difference at byte 0x11
+///     run with `env UPDATE_EXPECT=1 cargo test` to update the snapshot
+/// ```
+///
+/// Other panics are passed to the previous hook. Installing the hook again does nothing.
+pub fn install_panic_hook() {
+    if INSTALLED.swap(true, Ordering::SeqCst) {
+        return;
+    }
+    let previous = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        match info.payload().downcast_ref::<Failure>() {
+            Some(failure) => {
+                let _ = write_summary(failure, thread::current().name(), &mut io::stderr());
+            }
+            None => previous(info),
+        }
+    }));
+}
+
+pub(crate) fn backtrace_from_env() -> bool {
+    std::env::var_os(BACKTRACE_VAR_NAME).is_some()
+}
+
+/// Unwinds from the failed assertion of the snapshot at `location`, after printing its
+/// single-line summary if enabled.
+///
+/// With `backtrace`, this is a regular panic at the caller, which prints its location and a
+/// backtrace if enabled.
+#[track_caller]
+pub(crate) fn fail(location: String, details: Details, backtrace: bool) -> ! {
+    if failure::fail_line_from_env() {
+        failure::write_line(&mut output::stdout(), &location, details).unwrap();
+    }
+    if std::env::var_os(ABORT_VAR_NAME).is_some() {
+        // Exiting still runs the summaries registered with `atexit`
+        let _ = writeln!(
+            output::stderr(),
+            "{ERROR}aborting{ERROR:#}: {location} failed and {ABORT_VAR_NAME} is set"
+        );
+        std::process::exit(101);
+    }
+    assert!(!backtrace, "expect test failed: {location}");
+    if INSTALLED.load(Ordering::SeqCst) {
+        std::panic::panic_any(Failure {
+            location,
+            first_diff: details.first_diff,
+        });
+    }
+    // Use resume_unwind instead of panic!() to prevent a backtrace, which is unnecessary noise.
+    std::panic::resume_unwind(Box::new(()));
+}
+
+pub(crate) fn write_summary<W: io::Write>(
+    failure: &Failure,
+    thread_name: Option<&str>,
+    writer: &mut W,
+) -> io::Result<()> {
+    write!(
+        writer,
+        "thread '{}' failed an expect test: {}",
+        thread_name.unwrap_or("<unnamed>"),
+        failure.location
+    )?;
+    if let Some(first_diff) = failure.first_diff {
+        write!(writer, ", first difference at byte {first_diff:#x}")?;
+    }
+    writeln!(
+        writer,
+        "\n    run with `env UPDATE_EXPECT=1 cargo test` to update the snapshot"
+    )
+}
@@ -0,0 +1,652 @@
+//! Logical comparison of Parquet files by their schema and column values.
+
+use crate::columnar::{self, Column, Table, Value};
+use crate::structure::Difference;
+
+const MAGIC: &[u8] = b"PAR1";
+/// Thrift structures nest a few levels deep, so deeper ones are malformed.
+const MAX_DEPTH: usize = 32;
+
+/// A value decoded with the Thrift compact protocol.
+#[derive(Debug)]
+enum Thrift<'a> {
+    Bool(bool),
+    Int(i64),
+    Double,
+    Binary(&'a [u8]),
+    List(Vec<Thrift<'a>>),
+    Map,
+    Struct(Vec<(i16, Thrift<'a>)>),
+}
+
+impl<'a> Thrift<'a> {
+    fn field(&self, id: i16) -> Option<&Thrift<'a>> {
+        match self {
+            Self::Struct(fields) => fields
+                .iter()
+                .find(|(field_id, _)| *field_id == id)
+                .map(|(_, value)| value),
+            _ => None,
+        }
+    }
+
+    fn int(&self, id: i16) -> Option<i64> {
+        match self.field(id)? {
+            Self::Int(i) => Some(*i),
+            _ => None,
+        }
+    }
+
+    fn bool(&self, id: i16) -> Option<bool> {
+        match self.field(id)? {
+            Self::Bool(b) => Some(*b),
+            _ => None,
+        }
+    }
+
+    fn string(&self, id: i16) -> Option<String> {
+        match self.field(id)? {
+            Self::Binary(b) => String::from_utf8(b.to_vec()).ok(),
+            _ => None,
+        }
+    }
+
+    fn list(&self, id: i16) -> Option<&[Thrift<'a>]> {
+        match self.field(id)? {
+            Self::List(list) => Some(list),
+            _ => None,
+        }
+    }
+
+    /// Returns the id of the field set in a union, and its value.
+    fn union(&self) -> Option<(i16, &Thrift<'a>)> {
+        match self {
+            Self::Struct(fields) => fields.first().map(|(id, value)| (*id, value)),
+            _ => None,
+        }
+    }
+}
+
+/// A reader of the Thrift compact protocol.
+struct Reader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn byte(&mut self) -> Option<u8> {
+        let byte = *self.data.get(self.pos)?;
+        self.pos += 1;
+        Some(byte)
+    }
+
+    fn varint(&mut self) -> Option<u64> {
+        let mut value = 0;
+        for shift in (0..64).step_by(7) {
+            let byte = self.byte()?;
+            value |= u64::from(byte & 0x7f) << shift;
+            if byte & 0x80 == 0 {
+                return Some(value);
+            }
+        }
+        None
+    }
+
+    fn zigzag(&mut self) -> Option<i64> {
+        let value = self.varint()?;
+        let half = i64::try_from(value >> 1).ok()?;
+        Some(if value & 1 == 0 { half } else { -half - 1 })
+    }
+
+    fn bytes(&mut self, len: usize) -> Option<&'a [u8]> {
+        let bytes = self.data.get(self.pos..self.pos.checked_add(len)?)?;
+        self.pos += len;
+        Some(bytes)
+    }
+
+    fn value(&mut self, kind: u8, depth: usize) -> Option<Thrift<'a>> {
+        if depth > MAX_DEPTH {
+            return None;
+        }
+        Some(match kind {
+            1 => Thrift::Bool(true),
+            2 => Thrift::Bool(false),
+            3 => Thrift::Int(i64::from(i8::from_le_bytes([self.byte()?]))),
+            4..=6 => Thrift::Int(self.zigzag()?),
+            7 => {
+                self.bytes(8)?;
+                Thrift::Double
+            }
+            8 => {
+                let len = usize::try_from(self.varint()?).ok()?;
+                Thrift::Binary(self.bytes(len)?)
+            }
+            9 | 10 => {
+                let header = self.byte()?;
+                let len = match header >> 4 {
+                    15 => usize::try_from(self.varint()?).ok()?,
+                    len => usize::from(len),
+                };
+                let kind = header & 0xf;
+                // Every element takes at least a byte, so longer lists are malformed
+                if len > self.data.len() - self.pos {
+                    return None;
+                }
+                let list = (0..len)
+                    .map(|_| match kind {
+                        // Booleans of lists are stored in a byte each
+                        1 | 2 => Some(Thrift::Bool(self.byte()? == 1)),
+                        _ => self.value(kind, depth + 1),
+                    })
+                    .collect::<Option<_>>()?;
+                Thrift::List(list)
+            }
+            11 => {
+                let len = self.varint()?;
+                if len > 0 {
+                    let kinds = self.byte()?;
+                    for _ in 0..len {
+                        self.value(kinds >> 4, depth + 1)?;
+                        self.value(kinds & 0xf, depth + 1)?;
+                    }
+                }
+                Thrift::Map
+            }
+            12 => {
+                let mut fields = Vec::new();
+                let mut id = 0_i16;
+                loop {
+                    let header = self.byte()?;
+                    if header == 0 {
+                        break;
+                    }
+                    id = match header >> 4 {
+                        0 => i16::try_from(self.zigzag()?).ok()?,
+                        delta => id.checked_add(i16::from(delta))?,
+                    };
+                    fields.push((id, self.value(header & 0xf, depth + 1)?));
+                }
+                Thrift::Struct(fields)
+            }
+            _ => return None,
+        })
+    }
+
+    fn read_struct(&mut self) -> Option<Thrift<'a>> {
+        self.value(12, 0)
+    }
+}
+
+/// Physical types of Parquet columns.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Physical {
+    Boolean,
+    Int32,
+    Int64,
+    Int96,
+    Float,
+    Double,
+    ByteArray,
+    FixedLenByteArray(usize),
+}
+
+/// How the values of a column are decoded into [`Value`]s.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Interpretation {
+    Plain,
+    Text,
+    Unsigned,
+    Decimal { scale: i32 },
+}
+
+/// A leaf column of the schema, as needed to decode its values.
+struct Leaf {
+    physical: Physical,
+    interpretation: Interpretation,
+    optional: bool,
+}
+
+const TIME_UNITS: [&str; 3] = ["MILLIS", "MICROS", "NANOS"];
+
+/// Describes the logical type of a schema element, if it has one, and how its values are
+/// interpreted.
+fn logical_type(element: &Thrift<'_>) -> Option<(Option<String>, Interpretation)> {
+    if let Some(logical) = element.field(10) {
+        let (id, value) = logical.union()?;
+        let unit = |value: &Thrift<'_>| {
+            let (unit, _) = value.field(2)?.union()?;
+            TIME_UNITS.get(usize::try_from(unit - 1).ok()?).copied()
+        };
+        let adjusted = |value: &Thrift<'_>| {
+            if value.bool(1).unwrap_or(true) {
+                ""
+            } else {
+                ", local"
+            }
+        };
+        let (name, interpretation) = match id {
+            1 => ("STRING".to_owned(), Interpretation::Text),
+            4 => ("ENUM".to_owned(), Interpretation::Text),
+            5 => {
+                let scale = i32::try_from(value.int(1)?).ok()?;
+                let precision = value.int(2)?;
+                (
+                    format!("DECIMAL({precision}, {scale})"),
+                    Interpretation::Decimal { scale },
+                )
+            }
+            6 => ("DATE".to_owned(), Interpretation::Plain),
+            7 => (
+                format!("TIME({}{})", unit(value)?, adjusted(value)),
+                Interpretation::Plain,
+            ),
+            8 => (
+                format!("TIMESTAMP({}{})", unit(value)?, adjusted(value)),
+                Interpretation::Plain,
+            ),
+            10 => {
+                let bits = value.int(1)?;
+                if value.bool(2)? {
+                    (format!("INT{bits}"), Interpretation::Plain)
+                } else {
+                    (format!("UINT{bits}"), Interpretation::Unsigned)
+                }
+            }
+            11 => ("UNKNOWN".to_owned(), Interpretation::Plain),
+            12 => ("JSON".to_owned(), Interpretation::Text),
+            13 => ("BSON".to_owned(), Interpretation::Plain),
+            14 => ("UUID".to_owned(), Interpretation::Plain),
+            15 => ("FLOAT16".to_owned(), Interpretation::Plain),
+            // Maps, lists and variants are nested
+            _ => return None,
+        };
+        return Some((Some(name), interpretation));
+    }
+    let converted = match element.int(6) {
+        Some(converted) => converted,
+        None => return Some((None, Interpretation::Plain)),
+    };
+    // Converted types are described like the logical types that replaced them
+    let (name, interpretation) = match converted {
+        0 => ("STRING".to_owned(), Interpretation::Text),
+        4 => ("ENUM".to_owned(), Interpretation::Text),
+        5 => {
+            let scale = i32::try_from(element.int(7).unwrap_or(0)).ok()?;
+            let precision = element.int(8)?;
+            (
+                format!("DECIMAL({precision}, {scale})"),
+                Interpretation::Decimal { scale },
+            )
+        }
+        6 => ("DATE".to_owned(), Interpretation::Plain),
+        7 => ("TIME(MILLIS)".to_owned(), Interpretation::Plain),
+        8 => ("TIME(MICROS)".to_owned(), Interpretation::Plain),
+        9 => ("TIMESTAMP(MILLIS)".to_owned(), Interpretation::Plain),
+        10 => ("TIMESTAMP(MICROS)".to_owned(), Interpretation::Plain),
+        11..=14 => (
+            format!("UINT{}", 8 << (converted - 11)),
+            Interpretation::Unsigned,
+        ),
+        15..=18 => (
+            format!("INT{}", 8 << (converted - 15)),
+            Interpretation::Plain,
+        ),
+        19 => ("JSON".to_owned(), Interpretation::Text),
+        20 => ("BSON".to_owned(), Interpretation::Plain),
+        21 => ("INTERVAL".to_owned(), Interpretation::Plain),
+        _ => return None,
+    };
+    Some((Some(name), interpretation))
+}
+
+/// Reads the schema, or returns `None` if it's nested, which isn't supported.
+fn read_schema(metadata: &Thrift<'_>) -> Option<(Vec<Column>, Vec<Leaf>)> {
+    let (root, elements) = metadata.list(2)?.split_first()?;
+    if usize::try_from(root.int(5)?).ok()? != elements.len() {
+        return None;
+    }
+    elements
+        .iter()
+        .map(|element| {
+            let repetition = element.int(3).unwrap_or(0);
+            if element.field(5).is_some() || repetition == 2 {
+                return None;
+            }
+            let physical = match element.int(1)? {
+                0 => Physical::Boolean,
+                1 => Physical::Int32,
+                2 => Physical::Int64,
+                3 => Physical::Int96,
+                4 => Physical::Float,
+                5 => Physical::Double,
+                6 => Physical::ByteArray,
+                7 => Physical::FixedLenByteArray(usize::try_from(element.int(2)?).ok()?),
+                _ => return None,
+            };
+            let physical_name = match physical {
+                Physical::Boolean => "BOOLEAN".to_owned(),
+                Physical::Int32 => "INT32".to_owned(),
+                Physical::Int64 => "INT64".to_owned(),
+                Physical::Int96 => "INT96".to_owned(),
+                Physical::Float => "FLOAT".to_owned(),
+                Physical::Double => "DOUBLE".to_owned(),
+                Physical::ByteArray => "BYTE_ARRAY".to_owned(),
+                Physical::FixedLenByteArray(len) => format!("FIXED_LEN_BYTE_ARRAY({len})"),
+            };
+            let (logical_name, interpretation) = logical_type(element)?;
+            let data_type = match logical_name {
+                Some(name) => format!("{physical_name} ({name})"),
+                None => physical_name,
+            };
+            let column = Column {
+                name: element.string(4)?,
+                data_type,
+                nullable: repetition == 1,
+            };
+            let leaf = Leaf {
+                physical,
+                interpretation,
+                optional: repetition == 1,
+            };
+            Some((column, leaf))
+        })
+        .collect::<Option<Vec<_>>>()
+        .map(|columns| columns.into_iter().unzip())
+}
+
+fn decompress(codec: i64, data: &[u8], len: usize) -> Option<Vec<u8>> {
+    let data = match codec {
+        0 => data.to_vec(),
+        1 => crate::snappy::decompress(data)?,
+        2 => crate::gzip::decompress(data).ok()?,
+        6 => crate::zstd::decompress(data).ok()?,
+        7 => crate::lz4::decompress_block(data)?,
+        // LZO, Brotli and the Hadoop framing of LZ4
+        _ => return None,
+    };
+    (data.len() == len).then(|| data)
+}
+
+/// Decodes `count` values of the RLE and bit-packing hybrid encoding with `bit_width` bits each.
+fn read_hybrid(data: &[u8], bit_width: u32, count: usize) -> Option<Vec<u32>> {
+    if bit_width > 32 {
+        return None;
+    }
+    let mut reader = Reader { data, pos: 0 };
+    let mut values = Vec::with_capacity(count.min(data.len().saturating_mul(8)));
+    let value_bytes = usize::try_from((bit_width + 7) / 8).ok()?;
+    while values.len() < count {
+        let header = reader.varint()?;
+        let len = usize::try_from(header >> 1).ok()?;
+        if header & 1 == 0 {
+            let mut bytes = [0; 4];
+            bytes[..value_bytes].copy_from_slice(reader.bytes(value_bytes)?);
+            let remaining = count - values.len();
+            values.extend(std::iter::repeat(u32::from_le_bytes(bytes)).take(len.min(remaining)));
+        } else {
+            let bytes = reader.bytes(len.checked_mul(usize::try_from(bit_width).ok()?)?)?;
+            let mask = if bit_width == 32 {
+                u64::from(u32::MAX)
+            } else {
+                (1 << bit_width) - 1
+            };
+            let mut buffer = 0_u64;
+            let mut buffered = 0;
+            let mut bytes = bytes.iter();
+            for _ in 0..len * 8 {
+                while buffered < bit_width {
+                    buffer |= u64::from(*bytes.next()?) << buffered;
+                    buffered += 8;
+                }
+                if values.len() < count {
+                    values.push(u32::try_from(buffer & mask).ok()?);
+                }
+                buffer >>= bit_width;
+                buffered -= bit_width;
+            }
+        }
+    }
+    Some(values)
+}
+
+/// Decodes `count` plain-encoded values of `leaf`.
+fn read_plain(leaf: &Leaf, data: &[u8], count: usize) -> Option<Vec<Value>> {
+    let mut pos = 0_usize;
+    let mut take = |len: usize| {
+        let bytes = data.get(pos..pos.checked_add(len)?)?;
+        pos += len;
+        Some(bytes)
+    };
+    (0..count)
+        .map(|i| {
+            Some(match leaf.physical {
+                Physical::Boolean => Value::Bool(data.get(i / 8)? >> (i % 8) & 1 != 0),
+                Physical::Int32 => {
+                    let bytes = take(4)?.try_into().ok()?;
+                    match leaf.interpretation {
+                        Interpretation::Unsigned => {
+                            Value::Int(i128::from(u32::from_le_bytes(bytes)))
+                        }
+                        _ => interpret_int(leaf, i128::from(i32::from_le_bytes(bytes))),
+                    }
+                }
+                Physical::Int64 => {
+                    let bytes = take(8)?.try_into().ok()?;
+                    match leaf.interpretation {
+                        Interpretation::Unsigned => {
+                            Value::Int(i128::from(u64::from_le_bytes(bytes)))
+                        }
+                        _ => interpret_int(leaf, i128::from(i64::from_le_bytes(bytes))),
+                    }
+                }
+                Physical::Int96 => Value::Bytes(take(12)?.to_vec()),
+                Physical::Float => {
+                    let bytes = take(4)?.try_into().ok()?;
+                    Value::float(f64::from(f32::from_le_bytes(bytes)))
+                }
+                Physical::Double => Value::float(f64::from_le_bytes(take(8)?.try_into().ok()?)),
+                Physical::ByteArray => {
+                    let len = u32::from_le_bytes(take(4)?.try_into().ok()?);
+                    interpret_bytes(leaf, take(usize::try_from(len).ok()?)?)?
+                }
+                Physical::FixedLenByteArray(len) => interpret_bytes(leaf, take(len)?)?,
+            })
+        })
+        .collect()
+}
+
+fn interpret_int(leaf: &Leaf, value: i128) -> Value {
+    match leaf.interpretation {
+        Interpretation::Decimal { scale } => Value::Decimal { value, scale },
+        _ => Value::Int(value),
+    }
+}
+
+fn interpret_bytes(leaf: &Leaf, bytes: &[u8]) -> Option<Value> {
+    Some(match leaf.interpretation {
+        Interpretation::Text => Value::Text(String::from_utf8(bytes.to_vec()).ok()?),
+        // Decimals of byte arrays are big-endian, and wider ones don't fit in an `i128`
+        Interpretation::Decimal { scale } if bytes.len() <= 16 => {
+            let initial = if bytes.first().map_or(false, |&b| b & 0x80 != 0) {
+                -1
+            } else {
+                0
+            };
+            let value = bytes
+                .iter()
+                .fold(initial, |value: i128, &b| value << 8 | i128::from(b));
+            Value::Decimal { value, scale }
+        }
+        _ => Value::Bytes(bytes.to_vec()),
+    })
+}
+
+/// Decodes the values of a data page, given its definition levels and encoded values.
+fn read_values(
+    leaf: &Leaf,
+    dictionary: Option<&[Value]>,
+    encoding: i64,
+    definitions: &[u32],
+    data: &[u8],
+) -> Option<Vec<Value>> {
+    let present = definitions.iter().filter(|&&level| level == 1).count();
+    let present = if leaf.optional {
+        present
+    } else {
+        definitions.len()
+    };
+    let mut values = match encoding {
+        0 => read_plain(leaf, data, present)?,
+        2 | 8 => {
+            let dictionary = dictionary?;
+            let (&bit_width, indices) = data.split_first()?;
+            read_hybrid(indices, u32::from(bit_width), present)?
+                .into_iter()
+                .map(|index| dictionary.get(usize::try_from(index).ok()?).cloned())
+                .collect::<Option<_>>()?
+        }
+        _ => return None,
+    }
+    .into_iter();
+    if !leaf.optional {
+        return Some(values.collect());
+    }
+    definitions
+        .iter()
+        .map(|&level| {
+            if level == 1 {
+                values.next()
+            } else {
+                Some(Value::Null)
+            }
+        })
+        .collect()
+}
+
+/// Reads the values of a column chunk.
+fn read_chunk(data: &[u8], leaf: &Leaf, chunk: &Thrift<'_>) -> Option<Vec<Value>> {
+    let metadata = chunk.field(3)?;
+    let codec = metadata.int(4)?;
+    let count = usize::try_from(metadata.int(5)?).ok()?;
+    let start = match metadata.int(11) {
+        Some(offset) if offset > 0 => offset,
+        _ => metadata.int(9)?,
+    };
+    let start = usize::try_from(start).ok()?;
+    let len = usize::try_from(metadata.int(7)?).ok()?;
+    let chunk = data.get(start..start.checked_add(len)?)?;
+    let mut reader = Reader {
+        data: chunk,
+        pos: 0,
+    };
+    let mut dictionary = None;
+    let mut values = Vec::with_capacity(count.min(chunk.len().saturating_mul(8)));
+    while values.len() < count {
+        let header = reader.read_struct()?;
+        let uncompressed_len = usize::try_from(header.int(2)?).ok()?;
+        let page = reader.bytes(usize::try_from(header.int(3)?).ok()?)?;
+        match header.int(1)? {
+            0 => {
+                let page_header = header.field(5)?;
+                let page = decompress(codec, page, uncompressed_len)?;
+                let page_len = usize::try_from(page_header.int(1)?).ok()?;
+                let (definitions, data) = if leaf.optional {
+                    let len = u32::from_le_bytes(page.get(..4)?.try_into().ok()?);
+                    let len = usize::try_from(len).ok()?;
+                    let levels = page.get(4..4usize.checked_add(len)?)?;
+                    (read_hybrid(levels, 1, page_len)?, &page[4 + len..])
+                } else {
+                    (vec![1; page_len], &page[..])
+                };
+                values.extend(read_values(
+                    leaf,
+                    dictionary.as_deref(),
+                    page_header.int(2)?,
+                    &definitions,
+                    data,
+                )?);
+            }
+            2 => {
+                let page_header = header.field(7)?;
+                let page = decompress(codec, page, uncompressed_len)?;
+                let len = usize::try_from(page_header.int(1)?).ok()?;
+                // Only plain dictionaries exist, under either encoding
+                dictionary = Some(read_plain(leaf, &page, len)?);
+            }
+            3 => {
+                let page_header = header.field(8)?;
+                let page_len = usize::try_from(page_header.int(1)?).ok()?;
+                let definitions_len = usize::try_from(page_header.int(5)?).ok()?;
+                let repetitions_len = usize::try_from(page_header.int(6)?).ok()?;
+                let levels_len = definitions_len.checked_add(repetitions_len)?;
+                let levels = page.get(repetitions_len..levels_len)?;
+                let definitions = if leaf.optional {
+                    read_hybrid(levels, 1, page_len)?
+                } else {
+                    vec![1; page_len]
+                };
+                let data = &page[levels_len..];
+                let data = if page_header.bool(7).unwrap_or(true) {
+                    decompress(codec, data, uncompressed_len.checked_sub(levels_len)?)?
+                } else {
+                    data.to_vec()
+                };
+                values.extend(read_values(
+                    leaf,
+                    dictionary.as_deref(),
+                    page_header.int(4)?,
+                    &definitions,
+                    &data,
+                )?);
+            }
+            // Index pages
+            _ => {}
+        }
+    }
+    (values.len() == count).then(|| values)
+}
+
+/// Parses a Parquet file with a flat schema.
+fn parse(data: &[u8]) -> Option<Table> {
+    if !data.starts_with(MAGIC) || !data.ends_with(MAGIC) {
+        return None;
+    }
+    let footer_len_pos = data.len().checked_sub(8)?;
+    let footer_len = u32::from_le_bytes(data[footer_len_pos..footer_len_pos + 4].try_into().ok()?);
+    let footer_start = footer_len_pos.checked_sub(usize::try_from(footer_len).ok()?)?;
+    let metadata = Reader {
+        data: &data[footer_start..footer_len_pos],
+        pos: 0,
+    }
+    .read_struct()?;
+    let (columns, leaves) = read_schema(&metadata)?;
+    let groups = metadata
+        .list(4)
+        .unwrap_or_default()
+        .iter()
+        .map(|group| {
+            let chunks = group.list(1)?;
+            if chunks.len() != leaves.len() {
+                return None;
+            }
+            leaves
+                .iter()
+                .zip(chunks)
+                .map(|(leaf, chunk)| read_chunk(data, leaf, chunk))
+                .collect()
+        })
+        .collect::<Option<_>>()?;
+    Some(Table { columns, groups })
+}
+
+/// Compares two Parquet files by their schema and values, or returns `None` if either can't be
+/// parsed.
+pub(crate) fn equal(expected: &[u8], actual: &[u8]) -> Option<bool> {
+    Some(columnar::equal(&parse(expected)?, &parse(actual)?))
+}
+
+/// Finds the first difference in the schema or values of two Parquet files, or returns `None` if
+/// either can't be parsed.
+pub(crate) fn first_difference(expected: &[u8], actual: &[u8]) -> Option<Difference> {
+    columnar::first_difference(&parse(expected)?, &parse(actual)?, "row group")
+}
@@ -0,0 +1,71 @@
+//! Snapshots split into numbered part files, for hosts that limit file sizes.
+//!
+//! A file at `name` that was split is stored as `name.part000`, `name.part001`, and so on, and
+//! read back by concatenating the parts.
+
+use std::ffi::OsString;
+use std::fs;
+use std::io::{self, Read as _, Seek as _, SeekFrom};
+use std::path::{Path, PathBuf};
+
+use crate::not_found_to_none;
+
+/// Returns the path of part `n` of the file at `path`.
+pub(crate) fn part_path(path: &Path, n: usize) -> PathBuf {
+    let mut os: OsString = path.as_os_str().to_owned();
+    os.push(format!(".part{n:03}"));
+    os.into()
+}
+
+/// Reads the file at `path`, or its parts if it doesn't exist.
+///
+/// Returns `Ok(None)` if neither the file nor its first part exists.
+pub(crate) fn read(path: &Path) -> io::Result<Option<Vec<u8>>> {
+    if let Some(contents) = not_found_to_none(fs::read(path))? {
+        return Ok(Some(contents));
+    }
+    let mut contents: Option<Vec<u8>> = None;
+    for n in 0.. {
+        match not_found_to_none(fs::read(part_path(path, n)))? {
+            Some(part) => contents
+                .get_or_insert_with(Vec::new)
+                .extend_from_slice(&part),
+            None => break,
+        }
+    }
+    Ok(contents)
+}
+
+/// Reads `len` bytes at `offset` of the file at `path`, or of its parts if it doesn't exist.
+///
+/// The window is cut short at the end of the file.
+pub(crate) fn read_window(path: &Path, offset: usize, len: usize) -> io::Result<Option<Vec<u8>>> {
+    let mut file = match not_found_to_none(fs::File::open(path))? {
+        Some(file) => file,
+        None => {
+            return Ok(read(path)?.map(|contents| {
+                let start = offset.min(contents.len());
+                contents[start..start + len.min(contents.len() - start)].to_vec()
+            }))
+        }
+    };
+    file.seek(SeekFrom::Start(offset as u64))?;
+    let mut window = Vec::new();
+    file.take(len as u64).read_to_end(&mut window)?;
+    Ok(Some(window))
+}
+
+/// Removes part `first` and the following parts of the file at `path` with `remove`, which
+/// returns `None` if the part doesn't exist.
+pub(crate) fn remove_from(
+    path: &Path,
+    first: usize,
+    mut remove: impl FnMut(&Path) -> io::Result<Option<()>>,
+) -> io::Result<()> {
+    for n in first.. {
+        if remove(&part_path(path, n))?.is_none() {
+            break;
+        }
+    }
+    Ok(())
+}
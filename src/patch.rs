@@ -0,0 +1,90 @@
+//! Self-contained patch scripts reproducing updates that couldn't be written, for applying them
+//! on another machine.
+//!
+//! Unlike the overlay directory, the script contains the contents themselves, encoded in base64.
+//! Paths are relative to the package directory, which the script changes to, and the script only
+//! applies the updates of the latest test run.
+
+use std::io::{self, Write as _};
+use std::path::{Path, PathBuf};
+
+use crate::overlay;
+
+/// Patch script that updates are appended to when they can't be written in place.
+pub(crate) const PATCH_VAR_NAME: &str = "EXPECT_BYTES_PATCH";
+
+/// Ends the here-documents of the script, which base64 never contains.
+const DELIMITER: &str = "EXPECT_BYTES_EOF";
+/// Length of the base64 lines, as wrapped by the `base64` command.
+const LINE_LEN: usize = 76;
+
+const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+pub(crate) fn patch_path() -> Option<PathBuf> {
+    std::env::var_os(PATCH_VAR_NAME).map(PathBuf::from)
+}
+
+fn base64(data: &[u8]) -> String {
+    let mut encoded = String::with_capacity((data.len() + 2) / 3 * 4);
+    for chunk in data.chunks(3) {
+        let bytes = [
+            chunk[0],
+            *chunk.get(1).unwrap_or(&0),
+            *chunk.get(2).unwrap_or(&0),
+        ];
+        let indices = [
+            bytes[0] >> 2,
+            (bytes[0] & 0x03) << 4 | bytes[1] >> 4,
+            (bytes[1] & 0x0f) << 2 | bytes[2] >> 6,
+            bytes[2] & 0x3f,
+        ];
+        for (i, &index) in indices.iter().enumerate() {
+            if i <= chunk.len() {
+                encoded.push(char::from(ALPHABET[usize::from(index)]));
+            } else {
+                encoded.push('=');
+            }
+        }
+    }
+    encoded
+}
+
+/// Returns the shell commands that write `contents` to `path`, relative to the package directory.
+pub(crate) fn commands(path: &Path, contents: &[u8]) -> String {
+    let absolute = overlay::absolute(path);
+    let package = overlay::package_dir();
+    let destination = absolute.strip_prefix(&package).unwrap_or(&absolute);
+    let dir = match destination.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+    let mut commands = format!(
+        "mkdir -p {}\nbase64 -d > {} <<'{DELIMITER}'\n",
+        overlay::shell_quote(dir),
+        overlay::shell_quote(destination)
+    );
+    let encoded = base64(contents);
+    for line in encoded.as_bytes().chunks(LINE_LEN) {
+        commands.push_str(std::str::from_utf8(line).unwrap());
+        commands.push('\n');
+    }
+    commands.push_str(DELIMITER);
+    commands.push('\n');
+    commands
+}
+
+/// Returns the command that changes to the package directory on this machine.
+pub(crate) fn cd_command() -> String {
+    format!("cd {}\n", overlay::shell_quote(&overlay::package_dir()))
+}
+
+/// Appends the commands that write `contents` to `path` to the patch script at `script_path`,
+/// starting it over if it's from an earlier run.
+pub(crate) fn append(script_path: &Path, path: &Path, contents: &[u8]) -> io::Result<()> {
+    let mut script = overlay::open_run_script(
+        script_path,
+        "# Applies to the package directory given as the argument, by default the current one\n\
+         cd \"${1:-.}\"\n",
+    )?;
+    script.write_all(commands(path, contents).as_bytes())
+}
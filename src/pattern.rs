@@ -0,0 +1,451 @@
+//! Byte patterns for [`assert_matches`](crate::assert_matches).
+
+use std::ops::Range;
+use std::{error, fmt, io};
+
+use crate::render::write_byte_diff;
+use crate::style::BOLD;
+
+/// A pattern that matches byte strings.
+///
+/// Patterns are sequences of whitespace-separated elements:
+///
+/// - `de ad be ef` or `deadbeef`: literal bytes in hex.
+/// - `"GET "`: literal bytes from a string, with `\"`, `\\`, `\n`, `\r`, `\t`, `\0` and `\xNN`
+///   escapes.
+/// - `?`: any single byte.
+/// - `(len=4)`: any 4 bytes.
+/// - `[01 ?]`: a group of elements.
+/// - An element followed by `{3}`, `{1,3}`, `{2,}`, `*` or `+`: the element repeated 3 times,
+///   1 to 3 times, at least 2 times, any number of times or at least once.
+///
+/// ```
+/// # use expect_test_bytes::Pattern;
+/// let pattern = Pattern::parse(r#""HELO" 00 (len=4) [?? 00]*"#).unwrap();
+/// assert!(pattern.is_match(b"HELO\0\x12\x34\x56\x78ab\0"));
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Pattern {
+    source: String,
+    nodes: Vec<Node>,
+}
+
+/// Error returned when a [`Pattern`] can't be parsed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PatternError {
+    offset: usize,
+    message: &'static str,
+}
+
+impl PatternError {
+    /// Returns the byte offset of the error in the pattern source.
+    #[must_use]
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+}
+
+impl fmt::Display for PatternError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} at offset {}", self.message, self.offset)
+    }
+}
+
+impl error::Error for PatternError {}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Node {
+    kind: NodeKind,
+    /// Location of the element in the pattern source.
+    source: Range<usize>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum NodeKind {
+    Bytes(Vec<u8>),
+    Any(usize),
+    Repeat {
+        nodes: Vec<Node>,
+        min: usize,
+        max: Option<usize>,
+    },
+}
+
+struct Parser<'a> {
+    source: &'a str,
+    pos: usize,
+}
+
+impl Parser<'_> {
+    fn error<T>(&self, message: &'static str) -> Result<T, PatternError> {
+        Err(PatternError {
+            offset: self.pos,
+            message,
+        })
+    }
+
+    fn rest(&self) -> &[u8] {
+        &self.source.as_bytes()[self.pos..]
+    }
+
+    fn peek(&self) -> Option<u8> {
+        self.rest().first().copied()
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.peek().map_or(false, |b| b.is_ascii_whitespace()) {
+            self.pos += 1;
+        }
+    }
+
+    fn eat(&mut self, prefix: &str) -> bool {
+        let matches = self.rest().starts_with(prefix.as_bytes());
+        if matches {
+            self.pos += prefix.len();
+        }
+        matches
+    }
+
+    fn number(&mut self) -> Result<usize, PatternError> {
+        let len = self
+            .rest()
+            .iter()
+            .take_while(|b| b.is_ascii_digit())
+            .count();
+        if len == 0 {
+            return self.error("expected a number");
+        }
+        let number = self.source[self.pos..self.pos + len]
+            .parse()
+            .map_err(|_| PatternError {
+                offset: self.pos,
+                message: "number too large",
+            })?;
+        self.pos += len;
+        Ok(number)
+    }
+
+    /// Parses elements until the end of the source or a closing `]`.
+    fn sequence(&mut self) -> Result<Vec<Node>, PatternError> {
+        let mut nodes = Vec::new();
+        loop {
+            self.skip_whitespace();
+            match self.peek() {
+                None | Some(b']') => return Ok(nodes),
+                Some(_) => {
+                    let start = self.pos;
+                    let node = self.element()?;
+                    nodes.push(self.repetition(node, start)?);
+                }
+            }
+        }
+    }
+
+    fn element(&mut self) -> Result<Node, PatternError> {
+        let start = self.pos;
+        let kind = if self.eat("?") {
+            NodeKind::Any(1)
+        } else if self.eat("(len=") {
+            let len = self.number()?;
+            if !self.eat(")") {
+                return self.error("expected `)`");
+            }
+            NodeKind::Any(len)
+        } else if self.eat("[") {
+            let nodes = self.sequence()?;
+            if !self.eat("]") {
+                return self.error("expected `]`");
+            }
+            NodeKind::Repeat {
+                nodes,
+                min: 1,
+                max: Some(1),
+            }
+        } else if self.eat("\"") {
+            NodeKind::Bytes(self.string()?)
+        } else {
+            NodeKind::Bytes(self.hex()?)
+        };
+        Ok(Node {
+            kind,
+            source: start..self.pos,
+        })
+    }
+
+    fn repetition(&mut self, node: Node, start: usize) -> Result<Node, PatternError> {
+        let (min, max) = if self.eat("*") {
+            (0, None)
+        } else if self.eat("+") {
+            (1, None)
+        } else if self.eat("{") {
+            let min = self.number()?;
+            let max = if self.eat(",") {
+                if self.peek() == Some(b'}') {
+                    None
+                } else {
+                    Some(self.number()?)
+                }
+            } else {
+                Some(min)
+            };
+            if !self.eat("}") {
+                return self.error("expected `}`");
+            }
+            if max.map_or(false, |max| max < min) {
+                return self.error("repetition maximum is less than its minimum");
+            }
+            (min, max)
+        } else {
+            return Ok(node);
+        };
+        Ok(Node {
+            kind: NodeKind::Repeat {
+                nodes: vec![node],
+                min,
+                max,
+            },
+            source: start..self.pos,
+        })
+    }
+
+    fn string(&mut self) -> Result<Vec<u8>, PatternError> {
+        let mut bytes = Vec::new();
+        loop {
+            let b = match self.peek() {
+                Some(b) => b,
+                None => return self.error("unterminated string"),
+            };
+            self.pos += 1;
+            match b {
+                b'"' => return Ok(bytes),
+                b'\\' => {
+                    let escaped = match self.peek() {
+                        Some(b'"') => b'"',
+                        Some(b'\\') => b'\\',
+                        Some(b'n') => b'\n',
+                        Some(b'r') => b'\r',
+                        Some(b't') => b'\t',
+                        Some(b'0') => 0,
+                        Some(b'x') => {
+                            self.pos += 1;
+                            let byte = self.hex_byte()?;
+                            bytes.push(byte);
+                            continue;
+                        }
+                        _ => return self.error("unknown escape"),
+                    };
+                    self.pos += 1;
+                    bytes.push(escaped);
+                }
+                _ => bytes.push(b),
+            }
+        }
+    }
+
+    fn hex_byte(&mut self) -> Result<u8, PatternError> {
+        match self.rest().get(..2) {
+            Some(digits) if digits.iter().all(u8::is_ascii_hexdigit) => {
+                let byte = u8::from_str_radix(&self.source[self.pos..self.pos + 2], 16)
+                    .expect("checked to be hex digits");
+                self.pos += 2;
+                Ok(byte)
+            }
+            _ => self.error("expected two hex digits"),
+        }
+    }
+
+    fn hex(&mut self) -> Result<Vec<u8>, PatternError> {
+        let mut bytes = vec![self.hex_byte()?];
+        while self.peek().map_or(false, |b| b.is_ascii_hexdigit()) {
+            bytes.push(self.hex_byte()?);
+        }
+        Ok(bytes)
+    }
+}
+
+/// Where and why matching failed.
+#[derive(Debug, Clone, Copy)]
+enum Failure<'p> {
+    /// `node` didn't match the data at `offset`, which starts the node at `node_offset`.
+    Node {
+        node: &'p Node,
+        node_offset: usize,
+        offset: usize,
+    },
+    /// The pattern ended before the data at `offset`.
+    TrailingData { offset: usize },
+}
+
+impl Failure<'_> {
+    fn offset(&self) -> usize {
+        match *self {
+            Failure::Node { offset, .. } | Failure::TrailingData { offset } => offset,
+        }
+    }
+}
+
+/// Backtracking matcher that remembers the failure furthest into the data.
+struct Matcher<'p, 'd> {
+    data: &'d [u8],
+    furthest_failure: Option<Failure<'p>>,
+}
+
+impl<'p> Matcher<'p, '_> {
+    fn fail(&mut self, failure: Failure<'p>) -> bool {
+        if self
+            .furthest_failure
+            .map_or(true, |furthest| failure.offset() >= furthest.offset())
+        {
+            self.furthest_failure = Some(failure);
+        }
+        false
+    }
+
+    /// Matches `nodes` at `pos`, calling `then` with the end of each possible match until it
+    /// returns `true`.
+    fn nodes(
+        &mut self,
+        nodes: &'p [Node],
+        pos: usize,
+        then: &mut dyn FnMut(&mut Self, usize) -> bool,
+    ) -> bool {
+        let (node, rest) = match nodes.split_first() {
+            Some(split) => split,
+            None => return then(self, pos),
+        };
+        match &node.kind {
+            NodeKind::Bytes(bytes) => {
+                let data = &self.data[pos..];
+                if data.starts_with(bytes) {
+                    return self.nodes(rest, pos + bytes.len(), then);
+                }
+                let common = data.iter().zip(bytes).take_while(|(a, b)| a == b).count();
+                self.fail(Failure::Node {
+                    node,
+                    node_offset: pos,
+                    offset: pos + common,
+                })
+            }
+            NodeKind::Any(len) => {
+                if self.data.len() - pos >= *len {
+                    return self.nodes(rest, pos + len, then);
+                }
+                self.fail(Failure::Node {
+                    node,
+                    node_offset: pos,
+                    offset: self.data.len(),
+                })
+            }
+            NodeKind::Repeat { nodes, min, max } => {
+                self.repeat(nodes, (*min, *max), 0, pos, &mut |matcher, end| {
+                    matcher.nodes(rest, end, then)
+                })
+            }
+        }
+    }
+
+    /// Greedily matches `nodes` repeated `count..` times at `pos`.
+    fn repeat(
+        &mut self,
+        nodes: &'p [Node],
+        (min, max): (usize, Option<usize>),
+        count: usize,
+        pos: usize,
+        then: &mut dyn FnMut(&mut Self, usize) -> bool,
+    ) -> bool {
+        if max.map_or(true, |max| count < max)
+            && self.nodes(nodes, pos, &mut |matcher, end| {
+                // Stop repeating an element that matches nothing once the minimum is reached
+                (end != pos || count < min)
+                    && matcher.repeat(nodes, (min, max), count + 1, end, then)
+            })
+        {
+            return true;
+        }
+        count >= min && then(self, pos)
+    }
+}
+
+impl Pattern {
+    /// Parses a pattern.
+    ///
+    /// # Errors
+    ///
+    /// Will return an error if `source` isn't a valid pattern.
+    pub fn parse(source: &str) -> Result<Self, PatternError> {
+        let mut parser = Parser { source, pos: 0 };
+        let nodes = parser.sequence()?;
+        if parser.pos < source.len() {
+            return parser.error("unmatched `]`");
+        }
+        Ok(Self {
+            source: source.to_owned(),
+            nodes,
+        })
+    }
+
+    /// Checks whether the pattern matches all of `data`.
+    #[must_use]
+    pub fn is_match(&self, data: &[u8]) -> bool {
+        self.find_failure(data).is_none()
+    }
+
+    fn find_failure<'p>(&'p self, data: &[u8]) -> Option<Failure<'p>> {
+        let mut matcher = Matcher {
+            data,
+            furthest_failure: None,
+        };
+        if matcher.nodes(&self.nodes, 0, &mut |matcher, end| {
+            end == data.len() || matcher.fail(Failure::TrailingData { offset: end })
+        }) {
+            None
+        } else {
+            matcher.furthest_failure
+        }
+    }
+
+    /// Writes a report of why `data` doesn't match, or returns `Ok(())` if it does.
+    pub(crate) fn check<W: io::Write>(&self, data: &[u8], writer: &mut W) -> Result<(), ()> {
+        let failure = match self.find_failure(data) {
+            Some(failure) => failure,
+            None => return Ok(()),
+        };
+        match failure {
+            Failure::Node {
+                node,
+                node_offset,
+                offset,
+            } => {
+                writeln!(
+                    writer,
+                    "{BOLD}Pattern{BOLD:#}:\n{}\n{}{}\n",
+                    self.source,
+                    " ".repeat(self.source[..node.source.start].chars().count()),
+                    "^".repeat(self.source[node.source.clone()].chars().count().max(1))
+                )
+                .unwrap();
+                match &node.kind {
+                    NodeKind::Bytes(bytes) => {
+                        write_byte_diff(writer, (bytes, node_offset), (data, 0), offset);
+                    }
+                    _ => writeln!(
+                        writer,
+                        "The data ends at byte {:#x}, in the middle of the element starting at \
+                         byte {node_offset:#x}",
+                        data.len()
+                    )
+                    .unwrap(),
+                }
+            }
+            Failure::TrailingData { offset } => writeln!(
+                writer,
+                "{BOLD}Pattern{BOLD:#}:\n{}\n\nThe pattern ends at byte {offset:#x}, but the data \
+                 is {:#x} bytes long",
+                self.source,
+                data.len()
+            )
+            .unwrap(),
+        }
+        Err(())
+    }
+}
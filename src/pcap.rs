@@ -0,0 +1,237 @@
+//! Packet-by-packet comparison of pcap and pcapng captures.
+
+use crate::structure::Difference;
+
+const PCAP_MAGIC_MICROS: u32 = 0xa1b2_c3d4;
+const PCAP_MAGIC_NANOS: u32 = 0xa1b2_3c4d;
+const PCAP_HEADER_LEN: usize = 24;
+const PCAP_RECORD_HEADER_LEN: usize = 16;
+
+const PCAPNG_SECTION_HEADER: u32 = 0x0a0d_0d0a;
+const PCAPNG_BYTE_ORDER_MAGIC: u32 = 0x1a2b_3c4d;
+const PCAPNG_OBSOLETE_PACKET: u32 = 2;
+const PCAPNG_SIMPLE_PACKET: u32 = 3;
+const PCAPNG_ENHANCED_PACKET: u32 = 6;
+
+/// A file header, block or packet record of a capture.
+struct Record<'a> {
+    /// 1-based packet number, as shown by Wireshark, or `None` for other blocks.
+    packet_number: Option<usize>,
+    /// pcapng block type, or `None` for pcap headers and records.
+    block_type: Option<u32>,
+    /// The bytes of the record other than the timestamp and payload.
+    header: Vec<u8>,
+    /// The timestamp, formatted for the failure report.
+    timestamp: Option<String>,
+    payload: &'a [u8],
+}
+
+fn read_u32(data: &[u8], offset: usize, little_endian: bool) -> Option<u32> {
+    let bytes = data.get(offset..offset.checked_add(4)?)?;
+    let bytes = [bytes[0], bytes[1], bytes[2], bytes[3]];
+    Some(if little_endian {
+        u32::from_le_bytes(bytes)
+    } else {
+        u32::from_be_bytes(bytes)
+    })
+}
+
+fn read_len(data: &[u8], offset: usize, little_endian: bool) -> Option<usize> {
+    usize::try_from(read_u32(data, offset, little_endian)?).ok()
+}
+
+/// A record's header without the timestamp, the high and low halves of the timestamp and the
+/// payload.
+type SplitRecord<'a> = (Vec<u8>, Option<(u32, u32)>, &'a [u8]);
+
+/// Splits `record` with the timestamp at `timestamp_offset` and the payload at `payload`.
+fn split_record(
+    record: &[u8],
+    timestamp_offset: Option<usize>,
+    payload: std::ops::Range<usize>,
+    little_endian: bool,
+) -> Option<SplitRecord<'_>> {
+    let payload_bytes = record.get(payload.clone())?;
+    let mut header = [&record[..payload.start], &record[payload.end..]].concat();
+    let timestamp = match timestamp_offset {
+        Some(offset) => {
+            let high = read_u32(record, offset, little_endian)?;
+            let low = read_u32(record, offset + 4, little_endian)?;
+            header[offset..offset + 8].fill(0);
+            Some((high, low))
+        }
+        None => None,
+    };
+    Some((header, timestamp, payload_bytes))
+}
+
+fn parse_pcap(data: &[u8]) -> Option<Vec<Record<'_>>> {
+    let little_endian = match read_u32(data, 0, true)? {
+        PCAP_MAGIC_MICROS | PCAP_MAGIC_NANOS => true,
+        _ if matches!(
+            read_u32(data, 0, false)?,
+            PCAP_MAGIC_MICROS | PCAP_MAGIC_NANOS
+        ) =>
+        {
+            false
+        }
+        _ => return None,
+    };
+    let mut records = vec![Record {
+        packet_number: None,
+        block_type: None,
+        header: data.get(..PCAP_HEADER_LEN)?.to_vec(),
+        timestamp: None,
+        payload: &[],
+    }];
+    let mut offset = PCAP_HEADER_LEN;
+    while offset < data.len() {
+        let captured_len = read_len(data, offset + 8, little_endian)?;
+        let end = offset
+            .checked_add(PCAP_RECORD_HEADER_LEN)?
+            .checked_add(captured_len)?;
+        let (header, timestamp, payload) = split_record(
+            data.get(offset..end)?,
+            Some(0),
+            PCAP_RECORD_HEADER_LEN..PCAP_RECORD_HEADER_LEN + captured_len,
+            little_endian,
+        )?;
+        records.push(Record {
+            packet_number: Some(records.len()),
+            block_type: None,
+            header,
+            // Seconds and microseconds or nanoseconds
+            timestamp: timestamp.map(|(seconds, fraction)| format!("{seconds}.{fraction:06}")),
+            payload,
+        });
+        offset = end;
+    }
+    Some(records)
+}
+
+fn parse_pcapng(data: &[u8]) -> Option<Vec<Record<'_>>> {
+    if read_u32(data, 0, true)? != PCAPNG_SECTION_HEADER {
+        return None;
+    }
+    let mut records = Vec::new();
+    let mut packets = 0;
+    let mut little_endian = true;
+    let mut offset = 0;
+    while offset < data.len() {
+        if read_u32(data, offset, true)? == PCAPNG_SECTION_HEADER {
+            little_endian = read_u32(data, offset + 8, true)? == PCAPNG_BYTE_ORDER_MAGIC;
+        }
+        let block_type = read_u32(data, offset, little_endian)?;
+        let block_len = read_len(data, offset + 4, little_endian)?;
+        let block = data.get(offset..offset.checked_add(block_len)?)?;
+        let (header, timestamp, payload) = match block_type {
+            PCAPNG_ENHANCED_PACKET | PCAPNG_OBSOLETE_PACKET => {
+                let captured_len = read_len(block, 20, little_endian)?;
+                split_record(block, Some(12), 28..28 + captured_len, little_endian)?
+            }
+            PCAPNG_SIMPLE_PACKET => {
+                let original_len = read_len(block, 8, little_endian)?;
+                let captured_len = original_len.min(block_len.checked_sub(16)?);
+                split_record(block, None, 12..12 + captured_len, little_endian)?
+            }
+            _ => (block.to_vec(), None, &[][..]),
+        };
+        let is_packet = matches!(
+            block_type,
+            PCAPNG_ENHANCED_PACKET | PCAPNG_OBSOLETE_PACKET | PCAPNG_SIMPLE_PACKET
+        );
+        if is_packet {
+            packets += 1;
+        }
+        records.push(Record {
+            packet_number: is_packet.then(|| packets),
+            block_type: Some(block_type),
+            header,
+            // In units of the interface's timestamp resolution
+            timestamp: timestamp
+                .map(|(high, low)| (u64::from(high) << 32 | u64::from(low)).to_string()),
+            payload,
+        });
+        offset += block_len.max(12);
+    }
+    Some(records)
+}
+
+fn parse(data: &[u8]) -> Option<Vec<Record<'_>>> {
+    parse_pcap(data).or_else(|| parse_pcapng(data))
+}
+
+/// Compares two captures without their packet timestamps, or returns `None` if either can't be
+/// parsed.
+pub(crate) fn equal_ignoring_timestamps(expected: &[u8], actual: &[u8]) -> Option<bool> {
+    let (expected, actual) = (parse(expected)?, parse(actual)?);
+    Some(
+        expected.len() == actual.len()
+            && expected
+                .iter()
+                .zip(&actual)
+                .all(|(e, a)| e.header == a.header && e.payload == a.payload),
+    )
+}
+
+fn describe(record: &Record<'_>, index: usize) -> String {
+    match (record.packet_number, record.block_type) {
+        (Some(number), _) => format!("packet #{number}"),
+        (None, Some(block_type)) => format!("block #{} (type {block_type:#x})", index + 1),
+        (None, None) => "the file header".to_owned(),
+    }
+}
+
+/// Finds the first differing record of two captures, or returns `None` if either can't be
+/// parsed.
+pub(crate) fn first_difference(
+    expected: &[u8],
+    actual: &[u8],
+    ignore_timestamps: bool,
+) -> Option<Difference> {
+    let (expected_records, actual_records) = (parse(expected)?, parse(actual)?);
+    for (index, (e, a)) in expected_records.iter().zip(&actual_records).enumerate() {
+        let record = describe(a, index);
+        if e.payload != a.payload {
+            let diff_idx = crate::first_diff_index(e.payload, a.payload).unwrap_or(0);
+            return Some(Difference {
+                description: format!("{record} payload differs at byte {diff_idx}"),
+                regions: Some((e.payload.to_vec(), a.payload.to_vec())),
+            });
+        }
+        if e.header != a.header {
+            return Some(Difference {
+                description: format!("{record} header differs"),
+                regions: Some((e.header.clone(), a.header.clone())),
+            });
+        }
+        if !ignore_timestamps && e.timestamp != a.timestamp {
+            return Some(Difference {
+                description: format!(
+                    "{record} timestamp differs: expected {}, found {}",
+                    e.timestamp.as_deref().unwrap_or("none"),
+                    a.timestamp.as_deref().unwrap_or("none")
+                ),
+                regions: None,
+            });
+        }
+    }
+    let packets = |records: &[Record<'_>]| {
+        records
+            .iter()
+            .filter(|record| record.packet_number.is_some())
+            .count()
+    };
+    let (unit, expected_count, actual_count) =
+        match (packets(&expected_records), packets(&actual_records)) {
+            (e, a) if e != a => ("packets", e, a),
+            _ => ("blocks", expected_records.len(), actual_records.len()),
+        };
+    (expected_count != actual_count).then(|| Difference {
+        description: format!(
+            "the expected capture has {expected_count} {unit}, \
+             the actual capture has {actual_count}"
+        ),
+        regions: None,
+    })
+}
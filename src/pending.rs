@@ -0,0 +1,73 @@
+//! Pending snapshots, written next to the file when an assertion fails for reviewing them with
+//! tools such as `cargo insta review`.
+//!
+//! Files named like `frame.snap.bin` get insta's layout for binary snapshots: the metadata in
+//! `frame.snap.new` and the contents in `frame.snap.new.bin`, which insta moves to `frame.snap`
+//! and `frame.snap.bin` when accepting them. Other files get their contents written to a file
+//! with `.new` appended to the name, which is accepted by renaming it over the file.
+
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+use crate::Position;
+
+/// When set, a pending snapshot is written for each failing assertion.
+pub(crate) const PENDING_VAR_NAME: &str = "EXPECT_BYTES_PENDING";
+
+pub(crate) fn is_enabled() -> bool {
+    std::env::var_os(PENDING_VAR_NAME).is_some()
+}
+
+/// Files of the pending snapshot of a file.
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) struct Pending {
+    /// Path of insta's metadata, and the extension of the snapshot, if the file is named like
+    /// insta's binary snapshots.
+    pub(crate) metadata: Option<(PathBuf, String)>,
+    pub(crate) contents: PathBuf,
+}
+
+impl Pending {
+    pub(crate) fn of(path: &Path) -> Self {
+        let file_name = path
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        if let Some((stem, extension)) = file_name.split_once(".snap.") {
+            if !stem.is_empty() && !extension.is_empty() {
+                return Self {
+                    metadata: Some((
+                        path.with_file_name(format!("{stem}.snap.new")),
+                        extension.to_owned(),
+                    )),
+                    contents: path.with_file_name(format!("{stem}.snap.new.{extension}")),
+                };
+            }
+        }
+        let mut contents = path.as_os_str().to_owned();
+        contents.push(".new");
+        Self {
+            metadata: None,
+            contents: contents.into(),
+        }
+    }
+
+    /// Writes the pending snapshot with `contents`, asserted at `position`.
+    pub(crate) fn write(&self, position: Option<&Position>, contents: &[u8]) -> io::Result<()> {
+        if let Some((path, extension)) = &self.metadata {
+            let mut metadata = Vec::new();
+            writeln!(metadata, "---")?;
+            if let Some(position) = position {
+                writeln!(metadata, "source: {}", position.file)?;
+                writeln!(metadata, "assertion_line: {}", position.line)?;
+            }
+            writeln!(
+                metadata,
+                "extension: {extension}\nsnapshot_kind: binary\n---"
+            )?;
+            fs::write(path, metadata)?;
+        }
+        fs::write(&self.contents, contents)
+    }
+}
@@ -0,0 +1,79 @@
+//! Quarantine mode, where mismatches are collected and summarized when the process exits instead
+//! of failing the test.
+
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, Once, PoisonError};
+
+use crate::at_exit;
+use crate::global::Global;
+use crate::output;
+use crate::style::{ARROW, NOTE};
+
+/// When set, mismatching assertions pass and are summarized when the process exits.
+pub(crate) const QUARANTINE_VAR_NAME: &str = "EXPECT_BYTES_QUARANTINE";
+
+/// Paths of the files with mismatches during this run, in the order they were first recorded.
+static MISMATCHES: Global<Mutex<Vec<PathBuf>>> = Global::new();
+static REGISTER_SUMMARY: Once = Once::new();
+
+pub(crate) fn is_enabled() -> bool {
+    std::env::var_os(QUARANTINE_VAR_NAME).is_some()
+}
+
+/// Records a mismatch in `path`, printing a summary of all mismatches when the process exits.
+pub(crate) fn record(path: &Path) {
+    {
+        let mut mismatches = MISMATCHES
+            .get_or_init(Mutex::default)
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        if !mismatches.iter().any(|p| p == path) {
+            mismatches.push(path.to_owned());
+        }
+    }
+    REGISTER_SUMMARY.call_once(|| {
+        // SAFETY: `print_summary` doesn't unwind and only uses state that lives until the process
+        // exits.
+        unsafe {
+            at_exit::register(print_summary);
+        }
+    });
+}
+
+extern "C" fn print_summary() {
+    let mismatches = MISMATCHES
+        .get_or_init(Mutex::default)
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner);
+    // Errors can't be reported from here, and unwinding out of this function would abort
+    let _ = write_summary(&mismatches, &mut output::stderr());
+}
+
+/// Writes the end-of-run summary of quarantined mismatches.
+pub(crate) fn write_summary<W: io::Write>(
+    mismatches: &[PathBuf],
+    writer: &mut W,
+) -> io::Result<()> {
+    if mismatches.is_empty() {
+        return Ok(());
+    }
+    writeln!(
+        writer,
+        "\n{NOTE}quarantine{NOTE:#}: {} expect {} mismatched:",
+        mismatches.len(),
+        if mismatches.len() == 1 {
+            "file"
+        } else {
+            "files"
+        }
+    )?;
+    for path in mismatches {
+        writeln!(writer, "   {ARROW}-->{ARROW:#} {}", path.display())?;
+    }
+    writeln!(
+        writer,
+        "\nUnset {QUARANTINE_VAR_NAME} to see the diffs, or bless them with \
+         `env UPDATE_EXPECT=1 cargo test`."
+    )
+}
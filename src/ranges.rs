@@ -0,0 +1,33 @@
+//! Ranges of interest, for [`ExpectFile::only_ranges`].
+//!
+//! [`ExpectFile::only_ranges`]: crate::ExpectFile::only_ranges
+
+use std::borrow::Cow;
+use std::ops::Range;
+
+/// Zeroes the bytes of `data` outside of `ranges` and drops the bytes after the last one, so that
+/// only the ranges are compared.
+///
+/// Without ranges, `data` is returned unchanged.
+pub(crate) fn mask_outside<'a>(
+    ranges: &[Range<usize>],
+    data: impl Into<Cow<'a, [u8]>>,
+) -> Cow<'a, [u8]> {
+    let mut data = data.into();
+    if ranges.is_empty() {
+        return data;
+    }
+    let end = ranges.iter().map(|range| range.end).max().unwrap_or(0);
+    if data.len() > end {
+        data.to_mut().truncate(end);
+    }
+    let outside = |i: usize| !ranges.iter().any(|range| range.contains(&i));
+    if data.iter().enumerate().any(|(i, &b)| b != 0 && outside(i)) {
+        for (i, b) in data.to_mut().iter_mut().enumerate() {
+            if outside(i) {
+                *b = 0;
+            }
+        }
+    }
+    data
+}
@@ -0,0 +1,28 @@
+//! Coordinates of offsets in raster images, such as framebuffers.
+
+/// Layout of [`ExpectFile::raster`](crate::ExpectFile::raster) data: rows of `width` pixels of
+/// `bytes_per_pixel` bytes each, stored top to bottom without padding.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Raster {
+    pub(crate) width: usize,
+    pub(crate) bytes_per_pixel: usize,
+}
+
+impl Raster {
+    /// Describes the pixel and channel at `offset`, e.g. `pixel (x=37, y=12), channel G`.
+    ///
+    /// Channels of 3- and 4-byte pixels are named as RGB and RGBA, and others are numbered.
+    pub(crate) fn describe(self, offset: usize) -> String {
+        let pixel = offset / self.bytes_per_pixel;
+        let channel = offset % self.bytes_per_pixel;
+        let (x, y) = (pixel % self.width, pixel / self.width);
+        match self.bytes_per_pixel {
+            1 => format!("pixel (x={x}, y={y})"),
+            3 | 4 => format!(
+                "pixel (x={x}, y={y}), channel {}",
+                char::from(b"RGBA"[channel])
+            ),
+            _ => format!("pixel (x={x}, y={y}), byte {channel}"),
+        }
+    }
+}
@@ -0,0 +1,111 @@
+//! Record-by-record comparison of length-prefixed records.
+
+use crate::structure::Difference;
+
+/// Number of differing records listed in the failure report.
+const LISTED_RECORDS: usize = 8;
+
+/// Length prefix of each record in [`ExpectFile::records`](crate::ExpectFile::records) data. The
+/// length counts the bytes after the prefix.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum LengthPrefix {
+    /// 8-bit length.
+    U8,
+    /// 16-bit little-endian length.
+    U16Le,
+    /// 16-bit big-endian length.
+    U16Be,
+    /// 32-bit little-endian length.
+    U32Le,
+    /// 32-bit big-endian length.
+    U32Be,
+}
+
+impl LengthPrefix {
+    fn size(self) -> usize {
+        match self {
+            Self::U8 => 1,
+            Self::U16Le | Self::U16Be => 2,
+            Self::U32Le | Self::U32Be => 4,
+        }
+    }
+
+    fn parse(self, prefix: &[u8]) -> usize {
+        let len = match self {
+            Self::U8 => u32::from(prefix[0]),
+            Self::U16Le => u32::from(u16::from_le_bytes([prefix[0], prefix[1]])),
+            Self::U16Be => u32::from(u16::from_be_bytes([prefix[0], prefix[1]])),
+            Self::U32Le => u32::from_le_bytes([prefix[0], prefix[1], prefix[2], prefix[3]]),
+            Self::U32Be => u32::from_be_bytes([prefix[0], prefix[1], prefix[2], prefix[3]]),
+        };
+        usize::try_from(len).unwrap_or(usize::MAX)
+    }
+}
+
+/// Splits `data` into the contents of its records, or returns `None` if a record is truncated.
+fn parse_records(mut data: &[u8], prefix: LengthPrefix) -> Option<Vec<&[u8]>> {
+    let mut records = Vec::new();
+    while !data.is_empty() {
+        let len = prefix.parse(data.get(..prefix.size())?);
+        let end = prefix.size().checked_add(len)?;
+        records.push(data.get(prefix.size()..end)?);
+        data = &data[end..];
+    }
+    Some(records)
+}
+
+/// Lists the differing records of two sequences of records, or returns `None` if either can't be
+/// parsed.
+///
+/// Records are compared by their index, so a record that changed length doesn't shift the
+/// comparison of the following ones.
+pub(crate) fn first_difference(
+    expected: &[u8],
+    actual: &[u8],
+    prefix: LengthPrefix,
+) -> Option<Difference> {
+    let (expected, actual) = (
+        parse_records(expected, prefix)?,
+        parse_records(actual, prefix)?,
+    );
+    let differing: Vec<usize> = (0..expected.len().max(actual.len()))
+        .filter(|&i| expected.get(i) != actual.get(i))
+        .collect();
+    let first = *differing.first()?;
+    let mut listed: Vec<String> = differing
+        .iter()
+        .take(LISTED_RECORDS)
+        .map(|&i| match (expected.get(i), actual.get(i)) {
+            (Some(e), Some(a)) if e.len() != a.len() => {
+                format!("#{i} ({} bytes, expected {})", a.len(), e.len())
+            }
+            (Some(_), Some(_)) => format!("#{i}"),
+            (Some(_), None) => format!("#{i} (missing)"),
+            (None, _) => format!("#{i} (extra)"),
+        })
+        .collect();
+    if differing.len() > LISTED_RECORDS {
+        listed.push(format!("and {} more", differing.len() - LISTED_RECORDS));
+    }
+    let (first_record, regions) = match (expected.get(first), actual.get(first)) {
+        (Some(e), Some(a)) => {
+            let diff_idx = crate::first_diff_index(e, a).unwrap_or(0);
+            (
+                format!("\n\nRecord #{first} differs at byte {diff_idx:#x} of its data"),
+                Some((e.to_vec(), a.to_vec())),
+            )
+        }
+        _ => (String::new(), None),
+    };
+    let description = format!(
+        "{} of {} records differ: {}{first_record}",
+        differing.len(),
+        expected.len(),
+        listed.join(", ")
+    );
+    Some(Difference {
+        description,
+        regions,
+    })
+}
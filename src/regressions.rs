@@ -0,0 +1,120 @@
+//! Corpora of failing inputs found by property testing, replayed as regression tests.
+
+use std::fs;
+use std::io;
+use std::panic::{self, AssertUnwindSafe};
+use std::path::{Path, PathBuf};
+
+use crate::{not_found_to_none, resolve_root, sha256};
+
+/// Number of hex digits of the SHA-256 digest of an input used as its file name.
+const NAME_HEX_LEN: usize = 16;
+
+/// A directory of minimal failing inputs, each stored in a file named by its hash.
+///
+/// Inputs found by proptest or `arbitrary`-based fuzzing are recorded once shrunk, and every
+/// recorded input is replayed by a regular test, so fixed bugs stay fixed without maintaining the
+/// corpus by hand.
+///
+/// ```no_run
+/// use expect_test_bytes::RegressionCorpus;
+///
+/// # fn decode(_: &[u8]) {}
+/// # fn find_minimal_failure() -> Option<Vec<u8>> { None }
+/// let corpus = RegressionCorpus::new("tests/regressions/decode");
+/// // In the property test, after shrinking
+/// if let Some(input) = find_minimal_failure() {
+///     corpus.record(&input).unwrap();
+/// }
+/// // In the regression test
+/// corpus.replay(decode);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RegressionCorpus {
+    dir: PathBuf,
+}
+
+impl RegressionCorpus {
+    /// Creates a corpus in the directory at `dir`, which is created when the first input is
+    /// recorded.
+    ///
+    /// Relative paths are resolved like [`Fixture::new`](crate::Fixture::new) does.
+    #[must_use]
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self {
+            dir: resolve_root(dir.into()),
+        }
+    }
+
+    /// Stores `input` in the corpus unless it's already there, returning the path of its file.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if creating the directory or writing the file fails.
+    pub fn record(&self, input: &[u8]) -> io::Result<PathBuf> {
+        let hex = sha256::to_hex(&sha256::sha256(input));
+        let path = self.dir.join(format!("{}.bin", &hex[..NAME_HEX_LEN]));
+        if !path.exists() {
+            fs::create_dir_all(&self.dir)?;
+            fs::write(&path, input)?;
+        }
+        Ok(path)
+    }
+
+    /// Returns the recorded inputs and their paths, ordered by path.
+    ///
+    /// A corpus whose directory doesn't exist is empty.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if reading the directory or an input fails.
+    pub fn inputs(&self) -> io::Result<Vec<(PathBuf, Vec<u8>)>> {
+        read_inputs(&self.dir)
+    }
+
+    /// Calls `check` with every recorded input, which panics if the input still fails.
+    ///
+    /// Every input is checked instead of stopping at the first failure.
+    ///
+    /// # Panics
+    ///
+    /// Will panic listing the failing inputs if `check` panics for any of them, or if reading the
+    /// corpus fails.
+    #[track_caller]
+    pub fn replay(&self, mut check: impl FnMut(&[u8])) {
+        let inputs = self
+            .inputs()
+            .unwrap_or_else(|e| panic!("failed to read {}: {e}", self.dir.display()));
+        let failing: Vec<String> = inputs
+            .iter()
+            .filter(|(_, input)| panic::catch_unwind(AssertUnwindSafe(|| check(input))).is_err())
+            .map(|(path, _)| path.display().to_string())
+            .collect();
+        assert!(
+            failing.is_empty(),
+            "{} of {} regression inputs failed:\n    {}",
+            failing.len(),
+            inputs.len(),
+            failing.join("\n    ")
+        );
+    }
+}
+
+/// Reads the files in the directory at `dir` and their paths, ordered by path.
+///
+/// A directory that doesn't exist is empty.
+pub(crate) fn read_inputs(dir: &Path) -> io::Result<Vec<(PathBuf, Vec<u8>)>> {
+    let entries = match not_found_to_none(fs::read_dir(dir))? {
+        Some(entries) => entries,
+        None => return Ok(Vec::new()),
+    };
+    let mut paths = entries
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<io::Result<Vec<_>>>()?;
+    paths.retain(|path| path.is_file());
+    paths.sort();
+    paths
+        .into_iter()
+        .map(|path| fs::read(&path).map(|input| (path, input)))
+        .collect()
+}
@@ -0,0 +1,363 @@
+//! Rendering of the diff section of failure reports.
+
+use crate::style::{self, BOLD};
+use crate::{contains, unified, xxd};
+use std::fmt::{self, Write as _};
+use std::io;
+
+pub(crate) const BYTE_WINDOW_HALF_SIZE: usize = 4;
+
+/// Number of expected bytes after the first difference that are searched for in the actual data.
+pub(crate) const SHIFT_SEARCH_LEN: usize = 16;
+/// Minimum number of bytes searched for, to avoid reporting coincidental matches.
+const SHIFT_SEARCH_MIN_LEN: usize = 4;
+
+/// Writes where the expected bytes from the first difference onwards appear in the actual data, if
+/// the difference looks like a shift caused by inserted or removed bytes.
+///
+/// `expected` may be a region of the whole data starting at `expected_offset`, which must be at
+/// most `diff_idx`.
+pub(crate) fn write_shift_hint<W: io::Write>(
+    writer: &mut W,
+    (expected, expected_offset): (&[u8], usize),
+    actual: &[u8],
+    diff_idx: usize,
+) {
+    let needle = match expected.get(diff_idx - expected_offset..) {
+        Some(rest) if rest.len() >= SHIFT_SEARCH_MIN_LEN => {
+            &rest[..rest.len().min(SHIFT_SEARCH_LEN)]
+        }
+        _ => return,
+    };
+    let found = match contains::find_nearest(actual, needle, diff_idx) {
+        Some(found) if found != diff_idx => found,
+        _ => return,
+    };
+    let shift = if found > diff_idx {
+        format!("+{}", found - diff_idx)
+    } else {
+        format!("-{}", diff_idx - found)
+    };
+    writeln!(
+        writer,
+        "\nExpected content at byte {diff_idx:#x} appears at byte {found:#x} in actual \
+         (shifted by {shift} bytes)"
+    )
+    .unwrap();
+}
+
+/// Returns the length of the common suffix of `expected` and `actual` after their common prefix of
+/// `prefix` bytes.
+pub(crate) fn common_suffix_len(expected: &[u8], actual: &[u8], prefix: usize) -> usize {
+    expected[prefix.min(expected.len())..]
+        .iter()
+        .rev()
+        .zip(actual[prefix.min(actual.len())..].iter().rev())
+        .take_while(|(e, a)| e == a)
+        .count()
+}
+
+/// Writes the lengths of the common prefix and suffix and of the differing middle, which tell an
+/// in-place patch apart from a rewrite.
+///
+/// Only the prefix is written if the suffix isn't known, as when the file is compared as it's read.
+pub(crate) fn write_common_lengths<W: io::Write>(
+    writer: &mut W,
+    (prefix, suffix): (usize, Option<usize>),
+    expected_len: usize,
+    actual_len: usize,
+) -> io::Result<()> {
+    let plural = |len: usize| if len == 1 { "byte" } else { "bytes" };
+    let suffix = match suffix {
+        Some(suffix) => suffix,
+        None => return writeln!(writer, "\nCommon prefix: {prefix} {}", plural(prefix)),
+    };
+    let expected_middle = expected_len - prefix - suffix;
+    let actual_middle = actual_len - prefix - suffix;
+    let middle = if expected_middle == actual_middle {
+        format!("{actual_middle} {}", plural(actual_middle))
+    } else {
+        format!(
+            "{expected_middle} {} expected, {actual_middle} {} actual",
+            plural(expected_middle),
+            plural(actual_middle)
+        )
+    };
+    writeln!(
+        writer,
+        "\nCommon prefix: {prefix} {}, common suffix: {suffix} {}, differing middle: {middle}",
+        plural(prefix),
+        plural(suffix)
+    )
+}
+
+struct ByteWindowDisplay<'a> {
+    data: &'a [u8],
+    /// Offset of `data` in the whole file. Must be at most `start`.
+    data_offset: usize,
+    /// Offset of the first displayed byte.
+    start: usize,
+    diff_idx: usize,
+    is_expected: bool,
+}
+impl fmt::Display for ByteWindowDisplay<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let end =
+            (self.data_offset + self.data.len()).min(self.diff_idx + BYTE_WINDOW_HALF_SIZE + 1);
+        let window = &self.data[self.start - self.data_offset..end - self.data_offset];
+
+        // same as `self.diff_idx.min(BYTE_WINDOW_HALF_SIZE)` for whole files
+        let translated_diff_idx = self.diff_idx - self.start;
+
+        for (i, byte) in window.iter().enumerate() {
+            if i != 0 {
+                write!(f, " ").unwrap();
+            }
+            if i == translated_diff_idx {
+                write!(f, "{}", style::highlight(self.is_expected)).unwrap();
+            }
+
+            write!(f, "{byte:02x}").unwrap();
+
+            if i == translated_diff_idx {
+                write!(f, "{:#}", style::highlight(self.is_expected)).unwrap();
+            }
+        }
+
+        write!(f, " {}", CharacterPanel(window))?;
+        Ok(())
+    }
+}
+
+/// Writes the diff section for the first differing byte at `diff_idx`.
+///
+/// `expected` and `actual` may be regions of the whole data starting at `expected_offset` and
+/// `actual_offset`, which must be at most `diff_idx`.
+pub(crate) fn write_byte_diff<W: io::Write>(
+    writer: &mut W,
+    (expected, expected_offset): (&[u8], usize),
+    (actual, actual_offset): (&[u8], usize),
+    diff_idx: usize,
+) {
+    let start = diff_idx
+        .saturating_sub(BYTE_WINDOW_HALF_SIZE)
+        .max(expected_offset)
+        .max(actual_offset);
+    writeln!(
+        writer,
+        "{BOLD}Diff{BOLD:#}:
+Binary files differ at byte {diff_idx:#x}
+
+Expect: {expect}
+Actual: {actual}
+        {offset}{BOLD}^^{BOLD:#}",
+        expect = ByteWindowDisplay {
+            data: expected,
+            data_offset: expected_offset,
+            start,
+            diff_idx,
+            is_expected: true
+        },
+        actual = ByteWindowDisplay {
+            data: actual,
+            data_offset: actual_offset,
+            start,
+            diff_idx,
+            is_expected: false
+        },
+        offset = "   ".repeat(diff_idx - start),
+    )
+    .unwrap();
+}
+
+/// <https://github.com/sharkdp/hexyl/blob/9ef7c34/src/lib.rs#L30-L32>
+struct CharacterPanel<'a>(&'a [u8]);
+impl fmt::Display for CharacterPanel<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for byte in self.0 {
+            let ch = match *byte {
+                0 => '⋄',
+                _ if byte.is_ascii_graphic() => *byte as char,
+                b' ' => ' ',
+                _ if byte.is_ascii_whitespace() => '_',
+                _ if byte.is_ascii() => '•',
+                _ => '×',
+            };
+            f.write_char(ch)?;
+        }
+        Ok(())
+    }
+}
+
+/// How the differing bytes are shown in failure reports.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum DiffStyle {
+    /// A single row of bytes around the first difference for each side.
+    Window,
+    /// Expected and actual hex dumps in parallel columns, showing the rows that differ.
+    SideBySide,
+    /// A unified diff of `xxd` dumps of both sides, without ANSI escape codes.
+    Unified,
+    /// Hex dumps of the rows around the first difference on each side, rendered by hexyl with
+    /// its borders and colors.
+    #[cfg(feature = "hexyl")]
+    Hexyl,
+}
+
+impl Default for DiffStyle {
+    fn default() -> Self {
+        Self::Window
+    }
+}
+
+impl DiffStyle {
+    /// Parses the value of the `EXPECT_BYTES_DIFF_STYLE` environment variable.
+    pub(crate) fn from_env_value(value: &str) -> Option<Self> {
+        match value {
+            "window" => Some(Self::Window),
+            "side-by-side" => Some(Self::SideBySide),
+            "unified" => Some(Self::Unified),
+            #[cfg(feature = "hexyl")]
+            "hexyl" => Some(Self::Hexyl),
+            _ => None,
+        }
+    }
+}
+
+const SIDE_BY_SIDE_ROW_LEN: usize = 8;
+/// Maximum number of rows shown in the side-by-side view.
+const SIDE_BY_SIDE_MAX_ROWS: usize = 32;
+
+/// One column of a side-by-side row.
+struct SideBySideColumn<'a> {
+    row: &'a [u8],
+    other: &'a [u8],
+    is_expected: bool,
+}
+impl fmt::Display for SideBySideColumn<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let highlight = style::highlight(self.is_expected);
+        for i in 0..SIDE_BY_SIDE_ROW_LEN {
+            if i != 0 {
+                write!(f, " ")?;
+            }
+            match self.row.get(i) {
+                Some(byte) if self.other.get(i) != Some(byte) => {
+                    write!(f, "{highlight}{byte:02x}{highlight:#}")?;
+                }
+                Some(byte) => write!(f, "{byte:02x}")?,
+                None => write!(f, "  ")?,
+            }
+        }
+        write!(f, "  {}", CharacterPanel(self.row))?;
+        if self.is_expected {
+            // Align the separator after short rows
+            for _ in self.row.len()..SIDE_BY_SIDE_ROW_LEN {
+                f.write_char(' ')?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Writes the diff section as expected and actual hex dumps in parallel columns.
+///
+/// Only rows that differ and the rows next to them are shown. `expected` and `actual` both start
+/// at `offset` in the whole data.
+pub(crate) fn write_side_by_side<W: io::Write>(
+    writer: &mut W,
+    expected: &[u8],
+    actual: &[u8],
+    offset: usize,
+    diff_idx: usize,
+) {
+    let row = |data: &[u8], i: usize| -> Vec<u8> {
+        data.iter()
+            .skip(i * SIDE_BY_SIDE_ROW_LEN)
+            .take(SIDE_BY_SIDE_ROW_LEN)
+            .copied()
+            .collect()
+    };
+    let row_count =
+        (expected.len().max(actual.len()) + SIDE_BY_SIDE_ROW_LEN - 1) / SIDE_BY_SIDE_ROW_LEN;
+    let differs = |i: usize| row(expected, i) != row(actual, i);
+    let shown: Vec<usize> = (0..row_count)
+        .filter(|&i| {
+            differs(i) || (i > 0 && differs(i - 1)) || (i + 1 < row_count && differs(i + 1))
+        })
+        .collect();
+
+    writeln!(
+        writer,
+        "{BOLD}Diff{BOLD:#}:
+Binary files differ at byte {diff_idx:#x}
+
+{BOLD}  Offset  {:column_width$}  │ Actual{BOLD:#}",
+        "Expect",
+        // Hex bytes with separators, two spaces and the character panel
+        column_width = SIDE_BY_SIDE_ROW_LEN * 4 + 1,
+    )
+    .unwrap();
+
+    let mut previous = None;
+    for &i in shown.iter().take(SIDE_BY_SIDE_MAX_ROWS) {
+        if previous.map_or(i != 0, |previous| previous + 1 != i) {
+            writeln!(writer, "       ⋮").unwrap();
+        }
+        previous = Some(i);
+
+        let expected_row = row(expected, i);
+        let actual_row = row(actual, i);
+        writeln!(
+            writer,
+            "{:08x}  {}  │ {}",
+            offset + i * SIDE_BY_SIDE_ROW_LEN,
+            SideBySideColumn {
+                row: &expected_row,
+                other: &actual_row,
+                is_expected: true,
+            },
+            SideBySideColumn {
+                row: &actual_row,
+                other: &expected_row,
+                is_expected: false,
+            },
+        )
+        .unwrap();
+    }
+    if shown.len() > SIDE_BY_SIDE_MAX_ROWS {
+        writeln!(
+            writer,
+            "… {} more rows not shown",
+            shown.len() - SIDE_BY_SIDE_MAX_ROWS
+        )
+        .unwrap();
+    }
+}
+
+/// Writes the diff section as a unified diff of `xxd` dumps of both sides.
+///
+/// `expected` and `actual` both start at `offset` in the whole data.
+pub(crate) fn write_unified<W: io::Write>(
+    writer: &mut W,
+    expected: &[u8],
+    actual: &[u8],
+    offset: usize,
+    diff_idx: usize,
+) {
+    let diff = unified::unified_diff(
+        &xxd::dump_lines(expected, offset),
+        &xxd::dump_lines(actual, offset),
+    );
+    write!(
+        writer,
+        "Diff:
+Binary files differ at byte {diff_idx:#x}
+
+--- expected
++++ actual
+{diff}"
+    )
+    .unwrap();
+}
@@ -0,0 +1,125 @@
+//! Machine-readable report of the assertions of the run, appended to a file shared by all test
+//! processes for aggregating results across partitions and shards.
+
+use std::cell::Cell;
+use std::fs::OpenOptions;
+use std::io::{self, Write};
+
+use crate::failure::Details;
+
+/// Path of the file that a line of JSON is appended to for each assertion.
+pub(crate) const REPORT_VAR_NAME: &str = "EXPECT_BYTES_REPORT";
+
+/// Outcome of an assertion.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Outcome {
+    /// The data matched the file.
+    Passed,
+    /// The file was updated or created.
+    Updated,
+    /// The assertion failed.
+    Failed,
+    /// The assertion failed but passed since `EXPECT_BYTES_QUARANTINE` is set.
+    Quarantined,
+}
+
+impl Outcome {
+    /// Returns the status of the current assertion with `result`.
+    pub(crate) fn of(result: Result<(), ()>) -> Self {
+        // Always taken so that it doesn't leak into the next assertion
+        let updated = take_updated();
+        if result.is_err() {
+            Outcome::Failed
+        } else if updated {
+            Outcome::Updated
+        } else {
+            Outcome::Passed
+        }
+    }
+
+    fn as_str(self) -> &'static str {
+        match self {
+            Outcome::Passed => "passed",
+            Outcome::Updated => "updated",
+            Outcome::Failed => "failed",
+            Outcome::Quarantined => "quarantined",
+        }
+    }
+}
+
+thread_local! {
+    /// Whether the current assertion updated its file.
+    static UPDATED: Cell<bool> = const { Cell::new(false) };
+}
+
+/// Records that the current assertion updated its file.
+pub(crate) fn record_update() {
+    UPDATED.with(|updated| updated.set(true));
+}
+
+/// Takes whether the current assertion updated its file.
+fn take_updated() -> bool {
+    UPDATED.with(Cell::take)
+}
+
+/// Appends the outcome of the assertion at `location` to the report, if it's enabled.
+pub(crate) fn record(location: &str, status: Outcome, details: Details) {
+    let path = match std::env::var_os(REPORT_VAR_NAME) {
+        Some(path) => path,
+        None => return,
+    };
+    let thread = std::thread::current();
+    let mut line = Vec::new();
+    write_entry(&mut line, thread.name(), location, status, details).unwrap();
+    // Lines are appended with a single write so that processes writing at once don't interleave,
+    // and a report that can't be written must not fail the test
+    if let Ok(mut file) = OpenOptions::new().create(true).append(true).open(path) {
+        let _ = file.write_all(&line);
+    }
+}
+
+/// Writes a report entry as a line of JSON, e.g.
+/// `{"test":"tests::frame","path":"test_data/frame.bin","status":"failed","first_diff":28}`.
+///
+/// Unknown details are left out.
+pub(crate) fn write_entry<W: io::Write>(
+    writer: &mut W,
+    test: Option<&str>,
+    location: &str,
+    status: Outcome,
+    details: Details,
+) -> io::Result<()> {
+    write!(writer, "{{")?;
+    if let Some(test) = test {
+        write!(writer, "\"test\":")?;
+        write_json_string(writer, test)?;
+        write!(writer, ",")?;
+    }
+    write!(writer, "\"path\":")?;
+    write_json_string(writer, location)?;
+    write!(writer, ",\"status\":\"{}\"", status.as_str())?;
+    if let Some(first_diff) = details.first_diff {
+        write!(writer, ",\"first_diff\":{first_diff}")?;
+    }
+    if let Some(expected_len) = details.expected_len {
+        write!(writer, ",\"expected_len\":{expected_len}")?;
+    }
+    if let Some(actual_len) = details.actual_len {
+        write!(writer, ",\"actual_len\":{actual_len}")?;
+    }
+    writeln!(writer, "}}")
+}
+
+fn write_json_string<W: io::Write>(writer: &mut W, s: &str) -> io::Result<()> {
+    write!(writer, "\"")?;
+    for c in s.chars() {
+        match c {
+            '"' => write!(writer, "\\\"")?,
+            '\\' => write!(writer, "\\\\")?,
+            '\n' => write!(writer, "\\n")?,
+            c if u32::from(c) < 0x20 => write!(writer, "\\u{:04x}", u32::from(c))?,
+            c => write!(writer, "{c}")?,
+        }
+    }
+    write!(writer, "\"")
+}
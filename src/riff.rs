@@ -0,0 +1,154 @@
+//! Chunk-by-chunk comparison of RIFF and IFF files, such as WAV, AVI, WebP and AIFF.
+
+use crate::structure::Difference;
+
+/// A chunk and, for container chunks such as `RIFF` and `LIST`, its subchunks.
+struct Chunk<'a> {
+    id: [u8; 4],
+    /// Form or list type of a container chunk.
+    form_type: Option<[u8; 4]>,
+    /// The data after the form type.
+    data: &'a [u8],
+    children: Vec<Chunk<'a>>,
+}
+
+fn is_container(id: [u8; 4]) -> bool {
+    matches!(&id, b"RIFF" | b"RIFX" | b"LIST" | b"FORM" | b"CAT ")
+}
+
+/// Parses the chunks in `data`, or returns `None` if it isn't a sequence of chunks.
+fn parse_chunks(mut data: &[u8], little_endian: Option<bool>) -> Option<Vec<Chunk<'_>>> {
+    let mut chunks = Vec::new();
+    while !data.is_empty() {
+        let id = [*data.first()?, *data.get(1)?, *data.get(2)?, *data.get(3)?];
+        // Top-level chunks determine the byte order
+        let little_endian = match little_endian {
+            Some(little_endian) => little_endian,
+            None if &id == b"RIFF" => true,
+            None if matches!(&id, b"RIFX" | b"FORM" | b"CAT " | b"LIST") => false,
+            None => return None,
+        };
+        let size = data.get(4..8)?;
+        let size = [size[0], size[1], size[2], size[3]];
+        let size = usize::try_from(if little_endian {
+            u32::from_le_bytes(size)
+        } else {
+            u32::from_be_bytes(size)
+        })
+        .ok()?;
+        let body = data.get(8..8usize.checked_add(size)?)?;
+        let (form_type, body_data, children) = if is_container(id) {
+            let form_type = body.get(..4)?;
+            let form_type = [form_type[0], form_type[1], form_type[2], form_type[3]];
+            (
+                Some(form_type),
+                &body[4..],
+                parse_chunks(&body[4..], Some(little_endian))?,
+            )
+        } else {
+            (None, body, Vec::new())
+        };
+        chunks.push(Chunk {
+            id,
+            form_type,
+            data: body_data,
+            children,
+        });
+        // Chunks are padded to an even size
+        let padded = 8 + size + size % 2;
+        data = data.get(padded..).unwrap_or(&[]);
+    }
+    Some(chunks)
+}
+
+fn fourcc(id: [u8; 4]) -> String {
+    String::from_utf8_lossy(&id).into_owned()
+}
+
+/// Names the chunks of a sequence, numbering chunks whose ID occurs multiple times.
+fn names(chunks: &[Chunk<'_>]) -> Vec<String> {
+    chunks
+        .iter()
+        .enumerate()
+        .map(|(i, chunk)| {
+            let name = fourcc(chunk.id);
+            if chunks.iter().filter(|other| other.id == chunk.id).count() > 1 {
+                let n = chunks[..i]
+                    .iter()
+                    .filter(|other| other.id == chunk.id)
+                    .count();
+                format!("{name}[{n}]")
+            } else {
+                name
+            }
+        })
+        .collect()
+}
+
+fn first_difference_in(
+    parent: &str,
+    expected: &[Chunk<'_>],
+    actual: &[Chunk<'_>],
+) -> Option<Difference> {
+    let (expected_names, actual_names) = (names(expected), names(actual));
+    if expected_names != actual_names {
+        let list = |names: &[String]| {
+            names
+                .iter()
+                .map(|name| format!("`{name}`"))
+                .collect::<Vec<_>>()
+                .join(", ")
+        };
+        let chunks = if parent.is_empty() {
+            "the top-level chunks".to_owned()
+        } else {
+            format!("the subchunks of `{parent}`")
+        };
+        return Some(Difference {
+            description: format!(
+                "{chunks} differ: expected {}, found {}",
+                list(&expected_names),
+                list(&actual_names)
+            ),
+            regions: None,
+        });
+    }
+    for ((e, a), name) in expected.iter().zip(actual).zip(expected_names) {
+        let path = if parent.is_empty() {
+            name
+        } else {
+            format!("{parent}/{name}")
+        };
+        if e.form_type != a.form_type {
+            return Some(Difference {
+                description: format!(
+                    "chunk `{path}` has a different form type: expected `{}`, found `{}`",
+                    e.form_type.map_or_else(String::new, fourcc),
+                    a.form_type.map_or_else(String::new, fourcc)
+                ),
+                regions: None,
+            });
+        }
+        if e.data == a.data {
+            continue;
+        }
+        if e.form_type.is_some() {
+            if let Some(difference) = first_difference_in(&path, &e.children, &a.children) {
+                return Some(difference);
+            }
+        }
+        let diff_idx = crate::first_diff_index(e.data, a.data).unwrap_or(0);
+        return Some(Difference {
+            description: format!("chunk `{path}` differs at byte {diff_idx:#x} of its data"),
+            regions: Some((e.data.to_vec(), a.data.to_vec())),
+        });
+    }
+    None
+}
+
+/// Finds the first differing chunk of two RIFF or IFF files, or returns `None` if either can't
+/// be parsed.
+pub(crate) fn first_difference(expected: &[u8], actual: &[u8]) -> Option<Difference> {
+    let (expected, actual) = (parse_chunks(expected, None)?, parse_chunks(actual, None)?);
+    first_difference_in("", &expected, &actual)
+}
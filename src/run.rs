@@ -0,0 +1,220 @@
+//! Coordination between the processes of one test run, for runners such as cargo-nextest that
+//! run each test in its own process.
+//!
+//! State shared by the processes is kept in a directory named after the run ID that the runner
+//! passes to every process. The directories of runs that ended more than a day ago are removed
+//! when a new run starts.
+
+use std::fs::{self, OpenOptions};
+use std::io;
+#[cfg(unix)]
+use std::os::raw::c_int;
+use std::path::{Path, PathBuf};
+#[cfg(windows)]
+use std::thread;
+use std::time::Duration;
+
+use crate::sha256;
+
+/// Set by cargo-nextest to the same ID in every process of a run.
+pub(crate) const RUN_ID_VAR_NAME: &str = "NEXTEST_RUN_ID";
+
+/// Age of the directory of an earlier run after which it's removed.
+const OLD_RUN_AGE: Duration = Duration::from_secs(24 * 60 * 60);
+/// Prefix of the names of the directories of runs.
+const RUN_DIR_PREFIX: &str = "expect-test-bytes-run-";
+/// Time between attempts to take a held lock, where waiting for it isn't supported.
+#[cfg(windows)]
+const LOCK_POLL_INTERVAL: Duration = Duration::from_millis(1);
+
+/// Returns an ID of the current test run, shared by all of its processes.
+///
+/// That's the run ID passed by a process-per-test runner, or else the ID of the process group,
+/// which shells start for every command, so that it covers all test binaries of a `cargo test`.
+/// Group IDs are reused, so the start time of the group's leader is included where it's known.
+pub(crate) fn id() -> String {
+    if let Ok(id) = std::env::var(RUN_ID_VAR_NAME) {
+        return id;
+    }
+    #[cfg(unix)]
+    {
+        // SAFETY: `getpgrp` has no preconditions and always succeeds.
+        let group = unsafe { getpgrp() };
+        match fs::read_to_string(format!("/proc/{group}/stat"))
+            .ok()
+            .and_then(|stat| start_time(&stat))
+        {
+            Some(started) => format!("group-{group}-{started}"),
+            None => format!("group-{group}"),
+        }
+    }
+    #[cfg(not(unix))]
+    format!("process-{}", std::process::id())
+}
+
+#[cfg(unix)]
+extern "C" {
+    fn getpgrp() -> c_int;
+}
+
+#[cfg(unix)]
+/// Parses the start time of a process, in clock ticks since boot, from its `/proc/<pid>/stat`.
+pub(crate) fn start_time(stat: &str) -> Option<u64> {
+    // The command name before this may contain spaces and parentheses
+    let (_, fields) = stat.rsplit_once(')')?;
+    // The start time is the 22nd field, and the fields after the name start at the 3rd
+    fields.split_whitespace().nth(19)?.parse().ok()
+}
+
+/// Returns the directory shared by the processes of the current run, or `None` if the tests
+/// aren't run by a process-per-test runner.
+fn run_dir() -> Option<PathBuf> {
+    let id = std::env::var(RUN_ID_VAR_NAME).ok()?;
+    let id: String = id
+        .chars()
+        .filter(|c| c.is_ascii_alphanumeric() || *c == '-')
+        .collect();
+    let dir = std::env::temp_dir().join(format!("{RUN_DIR_PREFIX}{id}"));
+    if !dir.exists() {
+        fs::create_dir_all(&dir).ok()?;
+        remove_old_runs(&dir);
+    }
+    Some(dir)
+}
+
+/// Removes the directories of runs other than the one in `current` that haven't changed for
+/// `OLD_RUN_AGE`.
+fn remove_old_runs(current: &Path) {
+    let entries = match current.parent().map(fs::read_dir) {
+        Some(Ok(entries)) => entries,
+        _ => return,
+    };
+    for entry in entries.flatten() {
+        let path = entry.path();
+        let is_old = entry
+            .metadata()
+            .and_then(|metadata| metadata.modified())
+            .ok()
+            .and_then(|modified| modified.elapsed().ok())
+            .map_or(false, |age| age >= OLD_RUN_AGE);
+        if is_old
+            && path != current
+            && entry
+                .file_name()
+                .to_string_lossy()
+                .starts_with(RUN_DIR_PREFIX)
+        {
+            let _ = fs::remove_dir_all(path);
+        }
+    }
+}
+
+/// Returns whether this is the first process of the run to ask for `name`, or `None` if the
+/// tests aren't run by a process-per-test runner.
+pub(crate) fn first_in_run(name: &str) -> Option<bool> {
+    let flag = run_dir()?.join(format!("{name}.once"));
+    // Count as the first if the flag can't be written
+    Some(!matches!(
+        OpenOptions::new().write(true).create_new(true).open(flag),
+        Err(e) if e.kind() == io::ErrorKind::AlreadyExists
+    ))
+}
+
+/// Records that a file, identified by `key`, is updated to contents with `digest` during the run.
+///
+/// Returns `false` if another process of the run already updated it to different contents.
+pub(crate) fn record_update(key: &str, digest: &[u8; 32]) -> bool {
+    let dir = match run_dir() {
+        Some(dir) => dir,
+        None => return true,
+    };
+    let path = dir.join(format!(
+        "{}.update",
+        sha256::to_hex(&sha256::sha256(key.as_bytes()))
+    ));
+    if let Ok(recorded) = fs::read(&path) {
+        return recorded == digest;
+    }
+    let _ = fs::write(&path, digest);
+    true
+}
+
+/// A lock held by one process of the run at a time, released when dropped.
+///
+/// This is an advisory lock of the operating system on an open lock file, so it's also released
+/// when the process that holds it exits or crashes, however long it held it. The lock file itself
+/// is never removed.
+pub(crate) struct RunLock {
+    _file: fs::File,
+}
+
+/// Takes the lock of the run for updating files, waiting while another process holds it, or
+/// returns `None` if the tests aren't run by a process-per-test runner.
+pub(crate) fn lock() -> Option<RunLock> {
+    lock_at(&run_dir()?.join("update.lock"))
+}
+
+/// Takes the lock on the file at `path`, waiting while another process holds it.
+#[cfg(unix)]
+pub(crate) fn lock_at(path: &Path) -> Option<RunLock> {
+    use std::os::unix::io::AsRawFd as _;
+
+    // Updating without the lock is better than not updating
+    let file = OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(false)
+        .open(path)
+        .ok()?;
+    loop {
+        // SAFETY: the descriptor is open for as long as `file` lives.
+        if unsafe { flock(file.as_raw_fd(), LOCK_EX) } == 0 {
+            return Some(RunLock { _file: file });
+        }
+        if io::Error::last_os_error().kind() != io::ErrorKind::Interrupted {
+            return None;
+        }
+    }
+}
+
+/// Takes the lock on the file at `path`, waiting while another process holds it.
+#[cfg(windows)]
+pub(crate) fn lock_at(path: &Path) -> Option<RunLock> {
+    use std::os::windows::fs::OpenOptionsExt as _;
+
+    /// `ERROR_SHARING_VIOLATION`, returned while another handle has the file open.
+    const SHARING_VIOLATION: i32 = 32;
+
+    loop {
+        // Without sharing, the file can be open in one place at a time
+        match OpenOptions::new()
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .share_mode(0)
+            .open(path)
+        {
+            Ok(file) => return Some(RunLock { _file: file }),
+            Err(e) if e.raw_os_error() == Some(SHARING_VIOLATION) => {
+                thread::sleep(LOCK_POLL_INTERVAL);
+            }
+            // Updating without the lock is better than not updating
+            Err(_) => return None,
+        }
+    }
+}
+
+/// Takes the lock on the file at `path`, which isn't supported on this platform.
+#[cfg(not(any(unix, windows)))]
+pub(crate) fn lock_at(_path: &Path) -> Option<RunLock> {
+    None
+}
+
+/// Operation of `flock` that takes an exclusive lock.
+#[cfg(unix)]
+const LOCK_EX: c_int = 2;
+
+#[cfg(unix)]
+extern "C" {
+    fn flock(fd: c_int, operation: c_int) -> c_int;
+}
@@ -0,0 +1,22 @@
+//! Seeds of randomized but seeded output, stored next to snapshots so that reviewers can
+//! regenerate the data.
+
+use std::ffi::OsString;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Returns the path of the seed sidecar for the snapshot at `path`.
+pub(crate) fn seed_path(path: &Path) -> PathBuf {
+    let mut os: OsString = path.as_os_str().to_owned();
+    os.push(".seed");
+    os.into()
+}
+
+/// Reads the seed recorded for the snapshot at `path`, if there is a valid one.
+pub(crate) fn read(path: &Path) -> Option<u64> {
+    fs::read_to_string(seed_path(path))
+        .ok()?
+        .trim()
+        .parse()
+        .ok()
+}
@@ -0,0 +1,93 @@
+//! Actual data split into segments, such as the buffers of a vectored write, that is compared
+//! without concatenating it.
+
+use std::io::{self, Read};
+
+use crate::style::NOTE;
+
+/// Reads the segments yielded by an iterator one after the other, keeping their lengths.
+pub(crate) struct SegmentReader<I: Iterator> {
+    segments: I,
+    current: Option<(I::Item, usize)>,
+    lens: Vec<usize>,
+}
+
+impl<I: Iterator> SegmentReader<I>
+where
+    I::Item: AsRef<[u8]>,
+{
+    pub(crate) fn new(segments: I) -> Self {
+        Self {
+            segments,
+            current: None,
+            lens: Vec::new(),
+        }
+    }
+
+    /// Returns the lengths of the segments that were started.
+    pub(crate) fn lens(&self) -> &[usize] {
+        &self.lens
+    }
+}
+
+impl<I: Iterator> Read for SegmentReader<I>
+where
+    I::Item: AsRef<[u8]>,
+{
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        loop {
+            if let Some((segment, pos)) = &mut self.current {
+                let rest = &segment.as_ref()[*pos..];
+                if !rest.is_empty() || buf.is_empty() {
+                    let n = rest.len().min(buf.len());
+                    buf[..n].copy_from_slice(&rest[..n]);
+                    *pos += n;
+                    return Ok(n);
+                }
+            }
+            match self.segments.next() {
+                Some(segment) => {
+                    self.lens.push(segment.as_ref().len());
+                    self.current = Some((segment, 0));
+                }
+                None => return Ok(0),
+            }
+        }
+    }
+}
+
+/// Returns the index of the segment containing `offset` and the offset in it, given the lengths
+/// of the segments.
+pub(crate) fn locate(lens: &[usize], offset: usize) -> Option<(usize, usize)> {
+    let mut start = 0;
+    for (index, &len) in lens.iter().enumerate() {
+        if offset < start + len {
+            return Some((index, offset - start));
+        }
+        start += len;
+    }
+    None
+}
+
+/// Writes a note locating the first difference at `diff_idx` in the segments with `lens`, which
+/// are called `kind`s, e.g. "segment".
+pub(crate) fn write_note<W: io::Write>(
+    writer: &mut W,
+    lens: &[usize],
+    diff_idx: usize,
+    kind: &str,
+) -> io::Result<()> {
+    match locate(lens, diff_idx) {
+        Some((index, offset)) => writeln!(
+            writer,
+            "{NOTE}note{NOTE:#}: the first difference is at offset {offset:#x} of {kind} {index}"
+        ),
+        // The actual data is a prefix of the file
+        None if !lens.is_empty() => writeln!(
+            writer,
+            "{NOTE}note{NOTE:#}: the actual data ends with {kind} {}",
+            lens.len() - 1
+        ),
+        None => Ok(()),
+    }
+}
@@ -0,0 +1,72 @@
+//! Reading raw Snappy data, which compresses Parquet pages by default.
+
+/// Decompresses a raw Snappy block.
+pub(crate) fn decompress(data: &[u8]) -> Option<Vec<u8>> {
+    let mut pos = 0;
+    let len = read_varint(data, &mut pos)?;
+    // The length comes from the data, so it's only trusted for what the data could expand to
+    let mut out = Vec::with_capacity(len.min(data.len().saturating_mul(32)));
+    while pos < data.len() {
+        let tag = data[pos];
+        pos += 1;
+        let (len, offset) = match tag & 3 {
+            0 => {
+                let len = match usize::from(tag >> 2) {
+                    len @ 0..=59 => len + 1,
+                    extra => {
+                        let count = extra - 59;
+                        let bytes = data.get(pos..pos + count)?;
+                        pos += count;
+                        bytes
+                            .iter()
+                            .rev()
+                            .fold(0, |len, &byte| len << 8 | usize::from(byte))
+                            + 1
+                    }
+                };
+                out.extend_from_slice(data.get(pos..pos.checked_add(len)?)?);
+                pos += len;
+                continue;
+            }
+            1 => {
+                let offset = usize::from(tag >> 5) << 8 | usize::from(*data.get(pos)?);
+                pos += 1;
+                (usize::from((tag >> 2) & 7) + 4, offset)
+            }
+            2 => {
+                let bytes = data.get(pos..pos + 2)?;
+                pos += 2;
+                let offset = usize::from(u16::from_le_bytes([bytes[0], bytes[1]]));
+                (usize::from(tag >> 2) + 1, offset)
+            }
+            _ => {
+                let bytes = data.get(pos..pos + 4)?;
+                pos += 4;
+                let offset = u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
+                (usize::from(tag >> 2) + 1, usize::try_from(offset).ok()?)
+            }
+        };
+        if offset == 0 || offset > out.len() {
+            return None;
+        }
+        // The copy may overlap the bytes it produces
+        let start = out.len() - offset;
+        for i in 0..len {
+            out.push(out[start + i]);
+        }
+    }
+    (out.len() == len).then(|| out)
+}
+
+fn read_varint(data: &[u8], pos: &mut usize) -> Option<usize> {
+    let mut value = 0;
+    for shift in (0..35).step_by(7) {
+        let byte = *data.get(*pos)?;
+        *pos += 1;
+        value |= usize::from(byte & 0x7f) << shift;
+        if byte & 0x80 == 0 {
+            return Some(value);
+        }
+    }
+    None
+}
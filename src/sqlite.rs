@@ -0,0 +1,390 @@
+//! Logical comparison of SQLite databases by their schema and rows.
+
+use std::fmt;
+
+use crate::structure::Difference;
+
+const MAGIC: &[u8] = b"SQLite format 3\0";
+const HEADER_LEN: usize = 100;
+/// SQLite's own limit is around 20 levels, so deeper trees are cyclic.
+const MAX_DEPTH: usize = 64;
+
+const INDEX_INTERIOR: u8 = 0x02;
+const TABLE_INTERIOR: u8 = 0x05;
+const INDEX_LEAF: u8 = 0x0a;
+const TABLE_LEAF: u8 = 0x0d;
+
+#[derive(Debug, PartialEq)]
+enum Value {
+    Null,
+    Integer(i64),
+    Real(f64),
+    Text(String),
+    Blob(Vec<u8>),
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Null => f.write_str("NULL"),
+            Self::Integer(i) => write!(f, "{i}"),
+            Self::Real(r) => write!(f, "{r:?}"),
+            Self::Text(s) => write!(f, "'{}'", s.replace('\'', "''")),
+            Self::Blob(b) => {
+                f.write_str("x'")?;
+                for byte in b {
+                    write!(f, "{byte:02x}")?;
+                }
+                f.write_str("'")
+            }
+        }
+    }
+}
+
+/// A row of a table, with its rowid unless the table is `WITHOUT ROWID`.
+#[derive(Debug, PartialEq)]
+struct Row {
+    rowid: Option<i64>,
+    values: Vec<Value>,
+}
+
+impl fmt::Display for Row {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(rowid) = self.rowid {
+            write!(f, "rowid {rowid}: ")?;
+        }
+        f.write_str("(")?;
+        for (i, value) in self.values.iter().enumerate() {
+            if i != 0 {
+                f.write_str(", ")?;
+            }
+            write!(f, "{value}")?;
+        }
+        f.write_str(")")
+    }
+}
+
+/// An entry of the `sqlite_schema` table, without the root page which depends on the layout.
+#[derive(Debug, PartialEq)]
+struct SchemaEntry {
+    kind: String,
+    name: String,
+    sql: Option<String>,
+}
+
+#[derive(Debug, PartialEq)]
+struct Database {
+    /// Sorted by kind and name.
+    schema: Vec<SchemaEntry>,
+    /// The rows of each table in key order, sorted by table name.
+    tables: Vec<(String, Vec<Row>)>,
+}
+
+/// A database file being read.
+struct Reader<'a> {
+    data: &'a [u8],
+    page_size: usize,
+    /// Page size without the reserved bytes at the end of each page.
+    usable_size: usize,
+    utf16: Option<bool>,
+}
+
+fn read_varint(data: &[u8], pos: &mut usize) -> Option<u64> {
+    let mut value = 0_u64;
+    for i in 0..9 {
+        let b = *data.get(*pos)?;
+        *pos += 1;
+        if i == 8 {
+            return Some(value << 8 | u64::from(b));
+        }
+        value = value << 7 | u64::from(b & 0x7f);
+        if b & 0x80 == 0 {
+            return Some(value);
+        }
+    }
+    None
+}
+
+fn read_u16(data: &[u8], pos: usize) -> Option<usize> {
+    let bytes = data.get(pos..pos + 2)?;
+    Some(usize::from(u16::from_be_bytes([bytes[0], bytes[1]])))
+}
+
+fn read_u32(data: &[u8], pos: usize) -> Option<u32> {
+    let bytes = data.get(pos..pos + 4)?;
+    Some(u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+}
+
+fn read_int(bytes: &[u8]) -> i64 {
+    // Sign-extend from the first byte
+    let initial = if bytes.first().map_or(false, |&b| b & 0x80 != 0) {
+        -1
+    } else {
+        0
+    };
+    bytes
+        .iter()
+        .fold(initial, |value, &b| value << 8 | i64::from(b))
+}
+
+impl<'a> Reader<'a> {
+    fn new(data: &'a [u8]) -> Option<Self> {
+        if !data.starts_with(MAGIC) || data.len() < HEADER_LEN {
+            return None;
+        }
+        let page_size = match read_u16(data, 16)? {
+            1 => 65536,
+            size if size >= 512 && size.is_power_of_two() => size,
+            _ => return None,
+        };
+        let utf16 = match read_u32(data, 56)? {
+            0 | 1 => None,
+            2 => Some(false),
+            3 => Some(true),
+            _ => return None,
+        };
+        Some(Self {
+            data,
+            page_size,
+            usable_size: page_size.checked_sub(usize::from(data[20]))?,
+            utf16,
+        })
+    }
+
+    fn page(&self, number: u32) -> Option<&'a [u8]> {
+        let start = usize::try_from(number.checked_sub(1)?)
+            .ok()?
+            .checked_mul(self.page_size)?;
+        self.data.get(start..start.checked_add(self.page_size)?)
+    }
+
+    /// Reads the payload of the cell at `pos`, following overflow pages.
+    fn payload(&self, page: &[u8], pos: usize, len: usize, table: bool) -> Option<Vec<u8>> {
+        let usable = self.usable_size;
+        let max_local = if table {
+            usable - 35
+        } else {
+            (usable - 12) * 64 / 255 - 23
+        };
+        if len <= max_local {
+            return Some(page.get(pos..pos.checked_add(len)?)?.to_vec());
+        }
+        let min_local = (usable - 12) * 32 / 255 - 23;
+        let k = min_local + (len - min_local) % (usable - 4);
+        let local = if k <= max_local { k } else { min_local };
+        let mut payload = page.get(pos..pos + local)?.to_vec();
+        let mut next = read_u32(page, pos + local)?;
+        let mut pages = 0;
+        while payload.len() < len {
+            pages += 1;
+            if next == 0 || pages > self.data.len() / self.page_size {
+                return None;
+            }
+            let overflow = self.page(next)?;
+            next = read_u32(overflow, 0)?;
+            let chunk = (len - payload.len()).min(usable - 4);
+            payload.extend_from_slice(overflow.get(4..4 + chunk)?);
+        }
+        Some(payload)
+    }
+
+    fn text(&self, bytes: &[u8]) -> Option<String> {
+        match self.utf16 {
+            None => String::from_utf8(bytes.to_vec()).ok(),
+            Some(big_endian) => {
+                let units = bytes.chunks_exact(2).map(|unit| {
+                    let unit = [unit[0], unit[1]];
+                    if big_endian {
+                        u16::from_be_bytes(unit)
+                    } else {
+                        u16::from_le_bytes(unit)
+                    }
+                });
+                char::decode_utf16(units).collect::<Result<_, _>>().ok()
+            }
+        }
+    }
+
+    fn record(&self, payload: &[u8]) -> Option<Vec<Value>> {
+        let mut pos = 0;
+        let header_len = usize::try_from(read_varint(payload, &mut pos)?).ok()?;
+        let mut body = header_len;
+        let mut values = Vec::new();
+        while pos < header_len {
+            let serial_type = read_varint(payload, &mut pos)?;
+            let len = match serial_type {
+                0 | 8 | 9 => 0,
+                1..=4 => usize::try_from(serial_type).ok()?,
+                5 => 6,
+                6 | 7 => 8,
+                10 | 11 => return None,
+                _ => usize::try_from((serial_type - 12) / 2).ok()?,
+            };
+            let bytes = payload.get(body..body.checked_add(len)?)?;
+            body += len;
+            values.push(match serial_type {
+                0 => Value::Null,
+                8 => Value::Integer(0),
+                9 => Value::Integer(1),
+                7 => Value::Real(f64::from_bits(
+                    bytes.iter().fold(0, |bits, &b| bits << 8 | u64::from(b)),
+                )),
+                1..=6 => Value::Integer(read_int(bytes)),
+                _ if serial_type % 2 == 0 => Value::Blob(bytes.to_vec()),
+                _ => Value::Text(self.text(bytes)?),
+            });
+        }
+        Some(values)
+    }
+
+    /// Appends the rows of the b-tree rooted at `page_number` to `rows` in key order.
+    fn collect_rows(&self, page_number: u32, depth: usize, rows: &mut Vec<Row>) -> Option<()> {
+        if depth > MAX_DEPTH {
+            return None;
+        }
+        let page = self.page(page_number)?;
+        let header = if page_number == 1 { HEADER_LEN } else { 0 };
+        let page_type = *page.get(header)?;
+        let interior = match page_type {
+            INDEX_INTERIOR | TABLE_INTERIOR => true,
+            INDEX_LEAF | TABLE_LEAF => false,
+            _ => return None,
+        };
+        let cell_count = read_u16(page, header + 3)?;
+        let pointers = header + if interior { 12 } else { 8 };
+        for i in 0..cell_count {
+            let mut pos = read_u16(page, pointers + 2 * i)?;
+            if interior {
+                self.collect_rows(read_u32(page, pos)?, depth + 1, rows)?;
+                pos += 4;
+            }
+            // Interior cells of index b-trees hold entries too, unlike those of table b-trees
+            if page_type == TABLE_INTERIOR {
+                continue;
+            }
+            let len = usize::try_from(read_varint(page, &mut pos)?).ok()?;
+            let rowid = if page_type == TABLE_LEAF {
+                // Negative rowids are stored in two's complement
+                Some(i64::from_be_bytes(
+                    read_varint(page, &mut pos)?.to_be_bytes(),
+                ))
+            } else {
+                None
+            };
+            let payload = self.payload(page, pos, len, rowid.is_some())?;
+            rows.push(Row {
+                rowid,
+                values: self.record(&payload)?,
+            });
+        }
+        if interior {
+            self.collect_rows(read_u32(page, header + 8)?, depth + 1, rows)?;
+        }
+        Some(())
+    }
+}
+
+fn parse(data: &[u8]) -> Option<Database> {
+    let reader = Reader::new(data)?;
+    let mut schema_rows = Vec::new();
+    reader.collect_rows(1, 0, &mut schema_rows)?;
+    let mut schema = Vec::new();
+    let mut tables = Vec::new();
+    for row in schema_rows {
+        let (kind, name, root_page, sql) = match &row.values[..] {
+            [Value::Text(kind), Value::Text(name), _, root_page, sql] => {
+                (kind, name, root_page, sql)
+            }
+            _ => return None,
+        };
+        match root_page {
+            // Virtual tables and views have no b-tree, and index contents follow from the tables
+            Value::Integer(root_page) if kind == "table" && *root_page > 0 => {
+                let mut rows = Vec::new();
+                reader.collect_rows(u32::try_from(*root_page).ok()?, 0, &mut rows)?;
+                tables.push((name.clone(), rows));
+            }
+            _ => {}
+        }
+        schema.push(SchemaEntry {
+            kind: kind.clone(),
+            name: name.clone(),
+            sql: match sql {
+                Value::Text(sql) => Some(sql.clone()),
+                _ => None,
+            },
+        });
+    }
+    schema.sort_by(|a, b| (&a.kind, &a.name).cmp(&(&b.kind, &b.name)));
+    tables.sort_by(|a, b| a.0.cmp(&b.0));
+    Some(Database { schema, tables })
+}
+
+/// Compares two databases by their schema and rows, or returns `None` if either can't be
+/// parsed.
+pub(crate) fn equal(expected: &[u8], actual: &[u8]) -> Option<bool> {
+    Some(parse(expected)? == parse(actual)?)
+}
+
+fn first_schema_difference(expected: &[SchemaEntry], actual: &[SchemaEntry]) -> Option<String> {
+    let list = |schema: &[SchemaEntry]| {
+        schema
+            .iter()
+            .map(|entry| format!("{} `{}`", entry.kind, entry.name))
+            .collect::<Vec<_>>()
+            .join(", ")
+    };
+    let same_names = expected.len() == actual.len()
+        && expected
+            .iter()
+            .zip(actual)
+            .all(|(e, a)| (&e.kind, &e.name) == (&a.kind, &a.name));
+    if !same_names {
+        return Some(format!(
+            "the schema differs: expected {}, found {}",
+            list(expected),
+            list(actual)
+        ));
+    }
+    let (e, a) = expected.iter().zip(actual).find(|(e, a)| e.sql != a.sql)?;
+    Some(format!(
+        "the SQL of {} `{}` differs: expected `{}`, found `{}`",
+        e.kind,
+        e.name,
+        e.sql.as_deref().unwrap_or("NULL"),
+        a.sql.as_deref().unwrap_or("NULL")
+    ))
+}
+
+/// Finds the first difference in the schema or rows of two databases, or returns `None` if
+/// either can't be parsed.
+pub(crate) fn first_difference(expected: &[u8], actual: &[u8]) -> Option<Difference> {
+    let (expected, actual) = (parse(expected)?, parse(actual)?);
+    let description = first_schema_difference(&expected.schema, &actual.schema).or_else(|| {
+        expected
+            .tables
+            .iter()
+            .zip(&actual.tables)
+            .find_map(|((name, e), (_, a))| {
+                let row = e.iter().zip(a).position(|(e, a)| e != a);
+                match row {
+                    Some(i) => Some(format!(
+                        "row #{} of table `{name}` differs: expected {}, found {}",
+                        i + 1,
+                        e[i],
+                        a[i]
+                    )),
+                    None if e.len() != a.len() => Some(format!(
+                        "table `{name}` has {} rows, expected {}",
+                        a.len(),
+                        e.len()
+                    )),
+                    None => None,
+                }
+            })
+    })?;
+    Some(Difference {
+        description,
+        regions: None,
+    })
+}
@@ -0,0 +1,66 @@
+//! Stable output mode, for snapshotting the reports and for tools that parse them.
+//!
+//! Colors are stripped, the help text is printed with every failure, and long reports are printed
+//! in full instead of being paged or spilled to a file with a temporary name.
+
+use std::io;
+
+/// When set, reports are printed in the stable format.
+pub(crate) const STABLE_OUTPUT_VAR_NAME: &str = "EXPECT_BYTES_STABLE_OUTPUT";
+
+pub(crate) fn is_enabled() -> bool {
+    std::env::var_os(STABLE_OUTPUT_VAR_NAME).is_some()
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum State {
+    Text,
+    /// After an ESC byte.
+    Escape,
+    /// Inside a control sequence, which ends with a byte in `0x40..=0x7e`.
+    ControlSequence,
+}
+
+/// Writer that strips ANSI escape sequences from the output, even if they're split across writes.
+pub(crate) struct StripAnsi<W> {
+    inner: W,
+    state: State,
+}
+
+impl<W: io::Write> StripAnsi<W> {
+    pub(crate) fn new(inner: W) -> Self {
+        Self {
+            inner,
+            state: State::Text,
+        }
+    }
+}
+
+impl<W: io::Write> io::Write for StripAnsi<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let mut text_start = 0;
+        for (i, &b) in buf.iter().enumerate() {
+            self.state = match (self.state, b) {
+                (State::Text, 0x1b) => {
+                    self.inner.write_all(&buf[text_start..i])?;
+                    State::Escape
+                }
+                (State::Text, _) => continue,
+                (State::Escape, b'[') => State::ControlSequence,
+                (State::ControlSequence, 0x40..=0x7e) | (State::Escape, _) => {
+                    text_start = i + 1;
+                    State::Text
+                }
+                (State::ControlSequence, _) => State::ControlSequence,
+            };
+        }
+        if self.state == State::Text {
+            self.inner.write_all(&buf[text_start..])?;
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
@@ -0,0 +1,117 @@
+//! Statistics of the files updated during the run, printed when the process exits.
+
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, Once, PoisonError};
+
+use crate::at_exit;
+use crate::batch;
+use crate::global::Global;
+use crate::output;
+use crate::style::SUCCESS;
+
+/// A file updated during this run.
+pub(crate) struct Update {
+    pub(crate) path: PathBuf,
+    /// Length of the file after its last update.
+    pub(crate) len: usize,
+    /// Whether the file didn't exist before its first update.
+    pub(crate) created: bool,
+}
+
+/// Files updated during this run, in the order they were first written.
+static UPDATES: Global<Mutex<Vec<Update>>> = Global::new();
+/// Colors of the totals, chosen by the assertions that updated files.
+static COLOR: Global<Mutex<Option<bool>>> = Global::new();
+static REGISTER_FINISH: Once = Once::new();
+
+/// Records that `len` bytes were written to `path`, which is `created` if it didn't exist, by an
+/// assertion that chose `color` for its reports, printing the totals of all updated files when
+/// the process exits.
+pub(crate) fn record(path: &Path, len: usize, created: bool, color: Option<bool>) {
+    {
+        let mut chosen = COLOR
+            .get_or_init(Mutex::default)
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        *chosen = combine_color(*chosen, color);
+    }
+    {
+        let mut updates = UPDATES
+            .get_or_init(Mutex::default)
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        match updates.iter_mut().find(|update| update.path == path) {
+            Some(update) => update.len = len,
+            None => updates.push(Update {
+                path: path.to_owned(),
+                len,
+                created,
+            }),
+        }
+    }
+    REGISTER_FINISH.call_once(|| {
+        // SAFETY: `finish` doesn't unwind and only uses state that lives until the process exits.
+        unsafe {
+            at_exit::register(finish);
+        }
+    });
+}
+
+/// Combines the colors chosen by the assertions so far with the `color` of another one.
+///
+/// The totals are plain if any assertion asked for plain reports, since their output may be
+/// compared, and are colored regardless of the terminal if any other asked for that.
+pub(crate) fn combine_color(chosen: Option<bool>, color: Option<bool>) -> Option<bool> {
+    match (chosen, color) {
+        (Some(false), _) | (_, Some(false)) => Some(false),
+        (Some(true), _) | (_, Some(true)) => Some(true),
+        (None, None) => None,
+    }
+}
+
+extern "C" fn finish() {
+    let updates = UPDATES
+        .get_or_init(Mutex::default)
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner);
+    let color = *COLOR
+        .get_or_init(Mutex::default)
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner);
+    let mut stderr = output::stderr_with_color(color);
+    // Errors can't be reported from here, and unwinding out of this function would abort
+    if batch::is_enabled() {
+        batch::sync_dirs(updates.iter().map(|update| update.path.as_path()));
+        let _ = batch::write_summary(&updates, &mut stderr);
+    }
+    let _ = write_totals(&updates, &mut stderr);
+    #[cfg(feature = "git")]
+    if let Some(uncommitted) =
+        crate::git::uncommitted(updates.iter().map(|update| update.path.as_path()))
+    {
+        let _ = crate::git::write_reminder(&uncommitted, &mut stderr);
+    }
+}
+
+/// Writes the end-of-run totals of updated files.
+pub(crate) fn write_totals<W: io::Write>(updates: &[Update], writer: &mut W) -> io::Result<()> {
+    let largest = match updates.iter().max_by_key(|update| update.len) {
+        Some(largest) => largest,
+        None => return Ok(()),
+    };
+    let created = updates.iter().filter(|update| update.created).count();
+    let bytes: usize = updates.iter().map(|update| update.len).sum();
+    writeln!(
+        writer,
+        "\n{SUCCESS}update totals{SUCCESS:#}: {created} created, {} overwritten, \
+         {bytes} bytes written",
+        updates.len() - created
+    )?;
+    writeln!(
+        writer,
+        "   largest: {} ({} bytes)",
+        largest.path.display(),
+        largest.len
+    )
+}
@@ -0,0 +1,86 @@
+//! Format-aware comparison and reporting for structured data.
+
+/// Where structured data first differs.
+pub(crate) struct Difference {
+    /// Which element differs, and how.
+    pub(crate) description: String,
+    /// The differing element in the expected and the actual data, shown as a byte diff.
+    pub(crate) regions: Option<(Vec<u8>, Vec<u8>)>,
+}
+
+/// A format the data is parsed as in comparisons and failure reports.
+#[derive(Debug, Clone, Copy)]
+pub(crate) enum Structure {
+    /// An Arrow IPC file or stream.
+    #[cfg(feature = "arrow")]
+    Arrow,
+    /// ASN.1 DER data.
+    #[cfg(feature = "der")]
+    Der,
+    /// A Parquet file.
+    #[cfg(feature = "parquet")]
+    Parquet,
+    /// A pcap or pcapng capture.
+    #[cfg(feature = "pcap")]
+    Pcap { ignore_timestamps: bool },
+    /// Length-prefixed records.
+    Records(crate::LengthPrefix),
+    /// A RIFF or IFF file.
+    Riff,
+    /// An SQLite database.
+    #[cfg(feature = "sqlite")]
+    Sqlite,
+}
+
+impl Structure {
+    /// Compares the data structurally, or returns `None` if it should be compared byte-wise.
+    #[cfg_attr(
+        not(any(
+            feature = "arrow",
+            feature = "parquet",
+            feature = "pcap",
+            feature = "sqlite"
+        )),
+        allow(unused_variables)
+    )]
+    pub(crate) fn equal(self, expected: &[u8], actual: &[u8]) -> Option<bool> {
+        match self {
+            #[cfg(feature = "arrow")]
+            Self::Arrow => crate::arrow::equal(expected, actual),
+            #[cfg(feature = "der")]
+            Self::Der => None,
+            #[cfg(feature = "parquet")]
+            Self::Parquet => crate::parquet::equal(expected, actual),
+            #[cfg(feature = "pcap")]
+            Self::Pcap {
+                ignore_timestamps: true,
+            } => crate::pcap::equal_ignoring_timestamps(expected, actual),
+            // Structurally equal captures are byte-wise equal
+            #[cfg(feature = "pcap")]
+            Self::Pcap { .. } => None,
+            Self::Records(_) | Self::Riff => None,
+            #[cfg(feature = "sqlite")]
+            Self::Sqlite => crate::sqlite::equal(expected, actual),
+        }
+    }
+
+    /// Finds the first difference, or returns `None` if either side can't be parsed.
+    pub(crate) fn first_difference(self, expected: &[u8], actual: &[u8]) -> Option<Difference> {
+        match self {
+            #[cfg(feature = "arrow")]
+            Self::Arrow => crate::arrow::first_difference(expected, actual),
+            #[cfg(feature = "der")]
+            Self::Der => crate::der::first_difference(expected, actual),
+            #[cfg(feature = "parquet")]
+            Self::Parquet => crate::parquet::first_difference(expected, actual),
+            #[cfg(feature = "pcap")]
+            Self::Pcap { ignore_timestamps } => {
+                crate::pcap::first_difference(expected, actual, ignore_timestamps)
+            }
+            Self::Records(prefix) => crate::records::first_difference(expected, actual, prefix),
+            Self::Riff => crate::riff::first_difference(expected, actual),
+            #[cfg(feature = "sqlite")]
+            Self::Sqlite => crate::sqlite::first_difference(expected, actual),
+        }
+    }
+}
@@ -0,0 +1,123 @@
+//! Styles of the labels, headings and highlights in reports.
+//!
+//! With the `anstream` feature these are `anstyle` styles. Otherwise a stand-in with the same
+//! API and output is used, since `anstyle` needs a newer Rust than the crate.
+//!
+//! A style is written with `{STYLE}` and ended with `{STYLE:#}`.
+
+#[cfg(feature = "anstream")]
+use anstyle::{AnsiColor, Color, Style};
+#[cfg(not(feature = "anstream"))]
+use fallback::{AnsiColor, Color, Style};
+
+/// The label of failures, such as `error`.
+pub(crate) const ERROR: Style = Style::new()
+    .bold()
+    .fg_color(Some(Color::Ansi(AnsiColor::BrightRed)));
+/// The message after the label of a failure.
+pub(crate) const MESSAGE: Style = Style::new()
+    .bold()
+    .fg_color(Some(Color::Ansi(AnsiColor::BrightWhite)));
+/// The label of notes, warnings and reminders.
+pub(crate) const NOTE: Style = Style::new()
+    .bold()
+    .fg_color(Some(Color::Ansi(AnsiColor::BrightYellow)));
+/// The label of updates and successful checks.
+pub(crate) const SUCCESS: Style = Style::new()
+    .bold()
+    .fg_color(Some(Color::Ansi(AnsiColor::BrightGreen)));
+/// The arrow before a location.
+pub(crate) const ARROW: Style = Style::new()
+    .bold()
+    .fg_color(Some(Color::Ansi(AnsiColor::Blue)));
+/// Headings of report sections.
+pub(crate) const BOLD: Style = Style::new().bold();
+/// Differing bytes of the expected data.
+pub(crate) const EXPECTED: Style = Style::new().fg_color(Some(Color::Ansi(AnsiColor::Green)));
+/// Differing bytes of the actual data.
+pub(crate) const ACTUAL: Style = Style::new().fg_color(Some(Color::Ansi(AnsiColor::Red)));
+
+/// Returns the highlight of differing bytes on the expected or actual side.
+pub(crate) fn highlight(is_expected: bool) -> Style {
+    if is_expected {
+        EXPECTED
+    } else {
+        ACTUAL
+    }
+}
+
+#[cfg(not(feature = "anstream"))]
+mod fallback {
+    use std::fmt;
+
+    /// The subset of `anstyle::AnsiColor` used in reports.
+    #[derive(Clone, Copy, PartialEq, Eq)]
+    pub(crate) enum AnsiColor {
+        Red,
+        Green,
+        Blue,
+        BrightRed,
+        BrightGreen,
+        BrightYellow,
+        BrightWhite,
+    }
+
+    /// The subset of `anstyle::Color` used in reports.
+    #[derive(Clone, Copy, PartialEq, Eq)]
+    pub(crate) enum Color {
+        Ansi(AnsiColor),
+    }
+
+    /// Stand-in for `anstyle::Style`.
+    #[derive(Clone, Copy, PartialEq, Eq)]
+    pub(crate) struct Style {
+        bold: bool,
+        fg: Option<Color>,
+    }
+
+    impl Style {
+        pub(crate) const fn new() -> Self {
+            Self {
+                bold: false,
+                fg: None,
+            }
+        }
+
+        pub(crate) const fn bold(mut self) -> Self {
+            self.bold = true;
+            self
+        }
+
+        pub(crate) const fn fg_color(mut self, fg: Option<Color>) -> Self {
+            self.fg = fg;
+            self
+        }
+    }
+
+    impl fmt::Display for Style {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            if f.alternate() {
+                if *self != Self::new() {
+                    f.write_str("\x1b[0m")?;
+                }
+                return Ok(());
+            }
+            if self.bold {
+                f.write_str("\x1b[1m")?;
+            }
+            if let Some(Color::Ansi(color)) = self.fg {
+                let code = match color {
+                    AnsiColor::Red => 31,
+                    AnsiColor::Green => 32,
+                    AnsiColor::Blue => 34,
+                    AnsiColor::BrightRed => 91,
+                    AnsiColor::BrightGreen => 92,
+                    AnsiColor::BrightYellow => 93,
+                    AnsiColor::BrightWhite => 97,
+                };
+                write!(f, "\x1b[{code}m")?;
+            }
+            Ok(())
+        }
+    }
+}
@@ -0,0 +1,232 @@
+//! Checked-in lists of known differences that are accepted until they expire.
+//!
+//! Each line of the file names a snapshot relative to the file, the byte ranges whose differences
+//! are accepted, and an expiration date, a reason such as an issue link, or both:
+//!
+//! ```text
+//! # Regenerate once the encoder fix lands
+//! test_data/frame.bin 0x10..0x18,0x40..0x44 2025-06-30 https://github.com/org/repo/issues/12
+//! ```
+
+use std::ops::Range;
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+use std::{fmt, fs, io};
+
+use crate::canonicalize_parent;
+use crate::style::NOTE;
+
+/// Path of the suppression file.
+pub(crate) const SUPPRESSIONS_VAR_NAME: &str = "EXPECT_BYTES_SUPPRESSIONS";
+
+/// Known differences of a snapshot.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Suppression {
+    pub(crate) ranges: Vec<Range<usize>>,
+    pub(crate) until: Option<Date>,
+    pub(crate) reason: Option<String>,
+}
+
+/// A calendar date in UTC.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub(crate) struct Date {
+    year: u32,
+    month: u32,
+    day: u32,
+}
+
+impl Date {
+    fn parse(s: &str) -> Option<Self> {
+        let mut parts = s.splitn(3, '-');
+        let mut part = |len: usize| {
+            let part = parts.next()?;
+            if part.len() != len || !part.bytes().all(|b| b.is_ascii_digit()) {
+                return None;
+            }
+            part.parse().ok()
+        };
+        let date = Self {
+            year: part(4)?,
+            month: part(2)?,
+            day: part(2)?,
+        };
+        ((1..=12).contains(&date.month) && (1..=31).contains(&date.day)).then(|| date)
+    }
+
+    /// Returns the current date.
+    pub(crate) fn today() -> Self {
+        let secs = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |elapsed| elapsed.as_secs());
+        Self::from_days(secs / 86_400)
+    }
+
+    /// Converts days since 1970-01-01 to a date, with the algorithm from
+    /// <https://howardhinnant.github.io/date_algorithms.html#civil_from_days>.
+    pub(crate) fn from_days(days: u64) -> Self {
+        let days = days + 719_468;
+        let era = days / 146_097;
+        let day_of_era = days % 146_097;
+        let year_of_era =
+            (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+        let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+        let shifted_month = (5 * day_of_year + 2) / 153;
+        let day = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+        let month = if shifted_month < 10 {
+            shifted_month + 3
+        } else {
+            shifted_month - 9
+        };
+        let year = year_of_era + era * 400 + u64::from(month <= 2);
+        Self {
+            year: u32::try_from(year).unwrap_or(u32::MAX),
+            month: u32::try_from(month).unwrap_or(1),
+            day: u32::try_from(day).unwrap_or(1),
+        }
+    }
+}
+
+impl fmt::Display for Date {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:04}-{:02}-{:02}", self.year, self.month, self.day)
+    }
+}
+
+impl Suppression {
+    /// Returns whether the suppression expired before `today`.
+    pub(crate) fn is_expired(&self, today: Date) -> bool {
+        self.until.map_or(false, |until| until < today)
+    }
+
+    /// Returns whether `expected` and `actual` differ in the ranges.
+    pub(crate) fn differs(&self, expected: &[u8], actual: &[u8]) -> bool {
+        self.ranges.iter().any(|range| {
+            let clamp = |data: &[u8]| {
+                let end = range.end.min(data.len());
+                data[range.start.min(end)..end].to_vec()
+            };
+            clamp(expected) != clamp(actual)
+        })
+    }
+
+    /// Formats the ranges and the reason for the report.
+    pub(crate) fn describe(&self) -> String {
+        let ranges: Vec<_> = self
+            .ranges
+            .iter()
+            .map(|range| format!("{:#x}..{:#x}", range.start, range.end))
+            .collect();
+        match &self.reason {
+            Some(reason) => format!("bytes {} ({reason})", ranges.join(", ")),
+            None => format!("bytes {}", ranges.join(", ")),
+        }
+    }
+}
+
+/// Returns the suppressions of the snapshot at `path` in the file set in
+/// `EXPECT_BYTES_SUPPRESSIONS`.
+///
+/// # Panics
+///
+/// Will panic if the file can't be read or has an invalid line.
+pub(crate) fn for_path(path: &Path) -> Vec<Suppression> {
+    let file = match std::env::var_os(SUPPRESSIONS_VAR_NAME) {
+        Some(file) => file,
+        None => return Vec::new(),
+    };
+    let file = Path::new(&file);
+    let contents = fs::read_to_string(file)
+        .unwrap_or_else(|e| panic!("failed to read suppressions {}: {e}", file.display()));
+    parse(
+        &contents,
+        file.parent().unwrap_or_else(|| Path::new("")),
+        path,
+    )
+    .unwrap_or_else(|line| panic!("{}:{line}: invalid suppression", file.display()))
+}
+
+/// Parses the suppressions of the snapshot at `path` from `contents`, with paths relative to
+/// `dir`.
+///
+/// Returns the number of an invalid line as an error.
+pub(crate) fn parse(contents: &str, dir: &Path, path: &Path) -> Result<Vec<Suppression>, usize> {
+    let path = canonicalize_parent(path);
+    let mut suppressions = Vec::new();
+    for (i, line) in contents.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let mut fields = line.split_whitespace();
+        let (snapshot, ranges) = match (fields.next(), fields.next()) {
+            (Some(snapshot), Some(ranges)) => (snapshot, ranges),
+            _ => return Err(i + 1),
+        };
+        let ranges = ranges
+            .split(',')
+            .map(parse_range)
+            .collect::<Option<Vec<_>>>()
+            .ok_or(i + 1)?;
+        let mut rest: Vec<_> = fields.collect();
+        let until = match rest.first().map(|field| Date::parse(field)) {
+            Some(Some(date)) => {
+                rest.remove(0);
+                Some(date)
+            }
+            _ => None,
+        };
+        let reason = (!rest.is_empty()).then(|| rest.join(" "));
+        if until.is_none() && reason.is_none() {
+            // Suppressions must say when or why they can be removed
+            return Err(i + 1);
+        }
+        if canonicalize_parent(&dir.join(snapshot)) == path {
+            suppressions.push(Suppression {
+                ranges,
+                until,
+                reason,
+            });
+        }
+    }
+    Ok(suppressions)
+}
+
+fn parse_range(s: &str) -> Option<Range<usize>> {
+    let (start, end) = s.split_once("..")?;
+    let parse = |s: &str| usize::from_str_radix(s.strip_prefix("0x")?, 16).ok();
+    Some(parse(start)?..parse(end)?)
+}
+
+/// Zeroes the bytes of `data` in the ranges of `suppressions`.
+pub(crate) fn mask(suppressions: &[&Suppression], data: &[u8]) -> Vec<u8> {
+    let mut data = data.to_vec();
+    for range in suppressions
+        .iter()
+        .flat_map(|suppression| &suppression.ranges)
+    {
+        let end = range.end.min(data.len());
+        let start = range.start.min(end);
+        data[start..end].fill(0);
+    }
+    data
+}
+
+/// Writes a note for each expired suppression of differences between `expected` and `actual`.
+pub(crate) fn write_expired<W: io::Write>(
+    writer: &mut W,
+    suppressions: &[Suppression],
+    (expected, actual): (&[u8], &[u8]),
+    today: Date,
+) -> io::Result<()> {
+    for suppression in suppressions.iter().filter(|suppression| {
+        suppression.is_expired(today) && suppression.differs(expected, actual)
+    }) {
+        writeln!(
+            writer,
+            "{NOTE}note{NOTE:#}: the suppression of {} expired on {}",
+            suppression.describe(),
+            suppression.until.unwrap()
+        )?;
+    }
+    Ok(())
+}
@@ -0,0 +1,394 @@
+//! Symbol maps of compiled artifacts, for [`ExpectFile::symbol_map`].
+//!
+//! [`ExpectFile::symbol_map`]: crate::ExpectFile::symbol_map
+
+use std::ops::Range;
+use std::{error, fmt};
+
+/// Names of the symbols and sections of a compiled artifact by their offsets in the file.
+///
+/// Maps are built by hand, parsed from a simple text table with [`SymbolMap::parse`], or read
+/// from the symbol and section tables of an ELF file with [`SymbolMap::from_elf`].
+///
+/// ```
+/// # use expect_test_bytes::SymbolMap;
+/// let map = SymbolMap::new()
+///     .section(0x40..0x80, ".text")
+///     .symbol(0x40, "main")
+///     .symbol(0x60, "helper");
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SymbolMap {
+    /// Sorted by offset.
+    symbols: Vec<Symbol>,
+    sections: Vec<(Range<usize>, String)>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Symbol {
+    offset: usize,
+    size: Option<usize>,
+    name: String,
+}
+
+/// Error returned by [`SymbolMap::parse`] and [`SymbolMap::from_elf`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum SymbolMapError {
+    /// A line of a text table isn't an offset or range followed by a name. The line number
+    /// starts from 1.
+    InvalidLine(usize),
+    /// The data isn't a 32- or 64-bit ELF file.
+    NotElf,
+    /// A table of the ELF file is out of bounds.
+    Truncated,
+}
+
+impl fmt::Display for SymbolMapError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidLine(line) => write!(f, "invalid symbol map entry on line {line}"),
+            Self::NotElf => write!(f, "not an ELF file"),
+            Self::Truncated => write!(f, "truncated ELF file"),
+        }
+    }
+}
+
+impl error::Error for SymbolMapError {}
+
+impl SymbolMap {
+    /// Creates an empty map.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a symbol starting at `offset`, which extends to the next symbol.
+    #[must_use]
+    pub fn symbol(mut self, offset: usize, name: impl Into<String>) -> Self {
+        self.insert(offset, None, name.into());
+        self
+    }
+
+    /// Adds a section covering `range`.
+    #[must_use]
+    pub fn section(mut self, range: Range<usize>, name: impl Into<String>) -> Self {
+        self.sections.push((range, name.into()));
+        self
+    }
+
+    /// Parses a table with an entry on each line: a hexadecimal offset and a symbol name, or a
+    /// range of offsets and a section name.
+    ///
+    /// Empty lines and lines starting with `#` are ignored.
+    ///
+    /// ```
+    /// # use expect_test_bytes::SymbolMap;
+    /// let map = SymbolMap::parse(
+    ///     "# Generated by the linker
+    ///     0x40..0x80 .text
+    ///     0x40 main
+    ///     0x60 helper",
+    /// )
+    /// .unwrap();
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Will return an error if a line isn't a valid entry.
+    pub fn parse(table: &str) -> Result<Self, SymbolMapError> {
+        let mut map = Self::new();
+        for (i, line) in table.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let invalid = || SymbolMapError::InvalidLine(i + 1);
+            let (offsets, name) = line.split_once(char::is_whitespace).ok_or_else(invalid)?;
+            let name = name.trim().to_owned();
+            match offsets.split_once("..") {
+                Some((start, end)) => {
+                    let range = parse_hex(start).ok_or_else(invalid)?
+                        ..parse_hex(end).ok_or_else(invalid)?;
+                    map.sections.push((range, name));
+                }
+                None => map.insert(parse_hex(offsets).ok_or_else(invalid)?, None, name),
+            }
+        }
+        Ok(map)
+    }
+
+    /// Reads the function and object symbols and the sections of a 32- or 64-bit ELF file, such
+    /// as an object file, a shared library or an executable, with their offsets in the file.
+    ///
+    /// # Errors
+    ///
+    /// Will return an error if `data` isn't an ELF file or its tables are out of bounds.
+    pub fn from_elf(data: &[u8]) -> Result<Self, SymbolMapError> {
+        let elf = Elf::new(data)?;
+        let sections = elf.sections()?;
+        let names = sections
+            .get(usize::from(elf.u16(elf.header_offset(0x3e, 0x32))?))
+            .ok_or(SymbolMapError::Truncated)?;
+        let mut map = Self::new();
+        for section in &sections {
+            if section.kind != SHT_NOBITS && section.size > 0 && section.name_index != 0 {
+                let name = elf.string(names, section.name_index)?;
+                map.sections.push((
+                    section.offset..section.offset + section.size,
+                    name.to_owned(),
+                ));
+            }
+        }
+        for table in sections
+            .iter()
+            .filter(|section| section.kind == SHT_SYMTAB || section.kind == SHT_DYNSYM)
+        {
+            let strings = sections.get(table.link).ok_or(SymbolMapError::Truncated)?;
+            let entry_size = if elf.is_64 { 24 } else { 16 };
+            for entry in (0..table.size / entry_size).map(|i| table.offset + i * entry_size) {
+                let (info, index, value, size) = if elf.is_64 {
+                    (
+                        elf.u8(entry + 4)?,
+                        elf.u16(entry + 6)?,
+                        elf.u64(entry + 8)?,
+                        elf.u64(entry + 16)?,
+                    )
+                } else {
+                    (
+                        elf.u8(entry + 12)?,
+                        elf.u16(entry + 14)?,
+                        u64::from(elf.u32(entry + 4)?),
+                        u64::from(elf.u32(entry + 8)?),
+                    )
+                };
+                // Only functions and objects stored in a section of the file
+                let section = match sections.get(usize::from(index)) {
+                    Some(section) if index != 0 && section.kind != SHT_NOBITS => section,
+                    _ => continue,
+                };
+                if !matches!(info & 0xf, STT_OBJECT | STT_FUNC) {
+                    continue;
+                }
+                let offset = value
+                    .checked_sub(section.address)
+                    .and_then(|delta| usize::try_from(delta).ok())
+                    .and_then(|delta| section.offset.checked_add(delta))
+                    .ok_or(SymbolMapError::Truncated)?;
+                let size = usize::try_from(size).map_err(|_| SymbolMapError::Truncated)?;
+                let name = elf.string(strings, elf.u32(entry)?)?.to_owned();
+                map.insert(offset, (size > 0).then(|| size), name);
+            }
+        }
+        Ok(map)
+    }
+
+    fn insert(&mut self, offset: usize, size: Option<usize>, name: String) {
+        let i = self
+            .symbols
+            .partition_point(|symbol| symbol.offset <= offset);
+        self.symbols.insert(i, Symbol { offset, size, name });
+    }
+
+    /// Describes `offset` by the symbol and section containing it, such as ``in `main`+0x4 of
+    /// section `.text` ``.
+    pub(crate) fn describe(&self, offset: usize) -> Option<String> {
+        let section = self
+            .sections
+            .iter()
+            .find(|(range, _)| range.contains(&offset));
+        let i = self
+            .symbols
+            .partition_point(|symbol| symbol.offset <= offset);
+        let symbol = i
+            .checked_sub(1)
+            .map(|i| &self.symbols[i])
+            .filter(|symbol| {
+                symbol
+                    .size
+                    .map_or(true, |size| offset - symbol.offset < size)
+            })
+            // Symbols don't extend past their section
+            .filter(|symbol| {
+                self.sections
+                    .iter()
+                    .find(|(range, _)| range.contains(&symbol.offset))
+                    .map_or(true, |(range, _)| range.contains(&offset))
+            });
+        match (symbol, section) {
+            (Some(symbol), Some((_, section))) => Some(format!(
+                "in `{}`+{:#x} of section `{section}`",
+                symbol.name,
+                offset - symbol.offset
+            )),
+            (Some(symbol), None) => Some(format!(
+                "in `{}`+{:#x}",
+                symbol.name,
+                offset - symbol.offset
+            )),
+            (None, Some((range, section))) => Some(format!(
+                "in section `{section}`+{:#x}",
+                offset - range.start
+            )),
+            (None, None) => None,
+        }
+    }
+}
+
+fn parse_hex(s: &str) -> Option<usize> {
+    let digits = s.strip_prefix("0x").unwrap_or(s);
+    usize::from_str_radix(digits, 16).ok()
+}
+
+const SHT_SYMTAB: u32 = 2;
+const SHT_NOBITS: u32 = 8;
+const SHT_DYNSYM: u32 = 11;
+const STT_OBJECT: u8 = 1;
+const STT_FUNC: u8 = 2;
+
+struct Elf<'a> {
+    data: &'a [u8],
+    is_64: bool,
+    big_endian: bool,
+}
+
+struct Section {
+    name_index: u32,
+    kind: u32,
+    address: u64,
+    offset: usize,
+    size: usize,
+    link: usize,
+}
+
+impl<'a> Elf<'a> {
+    fn new(data: &'a [u8]) -> Result<Self, SymbolMapError> {
+        if data.get(..4) != Some(b"\x7fELF") {
+            return Err(SymbolMapError::NotElf);
+        }
+        let is_64 = match data.get(4) {
+            Some(1) => false,
+            Some(2) => true,
+            _ => return Err(SymbolMapError::NotElf),
+        };
+        let big_endian = match data.get(5) {
+            Some(1) => false,
+            Some(2) => true,
+            _ => return Err(SymbolMapError::NotElf),
+        };
+        Ok(Self {
+            data,
+            is_64,
+            big_endian,
+        })
+    }
+
+    /// Returns the offset of a field of the file header in 64- or 32-bit files.
+    fn header_offset(&self, offset_64: usize, offset_32: usize) -> usize {
+        if self.is_64 {
+            offset_64
+        } else {
+            offset_32
+        }
+    }
+
+    fn bytes<const N: usize>(&self, offset: usize) -> Result<[u8; N], SymbolMapError> {
+        let mut bytes = [0; N];
+        bytes.copy_from_slice(
+            offset
+                .checked_add(N)
+                .and_then(|end| self.data.get(offset..end))
+                .ok_or(SymbolMapError::Truncated)?,
+        );
+        Ok(bytes)
+    }
+
+    fn u8(&self, offset: usize) -> Result<u8, SymbolMapError> {
+        self.bytes::<1>(offset).map(|[b]| b)
+    }
+
+    fn u16(&self, offset: usize) -> Result<u16, SymbolMapError> {
+        let bytes = self.bytes(offset)?;
+        Ok(if self.big_endian {
+            u16::from_be_bytes(bytes)
+        } else {
+            u16::from_le_bytes(bytes)
+        })
+    }
+
+    fn u32(&self, offset: usize) -> Result<u32, SymbolMapError> {
+        let bytes = self.bytes(offset)?;
+        Ok(if self.big_endian {
+            u32::from_be_bytes(bytes)
+        } else {
+            u32::from_le_bytes(bytes)
+        })
+    }
+
+    fn u64(&self, offset: usize) -> Result<u64, SymbolMapError> {
+        let bytes = self.bytes(offset)?;
+        Ok(if self.big_endian {
+            u64::from_be_bytes(bytes)
+        } else {
+            u64::from_le_bytes(bytes)
+        })
+    }
+
+    /// Reads an address-sized field.
+    fn address(&self, offset: usize) -> Result<u64, SymbolMapError> {
+        if self.is_64 {
+            self.u64(offset)
+        } else {
+            self.u32(offset).map(u64::from)
+        }
+    }
+
+    fn usize(&self, offset: usize) -> Result<usize, SymbolMapError> {
+        usize::try_from(self.address(offset)?).map_err(|_| SymbolMapError::Truncated)
+    }
+
+    fn sections(&self) -> Result<Vec<Section>, SymbolMapError> {
+        let table = self.usize(self.header_offset(0x28, 0x20))?;
+        let entry_size = usize::from(self.u16(self.header_offset(0x3a, 0x2e))?);
+        let count = usize::from(self.u16(self.header_offset(0x3c, 0x30))?);
+        (0..count)
+            .map(|i| {
+                let entry = i
+                    .checked_mul(entry_size)
+                    .and_then(|delta| table.checked_add(delta))
+                    .ok_or(SymbolMapError::Truncated)?;
+                let (address, offset, size, link) = if self.is_64 {
+                    (0x10, 0x18, 0x20, 0x28)
+                } else {
+                    (0xc, 0x10, 0x14, 0x18)
+                };
+                let section = Section {
+                    name_index: self.u32(entry)?,
+                    kind: self.u32(entry + 4)?,
+                    address: self.address(entry + address)?,
+                    offset: self.usize(entry + offset)?,
+                    size: self.usize(entry + size)?,
+                    link: self.u32(entry + link)? as usize,
+                };
+                Ok(section)
+            })
+            .collect()
+    }
+
+    /// Reads the NUL-terminated string at `index` in the string table `table`.
+    fn string(&self, table: &Section, index: u32) -> Result<&'a str, SymbolMapError> {
+        let strings = table
+            .offset
+            .checked_add(table.size)
+            .and_then(|end| self.data.get(table.offset..end))
+            .ok_or(SymbolMapError::Truncated)?;
+        let rest = strings
+            .get(index as usize..)
+            .ok_or(SymbolMapError::Truncated)?;
+        let end = rest
+            .iter()
+            .position(|&b| b == 0)
+            .ok_or(SymbolMapError::Truncated)?;
+        std::str::from_utf8(&rest[..end]).map_err(|_| SymbolMapError::Truncated)
+    }
+}
@@ -1,5 +1,10 @@
-use super::UPDATE_EXPECT_VAR_NAME;
-use std::{fs, sync::RwLock};
+use super::{DiffStyle, Part, UPDATE_EXPECT_VAR_NAME};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    sync::RwLock,
+    time::Duration,
+};
 
 /// Makes tests that modify environment variables run independently.
 static ENVVAR_MUTATION: RwLock<()> = RwLock::new(());
@@ -43,6 +48,40 @@ fn fails_different() {
     expect_test::expect_file!["test_data/fails_different.ansi.bin"].assert_eq(&actual);
 }
 
+#[cfg(feature = "anstream")]
+#[test]
+fn fails_different_without_color() {
+    let _guard = ENVVAR_MUTATION.write().unwrap();
+    let expect = expect_file!["test_data/example"];
+    let mut report = Vec::new();
+    assert!(expect
+        .assert_eq_nopanic_imp(b"exa- not this\n", &mut report)
+        .is_err());
+    let mut stripped = Vec::new();
+    // Not public API!
+    super::output::with_color(&mut stripped, false)
+        .write_all(&report)
+        .unwrap();
+    assert!(!stripped.contains(&b'\x1b'));
+
+    let print = || {
+        let mut buf = Vec::new();
+        // Not public API!
+        super::output::auto(&mut buf).write_all(&report).unwrap();
+        buf
+    };
+    std::env::remove_var("NO_COLOR");
+    std::env::remove_var("CLICOLOR_FORCE");
+    // Not a terminal
+    assert_eq!(print(), stripped);
+    std::env::set_var("CLICOLOR_FORCE", "1");
+    assert_eq!(print(), report);
+    std::env::set_var("NO_COLOR", "1");
+    assert_eq!(print(), stripped);
+    std::env::remove_var("NO_COLOR");
+    std::env::remove_var("CLICOLOR_FORCE");
+}
+
 #[test]
 fn creates() {
     let actual = {
@@ -64,3 +103,3142 @@ fn creates() {
 
     expect_test::expect_file!["test_data/creates.ansi.bin"].assert_eq(&actual);
 }
+
+#[test]
+fn diff_tool_command() {
+    let args = super::diff_tool_command(
+        "vbindiff {expected} --actual={actual}",
+        "a/expected".as_ref(),
+        "b/actual".as_ref(),
+    );
+    assert_eq!(args, ["vbindiff", "a/expected", "--actual=b/actual"]);
+}
+
+#[test]
+fn custom_comparator() {
+    let _guard = ENVVAR_MUTATION.read().unwrap();
+    let expect = expect_file!["test_data/example"]
+        .with_comparator(|expected: &[u8], actual: &[u8]| (expected.len() == actual.len()).into());
+
+    let mut buf = Vec::new();
+    assert!(expect.assert_eq_nopanic_imp(b"EXAMPLE\n", &mut buf).is_ok());
+    assert!(expect.assert_eq_nopanic_imp(b"example", &mut buf).is_err());
+}
+
+#[test]
+fn sha256() {
+    use super::sha256::{sha256, to_hex};
+
+    assert_eq!(
+        to_hex(&sha256(b"")),
+        "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"
+    );
+    assert_eq!(
+        to_hex(&sha256(b"abc")),
+        "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+    );
+    assert_eq!(
+        to_hex(&sha256(&[b'a'; 1000])),
+        "41edece42d63e8d9bf515a9ba6932e1c20cbc9f5a5d134645adb5db1b9737ea3"
+    );
+}
+
+#[test]
+fn hash_only() {
+    let actual = {
+        let _guard = ENVVAR_MUTATION.read().unwrap();
+        let expect = expect_file!["test_data/example.sha256"].hash_only();
+
+        let mut buf = Vec::new();
+        assert!(expect.assert_eq_nopanic_imp(b"example\n", &mut buf).is_ok());
+        assert!(expect.assert_eq_nopanic_imp(b"example", &mut buf).is_err());
+        String::from_utf8(buf).expect("Only printing strings")
+    };
+
+    expect_test::expect_file!["test_data/hash_only.ansi.bin"].assert_eq(&actual);
+}
+
+#[test]
+fn chunk_index() {
+    let actual = {
+        let _guard = ENVVAR_MUTATION.write().unwrap();
+        let expect = expect_file!["test_data/chunked"].with_chunk_index(4);
+        let data = b"0123456789abcdef";
+
+        let mut buf = Vec::new();
+        std::env::set_var(UPDATE_EXPECT_VAR_NAME, "");
+        assert!(expect.assert_eq_nopanic_imp(data, &mut buf).is_ok());
+        std::env::remove_var(UPDATE_EXPECT_VAR_NAME);
+        buf.clear();
+
+        // Not public API!
+        let index_path = super::chunk_index::index_path(&expect.path);
+        assert!(index_path.exists());
+
+        assert!(expect.assert_eq_nopanic_imp(data, &mut buf).is_ok());
+        assert!(expect
+            .assert_eq_nopanic_imp(b"0123456789ABCDEF", &mut buf)
+            .is_err());
+
+        // Rewriting the file, as a checkout does, keeps the index up to date
+        fs::write(&expect.path, data).unwrap();
+        let mut report = Vec::new();
+        assert!(expect
+            .assert_eq_nopanic_imp(b"0123456789ABCDEF", &mut report)
+            .is_err());
+        assert!(String::from_utf8(report).unwrap().contains("Chunks differ"));
+
+        // An edited index is stale
+        let index = fs::read_to_string(&index_path).unwrap();
+        let first_chunk = super::sha256::to_hex(&super::sha256::sha256(b"0123"));
+        fs::write(&index_path, index.replace(&first_chunk, &"0".repeat(64))).unwrap();
+        assert!(expect.assert_eq_nopanic_imp(data, &mut Vec::new()).is_ok());
+        let mut report = Vec::new();
+        assert!(expect
+            .assert_eq_nopanic_imp(b"0123456789ABCDEF", &mut report)
+            .is_err());
+        assert!(!String::from_utf8(report).unwrap().contains("Chunks differ"));
+        fs::write(&index_path, index).unwrap();
+
+        // So is the index of a file whose length changed
+        fs::write(&expect.path, b"0123456789abcdef!").unwrap();
+        assert!(expect.assert_eq_nopanic_imp(data, &mut Vec::new()).is_err());
+
+        fs::remove_file(&expect.path).unwrap();
+        fs::remove_file(index_path).unwrap();
+
+        String::from_utf8(buf).expect("Only printing strings")
+    };
+
+    expect_test::expect_file!["test_data/chunk_index.ansi.bin"].assert_eq(&actual);
+}
+
+#[test]
+fn fails_described_offset() {
+    let actual = {
+        let _guard = ENVVAR_MUTATION.read().unwrap();
+        let expect = expect_file!["test_data/example"]
+            .describe_offsets(|offset| format!("record {}, field `payload`", offset / 4));
+
+        let mut buf = Vec::new();
+        assert!(expect.assert_eq_nopanic_imp(b"example!", &mut buf).is_err());
+        String::from_utf8(buf).expect("Only printing strings")
+    };
+
+    expect_test::expect_file!["test_data/fails_described_offset.ansi.bin"].assert_eq(&actual);
+}
+
+#[test]
+fn fails_decoded_diff() {
+    let actual = {
+        let _guard = ENVVAR_MUTATION.read().unwrap();
+        let expect = expect_file!["test_data/example"]
+            .decode_diff(|data, offset| {
+                let byte = data.get(offset)?;
+                Some(format!("flags {:?}", [byte & 1 != 0, byte & 2 != 0]))
+            })
+            // Only decodes lowercase letters
+            .decode_diff(|data, offset| {
+                let byte = data.get(offset)?;
+                byte.is_ascii_lowercase()
+                    .then(|| format!("letter #{}", byte - b'a'))
+            });
+
+        let mut buf = Vec::new();
+        assert!(expect
+            .assert_eq_nopanic_imp(b"exbmple\n", &mut buf)
+            .is_err());
+        // Past the end of the file
+        assert!(expect
+            .assert_eq_nopanic_imp(b"example\n!", &mut buf)
+            .is_err());
+        String::from_utf8(buf).expect("Only printing strings")
+    };
+
+    expect_test::expect_file!["test_data/fails_decoded_diff.ansi.bin"].assert_eq(&actual);
+}
+
+#[test]
+fn fails_slice() {
+    let actual = {
+        let _guard = ENVVAR_MUTATION.read().unwrap();
+        let expect = expect_file!["test_data/shared"].slice(4, 4);
+
+        let mut buf = Vec::new();
+        assert!(expect.assert_eq_nopanic_imp(b"4567", &mut buf).is_ok());
+        assert!(expect.assert_eq_nopanic_imp(b"45x7", &mut buf).is_err());
+        String::from_utf8(buf).expect("Only printing strings")
+    };
+
+    expect_test::expect_file!["test_data/fails_slice.ansi.bin"].assert_eq(&actual);
+}
+
+#[test]
+fn slice_update() {
+    let _guard = ENVVAR_MUTATION.write().unwrap();
+    let path = expect_file!["test_data/shared_update"].path;
+    fs::write(&path, b"0123456789").unwrap();
+
+    let mut buf = Vec::new();
+    std::env::set_var(UPDATE_EXPECT_VAR_NAME, "");
+    // Windows of the same file don't conflict
+    let first = expect_file!["test_data/shared_update"].slice(2, 2);
+    assert!(first.assert_eq_nopanic_imp(b"ab", &mut buf).is_ok());
+    let second = expect_file!["test_data/shared_update"].slice(6, 2);
+    assert!(second.assert_eq_nopanic_imp(b"cde", &mut buf).is_ok());
+    std::env::remove_var(UPDATE_EXPECT_VAR_NAME);
+    assert_eq!(fs::read(&path).unwrap(), b"01ab45cde89");
+
+    fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn compressed_slice_update() {
+    use super::compression::{self, Format};
+
+    let _guard = ENVVAR_MUTATION.read().unwrap();
+    let path =
+        std::env::temp_dir().join(format!("expect-test-bytes-slice-{}.gz", std::process::id()));
+    fs::write(&path, compression::compress(Format::Gzip, b"hello world\n")).unwrap();
+
+    // Not public API!
+    let expect = super::ExpectFile::new(path.clone()).slice(6, 5);
+    assert!(expect
+        .clone()
+        .force_update()
+        .assert_eq_nopanic_imp(b"earth", &mut Vec::new())
+        .is_ok());
+    assert!(expect
+        .assert_eq_nopanic_imp(b"earth", &mut Vec::new())
+        .is_ok());
+    let stored = fs::read(&path).unwrap();
+    assert_eq!(compression::detect(&stored), Some(Format::Gzip));
+    assert_eq!(
+        compression::decompress(Format::Gzip, &stored).unwrap(),
+        b"hello earth\n"
+    );
+
+    fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn fails_log() {
+    let actual = {
+        let _guard = ENVVAR_MUTATION.read().unwrap();
+        let mut buf = Vec::new();
+
+        let mut log = expect_log!["test_data/events.log"];
+        assert!(log.append_assert_nopanic_imp(b"first\n", &mut buf).is_ok());
+        assert!(log.append_assert_nopanic_imp(b"second\n", &mut buf).is_ok());
+        assert!(log.finish_nopanic_imp(&mut buf).is_ok());
+        assert_eq!(buf, b"");
+
+        let mut log = expect_log!["test_data/events.log"];
+        assert!(log.append_assert_nopanic_imp(b"first\n", &mut buf).is_ok());
+        assert!(log
+            .append_assert_nopanic_imp(b"sec0nd\n", &mut buf)
+            .is_err());
+
+        let mut log = expect_log!["test_data/events.log"];
+        assert!(log.append_assert_nopanic_imp(b"first\n", &mut buf).is_ok());
+        assert!(log.finish_nopanic_imp(&mut buf).is_err());
+        String::from_utf8(buf).expect("Only printing strings")
+    };
+
+    expect_test::expect_file!["test_data/fails_log.ansi.bin"].assert_eq(&actual);
+}
+
+#[test]
+fn log_update() {
+    let _guard = ENVVAR_MUTATION.write().unwrap();
+    let path = expect_file!["test_data/events_update.log"].path;
+    fs::write(&path, b"old\nlog\nrecords\n").unwrap();
+
+    let mut buf = Vec::new();
+    std::env::set_var(UPDATE_EXPECT_VAR_NAME, "");
+    let mut log = expect_log!["test_data/events_update.log"];
+    assert!(log.append_assert_nopanic_imp(b"new\n", &mut buf).is_ok());
+    assert!(log.append_assert_nopanic_imp(b"log\n", &mut buf).is_ok());
+    assert!(log.finish_nopanic_imp(&mut buf).is_ok());
+    std::env::remove_var(UPDATE_EXPECT_VAR_NAME);
+    assert_eq!(fs::read(&path).unwrap(), b"new\nlog\n");
+
+    fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn fails_corpus() {
+    let actual = {
+        let _guard = ENVVAR_MUTATION.read().unwrap();
+        let expect = expect_file!["test_data/corpus"];
+        let entries = [("a.bin", b"aaa"), ("b.bin", b"bxb"), ("c.bin", b"ccc")];
+
+        let mut buf = Vec::new();
+        assert!(expect
+            .assert_all_nopanic_imp(entries[..1].iter().copied(), &mut buf)
+            .is_ok());
+        assert!(expect.assert_all_nopanic_imp(entries, &mut buf).is_err());
+        assert_eq!(expect.path, expect_file!["test_data/corpus"].path);
+        String::from_utf8(buf).expect("Only printing strings")
+    };
+
+    expect_test::expect_file!["test_data/fails_corpus.ansi.bin"].assert_eq(&actual);
+}
+
+#[test]
+fn fails_group() {
+    let actual = {
+        let _guard = ENVVAR_MUTATION.read().unwrap();
+        let expect = expect_file!["test_data/group.etbc"];
+        let body: Vec<u8> = (0..0x20).collect();
+        let mut changed = body.clone();
+        changed[0x11] = 0xff;
+
+        let mut buf = Vec::new();
+        let entries = [
+            ("header", &b"HDR\x01"[..]),
+            ("body", &body),
+            ("trailer", b"END"),
+        ];
+        assert!(expect.assert_group_nopanic_imp(&entries, &mut buf).is_ok());
+        let (header, body, trailer) = (entries[0], entries[1], entries[2]);
+        assert!(expect
+            .assert_group_nopanic_imp(&[body, header, trailer], &mut buf)
+            .is_err());
+        assert!(expect
+            .assert_group_nopanic_imp(
+                &[header, ("body", &changed), ("crc", b"\x12\x34")],
+                &mut buf
+            )
+            .is_err());
+        String::from_utf8(buf).expect("Only printing strings")
+    };
+
+    expect_test::expect_file!["test_data/fails_group.ansi.bin"].assert_eq(&actual);
+}
+
+#[test]
+fn fails_path() {
+    let actual = {
+        let _guard = ENVVAR_MUTATION.read().unwrap();
+        let expect = expect_file!["test_data/cert.der"];
+        let path = std::env::temp_dir().join(format!(
+            "expect-test-bytes-actual-{}.der",
+            std::process::id()
+        ));
+        let mut data = fs::read("src/test_data/cert.der").unwrap();
+
+        let mut buf = Vec::new();
+        fs::write(&path, &data).unwrap();
+        assert!(expect.assert_eq_path_nopanic_imp(&path, &mut buf).is_ok());
+        data[0x120] ^= 0xff;
+        fs::write(&path, &data).unwrap();
+        assert!(expect.assert_eq_path_nopanic_imp(&path, &mut buf).is_err());
+        fs::write(&path, &data[..0x80]).unwrap();
+        assert!(expect.assert_eq_path_nopanic_imp(&path, &mut buf).is_err());
+        fs::remove_file(&path).unwrap();
+        String::from_utf8(buf).expect("Only printing strings")
+    };
+
+    expect_test::expect_file!["test_data/fails_path.ansi.bin"].assert_eq(&actual);
+}
+
+#[test]
+fn fails_reader() {
+    let actual = {
+        let _guard = ENVVAR_MUTATION.read().unwrap();
+        let expect = expect_file!["test_data/cert.der"];
+        let mut data = fs::read("src/test_data/cert.der").unwrap();
+
+        let mut buf = Vec::new();
+        assert!(expect
+            .assert_eq_reader_nopanic_imp(&mut &data[..], &mut buf)
+            .is_ok());
+        data[0x120] ^= 0xff;
+        assert!(expect
+            .assert_eq_reader_nopanic_imp(&mut &data[..], &mut buf)
+            .is_err());
+        assert!(expect
+            .assert_eq_reader_nopanic_imp(&mut &data[..0x80], &mut buf)
+            .is_err());
+        String::from_utf8(buf).expect("Only printing strings")
+    };
+
+    // Streamed like a file
+    expect_test::expect_file!["test_data/fails_path.ansi.bin"].assert_eq(&actual);
+}
+
+#[test]
+fn fails_seekable() {
+    use std::io::{Cursor, Seek, SeekFrom};
+
+    let actual = {
+        let _guard = ENVVAR_MUTATION.read().unwrap();
+        let expect = expect_file!["test_data/cert.der"];
+        // The data starts at the current position
+        let mut data = b"header".to_vec();
+        data.extend(fs::read("src/test_data/cert.der").unwrap());
+        let seekable = |data: &[u8]| {
+            let mut cursor = Cursor::new(data.to_vec());
+            cursor.seek(SeekFrom::Start(6)).unwrap();
+            cursor
+        };
+
+        let mut buf = Vec::new();
+        assert!(expect
+            .assert_eq_seekable_nopanic_imp(&mut seekable(&data), &mut buf)
+            .is_ok());
+        data[6 + 0x120] ^= 0xff;
+        assert!(expect
+            .assert_eq_seekable_nopanic_imp(&mut seekable(&data), &mut buf)
+            .is_err());
+        assert!(expect
+            .assert_eq_seekable_nopanic_imp(&mut seekable(&data[..6 + 0x80]), &mut buf)
+            .is_err());
+        String::from_utf8(buf).expect("Only printing strings")
+    };
+
+    // Streamed like a file
+    expect_test::expect_file!["test_data/fails_path.ansi.bin"].assert_eq(&actual);
+}
+
+#[test]
+fn fails_vectored() {
+    use std::io::IoSlice;
+
+    let actual = {
+        let _guard = ENVVAR_MUTATION.read().unwrap();
+        let expect = expect_file!["test_data/cert.der"];
+        let mut data = fs::read("src/test_data/cert.der").unwrap();
+
+        let mut buf = Vec::new();
+        let (head, tail) = data.split_at(0x100);
+        assert!(expect
+            .assert_eq_segments_nopanic_imp([head, tail].iter(), "segment", &mut buf)
+            .is_ok());
+        data[0x120] ^= 0xff;
+        let (head, tail) = data.split_at(0x100);
+        let segments = [IoSlice::new(head), IoSlice::new(&[]), IoSlice::new(tail)];
+        assert!(expect
+            .assert_eq_segments_nopanic_imp(segments.iter().map(|s| &**s), "segment", &mut buf)
+            .is_err());
+        super::failure::take();
+        assert!(expect
+            .assert_eq_segments_nopanic_imp(
+                [&data[..0x40], &data[0x40..0x80]].iter(),
+                "segment",
+                &mut buf
+            )
+            .is_err());
+        String::from_utf8(buf).expect("Only printing strings")
+    };
+
+    expect_test::expect_file!["test_data/fails_vectored.ansi.bin"].assert_eq(&actual);
+}
+
+#[test]
+fn chunks() {
+    let _guard = ENVVAR_MUTATION.read().unwrap();
+    let expect = expect_file!["test_data/example"];
+    let chunks = || "ex am ple\n".split(' ').map(String::from);
+    assert!(expect
+        .assert_eq_segments_nopanic_imp(chunks(), "chunk", &mut Vec::new())
+        .is_ok());
+
+    let mut buf = Vec::new();
+    assert!(expect
+        .assert_eq_segments_nopanic_imp(chunks().chain(Some("s".to_owned())), "chunk", &mut buf)
+        .is_err());
+    super::failure::take();
+    assert!(String::from_utf8(buf)
+        .unwrap()
+        .contains("note\x1b[0m: the first difference is at offset 0x0 of chunk 3"));
+}
+
+#[cfg(feature = "bytes")]
+#[test]
+fn buf() {
+    use bytes::{Buf, Bytes, BytesMut};
+
+    let _guard = ENVVAR_MUTATION.read().unwrap();
+    let expect = expect_file!["test_data/example"];
+    let mut frames = BytesMut::from(&b"example\nnext"[..]);
+    let mut frame = frames.split_to(8);
+    assert!(expect
+        .assert_eq_buf_nopanic_imp(&mut frame, &mut Vec::new())
+        .is_ok());
+    assert!(!frame.has_remaining());
+    assert_eq!(frames, b"next"[..]);
+
+    let mut buf = Vec::new();
+    let mut frame = Bytes::from_static(b"exam").chain(&b"pel\n"[..]);
+    assert!(expect
+        .assert_eq_buf_nopanic_imp(&mut frame, &mut buf)
+        .is_err());
+    super::failure::take();
+    assert!(!frame.has_remaining());
+    assert!(String::from_utf8(buf)
+        .unwrap()
+        .contains("note\x1b[0m: the first difference is at offset 0x1 of chunk 1"));
+}
+
+#[test]
+fn reader_across_reads() {
+    let _guard = ENVVAR_MUTATION.read().unwrap();
+    let path =
+        std::env::temp_dir().join(format!("expect-test-bytes-reader-{}", std::process::id()));
+    let actual_path = path.with_extension("actual");
+    let expect = super::ExpectFile::new(path.clone());
+    let data: Vec<u8> = (0..200_000_u32).map(|i| (i % 251) as u8).collect();
+    fs::write(&path, &data).unwrap();
+
+    // The window around the difference starts in the previous read
+    for diff_idx in [0x10002, 150_000] {
+        let mut changed = data.clone();
+        changed[diff_idx] ^= 0xff;
+        changed.truncate(190_000);
+        fs::write(&actual_path, &changed).unwrap();
+        let mut from_reader = Vec::new();
+        assert!(expect
+            .assert_eq_reader_nopanic_imp(&mut &changed[..], &mut from_reader)
+            .is_err());
+        let mut from_path = Vec::new();
+        assert!(expect
+            .assert_eq_path_nopanic_imp(&actual_path, &mut from_path)
+            .is_err());
+        assert_eq!(from_reader, from_path);
+    }
+
+    fs::remove_file(&path).unwrap();
+    fs::remove_file(&actual_path).unwrap();
+}
+
+#[test]
+#[cfg(unix)]
+fn succeeds_fd() {
+    use std::os::unix::io::AsRawFd;
+
+    let _guard = ENVVAR_MUTATION.read().unwrap();
+    let file = fs::File::open("src/test_data/example").unwrap();
+    // SAFETY: The file is open and only read here
+    unsafe { expect_file!["test_data/example"].assert_eq_fd(file.as_raw_fd()) };
+    // The descriptor is left open
+    assert!(file.metadata().is_ok());
+}
+
+#[test]
+fn fails_seed() {
+    let actual = {
+        let _guard = ENVVAR_MUTATION.read().unwrap();
+        let mut buf = Vec::new();
+        for seed in [7, 8] {
+            let expect = expect_file!["test_data/seeded"].with_seed(seed);
+            assert!(expect.assert_eq_nopanic_imp(b"seedef\n", &mut buf).is_err());
+        }
+        // No seed was recorded
+        let expect = expect_file!["test_data/example"].with_seed(3);
+        assert!(expect
+            .assert_eq_nopanic_imp(b"exbmple\n", &mut buf)
+            .is_err());
+        String::from_utf8(buf).expect("Only printing strings")
+    };
+
+    expect_test::expect_file!["test_data/fails_seed.ansi.bin"].assert_eq(&actual);
+}
+
+#[test]
+fn updates_seed() {
+    let _guard = ENVVAR_MUTATION.write().unwrap();
+    std::env::set_var(UPDATE_EXPECT_VAR_NAME, "");
+
+    let expect = expect_file!["test_data/seed_created"].with_seed(42);
+    assert!(expect
+        .assert_eq_nopanic_imp(b"random\n", &mut Vec::new())
+        .is_ok());
+    std::env::remove_var(UPDATE_EXPECT_VAR_NAME);
+
+    let seed_path = super::seed::seed_path(&expect.path);
+    assert_eq!(fs::read_to_string(&seed_path).unwrap(), "42\n");
+    fs::remove_file(&expect.path).unwrap();
+    fs::remove_file(&seed_path).unwrap();
+}
+
+#[test]
+fn updates_generations() {
+    let _guard = ENVVAR_MUTATION.write().unwrap();
+    std::env::set_var(UPDATE_EXPECT_VAR_NAME, "");
+
+    let expect = expect_file!["test_data/generations"].keep_generations(2);
+    fs::write(&expect.path, b"first\n").unwrap();
+    assert!(expect
+        .assert_eq_nopanic_imp(b"second\n", &mut Vec::new())
+        .is_ok());
+    std::env::remove_var(UPDATE_EXPECT_VAR_NAME);
+
+    let generation = |n| super::generations::generation_path(&expect.path, n);
+    assert_eq!(fs::read(generation(1)).unwrap(), b"first\n");
+
+    // Further updates of the same run would conflict
+    super::generations::rotate(&expect.path, b"second\n", 2).unwrap();
+    super::generations::rotate(&expect.path, b"third\n", 2).unwrap();
+    assert_eq!(fs::read(generation(1)).unwrap(), b"third\n");
+    assert_eq!(fs::read(generation(2)).unwrap(), b"second\n");
+    assert!(!generation(3).exists());
+
+    // Generations past a smaller setting are removed
+    super::generations::rotate(&expect.path, b"fourth\n", 1).unwrap();
+    assert_eq!(fs::read(generation(1)).unwrap(), b"fourth\n");
+    assert!(!generation(2).exists());
+
+    fs::remove_file(&expect.path).unwrap();
+    fs::remove_file(generation(1)).unwrap();
+}
+
+#[test]
+fn fails_integrity() {
+    let actual = {
+        let _guard = ENVVAR_MUTATION.read().unwrap();
+        let expect = expect_file!["test_data/edited"].with_integrity();
+
+        let mut buf = Vec::new();
+        // Matching the edited file doesn't help
+        assert!(expect
+            .assert_eq_nopanic_imp(b"edited by hand\n", &mut buf)
+            .is_err());
+        String::from_utf8(buf).expect("Only printing strings")
+    };
+
+    expect_test::expect_file!["test_data/fails_integrity.ansi.bin"].assert_eq(&actual);
+}
+
+#[test]
+fn updates_integrity() {
+    let _guard = ENVVAR_MUTATION.write().unwrap();
+    std::env::set_var(UPDATE_EXPECT_VAR_NAME, "");
+
+    let expect = expect_file!["test_data/integrity_created"].with_integrity();
+    assert!(expect
+        .assert_eq_nopanic_imp(b"checked\n", &mut Vec::new())
+        .is_ok());
+    std::env::remove_var(UPDATE_EXPECT_VAR_NAME);
+
+    let integrity_path = super::integrity::integrity_path(&expect.path);
+    assert_eq!(
+        fs::read_to_string(&integrity_path).unwrap(),
+        "sha256:77c2ca150b61c7330da139378ffd3940d093f1bd74a1294689345d27e15b5124 8\n"
+    );
+    assert!(expect
+        .assert_eq_nopanic_imp(b"checked\n", &mut Vec::new())
+        .is_ok());
+    fs::remove_file(&expect.path).unwrap();
+    fs::remove_file(&integrity_path).unwrap();
+}
+
+#[test]
+fn fails_symbol_map() {
+    use super::SymbolMap;
+
+    let actual = {
+        let _guard = ENVVAR_MUTATION.read().unwrap();
+        let expect = expect_file!["test_data/symbols.o"];
+        let data = fs::read(&expect.path).unwrap();
+        let expect = expect.symbol_map(SymbolMap::from_elf(&data).unwrap());
+
+        let mut buf = Vec::new();
+        // In `mul`, `greeting` and the symbol table
+        for offset in [0x45, 0x4d, 0x60] {
+            let mut changed = data.clone();
+            changed[offset] ^= 0xff;
+            assert!(expect.assert_eq_nopanic_imp(&changed, &mut buf).is_err());
+        }
+        String::from_utf8(buf).expect("Only printing strings")
+    };
+
+    expect_test::expect_file!["test_data/fails_symbol_map.ansi.bin"].assert_eq(&actual);
+}
+
+#[test]
+fn symbol_map_parsing() {
+    use super::{SymbolMap, SymbolMapError};
+
+    let map = SymbolMap::parse("# comment\n\n0x10..0x40 .text\n10 start\n0x20 helper\n").unwrap();
+    assert_eq!(
+        map,
+        SymbolMap::new()
+            .section(0x10..0x40, ".text")
+            .symbol(0x20, "helper")
+            .symbol(0x10, "start")
+    );
+    assert_eq!(
+        map.describe(0x24).as_deref(),
+        Some("in `helper`+0x4 of section `.text`")
+    );
+    assert_eq!(map.describe(0x40), None);
+    assert_eq!(
+        SymbolMap::parse("0x10 start\nstart").unwrap_err(),
+        SymbolMapError::InvalidLine(2)
+    );
+    assert_eq!(
+        SymbolMap::from_elf(b"example\n").unwrap_err(),
+        SymbolMapError::NotElf
+    );
+}
+
+#[test]
+fn fails_suppressed() {
+    let actual = {
+        let _guard = ENVVAR_MUTATION.write().unwrap();
+        std::env::set_var(
+            super::suppressions::SUPPRESSIONS_VAR_NAME,
+            "src/test_data/suppressions",
+        );
+        let expect = expect_file!["test_data/example"];
+
+        let mut buf = Vec::new();
+        assert!(expect.assert_eq_nopanic_imp(b"exbmpLe\n", &mut buf).is_ok());
+        // Outside of the ranges
+        assert!(expect
+            .assert_eq_nopanic_imp(b"exbmplE\n", &mut buf)
+            .is_err());
+        // In the range of an expired suppression
+        assert!(expect.assert_eq_nopanic_imp(b"example!", &mut buf).is_err());
+        std::env::remove_var(super::suppressions::SUPPRESSIONS_VAR_NAME);
+        String::from_utf8(buf).expect("Only printing strings")
+    };
+
+    expect_test::expect_file!["test_data/fails_suppressed.ansi.bin"].assert_eq(&actual);
+}
+
+#[test]
+fn suppression_parsing() {
+    use super::suppressions::{parse, Date};
+
+    let dir = Path::new("src/test_data");
+    let example = dir.join("example");
+    assert_eq!(parse("example 0x0..0x1\n", dir, &example), Err(1));
+    assert_eq!(parse("\nexample 0..1 2999-12-31\n", dir, &example), Err(2));
+    let suppressions = parse("example 0x0..0x1 2999-12-3 typo\n", dir, &example).unwrap();
+    assert_eq!(suppressions[0].until, None);
+    assert_eq!(suppressions[0].reason.as_deref(), Some("2999-12-3 typo"));
+    assert_eq!(parse("other 0x0..0x1 why\n", dir, &example), Ok(Vec::new()));
+
+    assert_eq!(Date::from_days(0).to_string(), "1970-01-01");
+    assert_eq!(Date::from_days(11_016).to_string(), "2000-02-29");
+    assert_eq!(Date::from_days(20_000).to_string(), "2024-10-04");
+}
+
+#[test]
+fn update_overrides() {
+    let _guard = ENVVAR_MUTATION.write().unwrap();
+    assert!(!super::is_update_mode());
+    let expect = expect_file!["test_data/forced_created"].force_update();
+    assert!(expect
+        .assert_eq_nopanic_imp(b"forced\n", &mut Vec::new())
+        .is_ok());
+    assert_eq!(fs::read(&expect.path).unwrap(), b"forced\n");
+    fs::remove_file(&expect.path).unwrap();
+
+    std::env::set_var(UPDATE_EXPECT_VAR_NAME, "");
+    assert!(super::is_update_mode());
+    let expect = expect_file!["test_data/example"].never_update();
+    let mut buf = Vec::new();
+    assert!(expect
+        .assert_eq_nopanic_imp(b"protected\n", &mut buf)
+        .is_err());
+    std::env::remove_var(UPDATE_EXPECT_VAR_NAME);
+    assert!(String::from_utf8(buf)
+        .unwrap()
+        .contains("the file isn't updated since it's marked with `never_update()`"));
+    assert_eq!(fs::read(&expect.path).unwrap(), b"example\n");
+}
+
+#[test]
+fn read_only() {
+    use super::{set_read_only_paths, MissingPolicy};
+
+    let _guard = ENVVAR_MUTATION.write().unwrap();
+    std::env::set_var(UPDATE_EXPECT_VAR_NAME, "");
+    let expect = expect_file!["test_data/example"].read_only().force_update();
+    let mut buf = Vec::new();
+    assert!(expect
+        .assert_eq_nopanic_imp(b"protected\n", &mut buf)
+        .is_err());
+    assert!(String::from_utf8(buf)
+        .unwrap()
+        .contains("the file isn't written since it's read-only"));
+    let expect = expect_file!["test_data/read_only_created"]
+        .read_only()
+        .on_missing(MissingPolicy::Create);
+    assert!(expect
+        .assert_eq_nopanic_imp(b"new\n", &mut Vec::new())
+        .is_err());
+    assert!(!expect.path.exists());
+
+    set_read_only_paths(["src/test_data"]);
+    let result =
+        expect_file!["test_data/example"].assert_eq_nopanic_imp(b"bulk\n", &mut Vec::new());
+    set_read_only_paths(Vec::<PathBuf>::new());
+    std::env::remove_var(UPDATE_EXPECT_VAR_NAME);
+    assert!(result.is_err());
+    assert_eq!(fs::read("src/test_data/example").unwrap(), b"example\n");
+}
+
+#[test]
+fn update_modes() {
+    use super::{set_update_mode, update_mode, UpdateMode};
+
+    let _guard = ENVVAR_MUTATION.write().unwrap();
+    std::env::set_var(UPDATE_EXPECT_VAR_NAME, "");
+    set_update_mode(UpdateMode::Off);
+    assert!(!super::is_update_mode());
+    assert!(expect_file!["test_data/example"]
+        .assert_eq_nopanic_imp(b"changed\n", &mut Vec::new())
+        .is_err());
+    std::env::remove_var(UPDATE_EXPECT_VAR_NAME);
+
+    set_update_mode(UpdateMode::New);
+    assert_eq!(update_mode(), UpdateMode::New);
+    assert!(expect_file!["test_data/example"]
+        .assert_eq_nopanic_imp(b"changed\n", &mut Vec::new())
+        .is_err());
+    let expect = expect_file!["test_data/new_created"];
+    assert!(expect
+        .assert_eq_nopanic_imp(b"new\n", &mut Vec::new())
+        .is_ok());
+    assert!(expect_file!["test_data/never_created"]
+        .never_update()
+        .assert_eq_nopanic_imp(b"new\n", &mut Vec::new())
+        .is_err());
+
+    set_update_mode(UpdateMode::All);
+    assert!(super::is_update_mode());
+    super::UPDATE_MODE.store(0, std::sync::atomic::Ordering::SeqCst);
+    assert_eq!(update_mode(), UpdateMode::Off);
+
+    assert_eq!(fs::read(&expect.path).unwrap(), b"new\n");
+    fs::remove_file(&expect.path).unwrap();
+    assert_eq!(fs::read("src/test_data/example").unwrap(), b"example\n");
+}
+
+#[test]
+fn fails_interleaved() {
+    let actual = {
+        let _guard = ENVVAR_MUTATION.read().unwrap();
+        let expect = expect_file!["test_data/rgba.bin"].interleaved(4, 1);
+        let data = fs::read(&expect.path).unwrap();
+
+        let mut buf = Vec::new();
+        let mut changed = data.clone();
+        changed[3 * 4 + 1] = b'g';
+        changed[5 * 4 + 3] = b'a';
+        assert!(expect.assert_eq_nopanic_imp(&changed, &mut buf).is_err());
+
+        // Two streams of 16-bit elements
+        let expect = expect_file!["test_data/rgba.bin"].interleaved(2, 2);
+        let mut changed = data.clone();
+        changed[6 * 4 + 2] = b'b';
+        assert!(expect.assert_eq_nopanic_imp(&changed, &mut buf).is_err());
+
+        // Only the partial frame at the end differs
+        let mut changed = data;
+        changed.push(b'R');
+        assert!(expect.assert_eq_nopanic_imp(&changed, &mut buf).is_err());
+        String::from_utf8(buf).expect("Only printing strings")
+    };
+
+    expect_test::expect_file!["test_data/fails_interleaved.ansi.bin"].assert_eq(&actual);
+}
+
+#[test]
+fn fails_raster() {
+    let actual = {
+        let _guard = ENVVAR_MUTATION.read().unwrap();
+        // 2x4 RGBA image
+        let expect = expect_file!["test_data/rgba.bin"].raster(2, 4);
+        let mut changed = fs::read(&expect.path).unwrap();
+        changed[5 * 4 + 1] = b'g';
+
+        let mut buf = Vec::new();
+        assert!(expect.assert_eq_nopanic_imp(&changed, &mut buf).is_err());
+        String::from_utf8(buf).expect("Only printing strings")
+    };
+
+    expect_test::expect_file!["test_data/fails_raster.ansi.bin"].assert_eq(&actual);
+}
+
+#[test]
+fn raster_coordinates() {
+    use super::raster::Raster;
+
+    let rgb = Raster {
+        width: 640,
+        bytes_per_pixel: 3,
+    };
+    assert_eq!(
+        rgb.describe((12 * 640 + 37) * 3 + 1),
+        "pixel (x=37, y=12), channel G"
+    );
+    let gray = Raster {
+        width: 8,
+        bytes_per_pixel: 1,
+    };
+    assert_eq!(gray.describe(17), "pixel (x=1, y=2)");
+    let wide = Raster {
+        width: 8,
+        bytes_per_pixel: 8,
+    };
+    assert_eq!(wide.describe(8 * 9 + 6), "pixel (x=1, y=1), byte 6");
+}
+
+#[test]
+fn diff_heatmap() {
+    let expected = vec![0; 0x4000];
+    let mut actual = expected.clone();
+    for i in (0x2100..0x2200).step_by(2) {
+        actual[i] = 1;
+    }
+    actual[0x10] = 1;
+    for chunk in [0x400, 0x900, 0xc00, 0xf00, 0x3000] {
+        actual[chunk] = 1;
+        actual[chunk + 1] = 1;
+    }
+    actual.extend_from_slice(&[0; 3]);
+
+    let mut buf = Vec::new();
+    super::heatmap::write(&mut buf, &expected, &actual, 0x400).unwrap();
+    let actual = String::from_utf8(buf).expect("Only printing strings");
+
+    assert_eq!(
+        actual,
+        "\x1b[1mDifferences by chunk\x1b[0m (0x400 bytes each): 7 of 17 chunks differ
+   chunk      offset  differing bytes
+       8      0x2000              128
+       3       0xc00                4
+      16      0x4000                3
+       1       0x400                2
+       2       0x800                2
+   ... and 2 more
+
+"
+    );
+}
+
+#[test]
+fn fails_records() {
+    let actual = {
+        let _guard = ENVVAR_MUTATION.read().unwrap();
+        let expect = expect_file!["test_data/records.bin"].records(super::LengthPrefix::U32Le);
+        let record = |data: &[u8]| {
+            let mut record = u32::try_from(data.len()).unwrap().to_le_bytes().to_vec();
+            record.extend_from_slice(data);
+            record
+        };
+
+        let mut buf = Vec::new();
+        // The second record is longer and the fourth changed
+        let changed: Vec<u8> = [
+            &b"first"[..],
+            b"second record, longer",
+            b"3rd",
+            b"Fourth",
+            b"five",
+        ]
+        .iter()
+        .flat_map(|data| record(data))
+        .collect();
+        assert!(expect.assert_eq_nopanic_imp(&changed, &mut buf).is_err());
+
+        // A missing record
+        let changed: Vec<u8> = [&b"first"[..], b"second record", b"3rd", b"fourth"]
+            .iter()
+            .flat_map(|data| record(data))
+            .collect();
+        assert!(expect.assert_eq_nopanic_imp(&changed, &mut buf).is_err());
+        String::from_utf8(buf).expect("Only printing strings")
+    };
+
+    expect_test::expect_file!["test_data/fails_records.ansi.bin"].assert_eq(&actual);
+}
+
+#[test]
+fn fails_varint() {
+    let actual = {
+        let _guard = ENVVAR_MUTATION.read().unwrap();
+        let expect = expect_file!["test_data/varints.bin"].decode_varints();
+        let data = fs::read(&expect.path).unwrap();
+
+        let mut buf = Vec::new();
+        // 300 became 16684, one byte longer
+        let mut changed = data.clone();
+        changed.splice(1..3, [0xac, 0x82, 0x01]);
+        assert!(expect.assert_eq_nopanic_imp(&changed, &mut buf).is_err());
+
+        // The middle byte of 70000 changed
+        let mut changed = data;
+        changed[4] = 0xa3;
+        assert!(expect.assert_eq_nopanic_imp(&changed, &mut buf).is_err());
+        String::from_utf8(buf).expect("Only printing strings")
+    };
+
+    expect_test::expect_file!["test_data/fails_varint.ansi.bin"].assert_eq(&actual);
+}
+
+#[test]
+fn varint_decoding() {
+    use super::varint::decode;
+
+    assert_eq!(decode(&[0x00]), Some((0, 1)));
+    assert_eq!(decode(&[0xac, 0x02, 0xff]), Some((300, 2)));
+    assert_eq!(decode(&[0x80, 0x80]), None);
+    let max = [0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x01];
+    assert_eq!(decode(&max), Some((u64::MAX, 10)));
+    let overflowing = [0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x02];
+    assert_eq!(decode(&overflowing), None);
+}
+
+#[test]
+fn updates_hexdump() {
+    let _guard = ENVVAR_MUTATION.write().unwrap();
+    std::env::set_var(UPDATE_EXPECT_VAR_NAME, "");
+
+    let expect = expect_file!["test_data/hexdump_created"].with_hexdump();
+    assert!(expect
+        .assert_eq_nopanic_imp(b"companion dump\n\x00\x01\x02", &mut Vec::new())
+        .is_ok());
+    std::env::remove_var(UPDATE_EXPECT_VAR_NAME);
+
+    let dump_path = super::xxd::dump_path(&expect.path);
+    assert_eq!(
+        fs::read_to_string(&dump_path).unwrap(),
+        "00000000: 636f 6d70 616e 696f 6e20 6475 6d70 0a00  companion dump..\n\
+         00000010: 0102                                     ..\n"
+    );
+    fs::remove_file(&expect.path).unwrap();
+    fs::remove_file(&dump_path).unwrap();
+}
+
+#[test]
+fn fails_line_endings() {
+    let actual = {
+        let _guard = ENVVAR_MUTATION.read().unwrap();
+        let mut buf = Vec::new();
+        // As if the file was checked out with CRLF line endings
+        let expect = expect_file!["test_data/crlf.txt"];
+        assert!(expect
+            .assert_eq_nopanic_imp(b"line one\nline two\n", &mut buf)
+            .is_err());
+        // Not only line endings differ
+        assert!(expect
+            .assert_eq_nopanic_imp(b"line one\nline 2\n", &mut buf)
+            .is_err());
+        // Compared with the whole file
+        let expect = expect_file!["test_data/example"].with_diff_style(DiffStyle::Unified);
+        assert!(expect
+            .assert_eq_nopanic_imp(b"example\r\n", &mut buf)
+            .is_err());
+        String::from_utf8(buf).expect("Only printing strings")
+    };
+
+    expect_test::expect_file!["test_data/fails_line_endings.ansi.bin"].assert_eq(&actual);
+}
+
+#[test]
+fn byte_window_ends_with_data() {
+    // Not public API!
+    use super::render::write_byte_diff;
+    let mut buf = Vec::new();
+    // The window would reach past the end of both
+    write_byte_diff(&mut buf, (b"example!", 0), (b"example?", 0), 7);
+    // The window would reach past the end of the shorter one
+    write_byte_diff(&mut buf, (b"example\n", 0), (b"example\n more", 0), 8);
+    let actual = String::from_utf8(buf).unwrap();
+    assert!(actual.contains("Expect: 6d 70 6c 65 \x1b[32m21\x1b[0m mple!\n"));
+    assert!(actual.contains("Actual: 6d 70 6c 65 \x1b[31m3f\x1b[0m mple?\n"));
+    assert!(actual.contains("Expect: 70 6c 65 0a ple_\n"));
+    assert!(actual.contains("Actual: 70 6c 65 0a \x1b[31m20\x1b[0m 6d 6f 72 65 ple_ more\n"));
+}
+
+#[test]
+fn line_endings_ruled_out_early() {
+    // Not public API!
+    use super::line_endings::could_be_converted;
+    let actual = b"line one\nline two\n";
+    // A CR was added where the first difference is
+    assert!(could_be_converted(20, Some(b'\r'), actual, 8));
+    // The lengths can't come from converting line endings
+    assert!(!could_be_converted(18, Some(b'\r'), actual, 8));
+    assert!(!could_be_converted(50, Some(b'\r'), actual, 8));
+    // The first difference isn't at a line ending
+    assert!(!could_be_converted(20, Some(b'x'), actual, 0));
+}
+
+#[test]
+fn fixture_window() {
+    use super::{ExpectFile, Fixture};
+
+    let _guard = ENVVAR_MUTATION.read().unwrap();
+    let data = fs::read("src/test_data/cert.der").unwrap();
+    let fixture = Fixture::new("src/test_data/cert.der");
+    assert_eq!(fixture.read().unwrap(), data);
+    let window = fixture.clone().window(0x10, 0x20);
+    assert_eq!(window.read().unwrap(), &data[0x10..0x30]);
+
+    let past_end = fixture.window(data.len() - 4, 8).read().unwrap_err();
+    assert_eq!(past_end.kind(), std::io::ErrorKind::UnexpectedEof);
+    let missing = Fixture::new("src/test_data/missing.img")
+        .read()
+        .unwrap_err();
+    assert_eq!(missing.kind(), std::io::ErrorKind::NotFound);
+
+    let expect = ExpectFile::from(window);
+    let mut buf = Vec::new();
+    assert!(expect
+        .assert_eq_nopanic_imp(&data[0x10..0x30], &mut buf)
+        .is_ok());
+    assert!(expect
+        .assert_eq_nopanic_imp(&data[0x11..0x31], &mut buf)
+        .is_err());
+}
+
+#[test]
+fn fails_embedded() {
+    let actual = {
+        let _guard = ENVVAR_MUTATION.read().unwrap();
+        let expect = crate::expect_bytes_file!["test_data/example"];
+        assert_eq!(expect.data, b"example\n");
+
+        let mut buf = Vec::new();
+        assert!(expect.assert_eq_nopanic_imp(b"example\n", &mut buf).is_ok());
+        assert!(expect
+            .assert_eq_nopanic_imp(b"exa- not this\n", &mut buf)
+            .is_err());
+        String::from_utf8(buf).expect("Only printing strings")
+    };
+
+    // The same report as for the file
+    expect_test::expect_file!["test_data/fails_different.ansi.bin"].assert_eq(&actual);
+}
+
+#[test]
+fn regression_corpus() {
+    let dir = std::env::temp_dir().join(format!(
+        "expect-test-bytes-regressions-{}",
+        std::process::id()
+    ));
+    let corpus = super::RegressionCorpus::new(&dir);
+    assert_eq!(corpus.inputs().unwrap(), Vec::new());
+
+    let path = corpus.record(b"\xff\x00").unwrap();
+    assert_eq!(corpus.record(b"\xff\x00").unwrap(), path);
+    corpus.record(b"").unwrap();
+    let inputs = corpus.inputs().unwrap();
+    assert_eq!(inputs.len(), 2);
+    assert!(inputs.contains(&(path.clone(), b"\xff\x00".to_vec())));
+
+    corpus.replay(|_| {});
+    let failure =
+        std::panic::catch_unwind(|| corpus.replay(|input| assert_eq!(input, b""))).unwrap_err();
+    assert_eq!(
+        failure.downcast_ref::<String>(),
+        Some(&format!(
+            "1 of 2 regression inputs failed:\n    {}",
+            path.display()
+        ))
+    );
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn corpus_stable() {
+    let _guard = ENVVAR_MUTATION.read().unwrap();
+    let mut inputs = Vec::new();
+    expect_file!["test_data/corpus_lengths"].assert_corpus_stable(
+        "src/test_data/corpus",
+        |input| {
+            inputs.push(input.to_vec());
+            input.len().to_string()
+        },
+    );
+    assert_eq!(inputs, [b"aaa", b"bbb"]);
+}
+
+#[test]
+fn incremental_compare() {
+    use super::incremental::{compare, Incremental};
+
+    let path = std::env::temp_dir().join(format!(
+        "expect-test-bytes-incremental-{}",
+        std::process::id()
+    ));
+    let data: Vec<u8> = (0..200_000_u32).map(|i| (i % 251) as u8).collect();
+    fs::write(&path, &data).unwrap();
+
+    assert!(matches!(
+        compare(&path, &data),
+        Ok(Some(Incremental::Equal))
+    ));
+    let mut changed = data.clone();
+    changed[150_000] ^= 0xff;
+    let mut changed_late = data.clone();
+    changed_late[199_990] ^= 0xff;
+    // The common suffix is only found if the window reaches the end of the file, so that an early
+    // mismatch doesn't read the rest of it
+    for (actual, expected_diff_idx, expected_suffix) in [
+        (&changed[..], 150_000, None),
+        (&changed_late[..], 199_990, Some(9)),
+        (&data[..100_000], 100_000, None),
+        (&[&data[..], b"more"].concat()[..], 200_000, Some(0)),
+    ] {
+        match compare(&path, actual) {
+            Ok(Some(Incremental::Different {
+                diff_idx,
+                window,
+                window_offset,
+                expected_len,
+                common_suffix,
+            })) => {
+                assert_eq!(diff_idx, expected_diff_idx);
+                assert_eq!(common_suffix, expected_suffix);
+                assert_eq!(expected_len, data.len());
+                assert_eq!(window, &data[window_offset..data.len().min(diff_idx + 16)]);
+            }
+            _ => panic!("expected a difference"),
+        }
+    }
+
+    fs::remove_file(&path).unwrap();
+    assert!(matches!(compare(&path, &data), Ok(None)));
+}
+
+#[test]
+fn split_into_parts() {
+    let _guard = ENVVAR_MUTATION.write().unwrap();
+    let expect = expect_file!["test_data/split"].split_into_parts(4);
+    // Not public API!
+    let part = |n| super::parts::part_path(&expect.path, n);
+
+    let mut buf = Vec::new();
+    std::env::set_var(UPDATE_EXPECT_VAR_NAME, "");
+    assert!(expect
+        .assert_eq_nopanic_imp(b"0123456789", &mut buf)
+        .is_ok());
+    std::env::remove_var(UPDATE_EXPECT_VAR_NAME);
+    assert!(!expect.path.exists());
+    assert_eq!(fs::read(part(2)).unwrap(), b"89");
+    assert!(!part(3).exists());
+
+    assert!(expect
+        .assert_eq_nopanic_imp(b"0123456789", &mut buf)
+        .is_ok());
+    assert!(expect
+        .assert_eq_nopanic_imp(b"012345678", &mut buf)
+        .is_err());
+
+    for n in 0..3 {
+        fs::remove_file(part(n)).unwrap();
+    }
+
+    // Small enough to fit in one file, replacing stale parts
+    let expect = expect_file!["test_data/split_small"].split_into_parts(4);
+    let part = super::parts::part_path(&expect.path, 0);
+    fs::write(&part, b"stale").unwrap();
+    std::env::set_var(UPDATE_EXPECT_VAR_NAME, "");
+    assert!(expect.assert_eq_nopanic_imp(b"0123", &mut buf).is_ok());
+    std::env::remove_var(UPDATE_EXPECT_VAR_NAME);
+    assert!(!part.exists());
+    assert_eq!(fs::read(&expect.path).unwrap(), b"0123");
+
+    fs::remove_file(&expect.path).unwrap();
+}
+
+#[test]
+fn conflicting_update() {
+    let actual = {
+        let _guard = ENVVAR_MUTATION.write().unwrap();
+        std::env::set_var(UPDATE_EXPECT_VAR_NAME, "");
+
+        let expect = expect_file!["test_data/conflicting"];
+
+        let mut buf = Vec::new();
+        assert!(expect.assert_eq_nopanic_imp(b"first\n", &mut buf).is_ok());
+        assert!(expect.assert_eq_nopanic_imp(b"first\n", &mut buf).is_ok());
+        assert!(expect.assert_eq_nopanic_imp(b"second\n", &mut buf).is_err());
+
+        // Not public API!
+        fs::remove_file(&expect.path).unwrap();
+
+        std::env::remove_var(UPDATE_EXPECT_VAR_NAME);
+
+        String::from_utf8(buf).expect("Only printing strings")
+    };
+
+    expect_test::expect_file!["test_data/conflicting_update.ansi.bin"].assert_eq(&actual);
+}
+
+#[cfg(unix)]
+#[test]
+fn run_id() {
+    let _guard = ENVVAR_MUTATION.read().unwrap();
+    // Not public API!
+    let stat = "1234 (a) b (c) R 1 1234 1234 0 -1 4194304 104 0 0 0 0 0 0 0 20 0 1 0 1188692 0\n";
+    assert_eq!(super::run::start_time(stat), Some(1_188_692));
+    assert_eq!(super::run::start_time("1234 (truncated"), None);
+
+    if std::env::var_os(super::run::RUN_ID_VAR_NAME).is_none() {
+        let id = super::run::id();
+        assert!(id.starts_with("group-"));
+        assert_eq!(super::run::id(), id);
+    }
+}
+
+#[test]
+fn run_coordination() {
+    let _guard = ENVVAR_MUTATION.write().unwrap();
+    let id = format!("test-{}", std::process::id());
+    std::env::set_var(crate::run::RUN_ID_VAR_NAME, &id);
+
+    // As if each call came from another test process of the run
+    assert_eq!(crate::run::first_in_run("help-test"), Some(true));
+    assert_eq!(crate::run::first_in_run("help-test"), Some(false));
+
+    assert!(crate::run::record_update("conflicting", &[1; 32]));
+    assert!(crate::run::record_update("conflicting", &[1; 32]));
+    assert!(!crate::run::record_update("conflicting", &[2; 32]));
+
+    // The lock is released when dropped
+    drop(crate::run::lock().unwrap());
+    drop(crate::run::lock().unwrap());
+
+    // Another holder waits for the lock however long it's held, and takes it once released
+    let lock_path = std::env::temp_dir()
+        .join(format!("expect-test-bytes-run-{id}"))
+        .join("update.lock");
+    let held = crate::run::lock().unwrap();
+    let (sender, receiver) = std::sync::mpsc::channel();
+    let waiter = std::thread::spawn(move || {
+        let lock = crate::run::lock_at(&lock_path);
+        sender.send(()).unwrap();
+        lock.is_some()
+    });
+    assert!(receiver.recv_timeout(Duration::from_millis(50)).is_err());
+    drop(held);
+    assert!(receiver.recv_timeout(Duration::from_secs(10)).is_ok());
+    assert!(waiter.join().unwrap());
+
+    std::env::remove_var(crate::run::RUN_ID_VAR_NAME);
+    assert_eq!(crate::run::first_in_run("help-test"), None);
+    fs::remove_dir_all(std::env::temp_dir().join(format!("expect-test-bytes-run-{id}"))).unwrap();
+}
+
+#[test]
+fn pending_snapshots() {
+    let dir =
+        std::env::temp_dir().join(format!("expect-test-bytes-pending-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let snap = dir.join("frame.snap.bin");
+    let plain = dir.join("frame.bin");
+    fs::write(&snap, b"old\n").unwrap();
+    fs::write(&plain, b"old\n").unwrap();
+    {
+        let _guard = ENVVAR_MUTATION.write().unwrap();
+        std::env::set_var(super::pending::PENDING_VAR_NAME, "");
+
+        let expect = super::ExpectFile::new(snap.clone()).at(super::Position {
+            file: "src/tests.rs",
+            line: 12,
+            column: 5,
+        });
+        assert!(expect
+            .assert_eq_nopanic_imp(b"new\n", &mut Vec::new())
+            .is_err());
+        let expect = super::ExpectFile::new(plain.clone());
+        assert!(expect
+            .assert_eq_nopanic_imp(b"new\n", &mut Vec::new())
+            .is_err());
+        // Missing files are pending too
+        let expect = super::ExpectFile::new(dir.join("missing.bin"));
+        assert!(expect
+            .assert_eq_nopanic_imp(b"new\n", &mut Vec::new())
+            .is_err());
+
+        std::env::remove_var(super::pending::PENDING_VAR_NAME);
+    }
+
+    assert_eq!(
+        fs::read_to_string(dir.join("frame.snap.new")).unwrap(),
+        "---\nsource: src/tests.rs\nassertion_line: 12\nextension: bin\nsnapshot_kind: binary\n---\n"
+    );
+    assert_eq!(fs::read(dir.join("frame.snap.new.bin")).unwrap(), b"new\n");
+    assert_eq!(fs::read(dir.join("frame.bin.new")).unwrap(), b"new\n");
+    assert_eq!(fs::read(dir.join("missing.bin.new")).unwrap(), b"new\n");
+    // The files themselves are left alone
+    assert_eq!(fs::read(&snap).unwrap(), b"old\n");
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn claims_registry() {
+    use super::claims::claim_in;
+
+    let dir = std::env::temp_dir().join(format!("expect-test-bytes-claims-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let path = std::path::Path::new("src/test_data/frame.bin");
+
+    assert_eq!(claim_in(&dir, "tests::a", path, &[1; 32]), None);
+    // A test may change its own contents
+    assert_eq!(claim_in(&dir, "tests::a", path, &[3; 32]), None);
+    assert_eq!(claim_in(&dir, "tests::b", path, &[3; 32]), None);
+    assert_eq!(
+        claim_in(&dir, "tests::c", path, &[2; 32]),
+        Some("tests::a".to_owned())
+    );
+    let other = std::path::Path::new("src/test_data/other.bin");
+    assert_eq!(claim_in(&dir, "tests::c", other, &[2; 32]), None);
+
+    // Tests of the same name in other packages of the workspace are other tests
+    let test = super::claims::test_id("tests::a");
+    assert_eq!(test, "expect-test-bytes/expect_test_bytes::tests::a");
+    let shared = std::path::Path::new("src/test_data/shared.bin");
+    assert_eq!(claim_in(&dir, &test, shared, &[1; 32]), None);
+    assert_eq!(claim_in(&dir, &test, shared, &[2; 32]), None);
+    assert_eq!(
+        claim_in(
+            &dir,
+            "other-package/other_package::tests::a",
+            shared,
+            &[3; 32]
+        ),
+        Some(test)
+    );
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn update_mirror() {
+    let dir = std::env::temp_dir().join(format!("expect-test-bytes-mirror-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let generated = dir.join("generated.bin");
+    let mirror = dir.join("mirror.bin");
+    fs::write(&generated, b"old\n").unwrap();
+    {
+        let _guard = ENVVAR_MUTATION.write().unwrap();
+        let expect = super::ExpectFile::new(generated.clone()).update_mirror(mirror.clone());
+        assert!(expect
+            .assert_eq_nopanic_imp(b"old\n", &mut Vec::new())
+            .is_ok());
+
+        std::env::set_var(UPDATE_EXPECT_VAR_NAME, "");
+        let mut buf = Vec::new();
+        assert!(expect.assert_eq_nopanic_imp(b"new\n", &mut buf).is_ok());
+        std::env::remove_var(UPDATE_EXPECT_VAR_NAME);
+        assert!(String::from_utf8(buf).unwrap().contains("mirror.bin"));
+    }
+    assert_eq!(fs::read(&generated).unwrap(), b"old\n");
+    assert_eq!(fs::read(&mirror).unwrap(), b"new\n");
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn root_override() {
+    let _guard = ENVVAR_MUTATION.write().unwrap();
+    std::env::set_var(super::ROOT_VAR_NAME, "/sandbox/root");
+    let relative = expect_file!["test_data/example"];
+    let absolute = expect_file!["/absolute/example"];
+    std::env::remove_var(super::ROOT_VAR_NAME);
+
+    assert_eq!(
+        relative.path,
+        std::path::Path::new("/sandbox/root/src/test_data/example")
+    );
+    assert_eq!(absolute.path, std::path::Path::new("/absolute/example"));
+}
+
+#[test]
+fn update_overlay_fallback() {
+    let _guard = ENVVAR_MUTATION.write().unwrap();
+    let overlay_dir = std::env::temp_dir().join(format!(
+        "expect-test-bytes-overlay-test-{}",
+        std::process::id()
+    ));
+    std::env::set_var(super::overlay::OVERLAY_DIR_VAR_NAME, &overlay_dir);
+
+    // `example` is a file, so this can't be written
+    let path = std::path::Path::new("src/test_data/example/unwritable");
+    let mut buf = Vec::new();
+    super::write_or_overlay(path, b"example\n", &mut buf);
+
+    std::env::remove_var(super::overlay::OVERLAY_DIR_VAR_NAME);
+
+    let written = super::overlay::mirror_path(&overlay_dir, path);
+    assert_eq!(fs::read(&written).unwrap(), b"example\n");
+    let script = fs::read_to_string(overlay_dir.join(super::overlay::APPLY_SCRIPT_NAME)).unwrap();
+    assert!(script.starts_with("#!/bin/sh\n"));
+    assert!(script.contains("unwritable'\n"));
+
+    // The script of a previous run is started over
+    let script_path = overlay_dir.join(super::overlay::APPLY_SCRIPT_NAME);
+    fs::write(
+        &script_path,
+        "#!/bin/sh\nset -e\n# run previous\ncp old 'stale'\n",
+    )
+    .unwrap();
+    std::env::set_var(super::overlay::OVERLAY_DIR_VAR_NAME, &overlay_dir);
+    super::write_or_overlay(path, b"example\n", &mut buf);
+    std::env::remove_var(super::overlay::OVERLAY_DIR_VAR_NAME);
+    let script = fs::read_to_string(&script_path).unwrap();
+    assert!(!script.contains("stale"));
+    assert_eq!(script.matches("unwritable'\n").count(), 1);
+
+    fs::remove_dir_all(overlay_dir).unwrap();
+}
+
+#[test]
+fn update_patch_fallback() {
+    let _guard = ENVVAR_MUTATION.write().unwrap();
+    let script_path = std::env::temp_dir().join(format!(
+        "expect-test-bytes-patch-test-{}.sh",
+        std::process::id()
+    ));
+    std::env::set_var(super::patch::PATCH_VAR_NAME, &script_path);
+    // The script of an earlier run is started over
+    fs::write(&script_path, "#!/bin/sh\nset -e\n# run previous\nstale\n").unwrap();
+
+    // `example` is a file, so these can't be written
+    let path = std::path::Path::new("src/test_data/example/unwritable");
+    let mut buf = Vec::new();
+    super::write_or_overlay(path, b"example\n", &mut buf);
+    super::write_or_overlay(&path.with_extension("bin"), &[0xff; 100], &mut buf);
+
+    // Nothing can be written, so the commands are in the report
+    std::env::set_var(super::patch::PATCH_VAR_NAME, path.join("patch.sh"));
+    let mut report = Vec::new();
+    super::write_or_overlay(path, b"ex", &mut report);
+
+    std::env::remove_var(super::patch::PATCH_VAR_NAME);
+
+    let script = fs::read_to_string(&script_path).unwrap();
+    assert!(script.starts_with("#!/bin/sh\nset -e\n"));
+    assert!(!script.contains("stale"));
+    // Paths are relative to the package, so the script applies on other machines
+    let package = env!("CARGO_MANIFEST_DIR");
+    assert!(!script.contains(package));
+    assert!(script.contains(
+        "base64 -d > 'src/test_data/example/unwritable' <<'EXPECT_BYTES_EOF'\nZXhhbXBsZQo=\n\
+         EXPECT_BYTES_EOF\n"
+    ));
+    let encoded = format!("{}\n{}w==\n", "/".repeat(76), "/".repeat(57));
+    assert!(script.contains(&encoded));
+    let report = String::from_utf8(report).unwrap();
+    assert!(report.contains(&format!(
+        "run these commands to apply the update:\n\ncd '{package}'\nmkdir -p "
+    )));
+    assert!(report.ends_with("\nZXg=\nEXPECT_BYTES_EOF\n"));
+
+    if cfg!(unix) {
+        let checkout = std::env::temp_dir().join(format!(
+            "expect-test-bytes-patch-checkout-{}",
+            std::process::id()
+        ));
+        fs::create_dir_all(&checkout).unwrap();
+        let status = std::process::Command::new("sh")
+            .arg(&script_path)
+            .arg(&checkout)
+            .status()
+            .unwrap();
+        assert!(status.success());
+        assert_eq!(
+            fs::read(checkout.join("src/test_data/example/unwritable")).unwrap(),
+            b"example\n"
+        );
+        fs::remove_dir_all(checkout).unwrap();
+    }
+
+    fs::remove_file(script_path).unwrap();
+}
+
+#[test]
+fn update_dir() {
+    let _guard = ENVVAR_MUTATION.write().unwrap();
+    let update_dir = std::env::temp_dir().join(format!(
+        "expect-test-bytes-update-dir-test-{}",
+        std::process::id()
+    ));
+    std::env::set_var(super::overlay::UPDATE_DIR_VAR_NAME, &update_dir);
+
+    let path = std::path::Path::new("src/test_data/example");
+    let mut buf = Vec::new();
+    super::write_or_overlay(path, b"updated\n", &mut buf);
+    assert!(super::remove_or_overlay(path).unwrap().is_some());
+    assert!(super::remove_or_overlay(&path.with_extension("missing"))
+        .unwrap()
+        .is_none());
+
+    std::env::remove_var(super::overlay::UPDATE_DIR_VAR_NAME);
+
+    // The file in place is neither written nor removed
+    assert_eq!(fs::read(path).unwrap(), b"example\n");
+    let written = super::overlay::mirror_path(&update_dir, path);
+    assert_eq!(fs::read(&written).unwrap(), b"updated\n");
+    let script = fs::read_to_string(update_dir.join(super::overlay::APPLY_SCRIPT_NAME)).unwrap();
+    let commands: Vec<_> = script.lines().skip(3).collect();
+    assert_eq!(commands.len(), 2);
+    assert!(commands[0].starts_with("mkdir -p ") && commands[0].ends_with("example'"));
+    assert!(commands[1].starts_with("rm -f '") && commands[1].ends_with("example'"));
+    assert!(String::from_utf8(buf)
+        .unwrap()
+        .contains("EXPECT_BYTES_UPDATE_DIR is set"));
+
+    fs::remove_dir_all(update_dir).unwrap();
+}
+
+#[test]
+fn update_hook() {
+    use std::sync::{Arc, Mutex};
+
+    let _guard = ENVVAR_MUTATION.write().unwrap();
+    std::env::set_var(UPDATE_EXPECT_VAR_NAME, "");
+
+    let calls = Arc::new(Mutex::new(Vec::new()));
+    let expect = expect_file!["test_data/update_hook"].on_update({
+        let calls = Arc::clone(&calls);
+        move |_path, old, new| {
+            calls
+                .lock()
+                .unwrap()
+                .push((old.map(<[u8]>::to_vec), new.to_vec()));
+        }
+    });
+
+    let mut buf = Vec::new();
+    assert!(expect.assert_eq_nopanic_imp(b"old\n", &mut buf).is_ok());
+    // Not public API!
+    fs::write(&expect.path, b"edited\n").unwrap();
+    assert!(expect.assert_eq_nopanic_imp(b"old\n", &mut buf).is_ok());
+
+    // Not public API!
+    fs::remove_file(&expect.path).unwrap();
+
+    std::env::remove_var(UPDATE_EXPECT_VAR_NAME);
+
+    assert_eq!(
+        *calls.lock().unwrap(),
+        [
+            (None, b"old\n".to_vec()),
+            (Some(b"edited\n".to_vec()), b"old\n".to_vec())
+        ]
+    );
+}
+
+#[test]
+fn fails_validation() {
+    let actual = {
+        let _guard = ENVVAR_MUTATION.read().unwrap();
+        let expect = expect_file!["test_data/example"].validate(|actual| {
+            if actual.starts_with(b"exa") {
+                Ok(())
+            } else {
+                Err("invalid header magic".to_owned())
+            }
+        });
+
+        let mut buf = Vec::new();
+        assert!(expect.assert_eq_nopanic_imp(b"example\n", &mut buf).is_ok());
+        assert!(expect
+            .assert_eq_nopanic_imp(b"elpmaxe\n", &mut buf)
+            .is_err());
+        String::from_utf8(buf).expect("Only printing strings")
+    };
+
+    expect_test::expect_file!["test_data/fails_validation.ansi.bin"].assert_eq(&actual);
+}
+
+#[test]
+fn prefix_and_suffix() {
+    let actual = {
+        let _guard = ENVVAR_MUTATION.read().unwrap();
+        let expect = expect_file!["test_data/example"];
+
+        let mut buf = Vec::new();
+        assert!(expect
+            .assert_part_nopanic_imp(b"exam", Part::Prefix, &mut buf)
+            .is_ok());
+        assert!(expect
+            .assert_part_nopanic_imp(b"ple\n", Part::Suffix, &mut buf)
+            .is_ok());
+        assert!(expect
+            .assert_part_nopanic_imp(b"ample\n", Part::Prefix, &mut buf)
+            .is_err());
+        assert!(expect
+            .assert_part_nopanic_imp(b"mpl!\n", Part::Suffix, &mut buf)
+            .is_err());
+        String::from_utf8(buf).expect("Only printing strings")
+    };
+
+    expect_test::expect_file!["test_data/prefix_and_suffix.ansi.bin"].assert_eq(&actual);
+}
+
+#[test]
+fn prefix_update_keeps_rest() {
+    assert_eq!(
+        Part::Prefix.merge(Some(b"example\n"), b"EXA"),
+        &b"EXAmple\n"[..]
+    );
+    assert_eq!(
+        Part::Suffix.merge(Some(b"example\n"), b"LE\n"),
+        &b"exampLE\n"[..]
+    );
+    assert_eq!(
+        Part::Prefix.merge(Some(b"exa"), b"example\n"),
+        &b"example\n"[..]
+    );
+}
+
+#[test]
+fn contains() {
+    let actual = {
+        let _guard = ENVVAR_MUTATION.read().unwrap();
+        let expect = expect_file!["test_data/example"];
+
+        let mut buf = Vec::new();
+        assert!(expect
+            .assert_contains_nopanic_imp(b"an example\n!", true, &mut buf)
+            .is_ok());
+        assert!(expect
+            .assert_contains_nopanic_imp(b"amp", false, &mut buf)
+            .is_ok());
+        assert!(expect
+            .assert_contains_nopanic_imp(b"an exam\n", true, &mut buf)
+            .is_err());
+        assert!(expect
+            .assert_contains_nopanic_imp(b"ample!", false, &mut buf)
+            .is_err());
+        String::from_utf8(buf).expect("Only printing strings")
+    };
+
+    expect_test::expect_file!["test_data/contains.ansi.bin"].assert_eq(&actual);
+}
+
+#[test]
+fn fails_shifted() {
+    let actual = {
+        let _guard = ENVVAR_MUTATION.read().unwrap();
+        let expect = expect_file!["test_data/example"];
+
+        let mut buf = Vec::new();
+        assert!(expect
+            .assert_eq_nopanic_imp(b"exa--mple\n", &mut buf)
+            .is_err());
+        String::from_utf8(buf).expect("Only printing strings")
+    };
+
+    expect_test::expect_file!["test_data/fails_shifted.ansi.bin"].assert_eq(&actual);
+}
+
+// The `similar` feature adds a list of hunks
+#[cfg(not(feature = "similar"))]
+#[test]
+fn fails_side_by_side() {
+    let actual = {
+        let _guard = ENVVAR_MUTATION.read().unwrap();
+        let expect = expect_file!["test_data/example"].with_diff_style(DiffStyle::SideBySide);
+
+        let mut buf = Vec::new();
+        assert!(expect
+            .assert_eq_nopanic_imp(b"exa- not this\n", &mut buf)
+            .is_err());
+        String::from_utf8(buf).expect("Only printing strings")
+    };
+
+    expect_test::expect_file!["test_data/fails_side_by_side.ansi.bin"].assert_eq(&actual);
+}
+
+#[test]
+fn fails_unified() {
+    let actual = {
+        let _guard = ENVVAR_MUTATION.read().unwrap();
+        let expect = expect_file!["test_data/example"].with_diff_style(DiffStyle::Unified);
+
+        let mut buf = Vec::new();
+        assert!(expect
+            .assert_eq_nopanic_imp(b"exa- not this\n", &mut buf)
+            .is_err());
+        String::from_utf8(buf).expect("Only printing strings")
+    };
+
+    expect_test::expect_file!["test_data/fails_unified.ansi.bin"].assert_eq(&actual);
+}
+
+#[test]
+fn unified_diff() {
+    let lines = |s: &str| s.lines().map(str::to_owned).collect::<Vec<_>>();
+    let diff = super::unified::unified_diff(
+        &lines("a\nb\nc\nd\ne\nf\ng\nh\ni"),
+        &lines("a\nB\nc\nd\ne\nf\ng\nh\ni\nj"),
+    );
+    assert_eq!(
+        diff,
+        "@@ -1,4 +1,4 @@\n a\n-b\n+B\n c\n d\n@@ -8,2 +8,3 @@\n h\n i\n+j\n"
+    );
+}
+
+#[cfg(feature = "hexyl")]
+#[test]
+fn fails_hexyl() {
+    let actual = {
+        let _guard = ENVVAR_MUTATION.read().unwrap();
+        let expect = expect_file!["test_data/cert.der"].with_diff_style(DiffStyle::Hexyl);
+        let mut changed = fs::read("src/test_data/cert.der").unwrap();
+        changed[0x45] ^= 0xff;
+
+        let mut buf = Vec::new();
+        assert!(expect.assert_eq_nopanic_imp(&changed, &mut buf).is_err());
+        String::from_utf8(buf).expect("Only printing strings")
+    };
+
+    expect_test::expect_file!["test_data/fails_hexyl.ansi.bin"].assert_eq(&actual);
+}
+
+#[cfg(feature = "similar")]
+#[test]
+fn byte_hunks() {
+    let hunks = super::hunks::byte_hunks(b"example\n", b"ex-ample!\n");
+    let ranges: Vec<_> = hunks
+        .iter()
+        .map(|hunk| (hunk.expected.clone(), hunk.actual.clone()))
+        .collect();
+    assert_eq!(ranges, [(2..2, 2..3), (7..7, 8..9)]);
+}
+
+#[test]
+fn spills_long_report() {
+    let _guard = ENVVAR_MUTATION.read().unwrap();
+    let report = b"1\n2\n3\n4\n";
+
+    let mut buf = Vec::new();
+    super::pager::print_report(report, 4, None, &mut buf);
+    assert_eq!(buf, report);
+
+    buf.clear();
+    super::pager::print_report(report, 2, None, &mut buf);
+    let printed = String::from_utf8(buf).unwrap();
+    let path = printed
+        .strip_prefix("1\n2\n… 2 more lines, full report written to ")
+        .unwrap()
+        .trim_end();
+    assert_eq!(fs::read(path).unwrap(), report);
+    fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn caps_report_size() {
+    let _guard = ENVVAR_MUTATION.read().unwrap();
+    let report = b"header\n\nhunk 1\nline\n\nhunk 2\n\nhunk 3\n";
+
+    let mut buf = Vec::new();
+    super::pager::print_report(report, 200, Some(report.len()), &mut buf);
+    assert_eq!(buf, report);
+
+    // Cut at the blank line before the second hunk
+    buf.clear();
+    super::pager::print_report(report, 200, Some(22), &mut buf);
+    assert_eq!(
+        String::from_utf8(buf).unwrap(),
+        "header\n\nhunk 1\nline\n\n… output truncated (2 more hunks)\n"
+    );
+
+    // Cut at a line without a blank line before it
+    let mut buf = Vec::new();
+    super::pager::print_report(b"line 1\nline 2\nline 3\n", 200, Some(10), &mut buf);
+    assert_eq!(
+        String::from_utf8(buf).unwrap(),
+        "line 1\n… output truncated (1 more hunk)\n"
+    );
+}
+
+#[test]
+fn stable_output_isnt_capped() {
+    let _guard = ENVVAR_MUTATION.write().unwrap();
+    let report = b"header\n\nhunk 1\nline\n\nhunk 2\n\nhunk 3\n";
+
+    std::env::set_var(super::stable::STABLE_OUTPUT_VAR_NAME, "");
+    let mut buf = Vec::new();
+    super::pager::print_report(report, 200, Some(22), &mut buf);
+    std::env::remove_var(super::stable::STABLE_OUTPUT_VAR_NAME);
+    assert_eq!(buf, report);
+}
+
+#[test]
+fn forces_color() {
+    let report = b"\x1b[1m\x1b[93mnote\x1b[0m: byte 0x2\n";
+
+    let mut colored = Vec::new();
+    super::output::with_color(&mut colored, true)
+        .write_all(report)
+        .unwrap();
+    assert_eq!(colored, report);
+
+    let mut plain = Vec::new();
+    super::output::with_color(&mut plain, false)
+        .write_all(report)
+        .unwrap();
+    assert_eq!(plain, b"note: byte 0x2\n");
+}
+
+#[test]
+fn forced_color_report() {
+    let actual = {
+        let _guard = ENVVAR_MUTATION.read().unwrap();
+        let expect = expect_file!["test_data/example"];
+
+        let mut buf = Vec::new();
+        let mut stream = super::output::with_color(&mut buf, true);
+        assert!(expect
+            .assert_eq_nopanic_imp(b"exa- not this\n", &mut stream)
+            .is_err());
+        drop(stream);
+        String::from_utf8(buf).expect("Only printing strings")
+    };
+
+    // Not public API!
+    let error = super::style::ERROR;
+    assert!(actual.contains(&format!("{error}error{error:#}")));
+    // Forcing colors passes the styled report through unchanged
+    expect_test::expect_file!["test_data/fails_different.ansi.bin"].assert_eq(&actual);
+}
+
+#[test]
+fn container_rejects_unknown_version() {
+    use super::{Container, ContainerError};
+
+    let mut bytes = Container::new().with_entry("a", *b"1").to_bytes();
+    let parsed = Container::parse(&bytes).unwrap();
+    assert_eq!(parsed.get("a"), Some(&b"1"[..]));
+    assert!(parsed.tool().unwrap().starts_with("expect-test-bytes "));
+
+    bytes[4] = 2;
+    assert_eq!(
+        Container::parse(&bytes),
+        Err(ContainerError::UnsupportedVersion(2))
+    );
+    assert_eq!(Container::parse(b"ETB"), Err(ContainerError::BadMagic));
+    assert_eq!(
+        Container::parse(&bytes[..6]),
+        Err(ContainerError::UnsupportedVersion(2))
+    );
+}
+
+#[test]
+fn missing_policy() {
+    use super::MissingPolicy;
+
+    let actual = {
+        let _guard = ENVVAR_MUTATION.read().unwrap();
+
+        let mut buf = Vec::new();
+        let expect = expect_file!["test_data/missing"].on_missing(MissingPolicy::Skip);
+        assert!(expect.assert_eq_nopanic_imp(b"example\n", &mut buf).is_ok());
+        assert!(!expect.path.exists());
+
+        let expect = expect_file!["test_data/bootstrapped"].on_missing(MissingPolicy::Create);
+        assert!(expect.assert_eq_nopanic_imp(b"example\n", &mut buf).is_ok());
+        // Not public API!
+        assert_eq!(fs::read(&expect.path).unwrap(), b"example\n");
+        fs::remove_file(&expect.path).unwrap();
+
+        String::from_utf8(buf).expect("Only printing strings")
+    };
+
+    expect_test::expect_file!["test_data/missing_policy.ansi.bin"].assert_eq(&actual);
+}
+
+#[test]
+fn missing_policy_create_and_fail() {
+    use super::MissingPolicy;
+
+    let actual = {
+        let _guard = ENVVAR_MUTATION.read().unwrap();
+
+        let mut buf = Vec::new();
+        let expect = expect_file!["test_data/blessed"].on_missing(MissingPolicy::CreateAndFail);
+        assert!(expect
+            .assert_eq_nopanic_imp(b"example\n", &mut buf)
+            .is_err());
+        // Not public API!
+        assert_eq!(fs::read(&expect.path).unwrap(), b"example\n");
+
+        // The next run checks the created file
+        assert!(expect.assert_eq_nopanic_imp(b"example\n", &mut buf).is_ok());
+        assert!(expect
+            .assert_eq_nopanic_imp(b"changed\n", &mut Vec::new())
+            .is_err());
+        fs::remove_file(&expect.path).unwrap();
+
+        String::from_utf8(buf).expect("Only printing strings")
+    };
+
+    expect_test::expect_file!["test_data/missing_policy_create_and_fail.ansi.bin"]
+        .assert_eq(&actual);
+}
+
+#[test]
+fn xfail() {
+    let actual = {
+        let _guard = ENVVAR_MUTATION.read().unwrap();
+        let expect = expect_file!["test_data/example"].xfail("issue #123");
+
+        let mut buf = Vec::new();
+        let result = expect.assert_eq_nopanic_imp(b"example!", &mut buf);
+        assert!(expect.apply_xfail(result, &mut buf).is_ok());
+        let result = expect.assert_eq_nopanic_imp(b"example\n", &mut buf);
+        assert!(expect.apply_xfail(result, &mut buf).is_err());
+        String::from_utf8(buf).expect("Only printing strings")
+    };
+
+    expect_test::expect_file!["test_data/xfail.ansi.bin"].assert_eq(&actual);
+}
+
+#[test]
+fn batch_summary() {
+    let mut buf = Vec::new();
+    super::batch::write_summary(&[], &mut buf).unwrap();
+    assert_eq!(buf, b"");
+
+    super::batch::write_summary(&example_updates(), &mut buf).unwrap();
+    let actual = String::from_utf8(buf).expect("Only printing strings");
+
+    expect_test::expect_file!["test_data/batch_summary.ansi.bin"].assert_eq(&actual);
+}
+
+fn example_updates() -> [super::stats::Update; 3] {
+    let update = |path: &str, len, created| super::stats::Update {
+        path: PathBuf::from(path),
+        len,
+        created,
+    };
+    [
+        update("src/test_data/example", 8, false),
+        update("src/test_data/disk.img", 1_048_576, true),
+        update("src/test_data/header.bin", 64, false),
+    ]
+}
+
+#[test]
+fn update_totals() {
+    let mut buf = Vec::new();
+    super::stats::write_totals(&[], &mut buf).unwrap();
+    assert_eq!(buf, b"");
+
+    super::stats::write_totals(&example_updates(), &mut buf).unwrap();
+    let actual = String::from_utf8(buf).expect("Only printing strings");
+
+    expect_test::expect_file!["test_data/update_totals.ansi.bin"].assert_eq(&actual);
+}
+
+#[test]
+fn update_totals_color() {
+    use super::stats::combine_color;
+
+    assert_eq!(combine_color(None, None), None);
+    assert_eq!(combine_color(None, Some(true)), Some(true));
+    assert_eq!(combine_color(Some(true), None), Some(true));
+    // Plain wins, whichever assertion asked for it
+    assert_eq!(combine_color(Some(true), Some(false)), Some(false));
+    assert_eq!(combine_color(Some(false), Some(true)), Some(false));
+    assert_eq!(combine_color(Some(false), None), Some(false));
+
+    let mut buf = Vec::new();
+    // Not public API!
+    super::stats::write_totals(
+        &example_updates(),
+        &mut super::output::with_color(&mut buf, false),
+    )
+    .unwrap();
+    assert!(!buf.contains(&b'\x1b'));
+}
+
+#[cfg(not(feature = "anstream"))]
+#[test]
+fn no_color() {
+    let _guard = ENVVAR_MUTATION.write().unwrap();
+    let print = || {
+        let mut buf = Vec::new();
+        // Not public API!
+        super::output::auto(&mut buf)
+            .write_all(b"\x1b[1mbold\x1b[0m")
+            .unwrap();
+        buf
+    };
+    std::env::remove_var("NO_COLOR");
+    assert_eq!(print(), b"\x1b[1mbold\x1b[0m");
+    std::env::set_var("NO_COLOR", "");
+    assert_eq!(print(), b"\x1b[1mbold\x1b[0m");
+    std::env::set_var("NO_COLOR", "1");
+    assert_eq!(print(), b"bold");
+    std::env::remove_var("NO_COLOR");
+}
+
+#[test]
+fn fails_with_backtrace() {
+    let payload = |backtrace| {
+        std::panic::catch_unwind(|| {
+            super::panic_hook::fail(
+                "src/test_data/example".to_owned(),
+                super::failure::Details::default(),
+                backtrace,
+            )
+        })
+        .unwrap_err()
+    };
+
+    assert!(payload(false).is::<()>());
+    assert_eq!(
+        payload(true).downcast_ref::<String>().map(String::as_str),
+        Some("expect test failed: src/test_data/example")
+    );
+}
+
+#[test]
+#[ignore = "run by `aborts_on_failure` in a child process"]
+fn abort_on_failure_child() {
+    expect_file!["test_data/example"].assert_eq(b"changed\n");
+    unreachable!("the process should have exited");
+}
+
+#[test]
+fn aborts_on_failure() {
+    let output = std::process::Command::new(std::env::current_exe().unwrap())
+        .args(["--exact", "tests::abort_on_failure_child", "--ignored"])
+        .env(super::panic_hook::ABORT_VAR_NAME, "")
+        .env_remove(UPDATE_EXPECT_VAR_NAME)
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(101));
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(
+        stderr.contains("src/test_data/example failed and EXPECT_BYTES_ABORT_ON_FAILURE is set")
+    );
+    assert!(!stderr.contains("should have exited"));
+}
+
+#[test]
+fn assertion_metrics() {
+    let _guard = ENVVAR_MUTATION.read().unwrap();
+    super::ExpectFile::new(PathBuf::from("src/test_data/./example")).assert_eq(b"example\n");
+
+    let metrics = super::assertion_metrics();
+    let metrics = metrics
+        .iter()
+        .find(|metrics| metrics.location == "src/test_data/./example")
+        .unwrap();
+    assert_eq!(metrics.test.as_deref(), Some("tests::assertion_metrics"));
+    assert_eq!(metrics.bytes_compared, 8);
+    assert_eq!(metrics.outcome, super::Outcome::Passed);
+    let aggregate = super::aggregate_metrics();
+    assert!(aggregate.assertions >= 1);
+    assert!(aggregate.bytes_compared >= 8);
+    assert!(aggregate.duration >= metrics.duration);
+}
+
+#[test]
+fn inline_assertion_metrics() {
+    let _guard = ENVVAR_MUTATION.read().unwrap();
+    let data = vec![0; 1000];
+    let before = super::aggregate_metrics();
+    super::assert_len(&data, 1000);
+    super::assert_sha256(
+        &data,
+        "541b3e9daa09b20bf85fa273e5cbd3e80185aa4ec298e765db87742b70138a53",
+    );
+
+    let metrics = super::assertion_metrics();
+    let compared: Vec<_> = metrics
+        .iter()
+        .filter(|metrics| metrics.test.as_deref() == Some("tests::inline_assertion_metrics"))
+        .map(|metrics| metrics.bytes_compared)
+        .collect();
+    // The lengths of the data, not of its description
+    assert_eq!(compared, [1000, 1000]);
+    assert!(super::aggregate_metrics().bytes_compared >= before.bytes_compared + 2000);
+}
+
+#[test]
+fn corpus_metrics() {
+    let _guard = ENVVAR_MUTATION.read().unwrap();
+    let entries = [("a.bin", b"aaa"), ("b.bin", b"bbb")];
+    expect_file!["test_data/corpus"].assert_all(entries.iter().map(|entry| {
+        std::thread::sleep(Duration::from_millis(10));
+        *entry
+    }));
+
+    let metrics: Vec<_> = super::assertion_metrics()
+        .into_iter()
+        .filter(|metrics| metrics.test.as_deref() == Some("tests::corpus_metrics"))
+        .collect();
+    assert_eq!(metrics.len(), 1);
+    // The whole comparison is measured, not only printing its report
+    assert_eq!(metrics[0].bytes_compared, 6);
+    assert!(metrics[0].duration >= Duration::from_millis(20));
+}
+
+#[test]
+fn fails_hexdump() {
+    let actual = {
+        let _guard = ENVVAR_MUTATION.read().unwrap();
+        let expect = expect_file!["test_data/text_only.xxd"];
+        assert!(expect
+            .assert_eq_hexdump_nopanic_imp(
+                b"A hex dump in a text-only repository\n\x00\x01\x02\x03",
+                &mut Vec::new()
+            )
+            .is_ok());
+
+        let mut buf = Vec::new();
+        assert!(expect
+            .assert_eq_hexdump_nopanic_imp(
+                b"A hex dump in a text-only repository\n\x00\x01\x02\x04",
+                &mut buf
+            )
+            .is_err());
+        String::from_utf8(buf).expect("Only printing strings")
+    };
+
+    expect_test::expect_file!["test_data/fails_hexdump.ansi.bin"].assert_eq(&actual);
+}
+
+#[test]
+fn hexdump_parsing() {
+    use super::xxd::parse;
+
+    let data = b"AAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAB|:";
+    assert_eq!(
+        parse(&super::xxd::dump_lines(data, 0).join("\n")),
+        Ok(data.to_vec())
+    );
+    let hexdump = "\
+00000000  41 41 41 41 41 41 41 41  41 41 41 41 41 41 41 41  |AAAAAAAAAAAAAAAA|
+*
+00000030  42 7c 3a                                          |B|:|
+00000033
+";
+    assert_eq!(parse(hexdump), Ok(data.to_vec()));
+
+    assert_eq!(parse("00000000: 4142\n00000004: 4344\n"), Err(2));
+    assert_eq!(parse("00000000: 414\n"), Err(1));
+    assert_eq!(parse("zz: 4142\n"), Err(1));
+}
+
+#[test]
+fn fallback() {
+    let _guard = ENVVAR_MUTATION.read().unwrap();
+    let expect = expect_file!["test_data/relocated"].fallback(include_bytes!("test_data/example"));
+    assert!(expect
+        .assert_eq_nopanic_imp(b"example\n", &mut Vec::new())
+        .is_ok());
+
+    let mut buf = Vec::new();
+    assert!(expect
+        .assert_eq_nopanic_imp(b"changed\n", &mut buf)
+        .is_err());
+    assert!(String::from_utf8(buf).unwrap().contains(
+        "the file couldn't be read, so the contents given with `fallback()` were checked"
+    ));
+    assert!(!expect.path.exists());
+
+    // The file takes precedence over the fallback
+    let expect = expect_file!["test_data/example"].fallback(b"fallback\n");
+    assert!(expect
+        .assert_eq_nopanic_imp(b"example\n", &mut Vec::new())
+        .is_ok());
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn canonical_json() {
+    use std::collections::HashMap;
+
+    use super::canonical_json::to_vec;
+
+    let json = |value| String::from_utf8(value).unwrap();
+    // Sorted by UTF-16 code units, where the surrogates of U+1F600 come before U+FF61
+    let map = HashMap::from([("zeta", 1), ("alpha", 2), ("\u{ff61}", 3), ("\u{1f600}", 4)]);
+    assert_eq!(
+        json(to_vec(&map).unwrap()),
+        "{\"alpha\":2,\"zeta\":1,\"\u{1f600}\":4,\"\u{ff61}\":3}"
+    );
+    assert_eq!(
+        json(to_vec(&[0.1, 1.0, -0.0, 1e21, 1.5e-7, 123_456.789]).unwrap()),
+        "[0.1,1,0,1e+21,1.5e-7,123456.789]"
+    );
+    assert_eq!(json(to_vec(&0.1f32).unwrap()), "0.1");
+    assert_eq!(
+        json(to_vec(&(Ok::<_, ()>(1), Some('x'), None::<u8>, ())).unwrap()),
+        r#"[{"Ok":1},"x",null,null]"#
+    );
+    assert_eq!(
+        json(to_vec("quote \" \\ \n \u{1}").unwrap()),
+        r#""quote \" \\ \n \u0001""#
+    );
+    assert!(to_vec(&f64::NAN).is_err());
+    assert!(to_vec(&HashMap::from([((), 1)])).is_err());
+    assert_eq!(
+        json(to_vec(&HashMap::from([(1, 1)])).unwrap()),
+        r#"{"1":1}"#
+    );
+}
+
+#[test]
+fn fails_header_format() {
+    let actual = {
+        let _guard = ENVVAR_MUTATION.read().unwrap();
+        let expect = expect_file!["test_data/example"]
+            .with_header_format("{file}({line},{column}): error EXP001: {message} [{path}] {other}")
+            // The position of the macro would change with every edit of this file
+            .at(super::Position {
+                file: "src/tests.rs",
+                line: 12,
+                column: 5,
+            });
+
+        let mut buf = Vec::new();
+        assert!(expect
+            .assert_eq_nopanic_imp(b"exbmple\n", &mut buf)
+            .is_err());
+        String::from_utf8(buf).expect("Only printing strings")
+    };
+
+    expect_test::expect_file!["test_data/fails_header_format.ansi.bin"].assert_eq(&actual);
+}
+
+#[test]
+fn fail_line() {
+    use super::failure::{take, write_line};
+
+    let mut buf = Vec::new();
+    {
+        let _guard = ENVVAR_MUTATION.read().unwrap();
+        let expect = expect_file!["test_data/example"];
+        assert!(expect
+            .assert_eq_nopanic_imp(b"exbmple\n\n", &mut Vec::new())
+            .is_err());
+    }
+    write_line(&mut buf, "src/test_data/example", take()).unwrap();
+    // The details are taken
+    write_line(&mut buf, "src/test_data/with space", take()).unwrap();
+    let actual = String::from_utf8(buf).expect("Only printing strings");
+
+    assert_eq!(
+        actual,
+        "EXPECT-FAIL path=src/test_data/example offset=0x2 expected_len=8 actual_len=9\n\
+         EXPECT-FAIL path=\"src/test_data/with space\"\n"
+    );
+}
+
+#[test]
+fn report_entries() {
+    use super::failure::take;
+    use super::report::{write_entry, Outcome};
+
+    let mut buf = Vec::new();
+    {
+        let _guard = ENVVAR_MUTATION.read().unwrap();
+        let expect = expect_file!["test_data/example"];
+        let result = expect.assert_eq_nopanic_imp(b"exbmple\n\n", &mut Vec::new());
+        let status = Outcome::of(result);
+        write_entry(
+            &mut buf,
+            Some("tests::frame"),
+            "src/test_data/example",
+            status,
+            take(),
+        )
+        .unwrap();
+
+        let result = expect.assert_eq_nopanic_imp(b"example\n", &mut Vec::new());
+        let status = Outcome::of(result);
+        write_entry(&mut buf, None, "src/test_data/\"quoted\"", status, take()).unwrap();
+    }
+    let actual = String::from_utf8(buf).expect("Only printing strings");
+
+    assert_eq!(
+        actual,
+        "{\"test\":\"tests::frame\",\"path\":\"src/test_data/example\",\"status\":\"failed\",\"first_diff\":2,\"expected_len\":8,\"actual_len\":9}\n\
+         {\"path\":\"src/test_data/\\\"quoted\\\"\",\"status\":\"passed\"}\n"
+    );
+}
+
+#[test]
+fn stable_output() {
+    use super::stable::StripAnsi;
+    use std::io::Write as _;
+
+    let report = {
+        let _guard = ENVVAR_MUTATION.read().unwrap();
+        let mut buf = Vec::new();
+        let expect = expect_file!["test_data/example"];
+        assert!(expect
+            .assert_eq_nopanic_imp(b"exbmple\n", &mut buf)
+            .is_err());
+        buf
+    };
+
+    let mut stripped = Vec::new();
+    StripAnsi::new(&mut stripped).write_all(&report).unwrap();
+    // Sequences split across writes are stripped too
+    let mut bytewise = Vec::new();
+    let mut writer = StripAnsi::new(&mut bytewise);
+    for b in &report {
+        writer.write_all(&[*b]).unwrap();
+    }
+    assert_eq!(bytewise, stripped);
+    let actual = String::from_utf8(stripped).expect("Only printing strings");
+    assert!(!actual.contains('\x1b'));
+
+    expect_test::expect_file!["test_data/stable_output.txt"].assert_eq(&actual);
+}
+
+#[test]
+fn panic_hook_summary() {
+    use super::panic_hook::{write_summary, Failure};
+
+    let mut buf = Vec::new();
+    let failure = Failure {
+        location: "src/test_data/frame.bin".to_owned(),
+        first_diff: Some(0x11),
+    };
+    write_summary(&failure, Some("tests::encode"), &mut buf).unwrap();
+    let failure = Failure {
+        location: "src/lib.rs:12:5".to_owned(),
+        first_diff: None,
+    };
+    write_summary(&failure, None, &mut buf).unwrap();
+    let actual = String::from_utf8(buf).expect("Only printing strings");
+
+    expect_test::expect_file!["test_data/panic_hook_summary.ansi.bin"].assert_eq(&actual);
+}
+
+#[test]
+fn quarantine_summary() {
+    let mut buf = Vec::new();
+    super::quarantine::write_summary(&[], &mut buf).unwrap();
+    assert_eq!(buf, b"");
+
+    let mismatches = [
+        PathBuf::from("src/test_data/example"),
+        PathBuf::from("src/test_data/example.sha256"),
+    ];
+    super::quarantine::write_summary(&mismatches, &mut buf).unwrap();
+    let actual = String::from_utf8(buf).expect("Only printing strings");
+
+    expect_test::expect_file!["test_data/quarantine_summary.ansi.bin"].assert_eq(&actual);
+}
+
+#[test]
+fn eventually_eq() {
+    let _guard = ENVVAR_MUTATION.read().unwrap();
+    let expect = expect_file!["test_data/example"];
+
+    let mut calls = 0;
+    let mut buf = Vec::new();
+    let result = expect.assert_eventually_eq_nopanic_imp(
+        Duration::from_secs(10),
+        || {
+            calls += 1;
+            if calls < 3 {
+                &b"exam"[..]
+            } else {
+                &b"example\n"[..]
+            }
+        },
+        &mut buf,
+    );
+    assert!(result.is_ok());
+    assert_eq!(calls, 3);
+
+    let result =
+        expect.assert_eventually_eq_nopanic_imp(Duration::from_millis(30), || b"exam", &mut buf);
+    assert!(result.is_err());
+    assert!(String::from_utf8(buf)
+        .unwrap()
+        .contains("expect test failed"));
+}
+
+#[test]
+fn normalizes_archives() {
+    use super::normalize;
+
+    let mut gzip = b"\x1f\x8b\x08\x00\x12\x34\x56\x78\x00\x03".to_vec();
+    normalize::gzip(&mut gzip);
+    assert_eq!(gzip, b"\x1f\x8b\x08\x00\x00\x00\x00\x00\x00\x03");
+
+    let mut a = include_bytes!("test_data/archive_1.zip").to_vec();
+    let mut b = include_bytes!("test_data/archive_2.zip").to_vec();
+    assert_ne!(a, b);
+    normalize::zip(&mut a);
+    normalize::zip(&mut b);
+    assert_eq!(a, b);
+
+    let mut not_zip = b"PK\x05\x06".to_vec();
+    normalize::zip(&mut not_zip);
+    assert_eq!(not_zip, b"PK\x05\x06");
+}
+
+#[test]
+fn scrubs_volatile_patterns() {
+    use super::normalize;
+
+    let mut data =
+        b"id=67e55044-10b1-426f-9247-bb680e5fe0c8 not-a-uuid-10b1-026f-9247-bb680e5fe0c8".to_vec();
+    normalize::uuids(&mut data);
+    assert_eq!(
+        data,
+        b"id=00000000-0000-0000-0000-000000000000 not-a-uuid-10b1-026f-9247-bb680e5fe0c8"
+    );
+
+    // 2020-05-17 12:30 UTC
+    let mut data = [
+        &[0xff][..],
+        &1_589_718_600_u64.to_le_bytes(),
+        &42_u64.to_le_bytes(),
+    ]
+    .concat();
+    normalize::unix_timestamps(&mut data);
+    assert_eq!(data, [&[0xff][..], &[0; 8], &42_u64.to_le_bytes()].concat());
+
+    let mut data = 132_340_194_000_000_000_u64.to_le_bytes().to_vec();
+    normalize::filetimes(&mut data);
+    assert_eq!(data, [0; 8]);
+}
+
+#[test]
+fn fails_pattern() {
+    use super::Pattern;
+
+    let pattern = Pattern::parse(r#""HELO" 00 (len=4) [?? 00]* ff{2,3}"#).unwrap();
+    assert!(pattern.is_match(b"HELO\0\x12\x34\x56\x78ab\0cd\0\xff\xff"));
+    assert!(!pattern.is_match(b"HELO\0\x12\x34\x56\x78ab\0\xff\xff\xff\xff"));
+    assert_eq!(Pattern::parse("01 (len=x)").unwrap_err().offset(), 8);
+    assert_eq!(Pattern::parse("01]").unwrap_err().offset(), 2);
+
+    let mut buf = Vec::new();
+    let mut check = |actual: &[u8]| {
+        super::assert_matches_nopanic_imp(
+            actual,
+            &pattern,
+            &super::Position {
+                file: "src/tests.rs",
+                line: 1,
+                column: 1,
+            },
+            &mut buf,
+        )
+    };
+    assert!(check(b"HELO\0\x12\x34\x56\x78ab\0cd\x01\xff\xff").is_err());
+    assert!(check(b"HELO\0\x12\x34").is_err());
+    assert!(check(b"HELO\0\x12\x34\x56\x78\xff\xff\xff\xff").is_err());
+    let actual = String::from_utf8(buf).expect("Only printing strings");
+
+    expect_test::expect_file!["test_data/fails_pattern.ansi.bin"].assert_eq(&actual);
+}
+
+#[test]
+fn inline_assertions() {
+    let actual = {
+        let _guard = ENVVAR_MUTATION.read().unwrap();
+        let location = &super::Position {
+            file: "src/tests.rs",
+            line: 1,
+            column: 1,
+        };
+
+        let mut buf = Vec::new();
+        let digest = "13550350a8681c84c861aac2e5b440161c2b33a3e4f302ac680ca5b686de48de";
+        assert!(super::assert_sha256_nopanic_imp(location, b"example\n", digest, &mut buf).is_ok());
+        assert!(super::assert_sha256_nopanic_imp(location, b"example!", digest, &mut buf).is_err());
+        assert!(super::assert_sha256_nopanic_imp(location, b"example!", "", &mut buf).is_err());
+        String::from_utf8(buf).expect("Only printing strings")
+    };
+
+    expect_test::expect_file!["test_data/inline_assertions.ansi.bin"].assert_eq(&actual);
+}
+
+#[test]
+fn replace_last_argument() {
+    use super::inline::replace_last_argument;
+
+    let source = "fn f() {\n    assert_len(&data[..(4)], 3);\n    g(\"(\", 1,\n    );\n}\n";
+    assert_eq!(
+        replace_last_argument(source, 2, 5, "42").unwrap(),
+        "fn f() {\n    assert_len(&data[..(4)], 42);\n    g(\"(\", 1,\n    );\n}\n"
+    );
+    assert_eq!(
+        replace_last_argument(source, 3, 5, "\"x\"").unwrap(),
+        "fn f() {\n    assert_len(&data[..(4)], 3);\n    g(\"(\", \"x\",\n    );\n}\n"
+    );
+    assert_eq!(replace_last_argument(source, 9, 1, "42"), None);
+
+    // Brackets in character literals, raw strings and comments aren't counted
+    let source = "f(')', '\\'', b'(', r#\")\"#, /* ) (/* ) */ */ x::<'a>, // )\n 1);\n";
+    assert_eq!(
+        replace_last_argument(source, 1, 1, "2").unwrap(),
+        "f(')', '\\'', b'(', r#\")\"#, /* ) (/* ) */ */ x::<'a>, // )\n 2);\n"
+    );
+    // A doubtful scan fails instead of editing the wrong span
+    assert_eq!(replace_last_argument("f(a, [b);\n", 1, 1, "2"), None);
+    assert_eq!(replace_last_argument("f(a, \"b);\n", 1, 1, "2"), None);
+}
+
+#[test]
+fn fails_stale_checksum() {
+    use super::{ChecksumAlgorithm, ChecksumField};
+
+    let actual = {
+        let _guard = ENVVAR_MUTATION.read().unwrap();
+        let expect = expect_file!["test_data/example"].checksum_field(ChecksumField::new(
+            ChecksumAlgorithm::Sum,
+            7..8,
+            0..7,
+        ));
+
+        let mut buf = Vec::new();
+        assert!(expect
+            .assert_eq_nopanic_imp(b"exbmple\n", &mut buf)
+            .is_err());
+
+        let crc32 = ChecksumField::new(ChecksumAlgorithm::Crc32, 9..13, 0..9);
+        let adler32 = ChecksumField::new(ChecksumAlgorithm::Adler32, 9..13, 0..9).big_endian();
+        super::checksum::write_stale_fields(
+            &[crc32, adler32],
+            b"123456789\0\0\0\0",
+            "the test data",
+            &mut buf,
+        );
+        String::from_utf8(buf).expect("Only printing strings")
+    };
+
+    expect_test::expect_file!["test_data/fails_stale_checksum.ansi.bin"].assert_eq(&actual);
+}
+
+#[test]
+fn fails_float_field() {
+    use super::FloatField;
+
+    let actual = {
+        let _guard = ENVVAR_MUTATION.read().unwrap();
+        // Two 12-byte records of an ID and two floats, followed by a big-endian f64
+        let expect = expect_file!["test_data/scene.bin"]
+            .float_field(FloatField::f32(4, 1e-4).repeated(12, usize::MAX))
+            .float_field(FloatField::f32(8, 1e-4).repeated(12, 2))
+            .float_field(FloatField::f64(24, 1e-9).big_endian());
+        let data = fs::read(&expect.path).unwrap();
+        let with = |offset: usize, bytes: &[u8]| {
+            let mut changed = data.clone();
+            changed[offset..offset + bytes.len()].copy_from_slice(bytes);
+            changed
+        };
+
+        let mut buf = Vec::new();
+        let close = with(16, &(-3.000_01_f32).to_le_bytes());
+        assert!(expect.assert_eq_nopanic_imp(&close, &mut buf).is_ok());
+        let close = with(24, &(2.5 + 1e-12_f64).to_be_bytes());
+        assert!(expect.assert_eq_nopanic_imp(&close, &mut buf).is_ok());
+        assert!(expect
+            .assert_eq_nopanic_imp(&with(20, &0.2_f32.to_le_bytes()), &mut buf)
+            .is_err());
+        // IDs are still compared exactly
+        assert!(expect
+            .assert_eq_nopanic_imp(&with(12, &[3]), &mut buf)
+            .is_err());
+        String::from_utf8(buf).expect("Only printing strings")
+    };
+
+    expect_test::expect_file!["test_data/fails_float_field.ansi.bin"].assert_eq(&actual);
+}
+
+#[test]
+fn fails_only_ranges() {
+    let actual = {
+        let _guard = ENVVAR_MUTATION.read().unwrap();
+        let expect = expect_file!["test_data/example"].only_ranges(&[0..2, 6..8]);
+
+        let mut buf = Vec::new();
+        assert!(expect.assert_eq_nopanic_imp(b"ex----e\n", &mut buf).is_ok());
+        assert!(expect
+            .assert_eq_nopanic_imp(b"ex----E\n", &mut buf)
+            .is_err());
+        // Ends within a range
+        assert!(expect.assert_eq_nopanic_imp(b"ex----e", &mut buf).is_err());
+        String::from_utf8(buf).expect("Only printing strings")
+    };
+
+    expect_test::expect_file!["test_data/fails_only_ranges.ansi.bin"].assert_eq(&actual);
+}
+
+#[test]
+fn excluded_checksum() {
+    use super::{ChecksumAlgorithm, ChecksumField};
+
+    let _guard = ENVVAR_MUTATION.read().unwrap();
+    let field = ChecksumField::new(ChecksumAlgorithm::Sum, 7..8, 0..7).excluded();
+    let expect = expect_file!["test_data/example"].checksum_field(field);
+
+    let mut buf = Vec::new();
+    assert!(expect
+        .assert_eq_nopanic_imp(b"example\xec", &mut buf)
+        .is_ok());
+    assert!(expect
+        .assert_eq_nopanic_imp(b"exbmple\xed", &mut buf)
+        .is_err());
+    let report = String::from_utf8(buf).unwrap();
+    assert!(report.contains("Binary files differ at byte 0x2"));
+}
+
+#[cfg(feature = "pcap")]
+#[test]
+fn fails_pcap() {
+    let actual = {
+        let _guard = ENVVAR_MUTATION.read().unwrap();
+        let mut capture = fs::read("src/test_data/capture.pcap").unwrap();
+        // Timestamp of packet #1
+        capture[24] = 0xff;
+
+        let mut buf = Vec::new();
+        let expect = expect_file!["test_data/capture.pcap"];
+        assert!(expect
+            .pcap(true)
+            .assert_eq_nopanic_imp(&capture, &mut buf)
+            .is_ok());
+        let expect = expect_file!["test_data/capture.pcap"].pcap(false);
+        assert!(expect.assert_eq_nopanic_imp(&capture, &mut buf).is_err());
+        // Payload of packet #2
+        capture[24] = 0xe8;
+        let len = capture.len();
+        capture[len - 8] = b'_';
+        assert!(expect.assert_eq_nopanic_imp(&capture, &mut buf).is_err());
+        String::from_utf8(buf).expect("Only printing strings")
+    };
+
+    expect_test::expect_file!["test_data/fails_pcap.ansi.bin"].assert_eq(&actual);
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn fails_decodes() {
+    use super::Codec;
+
+    let actual = {
+        let _guard = ENVVAR_MUTATION.read().unwrap();
+        let expect = expect_file!["test_data/decodes.bincode"];
+        let encoded = |value: &(u32, String)| bincode::serialize(value).unwrap();
+
+        let mut buf = Vec::new();
+        let value = (7, "seven".to_owned());
+        assert!(expect
+            .assert_decodes_eq_nopanic_imp::<(u32, String), _>(
+                &encoded(&value),
+                Codec::Bincode,
+                &mut buf
+            )
+            .is_ok());
+        let value = (8, "seven".to_owned());
+        assert!(expect
+            .assert_decodes_eq_nopanic_imp::<(u32, String), _>(
+                &encoded(&value),
+                Codec::Bincode,
+                &mut buf
+            )
+            .is_err());
+        assert!(expect
+            .assert_decodes_eq_nopanic_imp::<(u32, String), _>(b"\x07", Codec::Bincode, &mut buf)
+            .is_err());
+        String::from_utf8(buf).expect("Only printing strings")
+    };
+
+    expect_test::expect_file!["test_data/fails_decodes.ansi.bin"].assert_eq(&actual);
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn serialized_eq() {
+    use super::Codec;
+
+    let _guard = ENVVAR_MUTATION.read().unwrap();
+    expect_file!["test_data/serialized.postcard"]
+        .assert_serialized_eq(&(7_u32, "seven"), Codec::Postcard);
+    assert_eq!(
+        Codec::Bincode.encode(&(7_u32, "seven")).unwrap(),
+        fs::read("src/test_data/decodes.bincode").unwrap()
+    );
+}
+
+#[test]
+fn fails_riff() {
+    let actual = {
+        let _guard = ENVVAR_MUTATION.read().unwrap();
+        let expect = expect_file!["test_data/riff.wav"].riff();
+        let wav = fs::read(&expect.path).unwrap();
+
+        let mut buf = Vec::new();
+        let mut changed = wav.clone();
+        // Last byte of the name in the INFO list
+        let name = changed.windows(4).position(|w| w == b"tone").unwrap();
+        changed[name + 3] = b'y';
+        assert!(expect.assert_eq_nopanic_imp(&changed, &mut buf).is_err());
+
+        let mut changed = wav;
+        changed.truncate(changed.len() - 16 - 8);
+        changed[4] -= 16 + 8;
+        assert!(expect.assert_eq_nopanic_imp(&changed, &mut buf).is_err());
+        String::from_utf8(buf).expect("Only printing strings")
+    };
+
+    expect_test::expect_file!["test_data/fails_riff.ansi.bin"].assert_eq(&actual);
+}
+
+#[test]
+#[cfg(feature = "sqlite")]
+fn fails_sqlite() {
+    let actual = {
+        let _guard = ENVVAR_MUTATION.read().unwrap();
+        let expect = expect_file!["test_data/logical_1.sqlite"].sqlite();
+        // The same contents after inserting and deleting other rows
+        let churned = fs::read("src/test_data/logical_2.sqlite").unwrap();
+
+        let mut buf = Vec::new();
+        assert!(expect.assert_eq_nopanic_imp(&churned, &mut buf).is_ok());
+        assert_eq!(buf, b"");
+
+        let mut changed = churned;
+        let name = changed.windows(4).position(|w| w == b"bob9").unwrap();
+        changed[name + 1] = b'u';
+        assert!(expect.assert_eq_nopanic_imp(&changed, &mut buf).is_err());
+        String::from_utf8(buf).expect("Only printing strings")
+    };
+
+    expect_test::expect_file!["test_data/fails_sqlite.ansi.bin"].assert_eq(&actual);
+}
+
+#[test]
+#[cfg(feature = "arrow")]
+fn fails_arrow() {
+    let actual = {
+        let _guard = ENVVAR_MUTATION.read().unwrap();
+        // A stream with compressed buffers and three record batches
+        let expect = expect_file!["test_data/columnar_2.arrows"].arrow();
+        // The same rows in a file of two uncompressed record batches
+        let file = fs::read("src/test_data/columnar_1.arrow").unwrap();
+
+        let mut buf = Vec::new();
+        assert!(expect.assert_eq_nopanic_imp(&file, &mut buf).is_ok());
+        assert_eq!(buf, b"");
+
+        let mut changed = file;
+        let name = changed.windows(5).position(|w| w == b"carol").unwrap();
+        changed[name] = b'k';
+        assert!(expect.assert_eq_nopanic_imp(&changed, &mut buf).is_err());
+        String::from_utf8(buf).expect("Only printing strings")
+    };
+
+    expect_test::expect_file!["test_data/fails_arrow.ansi.bin"].assert_eq(&actual);
+}
+
+#[test]
+#[cfg(feature = "parquet")]
+fn fails_parquet() {
+    let actual = {
+        let _guard = ENVVAR_MUTATION.read().unwrap();
+        // Four row groups with different codecs, page versions and encodings
+        let expect = expect_file!["test_data/columnar_2.parquet"].parquet();
+        // The same rows in two uncompressed row groups
+        let file = fs::read("src/test_data/columnar_1.parquet").unwrap();
+
+        let mut buf = Vec::new();
+        assert!(expect.assert_eq_nopanic_imp(&file, &mut buf).is_ok());
+        assert_eq!(buf, b"");
+
+        let mut changed = file;
+        let name = changed.windows(5).position(|w| w == b"carol").unwrap();
+        changed[name] = b'k';
+        assert!(expect.assert_eq_nopanic_imp(&changed, &mut buf).is_err());
+        String::from_utf8(buf).expect("Only printing strings")
+    };
+
+    expect_test::expect_file!["test_data/fails_parquet.ansi.bin"].assert_eq(&actual);
+}
+
+#[test]
+#[cfg(feature = "der")]
+fn fails_der() {
+    let actual = {
+        let _guard = ENVVAR_MUTATION.read().unwrap();
+        let expect = expect_file!["test_data/cert.der"].der();
+        let cert = fs::read(&expect.path).unwrap();
+
+        let mut buf = Vec::new();
+        // Serial number
+        let mut changed = cert.clone();
+        changed[20] ^= 0xff;
+        assert!(expect.assert_eq_nopanic_imp(&changed, &mut buf).is_err());
+
+        // Named curve of the public key
+        let mut changed = cert.clone();
+        changed[149] += 1;
+        assert!(expect.assert_eq_nopanic_imp(&changed, &mut buf).is_err());
+
+        // Issuer common name as a PrintableString
+        let mut changed = cert;
+        changed[58] = 0x13;
+        assert!(expect.assert_eq_nopanic_imp(&changed, &mut buf).is_err());
+        String::from_utf8(buf).expect("Only printing strings")
+    };
+
+    expect_test::expect_file!["test_data/fails_der.ansi.bin"].assert_eq(&actual);
+}
+
+#[test]
+#[cfg(feature = "encryption")]
+fn encrypted() {
+    use super::encryption::{decrypt, KEY_VAR_NAME};
+
+    let _guard = ENVVAR_MUTATION.write().unwrap();
+    std::env::set_var(KEY_VAR_NAME, "00".repeat(32));
+    let expect = expect_file!["test_data/encrypted_created"].encrypted();
+
+    std::env::set_var(UPDATE_EXPECT_VAR_NAME, "");
+    assert!(expect
+        .assert_eq_nopanic_imp(b"sensitive\n", &mut Vec::new())
+        .is_ok());
+    let first = fs::read(&expect.path).unwrap();
+    assert!(!first.windows(9).any(|w| w == b"sensitive"));
+    // Unchanged contents are encrypted the same
+    fs::remove_file(&expect.path).unwrap();
+    assert!(expect
+        .assert_eq_nopanic_imp(b"sensitive\n", &mut Vec::new())
+        .is_ok());
+    assert_eq!(fs::read(&expect.path).unwrap(), first);
+    std::env::remove_var(UPDATE_EXPECT_VAR_NAME);
+
+    assert!(expect
+        .assert_eq_nopanic_imp(b"sensitive\n", &mut Vec::new())
+        .is_ok());
+    assert!(expect
+        .assert_eq_nopanic_imp(b"public\n", &mut Vec::new())
+        .is_err());
+
+    std::env::set_var(KEY_VAR_NAME, "01".repeat(32));
+    assert!(decrypt(&first).is_err());
+    std::env::set_var(KEY_VAR_NAME, "not a key");
+    assert!(decrypt(&first).is_err());
+
+    std::env::remove_var(KEY_VAR_NAME);
+    fs::remove_file(&expect.path).unwrap();
+}
+
+#[test]
+#[cfg(feature = "git")]
+fn git_status() {
+    use super::git::{uncommitted, write_reminder, Uncommitted, REQUIRE_CLEAN_VAR_NAME};
+
+    let _guard = ENVVAR_MUTATION.write().unwrap();
+    let committed = std::path::Path::new("src/test_data/example");
+    let untracked = PathBuf::from(format!("src/test_data/untracked-{}", std::process::id()));
+    fs::write(&untracked, b"untracked\n").unwrap();
+
+    assert_eq!(uncommitted([committed]), Some(Vec::new()));
+    let files = uncommitted([committed, untracked.as_path()]).unwrap();
+    assert_eq!(
+        files,
+        [Uncommitted {
+            path: untracked.display().to_string(),
+            untracked: true,
+            staged: false,
+        }]
+    );
+
+    let mut buf = Vec::new();
+    write_reminder(&files, &mut buf).unwrap();
+    assert_eq!(
+        String::from_utf8(buf).unwrap(),
+        format!(
+            "\n\x1b[1m\x1b[93mreminder\x1b[0m: 1 updated file is not added to git:\n   untracked  {}\n",
+            untracked.display()
+        )
+    );
+
+    std::env::set_var(REQUIRE_CLEAN_VAR_NAME, "");
+    let mut buf = Vec::new();
+    assert!(expect_file!["test_data/example"]
+        .check_committed(&mut buf)
+        .is_ok());
+    assert!(super::ExpectFile::new(untracked.clone())
+        .check_committed(&mut buf)
+        .is_err());
+    std::env::remove_var(REQUIRE_CLEAN_VAR_NAME);
+    assert!(String::from_utf8(buf)
+        .unwrap()
+        .contains("The file is untracked."));
+
+    fs::remove_file(&untracked).unwrap();
+}
+
+#[cfg(feature = "cli")]
+#[test]
+fn cli_snapshot_paths() {
+    let source = r#"
+        let expect = expect_file!["test_data/a.bin"].hash_only();
+        let group = expect_group![ "test_data/b" ];
+        let out = expect_out_file![TEST_DATA, mirror = "out/c.bin"];
+        let other = expect_out_file![TEST_DATA];
+        let not_a_literal = expect_file![PATH];
+    "#;
+    assert_eq!(
+        super::cli::snapshot_paths(source),
+        ["test_data/a.bin", "test_data/b", "out/c.bin"]
+    );
+}
+
+#[cfg(feature = "cli")]
+#[test]
+fn cli_list() {
+    let root = std::env::temp_dir().join(format!("expect-test-bytes-cli-{}", std::process::id()));
+    fs::create_dir_all(root.join("tests/test_data")).unwrap();
+    fs::write(
+        root.join("tests/it.rs"),
+        r#"expect_file!["test_data/a"]; expect_file!["test_data/b"];"#,
+    )
+    .unwrap();
+    fs::write(root.join("tests/test_data/a"), b"12345").unwrap();
+
+    let mut buf = Vec::new();
+    assert_eq!(super::cli::list(&root, &mut buf).unwrap(), 0);
+    fs::remove_dir_all(&root).unwrap();
+    let sep = std::path::MAIN_SEPARATOR;
+    assert_eq!(
+        String::from_utf8(buf).unwrap(),
+        format!(
+            "      5  tests{sep}test_data/a\nmissing  tests{sep}test_data/b\n\n2 snapshots, 5 bytes, 1 missing\n"
+        )
+    );
+}
+
+#[test]
+fn compressed_snapshots() {
+    use std::fmt::Write as _;
+
+    let _guard = ENVVAR_MUTATION.read().unwrap();
+    let mut data = String::new();
+    for i in 0..3000_usize {
+        let letter = &"abcdefghij"[i % 10..=i % 10];
+        writeln!(data, "line {i}: {} {}", i * i, letter.repeat(i % 7)).unwrap();
+    }
+    for expect in [
+        expect_file!["test_data/compressed.gz"],
+        expect_file!["test_data/compressed.zst"],
+        expect_file!["test_data/compressed_fast.zst"],
+    ] {
+        assert!(expect
+            .assert_eq_nopanic_imp(data.as_bytes(), &mut Vec::new())
+            .is_ok());
+        assert!(expect
+            .assert_eq_nopanic_imp(&data.as_bytes()[1..], &mut Vec::new())
+            .is_err());
+    }
+
+    // Compressed actual data is compared with the file as it's stored
+    expect_file!["test_data/compressed.gz"].assert_eq(include_bytes!("test_data/compressed.gz"));
+
+    // Updates keep the format of the file
+    let path = std::env::temp_dir().join(format!(
+        "expect-test-bytes-compressed-{}",
+        std::process::id()
+    ));
+    for (source, format) in [
+        (
+            "src/test_data/compressed.gz",
+            super::compression::Format::Gzip,
+        ),
+        (
+            "src/test_data/compressed.zst",
+            super::compression::Format::Zstd,
+        ),
+    ] {
+        fs::copy(source, &path).unwrap();
+        let expect = super::ExpectFile::new(path.clone()).force_update();
+        assert!(expect
+            .assert_eq_nopanic_imp(b"updated\n", &mut Vec::new())
+            .is_ok());
+        let stored = fs::read(&path).unwrap();
+        assert_eq!(super::compression::detect(&stored), Some(format));
+        assert_eq!(
+            super::compression::decompress(format, &stored).unwrap(),
+            b"updated\n"
+        );
+        assert!(super::ExpectFile::new(path.clone())
+            .assert_eq_nopanic_imp(b"updated\n", &mut Vec::new())
+            .is_ok());
+    }
+    fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn first_diff_index() {
+    use super::{first_diff_index, FAST_COMPARE_CHUNK_LEN};
+
+    let data: Vec<u8> = (0_u32..)
+        .map(|i| (i % 251) as u8)
+        .take(3 * FAST_COMPARE_CHUNK_LEN + 100)
+        .collect();
+    assert_eq!(first_diff_index(&data, &data), None);
+    for diff_idx in [
+        0,
+        FAST_COMPARE_CHUNK_LEN - 1,
+        FAST_COMPARE_CHUNK_LEN,
+        2 * FAST_COMPARE_CHUNK_LEN + 7,
+        data.len() - 1,
+    ] {
+        let mut changed = data.clone();
+        changed[diff_idx] ^= 0xff;
+        assert_eq!(first_diff_index(&data, &changed), Some(diff_idx));
+    }
+    assert_eq!(
+        first_diff_index(&data, &data[..=FAST_COMPARE_CHUNK_LEN]),
+        Some(FAST_COMPARE_CHUNK_LEN + 1)
+    );
+    assert_eq!(first_diff_index(&[], &data), Some(0));
+}
+
+#[test]
+fn compare_readers() {
+    use super::{compare_readers, StreamDifference};
+
+    let data: Vec<u8> = (0..200_000_u32).map(|i| (i % 251) as u8).collect();
+    assert_eq!(compare_readers(&data[..], &data[..]).unwrap(), None);
+    // The windows start in the previous read
+    for diff_idx in [0, 0x10002, 150_000] {
+        let mut changed = data.clone();
+        changed[diff_idx] ^= 0xff;
+        changed.truncate(190_000);
+        let StreamDifference {
+            diff_idx: found_idx,
+            window_offset,
+            expected_window,
+            actual_window,
+            expected_len,
+            actual_len,
+        } = compare_readers(&data[..], &changed[..]).unwrap().unwrap();
+        assert_eq!(found_idx, diff_idx);
+        assert_eq!((expected_len, actual_len), (200_000, 190_000));
+        let window = window_offset..window_offset + expected_window.len();
+        assert!(window.contains(&diff_idx));
+        assert_eq!(expected_window, data[window.clone()]);
+        assert_eq!(actual_window, changed[window]);
+    }
+    let difference = compare_readers(&data[..], &data[..0x10000])
+        .unwrap()
+        .unwrap();
+    assert_eq!(difference.diff_idx, 0x10000);
+    assert!(difference.actual_window.len() < difference.expected_window.len());
+}
@@ -0,0 +1,159 @@
+//! Unified line diffs.
+
+use std::fmt::Write as _;
+
+/// Number of unchanged lines shown around changes.
+const CONTEXT: usize = 2;
+/// Maximum number of line pairs for the quadratic LCS table. Larger inputs are shown as a single
+/// replacement of the differing middle.
+#[cfg(not(feature = "similar"))]
+const MAX_LCS_CELLS: usize = 1 << 22;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Op {
+    Equal,
+    Delete,
+    Insert,
+}
+
+/// Computes the edit script between `a` and `b` as (operation, line index) pairs with the
+/// patience algorithm.
+#[cfg(feature = "similar")]
+fn edit_script<T: PartialEq + std::hash::Hash + Ord>(a: &[T], b: &[T]) -> Vec<(Op, usize)> {
+    use similar::{Algorithm, DiffTag};
+
+    let mut ops = Vec::new();
+    for op in similar::capture_diff_slices(Algorithm::Patience, a, b) {
+        let (tag, a_range, b_range) = op.as_tag_tuple();
+        match tag {
+            DiffTag::Equal => ops.extend(a_range.map(|i| (Op::Equal, i))),
+            DiffTag::Delete => ops.extend(a_range.map(|i| (Op::Delete, i))),
+            DiffTag::Insert => ops.extend(b_range.map(|j| (Op::Insert, j))),
+            DiffTag::Replace => {
+                ops.extend(a_range.map(|i| (Op::Delete, i)));
+                ops.extend(b_range.map(|j| (Op::Insert, j)));
+            }
+        }
+    }
+    ops
+}
+
+/// Computes the edit script between `a` and `b` as (operation, line index) pairs.
+#[cfg(not(feature = "similar"))]
+fn edit_script<T: PartialEq>(a: &[T], b: &[T]) -> Vec<(Op, usize)> {
+    let prefix = a.iter().zip(b).take_while(|(x, y)| x == y).count();
+    let suffix = a[prefix..]
+        .iter()
+        .rev()
+        .zip(b[prefix..].iter().rev())
+        .take_while(|(x, y)| x == y)
+        .count();
+    let (a_mid, b_mid) = (&a[prefix..a.len() - suffix], &b[prefix..b.len() - suffix]);
+
+    let mut ops: Vec<(Op, usize)> = (0..prefix).map(|i| (Op::Equal, i)).collect();
+
+    if a_mid.len().saturating_mul(b_mid.len()) <= MAX_LCS_CELLS {
+        // lcs[i][j] is the LCS length of a_mid[i..] and b_mid[j..]
+        let width = b_mid.len() + 1;
+        let mut lcs = vec![0u32; (a_mid.len() + 1) * width];
+        for i in (0..a_mid.len()).rev() {
+            for j in (0..b_mid.len()).rev() {
+                lcs[i * width + j] = if a_mid[i] == b_mid[j] {
+                    lcs[(i + 1) * width + j + 1] + 1
+                } else {
+                    lcs[(i + 1) * width + j].max(lcs[i * width + j + 1])
+                };
+            }
+        }
+        let (mut i, mut j) = (0, 0);
+        while i < a_mid.len() || j < b_mid.len() {
+            if i < a_mid.len() && j < b_mid.len() && a_mid[i] == b_mid[j] {
+                ops.push((Op::Equal, prefix + i));
+                i += 1;
+                j += 1;
+            } else if i < a_mid.len()
+                && (j == b_mid.len() || lcs[(i + 1) * width + j] >= lcs[i * width + j + 1])
+            {
+                ops.push((Op::Delete, prefix + i));
+                i += 1;
+            } else {
+                ops.push((Op::Insert, prefix + j));
+                j += 1;
+            }
+        }
+    } else {
+        ops.extend((0..a_mid.len()).map(|i| (Op::Delete, prefix + i)));
+        ops.extend((0..b_mid.len()).map(|j| (Op::Insert, prefix + j)));
+    }
+
+    ops.extend((0..suffix).map(|k| (Op::Equal, a.len() - suffix + k)));
+    ops
+}
+
+/// Formats a unified diff of the lines `a` and `b`, without file name headers.
+pub(crate) fn unified_diff(a: &[String], b: &[String]) -> String {
+    let ops = edit_script(a, b);
+
+    // Positions in `a` and `b` before each operation
+    let mut positions = Vec::with_capacity(ops.len() + 1);
+    let (mut a_pos, mut b_pos) = (0, 0);
+    for &(op, _) in &ops {
+        positions.push((a_pos, b_pos));
+        match op {
+            Op::Equal => {
+                a_pos += 1;
+                b_pos += 1;
+            }
+            Op::Delete => a_pos += 1,
+            Op::Insert => b_pos += 1,
+        }
+    }
+
+    let mut out = String::new();
+    let mut i = 0;
+    while let Some(first_change) = ops[i..].iter().position(|&(op, _)| op != Op::Equal) {
+        let start = (i + first_change).saturating_sub(CONTEXT).max(i);
+        // Extend the hunk while changes are separated by at most 2 * CONTEXT equal lines
+        let mut end = i + first_change;
+        let mut equal_run = 0;
+        for (k, &(op, _)) in ops.iter().enumerate().skip(end) {
+            if op == Op::Equal {
+                equal_run += 1;
+                if equal_run > 2 * CONTEXT {
+                    break;
+                }
+            } else {
+                equal_run = 0;
+                end = k;
+            }
+        }
+        let end = (end + 1 + CONTEXT).min(ops.len());
+
+        let (a_start, b_start) = positions[start];
+        let a_len = ops[start..end]
+            .iter()
+            .filter(|(op, _)| *op != Op::Insert)
+            .count();
+        let b_len = ops[start..end]
+            .iter()
+            .filter(|(op, _)| *op != Op::Delete)
+            .count();
+        writeln!(
+            out,
+            "@@ -{},{a_len} +{},{b_len} @@",
+            a_start + usize::from(a_len != 0),
+            b_start + usize::from(b_len != 0)
+        )
+        .unwrap();
+        for &(op, line) in &ops[start..end] {
+            match op {
+                Op::Equal => writeln!(out, " {}", a[line]),
+                Op::Delete => writeln!(out, "-{}", a[line]),
+                Op::Insert => writeln!(out, "+{}", b[line]),
+            }
+            .unwrap();
+        }
+        i = end;
+    }
+    out
+}
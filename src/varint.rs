@@ -0,0 +1,56 @@
+//! Decoding of LEB128 varints around differences, whose continuation bytes otherwise make a single
+//! changed value look like a cascade of differing bytes.
+
+use std::io;
+
+use crate::style::NOTE;
+
+/// Maximum length of a varint encoding a `u64`.
+const MAX_LEN: usize = 10;
+
+/// Decodes the unsigned LEB128 varint at the start of `data`, returning its value and length, or
+/// returns `None` if it's unterminated or doesn't fit in a `u64`.
+pub(crate) fn decode(data: &[u8]) -> Option<(u64, usize)> {
+    let mut value = 0_u64;
+    for (i, &byte) in data.iter().take(MAX_LEN).enumerate() {
+        let bits = u64::from(byte & 0x7f);
+        if i == MAX_LEN - 1 && bits > 1 {
+            return None;
+        }
+        value |= bits << (7 * i);
+        if byte & 0x80 == 0 {
+            return Some((value, i + 1));
+        }
+    }
+    None
+}
+
+/// Writes the values of the varints containing `diff_idx` in `expected` and `actual`, which both
+/// start at `offset`.
+///
+/// The varint starts after the last byte before `diff_idx` without the continuation bit, which is
+/// the same on both sides.
+pub(crate) fn write_note<W: io::Write>(
+    writer: &mut W,
+    expected: &[u8],
+    actual: &[u8],
+    offset: usize,
+    diff_idx: usize,
+) -> io::Result<()> {
+    let mut start = diff_idx.saturating_sub(offset).min(expected.len());
+    while start > 0 && diff_idx - offset - start < MAX_LEN - 1 && expected[start - 1] & 0x80 != 0 {
+        start -= 1;
+    }
+    let describe = |data: &[u8]| match data.get(start..).and_then(decode) {
+        Some((value, 1)) => format!("{value} (1 byte)"),
+        Some((value, len)) => format!("{value} ({len} bytes)"),
+        None => "not a valid varint".to_owned(),
+    };
+    writeln!(
+        writer,
+        "{NOTE}note{NOTE:#}: the varint at byte {:#x} is {}, expected {}",
+        offset + start,
+        describe(actual),
+        describe(expected)
+    )
+}
@@ -0,0 +1,100 @@
+//! `xxd`-compatible hex dumps.
+
+use std::ffi::OsString;
+use std::fmt::Write as _;
+use std::path::{Path, PathBuf};
+
+/// Number of bytes per dump line, same as `xxd`.
+pub(crate) const LINE_LEN: usize = 16;
+
+/// Formats one line of an `xxd` dump for `chunk`, which starts at `offset`.
+pub(crate) fn dump_line(offset: usize, chunk: &[u8]) -> String {
+    let mut line = format!("{offset:08x}:");
+    for (i, byte) in chunk.iter().enumerate() {
+        if i % 2 == 0 {
+            line.push(' ');
+        }
+        write!(line, "{byte:02x}").unwrap();
+    }
+    // Pad the hex column to its full width, followed by two spaces
+    let hex_width = LINE_LEN * 2 + LINE_LEN / 2;
+    let written = chunk.len() * 2 + (chunk.len() + 1) / 2;
+    line.extend(std::iter::repeat(' ').take(hex_width - written + 2));
+    line.extend(chunk.iter().map(|&byte| {
+        if (0x20..0x7f).contains(&byte) {
+            byte as char
+        } else {
+            '.'
+        }
+    }));
+    line
+}
+
+/// Formats `data`, which starts at `offset`, as `xxd` dump lines.
+pub(crate) fn dump_lines(data: &[u8], offset: usize) -> Vec<String> {
+    data.chunks(LINE_LEN)
+        .enumerate()
+        .map(|(i, chunk)| dump_line(offset + i * LINE_LEN, chunk))
+        .collect()
+}
+
+/// Parses an `xxd` dump, or a `hexdump -C` dump whose repeated lines are collapsed into `*`, back
+/// into bytes.
+///
+/// Returns the number of an invalid line as an error.
+pub(crate) fn parse(dump: &str) -> Result<Vec<u8>, usize> {
+    let mut data = Vec::new();
+    let mut last_line = Vec::new();
+    let mut repeated = false;
+    for (i, line) in dump.lines().enumerate() {
+        let line = line.trim_end();
+        if line.is_empty() {
+            continue;
+        }
+        if line == "*" {
+            repeated = true;
+            continue;
+        }
+        let (offset, rest) = line.split_once(' ').unwrap_or((line, ""));
+        let (offset, hex) = match offset.strip_suffix(':') {
+            // The text column of `xxd` starts after two spaces
+            Some(offset) => (offset, rest.trim_start().split("  ").next().unwrap_or("")),
+            None => (offset, rest.split('|').next().unwrap_or("")),
+        };
+        let offset = usize::from_str_radix(offset.trim(), 16).map_err(|_| i + 1)?;
+        if repeated {
+            if last_line.is_empty() {
+                return Err(i + 1);
+            }
+            while data.len() < offset {
+                let len = last_line.len().min(offset - data.len());
+                data.extend_from_slice(&last_line[..len]);
+            }
+            repeated = false;
+        }
+        if offset != data.len() {
+            return Err(i + 1);
+        }
+        last_line.clear();
+        for group in hex.split_whitespace() {
+            if group.len() % 2 != 0 || !group.bytes().all(|b| b.is_ascii_hexdigit()) {
+                return Err(i + 1);
+            }
+            for pair in (0..group.len()).step_by(2) {
+                last_line.push(u8::from_str_radix(&group[pair..pair + 2], 16).unwrap());
+            }
+        }
+        data.extend_from_slice(&last_line);
+    }
+    if repeated {
+        return Err(dump.lines().count());
+    }
+    Ok(data)
+}
+
+/// Returns the path of the companion dump for the snapshot at `path`.
+pub(crate) fn dump_path(path: &Path) -> PathBuf {
+    let mut os: OsString = path.as_os_str().to_owned();
+    os.push(".hexdump");
+    os.into()
+}
@@ -0,0 +1,702 @@
+//! Reading and writing Zstandard files ([RFC 8878](https://www.rfc-editor.org/rfc/rfc8878)).
+//!
+//! Dictionaries aren't supported, and content checksums aren't verified since the decompressed
+//! data is compared anyway.
+
+/// Magic number of a Zstandard frame.
+pub(crate) const MAGIC: [u8; 4] = [0x28, 0xb5, 0x2f, 0xfd];
+
+/// Maximum size of a block.
+const MAX_BLOCK_LEN: usize = 1 << 17;
+
+const TRUNCATED: &str = "the zstd file is truncated";
+const CORRUPTED: &str = "the zstd file is corrupted";
+
+/// Decompresses the frames of a Zstandard file, skipping skippable frames.
+pub(crate) fn decompress(data: &[u8]) -> Result<Vec<u8>, String> {
+    let mut out = Vec::new();
+    let mut rest = data;
+    while !rest.is_empty() {
+        let magic = rest.get(..4).ok_or(TRUNCATED)?;
+        let magic = u32::from_le_bytes([magic[0], magic[1], magic[2], magic[3]]);
+        if magic & 0xffff_fff0 == 0x184d_2a50 {
+            let len = rest.get(4..8).ok_or(TRUNCATED)?;
+            let len = u32::from_le_bytes([len[0], len[1], len[2], len[3]]) as usize;
+            rest = rest.get(8 + len..).ok_or(TRUNCATED)?;
+        } else if rest.starts_with(&MAGIC) {
+            rest = decompress_frame(&rest[4..], &mut out)?;
+        } else {
+            return Err("trailing data after the last zstd frame".to_owned());
+        }
+    }
+    Ok(out)
+}
+
+/// Compresses `data` into a Zstandard frame of raw blocks, which every zstd reader can read.
+pub(crate) fn compress_raw(data: &[u8]) -> Vec<u8> {
+    let mut out = MAGIC.to_vec();
+    // Single segment with an 8-byte content size
+    out.push(0xe0);
+    out.extend_from_slice(&(data.len() as u64).to_le_bytes());
+    let mut blocks = data.chunks(MAX_BLOCK_LEN).peekable();
+    if blocks.peek().is_none() {
+        out.extend_from_slice(&[1, 0, 0]);
+    }
+    while let Some(block) = blocks.next() {
+        let header = u32::try_from(block.len()).unwrap() << 3 | u32::from(blocks.peek().is_none());
+        out.extend_from_slice(&header.to_le_bytes()[..3]);
+        out.extend_from_slice(block);
+    }
+    out
+}
+
+/// Decoding state that carries over from block to block of a frame.
+#[derive(Default)]
+struct FrameState {
+    huffman: Option<HuffmanTable>,
+    literal_lengths: Option<FseTable>,
+    offsets: Option<FseTable>,
+    match_lengths: Option<FseTable>,
+    repeat_offsets: [usize; 3],
+}
+
+/// Decompresses the frame after the magic number at the start of `data` into `out`, returning
+/// the data after it.
+fn decompress_frame<'a>(data: &'a [u8], out: &mut Vec<u8>) -> Result<&'a [u8], String> {
+    let descriptor = *data.first().ok_or(TRUNCATED)?;
+    let content_size_flag = descriptor >> 6;
+    let single_segment = descriptor & 0x20 != 0;
+    let has_checksum = descriptor & 0x04 != 0;
+    if descriptor & 0x08 != 0 {
+        return Err(CORRUPTED.to_owned());
+    }
+    let dictionary_id_len = [0, 1, 2, 4][usize::from(descriptor & 3)];
+    let content_size_len = match content_size_flag {
+        0 => usize::from(single_segment),
+        1 => 2,
+        2 => 4,
+        _ => 8,
+    };
+    let mut pos = 1 + usize::from(!single_segment);
+    let dictionary_id = data.get(pos..pos + dictionary_id_len).ok_or(TRUNCATED)?;
+    if dictionary_id.iter().any(|&byte| byte != 0) {
+        return Err("zstd files compressed with a dictionary aren't supported".to_owned());
+    }
+    pos += dictionary_id_len + content_size_len;
+
+    let start = out.len();
+    let mut state = FrameState {
+        repeat_offsets: [1, 4, 8],
+        ..FrameState::default()
+    };
+    loop {
+        let header = data.get(pos..pos + 3).ok_or(TRUNCATED)?;
+        let header = u32::from_le_bytes([header[0], header[1], header[2], 0]);
+        pos += 3;
+        let last = header & 1 != 0;
+        let len = (header >> 3) as usize;
+        match (header >> 1) & 3 {
+            0 => out.extend_from_slice(data.get(pos..pos + len).ok_or(TRUNCATED)?),
+            1 => {
+                let byte = *data.get(pos).ok_or(TRUNCATED)?;
+                out.resize(out.len() + len, byte);
+            }
+            2 => {
+                let block = data.get(pos..pos + len).ok_or(TRUNCATED)?;
+                decompress_block(block, start, out, &mut state)?;
+            }
+            _ => return Err(CORRUPTED.to_owned()),
+        }
+        // RLE blocks store their byte once
+        pos += if (header >> 1) & 3 == 1 { 1 } else { len };
+        if last {
+            break;
+        }
+    }
+    if has_checksum {
+        pos += 4;
+    }
+    data.get(pos..).ok_or_else(|| TRUNCATED.to_owned())
+}
+
+/// Decompresses a compressed block into `out`, where the frame started at `frame_start`.
+fn decompress_block(
+    block: &[u8],
+    frame_start: usize,
+    out: &mut Vec<u8>,
+    state: &mut FrameState,
+) -> Result<(), String> {
+    let (literals, len) = read_literals(block, &mut state.huffman)?;
+    let sequences = read_sequences(&block[len..], state)?;
+    let mut literals = &literals[..];
+    for sequence in sequences {
+        let copied = literals.get(..sequence.literal_len).ok_or(CORRUPTED)?;
+        out.extend_from_slice(copied);
+        literals = &literals[sequence.literal_len..];
+        let offset = resolve_offset(&mut state.repeat_offsets, sequence);
+        if offset == 0 || offset > out.len() - frame_start {
+            return Err("a zstd match offset points before the start".to_owned());
+        }
+        // The match may overlap the bytes it produces
+        let match_start = out.len() - offset;
+        for i in 0..sequence.match_len {
+            out.push(out[match_start + i]);
+        }
+    }
+    out.extend_from_slice(literals);
+    Ok(())
+}
+
+/// Returns the offset of a sequence, updating the repeated offsets.
+fn resolve_offset(repeat_offsets: &mut [usize; 3], sequence: Sequence) -> usize {
+    if sequence.offset_value > 3 {
+        let offset = sequence.offset_value - 3;
+        *repeat_offsets = [offset, repeat_offsets[0], repeat_offsets[1]];
+        return offset;
+    }
+    let index = sequence.offset_value - 1 + usize::from(sequence.literal_len == 0);
+    if index == 0 {
+        return repeat_offsets[0];
+    }
+    let offset = if index < 3 {
+        repeat_offsets[index]
+    } else {
+        repeat_offsets[0].wrapping_sub(1)
+    };
+    if index > 1 {
+        repeat_offsets[2] = repeat_offsets[1];
+    }
+    repeat_offsets[1] = repeat_offsets[0];
+    repeat_offsets[0] = offset;
+    offset
+}
+
+/// Reads the literals section at the start of a block, returning the literals and the length of
+/// the section.
+fn read_literals(
+    block: &[u8],
+    huffman: &mut Option<HuffmanTable>,
+) -> Result<(Vec<u8>, usize), String> {
+    let first = *block.first().ok_or(TRUNCATED)?;
+    let header = |len: usize| -> Result<usize, String> {
+        let bytes = block.get(..len).ok_or(TRUNCATED)?;
+        Ok(bytes
+            .iter()
+            .rev()
+            .fold(0, |value, &byte| value << 8 | usize::from(byte)))
+    };
+    let size_format = (first >> 2) & 3;
+    match first & 3 {
+        kind @ (0 | 1) => {
+            let (header_len, regenerated) = match size_format {
+                0 | 2 => (1, usize::from(first >> 3)),
+                1 => (2, header(2)? >> 4),
+                _ => (3, header(3)? >> 4),
+            };
+            if kind == 0 {
+                let literals = block
+                    .get(header_len..header_len + regenerated)
+                    .ok_or(TRUNCATED)?;
+                Ok((literals.to_vec(), header_len + regenerated))
+            } else {
+                let byte = *block.get(header_len).ok_or(TRUNCATED)?;
+                Ok((vec![byte; regenerated], header_len + 1))
+            }
+        }
+        kind => {
+            let (header_len, size_bits, streams) = match size_format {
+                0 => (3, 10, 1),
+                1 => (3, 10, 4),
+                2 => (4, 14, 4),
+                _ => (5, 18, 4),
+            };
+            let value = header(header_len)?;
+            let mask = (1 << size_bits) - 1;
+            let regenerated = (value >> 4) & mask;
+            let compressed = (value >> (4 + size_bits)) & mask;
+            let mut data = block
+                .get(header_len..header_len + compressed)
+                .ok_or(TRUNCATED)?;
+            if kind == 2 {
+                let (table, len) = HuffmanTable::read(data)?;
+                *huffman = Some(table);
+                data = &data[len..];
+            }
+            let table = huffman.as_ref().ok_or(CORRUPTED)?;
+            let literals = if streams == 1 {
+                table.decode_stream(data, regenerated)?
+            } else {
+                table.decode_four_streams(data, regenerated)?
+            };
+            Ok((literals, header_len + compressed))
+        }
+    }
+}
+
+/// A decoded sequence of a block.
+#[derive(Debug, Clone, Copy)]
+struct Sequence {
+    literal_len: usize,
+    match_len: usize,
+    offset_value: usize,
+}
+
+const LITERAL_LENGTH_BASES: [u32; 36] = [
+    0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16, 18, 20, 22, 24, 28, 32, 40, 48, 64,
+    128, 256, 512, 1024, 2048, 4096, 8192, 16384, 32768, 65536,
+];
+const LITERAL_LENGTH_EXTRA_BITS: [u8; 36] = [
+    0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 3, 3, 4, 6, 7, 8, 9, 10, 11,
+    12, 13, 14, 15, 16,
+];
+const MATCH_LENGTH_BASES: [u32; 53] = [
+    3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16, 17, 18, 19, 20, 21, 22, 23, 24, 25, 26, 27,
+    28, 29, 30, 31, 32, 33, 34, 35, 37, 39, 41, 43, 47, 51, 59, 67, 83, 99, 131, 259, 515, 1027,
+    2051, 4099, 8195, 16387, 32771, 65539,
+];
+const MATCH_LENGTH_EXTRA_BITS: [u8; 53] = [
+    0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+    1, 1, 1, 1, 2, 2, 3, 3, 4, 4, 5, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16,
+];
+const LITERAL_LENGTH_DEFAULT: [i16; 36] = [
+    4, 3, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 1, 1, 1, 2, 2, 2, 2, 2, 2, 2, 2, 2, 3, 2, 1, 1, 1, 1, 1,
+    -1, -1, -1, -1,
+];
+const MATCH_LENGTH_DEFAULT: [i16; 53] = [
+    1, 4, 3, 2, 2, 2, 2, 2, 2, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1,
+    1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, -1, -1, -1, -1, -1, -1, -1,
+];
+const OFFSET_DEFAULT: [i16; 29] = [
+    1, 1, 1, 1, 1, 1, 2, 2, 2, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, -1, -1, -1, -1, -1,
+];
+
+/// Reads the sequences section of a block, which is the rest of the block.
+fn read_sequences(data: &[u8], state: &mut FrameState) -> Result<Vec<Sequence>, String> {
+    let first = *data.first().ok_or(TRUNCATED)?;
+    let (count, mut pos) = match first {
+        0 => return Ok(Vec::new()),
+        1..=127 => (usize::from(first), 1),
+        128..=254 => {
+            let second = *data.get(1).ok_or(TRUNCATED)?;
+            ((usize::from(first - 128) << 8) + usize::from(second), 2)
+        }
+        255 => {
+            let bytes = data.get(1..3).ok_or(TRUNCATED)?;
+            (
+                usize::from(u16::from_le_bytes([bytes[0], bytes[1]])) + 0x7f00,
+                3,
+            )
+        }
+    };
+    let modes = *data.get(pos).ok_or(TRUNCATED)?;
+    pos += 1;
+    for (shift, table, default, max_log, max_symbol) in [
+        (
+            6,
+            &mut state.literal_lengths,
+            &LITERAL_LENGTH_DEFAULT[..],
+            9,
+            35,
+        ),
+        (4, &mut state.offsets, &OFFSET_DEFAULT[..], 8, 31),
+        (
+            2,
+            &mut state.match_lengths,
+            &MATCH_LENGTH_DEFAULT[..],
+            9,
+            52,
+        ),
+    ] {
+        let rest = data.get(pos..).ok_or(TRUNCATED)?;
+        match (modes >> shift) & 3 {
+            0 => *table = Some(FseTable::new(default, 6 - u32::from(shift == 4))?),
+            1 => {
+                let symbol = *rest.first().ok_or(TRUNCATED)?;
+                *table = Some(FseTable::rle(symbol));
+                pos += 1;
+            }
+            2 => {
+                let (read, len) = FseTable::read(rest, max_log, max_symbol)?;
+                *table = Some(read);
+                pos += len;
+            }
+            // The table of the previous block is repeated
+            _ => {}
+        }
+    }
+    let (literal_lengths, offsets, match_lengths) =
+        match (&state.literal_lengths, &state.offsets, &state.match_lengths) {
+            (Some(literal_lengths), Some(offsets), Some(match_lengths)) => {
+                (literal_lengths, offsets, match_lengths)
+            }
+            _ => return Err(CORRUPTED.to_owned()),
+        };
+
+    let mut bits = BackwardBits::new(data.get(pos..).ok_or(TRUNCATED)?)?;
+    let mut literal_length_state = bits.read(literal_lengths.log);
+    let mut offset_state = bits.read(offsets.log);
+    let mut match_length_state = bits.read(match_lengths.log);
+    let mut sequences = Vec::with_capacity(count);
+    for i in 0..count {
+        let offset_code = u32::from(offsets.symbol(offset_state));
+        let match_length_code = usize::from(match_lengths.symbol(match_length_state));
+        let literal_length_code = usize::from(literal_lengths.symbol(literal_length_state));
+        if offset_code > 31 || match_length_code > 52 || literal_length_code > 35 {
+            return Err(CORRUPTED.to_owned());
+        }
+        let offset_value = (1 << offset_code) + bits.read(offset_code);
+        let match_len = MATCH_LENGTH_BASES[match_length_code] as usize
+            + bits.read(u32::from(MATCH_LENGTH_EXTRA_BITS[match_length_code]));
+        let literal_len = LITERAL_LENGTH_BASES[literal_length_code] as usize
+            + bits.read(u32::from(LITERAL_LENGTH_EXTRA_BITS[literal_length_code]));
+        sequences.push(Sequence {
+            literal_len,
+            match_len,
+            offset_value,
+        });
+        if i + 1 != count {
+            literal_length_state = literal_lengths.update(literal_length_state, &mut bits);
+            match_length_state = match_lengths.update(match_length_state, &mut bits);
+            offset_state = offsets.update(offset_state, &mut bits);
+        }
+    }
+    if !bits.is_exhausted() {
+        return Err(CORRUPTED.to_owned());
+    }
+    Ok(sequences)
+}
+
+/// Reads bits forwards from the least significant bit of each byte.
+struct ForwardBits<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl ForwardBits<'_> {
+    fn read(&mut self, count: u32) -> Result<u32, String> {
+        let mut value = 0;
+        for i in 0..count {
+            let byte = self.data.get(self.pos / 8).ok_or(TRUNCATED)?;
+            value |= u32::from((byte >> (self.pos % 8)) & 1) << i;
+            self.pos += 1;
+        }
+        Ok(value)
+    }
+}
+
+/// Reads bits backwards from the padding marker in the last byte, with zeros past the start.
+struct BackwardBits<'a> {
+    data: &'a [u8],
+    /// Position of the bit after the next one to read, which is negative past the start.
+    pos: i64,
+}
+
+impl<'a> BackwardBits<'a> {
+    fn new(data: &'a [u8]) -> Result<Self, String> {
+        let last = *data.last().ok_or(TRUNCATED)?;
+        if last == 0 {
+            return Err(CORRUPTED.to_owned());
+        }
+        let len = i64::try_from(data.len()).map_err(|_| CORRUPTED)?;
+        Ok(Self {
+            data,
+            pos: (len - 1) * 8 + i64::from(7 - last.leading_zeros()),
+        })
+    }
+
+    fn read(&mut self, count: u32) -> usize {
+        self.pos -= i64::from(count);
+        let mut value = 0;
+        for i in 0..count {
+            if let Ok(pos) = usize::try_from(self.pos + i64::from(i)) {
+                value |= usize::from((self.data[pos / 8] >> (pos % 8)) & 1) << i;
+            }
+        }
+        value
+    }
+
+    /// Returns whether all bits were read, without reading past the start.
+    fn is_exhausted(&self) -> bool {
+        self.pos == 0
+    }
+
+    /// Returns whether reads went past the start.
+    fn is_overflowed(&self) -> bool {
+        self.pos < 0
+    }
+}
+
+/// A finite state entropy decoding table.
+struct FseTable {
+    log: u32,
+    symbols: Vec<u8>,
+    bit_counts: Vec<u8>,
+    bases: Vec<usize>,
+}
+
+impl FseTable {
+    /// Builds the table of the normalized probabilities `distribution`, where -1 stands for
+    /// "less than 1".
+    fn new(distribution: &[i16], log: u32) -> Result<Self, String> {
+        let size = 1 << log;
+        let mut symbols = vec![0; size];
+        let mut next_states = vec![0; distribution.len()];
+        let mut high = size;
+        for (symbol, &probability) in distribution.iter().enumerate() {
+            if probability == -1 {
+                high = high.checked_sub(1).ok_or(CORRUPTED)?;
+                symbols[high] = u8::try_from(symbol).map_err(|_| CORRUPTED)?;
+                next_states[symbol] = 1;
+            }
+        }
+        let step = (size >> 1) + (size >> 3) + 3;
+        let mut pos = 0;
+        for (symbol, &probability) in distribution.iter().enumerate() {
+            let probability = match usize::try_from(probability) {
+                Ok(probability) if probability > 0 => probability,
+                _ => continue,
+            };
+            next_states[symbol] = probability;
+            for _ in 0..probability {
+                symbols[pos] = u8::try_from(symbol).map_err(|_| CORRUPTED)?;
+                // Cells of "less than 1" probabilities are skipped
+                loop {
+                    pos = (pos + step) & (size - 1);
+                    if pos < high {
+                        break;
+                    }
+                }
+            }
+        }
+        if pos != 0 {
+            return Err(CORRUPTED.to_owned());
+        }
+        let mut bit_counts = Vec::with_capacity(size);
+        let mut bases = Vec::with_capacity(size);
+        for &symbol in &symbols {
+            let next_state = next_states[usize::from(symbol)];
+            next_states[usize::from(symbol)] += 1;
+            let bit_count = log - (usize::BITS - 1 - next_state.leading_zeros());
+            bit_counts.push(u8::try_from(bit_count).map_err(|_| CORRUPTED)?);
+            bases.push((next_state << bit_count) - size);
+        }
+        Ok(Self {
+            log,
+            symbols,
+            bit_counts,
+            bases,
+        })
+    }
+
+    /// Builds the table of a single symbol, which takes no bits.
+    fn rle(symbol: u8) -> Self {
+        Self {
+            log: 0,
+            symbols: vec![symbol],
+            bit_counts: vec![0],
+            bases: vec![0],
+        }
+    }
+
+    /// Reads a table description at the start of `data`, returning the table and the length of
+    /// the description.
+    fn read(data: &[u8], max_log: u32, max_symbol: usize) -> Result<(Self, usize), String> {
+        let mut bits = ForwardBits { data, pos: 0 };
+        let log = bits.read(4)? + 5;
+        if log > max_log {
+            return Err(CORRUPTED.to_owned());
+        }
+        let mut remaining = 1i32 << log;
+        let mut distribution = Vec::new();
+        while remaining > 0 && distribution.len() <= max_symbol {
+            let bit_count = 32 - (remaining + 1).leading_zeros();
+            let mut value = bits.read(bit_count)?;
+            let lower_mask = (1 << (bit_count - 1)) - 1;
+            let threshold = (1 << bit_count) - 1 - (remaining.unsigned_abs() + 1);
+            if value & lower_mask < threshold {
+                // Small values take one bit less
+                bits.pos -= 1;
+                value &= lower_mask;
+            } else if value > lower_mask {
+                value -= threshold;
+            }
+            let probability = i32::try_from(value).map_err(|_| CORRUPTED)? - 1;
+            remaining -= probability.abs();
+            distribution.push(i16::try_from(probability).map_err(|_| CORRUPTED)?);
+            if probability == 0 {
+                loop {
+                    let repeat = bits.read(2)?;
+                    distribution.extend(std::iter::repeat(0).take(repeat as usize));
+                    if repeat != 3 {
+                        break;
+                    }
+                }
+            }
+        }
+        if remaining != 0 || distribution.len() > max_symbol + 1 {
+            return Err(CORRUPTED.to_owned());
+        }
+        Ok((Self::new(&distribution, log)?, (bits.pos + 7) / 8))
+    }
+
+    fn symbol(&self, state: usize) -> u8 {
+        self.symbols[state]
+    }
+
+    fn update(&self, state: usize, bits: &mut BackwardBits<'_>) -> usize {
+        self.bases[state] + bits.read(u32::from(self.bit_counts[state]))
+    }
+}
+
+/// A Huffman decoding table of the literals.
+struct HuffmanTable {
+    max_bits: u32,
+    symbols: Vec<u8>,
+    bit_counts: Vec<u8>,
+}
+
+impl HuffmanTable {
+    /// Reads a tree description at the start of `data`, returning the table and the length of
+    /// the description.
+    fn read(data: &[u8]) -> Result<(Self, usize), String> {
+        let header = usize::from(*data.first().ok_or(TRUNCATED)?);
+        let (mut weights, len) = if header < 128 {
+            let description = data.get(1..1 + header).ok_or(TRUNCATED)?;
+            (read_fse_weights(description)?, 1 + header)
+        } else {
+            let count = header - 127;
+            let packed = data.get(1..1 + (count + 1) / 2).ok_or(TRUNCATED)?;
+            let weights = (0..count)
+                .map(|i| {
+                    let byte = packed[i / 2];
+                    if i % 2 == 0 {
+                        byte >> 4
+                    } else {
+                        byte & 0xf
+                    }
+                })
+                .collect();
+            (weights, 1 + (count + 1) / 2)
+        };
+        if weights.len() > 255 {
+            return Err(CORRUPTED.to_owned());
+        }
+        let total: u32 = weights
+            .iter()
+            .filter(|&&weight| weight > 0)
+            .map(|&weight| 1 << (weight - 1))
+            .sum();
+        if total == 0 {
+            return Err(CORRUPTED.to_owned());
+        }
+        let max_bits = 32 - total.leading_zeros();
+        let left = (1 << max_bits) - total;
+        if !left.is_power_of_two() || max_bits > 11 {
+            return Err(CORRUPTED.to_owned());
+        }
+        // The weight of the last symbol is implied by the others
+        weights.push(u8::try_from(left.trailing_zeros() + 1).map_err(|_| CORRUPTED)?);
+        Ok((Self::new(&weights, max_bits)?, len))
+    }
+
+    fn new(weights: &[u8], max_bits: u32) -> Result<Self, String> {
+        let bits_of = |weight: u8| {
+            if weight == 0 {
+                0
+            } else {
+                max_bits + 1 - u32::from(weight)
+            }
+        };
+        let mut counts = [0usize; 13];
+        for &weight in weights {
+            counts[bits_of(weight) as usize] += 1;
+        }
+        // Codes with the most bits come first
+        let mut starts = [0usize; 13];
+        for bits in (1..=max_bits as usize).rev() {
+            starts[bits - 1] = starts[bits] + (counts[bits] << (max_bits as usize - bits));
+        }
+        let size = 1 << max_bits;
+        let mut symbols = vec![0; size];
+        let mut bit_counts = vec![0; size];
+        for (symbol, &weight) in weights.iter().enumerate() {
+            let bits = bits_of(weight) as usize;
+            if bits == 0 {
+                continue;
+            }
+            let len = 1 << (max_bits as usize - bits);
+            let start = starts[bits];
+            let cells = start..start + len;
+            symbols
+                .get_mut(cells.clone())
+                .ok_or(CORRUPTED)?
+                .fill(u8::try_from(symbol).map_err(|_| CORRUPTED)?);
+            bit_counts[cells].fill(u8::try_from(bits).map_err(|_| CORRUPTED)?);
+            starts[bits] += len;
+        }
+        Ok(Self {
+            max_bits,
+            symbols,
+            bit_counts,
+        })
+    }
+
+    /// Decodes the `count` literals of a single stream.
+    fn decode_stream(&self, data: &[u8], count: usize) -> Result<Vec<u8>, String> {
+        let mut bits = BackwardBits::new(data)?;
+        let mask = (1 << self.max_bits) - 1;
+        let mut state = bits.read(self.max_bits);
+        let mut literals = Vec::with_capacity(count);
+        for _ in 0..count {
+            literals.push(self.symbols[state]);
+            let bit_count = self.bit_counts[state];
+            state = ((state << bit_count) | bits.read(u32::from(bit_count))) & mask;
+        }
+        // The final state was read from the bits right after the start
+        if bits.pos != -i64::from(self.max_bits) {
+            return Err(CORRUPTED.to_owned());
+        }
+        Ok(literals)
+    }
+
+    /// Decodes `count` literals split into four streams after a jump table of their lengths.
+    fn decode_four_streams(&self, data: &[u8], count: usize) -> Result<Vec<u8>, String> {
+        let jump_table = data.get(..6).ok_or(TRUNCATED)?;
+        let lens =
+            [0, 2, 4].map(|i| usize::from(u16::from_le_bytes([jump_table[i], jump_table[i + 1]])));
+        // The first three streams have the same number of literals
+        let segment = (count + 3) / 4;
+        let last = count.checked_sub(3 * segment).ok_or(CORRUPTED)?;
+        let mut literals = Vec::with_capacity(count);
+        let mut rest = &data[6..];
+        for len in lens {
+            let stream = rest.get(..len).ok_or(TRUNCATED)?;
+            literals.extend(self.decode_stream(stream, segment)?);
+            rest = &rest[len..];
+        }
+        literals.extend(self.decode_stream(rest, last)?);
+        Ok(literals)
+    }
+}
+
+/// Decodes the Huffman weights compressed with FSE in `description`.
+fn read_fse_weights(description: &[u8]) -> Result<Vec<u8>, String> {
+    let (table, len) = FseTable::read(description, 6, 255)?;
+    let mut bits = BackwardBits::new(description.get(len..).ok_or(TRUNCATED)?)?;
+    let mut states = [bits.read(table.log), bits.read(table.log)];
+    let mut weights = Vec::new();
+    // The states take turns until updating one reads past the start, after which the other
+    // state's symbol is the last one
+    for i in (0..2).cycle() {
+        weights.push(table.symbol(states[i]));
+        states[i] = table.update(states[i], &mut bits);
+        if bits.is_overflowed() {
+            weights.push(table.symbol(states[1 - i]));
+            break;
+        }
+        if weights.len() > 255 {
+            return Err(CORRUPTED.to_owned());
+        }
+    }
+    Ok(weights)
+}